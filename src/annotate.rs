@@ -0,0 +1,345 @@
+use crate::json::{FromJson, Json, ToJson};
+use crate::render::Frame;
+use crate::style::Style;
+use crate::text;
+use crate::viewport::Viewport;
+use num::complex::Complex;
+use std::path::{Path, PathBuf};
+
+const LINE_WIDTH: f64 = 2.0;
+// Length of the arrow head strokes in pixels and their angle to the shaft.
+const HEAD_LENGTH: f64 = 14.0;
+const HEAD_ANGLE: f64 = 0.45;
+
+// A presentation mark anchored to the complex plane, so it follows the features it points
+// at while zooming and panning.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Annotation {
+    Arrow {
+        from: Complex<f64>,
+        to: Complex<f64>,
+    },
+    Circle {
+        center: Complex<f64>,
+        radius: f64,
+    },
+    Label {
+        at: Complex<f64>,
+        text: String,
+    },
+}
+
+// Drawing tools of the annotation mode, cycled with `N`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Tool {
+    Arrow,
+    Circle,
+    Label,
+}
+
+impl Tool {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Tool::Arrow => "arrow",
+            Tool::Circle => "circle",
+            Tool::Label => "label",
+        }
+    }
+
+    // Cycles through the tools and back to no tool.
+    pub fn next(tool: Option<Tool>) -> Option<Tool> {
+        match tool {
+            None => Some(Tool::Arrow),
+            Some(Tool::Arrow) => Some(Tool::Circle),
+            Some(Tool::Circle) => Some(Tool::Label),
+            Some(Tool::Label) => None,
+        }
+    }
+
+    // The annotation dragged from `start` to `end`, `None` for labels which are placed by a
+    // click and then given their text.
+    pub fn shape(&self, start: Complex<f64>, end: Complex<f64>) -> Option<Annotation> {
+        match self {
+            Tool::Arrow => Some(Annotation::Arrow {
+                from: start,
+                to: end,
+            }),
+            Tool::Circle => Some(Annotation::Circle {
+                center: start,
+                radius: (end - start).norm(),
+            }),
+            Tool::Label => None,
+        }
+    }
+}
+
+fn point_json(name: &str, point: Complex<f64>) -> [(String, Json); 2] {
+    [
+        (format!("{name}_re"), Json::Number(point.re)),
+        (format!("{name}_im"), Json::Number(point.im)),
+    ]
+}
+
+fn point_from_json(json: &Json, name: &str) -> Result<Complex<f64>, String> {
+    Ok(Complex::new(
+        json.field(&format!("{name}_re"))?.as_f64()?,
+        json.field(&format!("{name}_im"))?.as_f64()?,
+    ))
+}
+
+impl ToJson for Annotation {
+    fn to_json(&self) -> Json {
+        let kind = |kind: &str| ("kind".to_string(), Json::String(kind.to_string()));
+        let fields = match self {
+            Annotation::Arrow { from, to } => [kind("arrow")]
+                .into_iter()
+                .chain(point_json("from", *from))
+                .chain(point_json("to", *to))
+                .collect::<Vec<_>>(),
+            Annotation::Circle { center, radius } => [kind("circle")]
+                .into_iter()
+                .chain(point_json("center", *center))
+                .chain([("radius".to_string(), Json::Number(*radius))])
+                .collect(),
+            Annotation::Label { at, text } => [kind("label")]
+                .into_iter()
+                .chain(point_json("at", *at))
+                .chain([("text".to_string(), Json::String(text.clone()))])
+                .collect(),
+        };
+        Json::Object(fields)
+    }
+}
+
+impl FromJson for Annotation {
+    fn from_json(json: &Json) -> Result<Self, String> {
+        match json.field("kind")? {
+            Json::String(kind) if kind == "arrow" => Ok(Annotation::Arrow {
+                from: point_from_json(json, "from")?,
+                to: point_from_json(json, "to")?,
+            }),
+            Json::String(kind) if kind == "circle" => Ok(Annotation::Circle {
+                center: point_from_json(json, "center")?,
+                radius: json.field("radius")?.as_f64()?,
+            }),
+            Json::String(kind) if kind == "label" => match json.field("text")? {
+                Json::String(text) => Ok(Annotation::Label {
+                    at: point_from_json(json, "at")?,
+                    text: text.clone(),
+                }),
+                other => Err(format!("Expected label text, got {other}")),
+            },
+            other => Err(format!("Unknown annotation {other}")),
+        }
+    }
+}
+
+pub fn save(path: &Path, annotations: &[Annotation]) -> Result<(), String> {
+    let json = Json::Array(annotations.iter().map(ToJson::to_json).collect());
+    std::fs::write(path, json.to_string()).map_err(|e| format!("{}: {e}", path.display()))
+}
+
+pub fn load(path: &Path) -> Result<Vec<Annotation>, String> {
+    let text = std::fs::read_to_string(path).map_err(|e| format!("{}: {e}", path.display()))?;
+    Json::parse(&text)?
+        .as_array()?
+        .iter()
+        .map(Annotation::from_json)
+        .collect()
+}
+
+// Change of the annotation list requested from the console.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Edit {
+    Add(Annotation),
+    Undo,
+    Clear,
+    Save(PathBuf),
+    Load(PathBuf),
+}
+
+// Applies the edit, returning a message for the console.
+pub fn edit(annotations: &mut Vec<Annotation>, edit: Edit) -> Result<String, String> {
+    match edit {
+        Edit::Add(annotation) => {
+            annotations.push(annotation);
+            Ok(format!("{} annotations", annotations.len()))
+        }
+        Edit::Undo => match annotations.pop() {
+            Some(_) => Ok(format!("{} annotations", annotations.len())),
+            None => Err("No annotations to undo".to_string()),
+        },
+        Edit::Clear => {
+            annotations.clear();
+            Ok("Annotations cleared".to_string())
+        }
+        Edit::Save(path) => {
+            save(&path, annotations)?;
+            Ok(format!("Annotations saved to {}", path.display()))
+        }
+        Edit::Load(path) => {
+            *annotations = load(&path)?;
+            Ok(format!(
+                "{} annotations loaded from {}",
+                annotations.len(),
+                path.display()
+            ))
+        }
+    }
+}
+
+// Clips the segment to the frame grown by the line width (Liang-Barsky), so that segments
+// reaching far outside the window at deep zooms cost no more than visible ones.
+fn clip(frame: &Frame, a: (f64, f64), b: (f64, f64)) -> Option<((f64, f64), (f64, f64))> {
+    let (dx, dy) = (b.0 - a.0, b.1 - a.1);
+    let (right, bottom) = (
+        frame.width as f64 + LINE_WIDTH,
+        frame.height as f64 + LINE_WIDTH,
+    );
+    let (mut t0, mut t1) = (0.0f64, 1.0f64);
+    for (p, q) in [
+        (-dx, a.0 + LINE_WIDTH),
+        (dx, right - a.0),
+        (-dy, a.1 + LINE_WIDTH),
+        (dy, bottom - a.1),
+    ] {
+        if p == 0.0 {
+            if q < 0.0 {
+                return None;
+            }
+        } else if p < 0.0 {
+            t0 = t0.max(q / p);
+        } else {
+            t1 = t1.min(q / p);
+        }
+    }
+    let at = |t: f64| (a.0 + t * dx, a.1 + t * dy);
+    (t0 <= t1).then(|| (at(t0), at(t1)))
+}
+
+fn plot(frame: &mut Frame, (x, y): (f64, f64), color: [u8; 3]) {
+    let half = LINE_WIDTH / 2.0;
+    for py in (y - half).round() as i64..(y + half).round() as i64 {
+        for px in (x - half).round() as i64..(x + half).round() as i64 {
+            if px >= 0 && py >= 0 && px < frame.width as i64 && py < frame.height as i64 {
+                let index = (py as usize * frame.width as usize + px as usize) * 3;
+                frame.rgb[index..index + 3].copy_from_slice(&color);
+            }
+        }
+    }
+}
+
+// Draws a segment between window positions.
+pub fn line(frame: &mut Frame, a: (f64, f64), b: (f64, f64), color: [u8; 3]) {
+    let Some((a, b)) = clip(frame, a, b) else {
+        return;
+    };
+    let steps = (b.0 - a.0).abs().max((b.1 - a.1).abs()).ceil().max(1.0) as usize;
+    for i in 0..=steps {
+        let t = i as f64 / steps as f64;
+        plot(frame, (a.0 + t * (b.0 - a.0), a.1 + t * (b.1 - a.1)), color);
+    }
+}
+
+// Draws the annotations of the view over the frame.
+pub fn draw(frame: &mut Frame, viewport: &Viewport, annotations: &[Annotation], style: &Style) {
+    let color = style.annotation;
+    let window_size = (frame.width, frame.height);
+    let to_pixel = |c: Complex<f64>| viewport.complex_to_pixel(c, window_size);
+    let diagonal = (frame.width as f64).hypot(frame.height as f64);
+    for annotation in annotations {
+        match annotation {
+            Annotation::Arrow { from, to } => {
+                let (a, b) = (to_pixel(*from), to_pixel(*to));
+                line(frame, a, b, color);
+                let back = Complex::new(a.0 - b.0, a.1 - b.1);
+                if back.norm() > 0.0 {
+                    let back = back / back.norm() * HEAD_LENGTH;
+                    for angle in [-HEAD_ANGLE, HEAD_ANGLE] {
+                        let stroke = back * Complex::from_polar(1.0, angle);
+                        line(frame, b, (b.0 + stroke.re, b.1 + stroke.im), color);
+                    }
+                }
+            }
+            Annotation::Circle { center, radius } => {
+                let c = to_pixel(*center);
+                // Window pixels per unit of the complex plane, square pixels assumed.
+                let r = radius * frame.height as f64 / viewport.height;
+                // Circles far larger than the window have no visible outline worth tracing.
+                if r > 64.0 * diagonal {
+                    continue;
+                }
+                let segments = (r * std::f64::consts::TAU / 4.0).ceil().max(16.0) as usize;
+                let point = |i: usize| {
+                    let p =
+                        Complex::from_polar(r, i as f64 * std::f64::consts::TAU / segments as f64);
+                    (c.0 + p.re, c.1 + p.im)
+                };
+                for i in 0..segments {
+                    line(frame, point(i), point(i + 1), color);
+                }
+            }
+            Annotation::Label { at, text } => {
+                let (x, y) = to_pixel(*at);
+                let (x, y) = (x.round() as i32, y.round() as i32);
+                let scale = 2;
+                let size = (
+                    text.chars().count() as u32 * text::ADVANCE * scale + 8,
+                    text::LINE_HEIGHT * scale + 4,
+                );
+                style.panel(frame, (x, y), size);
+                text::draw(frame, x + 4, y + 4, scale, style.text, text);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn annotations_round_trip_through_json() {
+        let annotations = [
+            Annotation::Arrow {
+                from: Complex::new(-0.75, 0.1),
+                to: Complex::new(-0.7436, 0.1318),
+            },
+            Annotation::Circle {
+                center: Complex::new(0.25, 0.0),
+                radius: 1e-3,
+            },
+            Annotation::Label {
+                at: Complex::new(-1.0, 0.0),
+                text: "period \"2\" bulb".to_string(),
+            },
+        ];
+        let path = std::env::temp_dir().join(format!("mandelbrot-notes-{}", std::process::id()));
+        save(&path, &annotations).unwrap();
+        assert_eq!(load(&path).unwrap(), annotations);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn marks_follow_the_view() {
+        let arrow = [Annotation::Arrow {
+            from: Complex::new(-1.0, 0.0),
+            to: Complex::new(1.0, 0.0),
+        }];
+        let lit = |viewport: Viewport| {
+            let mut frame = Frame::from_rgb(40, 30, vec![0; 40 * 30 * 3]);
+            draw(&mut frame, &viewport, &arrow, &Style::default());
+            (0..40 * 30)
+                .filter(|i| frame.rgb[i * 3] == Style::default().annotation[0])
+                .count()
+        };
+        let full = lit(Viewport::default());
+        assert!(full > 0);
+        // Zoomed far away from it, the arrow is clipped out entirely.
+        let elsewhere = Viewport::new(Complex::new(0.0, 1.0), 0.04, 0.03);
+        assert_eq!(lit(elsewhere), 0);
+        // Zoomed into its middle the shaft crosses the whole window.
+        let inside = Viewport::new(Complex::new(0.0, 0.0), 0.04, 0.03);
+        assert!(lit(inside) >= 40 * 2);
+    }
+}
@@ -0,0 +1,165 @@
+use crate::json::{FromJson, Json, ToJson};
+use crate::params::RenderParams;
+use std::path::Path;
+
+// A named location, stored with everything needed to render it again.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Bookmark {
+    pub name: String,
+    pub params: RenderParams,
+}
+
+impl ToJson for Bookmark {
+    fn to_json(&self) -> Json {
+        Json::object([
+            ("name", Json::String(self.name.clone())),
+            ("params", self.params.to_json()),
+        ])
+    }
+}
+
+impl FromJson for Bookmark {
+    fn from_json(json: &Json) -> Result<Self, String> {
+        Ok(Bookmark {
+            name: match json.field("name")? {
+                Json::String(name) => name.clone(),
+                other => return Err(format!("Invalid bookmark name {other}")),
+            },
+            params: RenderParams::from_json(json.field("params")?)?,
+        })
+    }
+}
+
+// A bookmarks file is a JSON array of bookmarks.
+pub fn load(path: &Path) -> Result<Vec<Bookmark>, String> {
+    let text = std::fs::read_to_string(path).map_err(|e| format!("{}: {e}", path.display()))?;
+    Json::parse(&text)?
+        .as_array()?
+        .iter()
+        .map(Bookmark::from_json)
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("{}: {e}", path.display()))
+}
+
+// The bookmarks of the file, none while it does not exist yet.
+pub fn load_or_empty(path: &Path) -> Result<Vec<Bookmark>, String> {
+    match path.exists() {
+        true => load(path),
+        false => Ok(Vec::new()),
+    }
+}
+
+pub fn save(path: &Path, bookmarks: &[Bookmark]) -> Result<(), String> {
+    let json = Json::Array(bookmarks.iter().map(Bookmark::to_json).collect());
+    std::fs::write(path, json.to_string()).map_err(|e| format!("{}: {e}", path.display()))
+}
+
+// Whether the bookmarks show the same place: the same fractal, with centers and view heights
+// agreeing to a thousandth of the height, whatever their names and colorings.
+pub fn same_location(a: &Bookmark, b: &Bookmark) -> bool {
+    let (a, b) = (&a.params, &b.params);
+    let height = a.viewport.height.max(b.viewport.height);
+    a.fractal == b.fractal
+        && (a.viewport.center - b.viewport.center).norm() <= height * 1e-3
+        && (a.viewport.height - b.viewport.height).abs() <= height * 1e-3
+}
+
+// Appends the bookmarks of another collection that are not already in it, returning how many.
+pub fn merge(bookmarks: &mut Vec<Bookmark>, other: impl IntoIterator<Item = Bookmark>) -> usize {
+    let before = bookmarks.len();
+    for bookmark in other {
+        if !bookmarks.iter().any(|kept| same_location(kept, &bookmark)) {
+            bookmarks.push(bookmark);
+        }
+    }
+    bookmarks.len() - before
+}
+
+// The index of the bookmark of the name, or else of the number counted from 1.
+pub fn find(bookmarks: &[Bookmark], key: &str) -> Option<usize> {
+    bookmarks
+        .iter()
+        .position(|bookmark| bookmark.name == key)
+        .or_else(|| {
+            let number = key.parse::<usize>().ok()?;
+            (1..=bookmarks.len()).contains(&number).then(|| number - 1)
+        })
+}
+
+// A name of no bookmark yet, for those saved without one.
+pub fn unused_name(bookmarks: &[Bookmark]) -> String {
+    (bookmarks.len() + 1..)
+        .map(|number| format!("Bookmark {number}"))
+        .find(|name| bookmarks.iter().all(|bookmark| bookmark.name != *name))
+        .unwrap()
+}
+
+// Adds the bookmark, replacing one of the same name, and returns its index.
+pub fn put(bookmarks: &mut Vec<Bookmark>, bookmark: Bookmark) -> usize {
+    match bookmarks.iter().position(|kept| kept.name == bookmark.name) {
+        Some(index) => {
+            bookmarks[index] = bookmark;
+            index
+        }
+        None => {
+            bookmarks.push(bookmark);
+            bookmarks.len() - 1
+        }
+    }
+}
+
+// The bookmarked view at another image size, widened or narrowed to its aspect ratio around
+// the same center and height.
+pub fn resized(params: RenderParams, width: u32, height: u32) -> Result<RenderParams, String> {
+    params
+        .to_builder()
+        .size(width, height)
+        .viewport(params.viewport.fit((width, height)))
+        .build()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::viewport::Viewport;
+    use num::complex::Complex;
+
+    #[test]
+    fn merges_by_location() {
+        let home = RenderParams::builder().size(80, 60).build().unwrap();
+        let at = |re: f64, height: f64| Bookmark {
+            name: format!("{re} {height}"),
+            params: home
+                .to_builder()
+                .viewport(Viewport::new(Complex::new(re, 0.1), height, height))
+                .build()
+                .unwrap(),
+        };
+        let mut bookmarks = vec![at(-0.75, 0.1)];
+        let added = merge(
+            &mut bookmarks,
+            [
+                at(-0.75 + 1e-5, 0.1),
+                at(-0.75, 0.05),
+                at(0.25, 0.1),
+                at(0.25, 0.1),
+            ],
+        );
+        assert_eq!(added, 2);
+        assert_eq!(bookmarks, [at(-0.75, 0.1), at(-0.75, 0.05), at(0.25, 0.1)]);
+        assert_eq!(find(&bookmarks, "0.25 0.1"), Some(2));
+        assert_eq!(find(&bookmarks, "2"), Some(1));
+        assert_eq!(find(&bookmarks, "4"), None);
+        let renamed = Bookmark {
+            name: "-0.75 0.1".to_string(),
+            ..at(0.25, 0.05)
+        };
+        assert_eq!(put(&mut bookmarks.clone(), renamed), 0);
+        assert_eq!(put(&mut bookmarks.clone(), at(0.25, 0.05)), 3);
+        bookmarks[0].name = "Bookmark 4".to_string();
+        assert_eq!(unused_name(&bookmarks), "Bookmark 5");
+        let resized = resized(bookmarks[0].params, 40, 10).unwrap();
+        assert!((resized.viewport.width - 0.4).abs() < 1e-12);
+        assert_eq!(resized.viewport.center, bookmarks[0].params.viewport.center);
+    }
+}
@@ -0,0 +1,751 @@
+use crate::json::{FromJson, Json, ToJson};
+use crate::params::{Fractal, RenderParams};
+#[cfg(feature = "gpu")]
+use crate::scheduler::Precision;
+use num::complex::Complex;
+use rayon::prelude::*;
+use std::ops::Range;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+// Orbits are sampled in fixed-size chunks, each seeded by its index, so the result does not
+// depend on how rayon splits the work.
+const CHUNK: u64 = 1 << 14;
+
+// The lengths of the orbits, in iterations before escaping, that a channel counts. Short orbits
+// make the smooth haze of the Buddhabrot, long ones trace its filaments.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct OrbitLengths {
+    pub min: u32,
+    pub max: u32,
+}
+
+impl OrbitLengths {
+    pub const ALL: OrbitLengths = OrbitLengths {
+        min: 0,
+        max: u32::MAX,
+    };
+
+    fn contains(&self, length: usize) -> bool {
+        (self.min as usize..=self.max as usize).contains(&length)
+    }
+
+    // `MIN:MAX`, either of them left out for no bound.
+    fn parse(text: &str) -> Result<OrbitLengths, String> {
+        let invalid = || format!("Expected orbit lengths MIN:MAX, got {text}");
+        let (min, max) = text.split_once(':').ok_or_else(invalid)?;
+        let bound = |bound: &str, none| match bound {
+            "" => Ok(none),
+            bound => bound.parse().map_err(|_| invalid()),
+        };
+        let lengths = OrbitLengths {
+            min: bound(min, 0)?,
+            max: bound(max, u32::MAX)?,
+        };
+        match lengths.min <= lengths.max {
+            true => Ok(lengths),
+            false => Err(format!("Empty orbit lengths {text}")),
+        }
+    }
+}
+
+// The orbits accumulated: of one range of lengths, or in Nebulabrot mode of one range for each of
+// the red, green and blue channels, tone-mapped each on its own.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Orbits {
+    Gray(OrbitLengths),
+    Nebulabrot([OrbitLengths; 3]),
+}
+
+impl Orbits {
+    pub const ALL: Orbits = Orbits::Gray(OrbitLengths::ALL);
+
+    pub fn channels(&self) -> &[OrbitLengths] {
+        match self {
+            Orbits::Gray(lengths) => std::slice::from_ref(lengths),
+            Orbits::Nebulabrot(channels) => channels,
+        }
+    }
+
+    // A range of lengths or three of them separated by commas, e.g. `:5000,:500,:50` for the
+    // classic Nebulabrot.
+    pub fn parse(text: &str) -> Result<Orbits, String> {
+        let channels = text
+            .split(',')
+            .map(OrbitLengths::parse)
+            .collect::<Result<Vec<_>, _>>()?;
+        match channels[..] {
+            [lengths] => Ok(Orbits::Gray(lengths)),
+            [red, green, blue] => Ok(Orbits::Nebulabrot([red, green, blue])),
+            _ => Err(format!(
+                "Expected one or three orbit length ranges, got {text}"
+            )),
+        }
+    }
+}
+
+// A list of `[min, max]` pairs, `max` null when unbounded.
+impl ToJson for Orbits {
+    fn to_json(&self) -> Json {
+        Json::Array(
+            self.channels()
+                .iter()
+                .map(|lengths| {
+                    Json::Array(vec![
+                        Json::Number(lengths.min as f64),
+                        match lengths.max {
+                            u32::MAX => Json::Null,
+                            max => Json::Number(max as f64),
+                        },
+                    ])
+                })
+                .collect(),
+        )
+    }
+}
+
+impl FromJson for Orbits {
+    fn from_json(json: &Json) -> Result<Self, String> {
+        let Json::Array(channels) = json else {
+            return Err(format!("Expected a list of orbit lengths, got {json}"));
+        };
+        let text = channels
+            .iter()
+            .map(|lengths| match lengths {
+                Json::Array(bounds) if bounds.len() == 2 => {
+                    let max = match &bounds[1] {
+                        Json::Null => String::new(),
+                        max => (max.as_f64()? as u32).to_string(),
+                    };
+                    Ok(format!("{}:{max}", bounds[0].as_f64()? as u32))
+                }
+                other => Err(format!("Expected [min, max] orbit lengths, got {other}")),
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+        Orbits::parse(&text.join(","))
+    }
+}
+
+pub struct SplitMix64(u64);
+
+impl SplitMix64 {
+    pub fn new(seed: u64) -> Self {
+        SplitMix64(seed)
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e3779b97f4a7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+        z ^ (z >> 31)
+    }
+
+    // Uniform in [0, 1).
+    pub fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+// The hit counts of the pixels and the orbits started and hits scored per cell of a grid.
+pub type Accumulation = (Vec<f32>, Vec<(u32, u32)>);
+
+// Points in the main cardioid or the period-2 bulb never escape.
+fn in_main_components(c: Complex<f64>) -> bool {
+    let q = (c.re - 0.25).powi(2) + c.im * c.im;
+    q * (q + (c.re - 0.25)) <= 0.25 * c.im * c.im || (c.re + 1.0).powi(2) + c.im * c.im <= 0.0625
+}
+
+// Side of the grid of the importance maps learned while sampling.
+const CELLS: usize = 64;
+// Share of the orbits drawn uniformly when warm-started from an importance map, so that the
+// regions it missed are still sampled and the hit counts stay unbiased.
+const UNIFORM: f64 = 0.1;
+
+// How many hits in the view the orbits starting in each cell of a grid over the sampled square
+// scored on average, row by row from -2 - 2i. Sampling the cells in proportion warm-starts
+// later renders of the same region, and the map is kept as a 16-bit grayscale PGM image.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Importance {
+    pub size: usize,
+    pub cells: Vec<f32>,
+}
+
+impl Importance {
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        let max = self.cells.iter().fold(0f32, |max, &cell| max.max(cell));
+        let mut data = format!("P5\n{0} {0}\n65535\n", self.size).into_bytes();
+        for &cell in &self.cells {
+            let level = match max {
+                0.0 => 0,
+                _ => (cell / max * 65535.0).round() as u16,
+            };
+            data.extend(level.to_be_bytes());
+        }
+        std::fs::write(path, data).map_err(|e| format!("{}: {e}", path.display()))
+    }
+
+    pub fn load(path: &Path) -> Result<Importance, String> {
+        let invalid = || format!("{}: not a square grayscale PGM image", path.display());
+        let data = std::fs::read(path).map_err(|e| format!("{}: {e}", path.display()))?;
+        // The magic number, the size and the maximum, each followed by one whitespace byte
+        // before the raster.
+        let mut fields = Vec::new();
+        let mut at = 0;
+        while fields.len() < 4 {
+            while data.get(at).is_some_and(u8::is_ascii_whitespace) {
+                at += 1;
+            }
+            let field = at;
+            while data.get(at).is_some_and(|byte| !byte.is_ascii_whitespace()) {
+                at += 1;
+            }
+            if at == field || at == data.len() {
+                return Err(invalid());
+            }
+            fields.push(String::from_utf8_lossy(&data[field..at]).into_owned());
+        }
+        let number = |field: &str| field.parse::<usize>().map_err(|_| invalid());
+        let (size, max) = (number(&fields[1])?, number(&fields[3])?);
+        let bytes = if max > 255 { 2 } else { 1 };
+        let raster = &data[at + 1..];
+        if fields[0] != "P5" || size == 0 || size != number(&fields[2])? || max == 0 {
+            return Err(invalid());
+        }
+        if raster.len() != size * size * bytes {
+            return Err(invalid());
+        }
+        let cells = raster
+            .chunks(bytes)
+            .map(|level| {
+                level
+                    .iter()
+                    .fold(0, |value, &byte| value << 8 | byte as usize)
+            })
+            .map(|level| level as f32 / max as f32)
+            .collect();
+        Ok(Importance { size, cells })
+    }
+
+    // The cell of a point of the sampled square.
+    fn cell(&self, c: Complex<f64>) -> usize {
+        let index = |x: f64| (((x + 2.0) / 4.0 * self.size as f64) as usize).min(self.size - 1);
+        index(c.im) * self.size + index(c.re)
+    }
+
+    // The mean hits of the orbits started in each cell.
+    fn learned(size: usize, scores: &[(u32, u32)]) -> Importance {
+        let cells = scores
+            .iter()
+            .map(|&(orbits, scored)| match orbits {
+                0 => 0.0,
+                _ => scored as f32 / orbits as f32,
+            })
+            .collect();
+        Importance { size, cells }
+    }
+}
+
+// Draws the starting points of the orbits from the cells of an importance map, mixed with a
+// uniform share, with the weight making up for the density.
+struct Sampler {
+    size: usize,
+    // Cumulative probabilities of the cells.
+    cumulative: Vec<f64>,
+}
+
+impl Sampler {
+    fn new(importance: &Importance) -> Option<Sampler> {
+        let total = importance
+            .cells
+            .iter()
+            .map(|&cell| cell as f64)
+            .sum::<f64>();
+        if total <= 0.0 {
+            return None;
+        }
+        let uniform = UNIFORM / importance.cells.len() as f64;
+        let cumulative = importance
+            .cells
+            .iter()
+            .scan(0.0, |sum, &cell| {
+                *sum += (1.0 - UNIFORM) * cell as f64 / total + uniform;
+                Some(*sum)
+            })
+            .collect();
+        Some(Sampler {
+            size: importance.size,
+            cumulative,
+        })
+    }
+
+    fn draw(&self, rng: &mut SplitMix64) -> (Complex<f64>, f32) {
+        let u = rng.next_f64() * self.cumulative.last().unwrap();
+        let cell = self
+            .cumulative
+            .partition_point(|&sum| sum <= u)
+            .min(self.cumulative.len() - 1);
+        let probability =
+            self.cumulative[cell] - cell.checked_sub(1).map_or(0.0, |i| self.cumulative[i]);
+        let side = 4.0 / self.size as f64;
+        let (x, y) = ((cell % self.size) as f64, (cell / self.size) as f64);
+        let c = Complex::new(
+            -2.0 + (x + rng.next_f64()) * side,
+            -2.0 + (y + rng.next_f64()) * side,
+        );
+        let weight = 1.0 / (probability * self.cumulative.len() as f64);
+        (c, weight as f32)
+    }
+}
+
+// Accumulates, for every pixel, how many escaping orbits passed through it. Starting points are
+// drawn from the [-2, 2] x [-2, 2] square, `samples_per_pixel * width * height` of them in
+// total, uniformly or, warm-started, mostly from the cells `warm` found to contribute. Returns
+// the importance map learned along.
+pub fn accumulate(
+    params: &RenderParams,
+    samples_per_pixel: u32,
+    warm: Option<&Importance>,
+) -> (Vec<f32>, Importance) {
+    let total = total_samples(params, samples_per_pixel);
+    let sampler = warm.and_then(Sampler::new);
+    let size = warm.map_or(CELLS, |warm| warm.size);
+    let chunks = 0..total.div_ceil(CHUNK);
+    let (hits, scores) = sample_chunks(params, chunks, total, sampler.as_ref(), size);
+    (hits, Importance::learned(size, &scores))
+}
+
+fn total_samples(params: &RenderParams, samples_per_pixel: u32) -> u64 {
+    (params.width * params.height) as u64 * samples_per_pixel as u64
+}
+
+// The hit counts of the chunks in the range, out of `total` starting points, and the orbits
+// started and hits scored per cell of a grid of `size` cells a side. The counts of the channels
+// of a pixel follow each other.
+fn sample_chunks(
+    params: &RenderParams,
+    chunks: Range<u64>,
+    total: u64,
+    sampler: Option<&Sampler>,
+    size: usize,
+) -> Accumulation {
+    #[cfg(feature = "gpu")]
+    if let Some(accumulated) = gpu_chunks(params, chunks.clone(), total, sampler, size) {
+        return accumulated;
+    }
+    cpu_chunks(params, chunks, total, sampler, size)
+}
+
+// The same on the GPU, for views f32 resolves, the starting points drawn by the shader. `None`
+// without a GPU.
+#[cfg(feature = "gpu")]
+fn gpu_chunks(
+    params: &RenderParams,
+    chunks: Range<u64>,
+    total: u64,
+    sampler: Option<&Sampler>,
+    size: usize,
+) -> Option<Accumulation> {
+    let device = crate::gpu::device().ok()?;
+    if !Precision::Single.resolves(params) {
+        return None;
+    }
+    let samples = chunks.start * CHUNK..(chunks.end * CHUNK).min(total);
+    let cells = sampler.map_or(Vec::new(), |sampler| {
+        let previous = std::iter::once(0.0).chain(sampler.cumulative.iter().copied());
+        sampler
+            .cumulative
+            .iter()
+            .zip(previous)
+            .map(|(&sum, previous)| [sum as f32, (sum - previous) as f32])
+            .collect()
+    });
+    match device.accumulate(*params, samples, cells, size) {
+        Ok(accumulated) => Some(accumulated),
+        Err(e) => {
+            println!("{e}, accumulating on the CPU instead");
+            None
+        }
+    }
+}
+
+fn cpu_chunks(
+    params: &RenderParams,
+    chunks: Range<u64>,
+    total: u64,
+    sampler: Option<&Sampler>,
+    size: usize,
+) -> Accumulation {
+    let (width, height) = (params.width as usize, params.height as usize);
+    let window_size = (params.width, params.height);
+    let grid = Importance {
+        size,
+        cells: Vec::new(),
+    };
+    let channels = params.orbits.channels();
+    let pixels = width * height * channels.len();
+    let empty = || (vec![0f32; pixels], vec![(0u32, 0u32); size * size]);
+    chunks
+        .into_par_iter()
+        .fold(
+            || (empty(), Vec::new()),
+            |((mut hits, mut scores), mut orbit), chunk| {
+                let mut rng = SplitMix64::new(chunk);
+                for _ in 0..CHUNK.min(total - chunk * CHUNK) {
+                    let (c, weight) = match sampler {
+                        Some(sampler) => sampler.draw(&mut rng),
+                        None => (
+                            Complex::new(rng.next_f64() * 4.0 - 2.0, rng.next_f64() * 4.0 - 2.0),
+                            1.0,
+                        ),
+                    };
+                    let score = &mut scores[grid.cell(c)];
+                    score.0 += 1;
+                    if in_main_components(c) {
+                        continue;
+                    }
+                    orbit.clear();
+                    let mut z = Complex::new(0.0, 0.0);
+                    let mut escaped = false;
+                    for _ in 0..params.iterations {
+                        z = z * z + c;
+                        if z.norm_sqr() > 4.0 {
+                            escaped = true;
+                            break;
+                        }
+                        orbit.push(z);
+                    }
+                    let length = orbit.len();
+                    if !escaped || !channels.iter().any(|lengths| lengths.contains(length)) {
+                        continue;
+                    }
+                    for z in &orbit {
+                        let (x, y) = params.viewport.complex_to_pixel(*z, window_size);
+                        if x >= 0.0 && y >= 0.0 && (x as usize) < width && (y as usize) < height {
+                            let pixel = (y as usize * width + x as usize) * channels.len();
+                            for (channel, lengths) in channels.iter().enumerate() {
+                                if lengths.contains(length) {
+                                    hits[pixel + channel] += weight;
+                                }
+                            }
+                            score.1 += 1;
+                        }
+                    }
+                }
+                ((hits, scores), orbit)
+            },
+        )
+        .map(|(accumulated, _)| accumulated)
+        .reduce(empty, |(mut hits, mut scores), (more_hits, more_scores)| {
+            hits.iter_mut().zip(more_hits).for_each(|(a, b)| *a += b);
+            scores.iter_mut().zip(more_scores).for_each(|(a, b)| {
+                *a = (a.0 + b.0, a.1 + b.1);
+            });
+            (hits, scores)
+        })
+}
+
+// The accumulation of `accumulate` a pass at a time, about one starting point per pixel each, for
+// showing the image as it improves. The passes take the chunks in order, so the finished buffer
+// is the one `accumulate` returns.
+pub struct Progressive {
+    params: RenderParams,
+    sampler: Option<Sampler>,
+    size: usize,
+    hits: Vec<f32>,
+    scores: Vec<(u32, u32)>,
+    next: u64,
+    chunks: u64,
+    total: u64,
+    elapsed: Duration,
+    pub paused: bool,
+}
+
+impl Progressive {
+    // `None` for the other fractals.
+    pub fn new(params: RenderParams, warm: Option<&Importance>) -> Option<Progressive> {
+        let Fractal::Buddhabrot { samples_per_pixel } = params.fractal else {
+            return None;
+        };
+        let total = total_samples(&params, samples_per_pixel);
+        let size = warm.map_or(CELLS, |warm| warm.size);
+        Some(Progressive {
+            params,
+            sampler: warm.and_then(Sampler::new),
+            size,
+            hits: vec![
+                0.0;
+                (params.width * params.height) as usize * params.orbits.channels().len()
+            ],
+            scores: vec![(0, 0); size * size],
+            next: 0,
+            chunks: total.div_ceil(CHUNK),
+            total,
+            elapsed: Duration::ZERO,
+            paused: false,
+        })
+    }
+
+    fn per_pass(&self) -> u64 {
+        (self.params.width * self.params.height).div_ceil(CHUNK as u32) as u64
+    }
+
+    pub fn done(&self) -> bool {
+        self.next == self.chunks
+    }
+
+    pub fn running(&self) -> bool {
+        !self.paused && !self.done()
+    }
+
+    pub fn pass(&mut self) {
+        let stamp = Instant::now();
+        let end = (self.next + self.per_pass()).min(self.chunks);
+        let chunks = self.next..end;
+        let sampler = self.sampler.as_ref();
+        let (hits, scores) = sample_chunks(&self.params, chunks, self.total, sampler, self.size);
+        self.hits.iter_mut().zip(hits).for_each(|(a, b)| *a += b);
+        self.scores.iter_mut().zip(scores).for_each(|(a, b)| {
+            *a = (a.0 + b.0, a.1 + b.1);
+        });
+        self.next = end;
+        self.elapsed += stamp.elapsed();
+    }
+
+    pub fn samples(&self) -> u64 {
+        (self.next * CHUNK).min(self.total)
+    }
+
+    // The passes done and those of the target sample count.
+    pub fn passes(&self) -> (u64, u64) {
+        let per_pass = self.per_pass();
+        (self.next.div_ceil(per_pass), self.chunks.div_ceil(per_pass))
+    }
+
+    // Extrapolated from the time per sample so far.
+    pub fn remaining(&self) -> Option<Duration> {
+        let samples = self.samples();
+        (samples > 0).then(|| {
+            self.elapsed
+                .mul_f64((self.total - samples) as f64 / samples as f64)
+        })
+    }
+
+    pub fn accumulation(&self) -> Vec<f32> {
+        self.hits.clone()
+    }
+
+    // The importance map learned from the orbits so far.
+    pub fn learned(&self) -> Importance {
+        Importance::learned(self.size, &self.scores)
+    }
+
+    pub fn status(&self) -> String {
+        let (done, passes) = self.passes();
+        let state = match (self.done(), self.paused, self.remaining()) {
+            (true, _, _) => format!("done in {:.1}s", self.elapsed.as_secs_f64()),
+            (false, true, _) => "paused".to_string(),
+            (false, false, Some(remaining)) => format!("{:.0}s left", remaining.as_secs_f64()),
+            (false, false, None) => String::new(),
+        };
+        format!(
+            "pass {done}/{passes}  {:.1}M/{:.1}M samples  {state}",
+            self.samples() as f64 / 1e6,
+            self.total as f64 / 1e6
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::viewport::Viewport;
+
+    #[test]
+    fn main_components_do_not_escape() {
+        for c in [
+            Complex::new(0.0, 0.0),
+            Complex::new(-1.0, 0.0),
+            Complex::new(0.2, 0.3),
+        ] {
+            assert!(in_main_components(c));
+        }
+        for c in [
+            Complex::new(0.4, 0.0),
+            Complex::new(-0.75, 0.2),
+            Complex::new(-1.5, 0.0),
+        ] {
+            assert!(!in_main_components(c));
+        }
+    }
+
+    #[cfg(feature = "gpu")]
+    #[test]
+    fn gpu_accumulation_matches_the_cpu_within_noise() {
+        // Without a GPU there is nothing to compare, the CPU accumulates alone.
+        if crate::gpu::device().is_err() {
+            return;
+        }
+        let params = RenderParams::builder()
+            .fractal(Fractal::Buddhabrot {
+                samples_per_pixel: 64,
+            })
+            .size(48, 36)
+            .iterations(200)
+            .build()
+            .unwrap();
+        let total = total_samples(&params, 64);
+        let chunks = total.div_ceil(CHUNK);
+        let cpu = cpu_chunks(&params, 0..chunks, total, None, CELLS);
+        let learned = Importance::learned(CELLS, &cpu.1);
+        let warm = Sampler::new(&learned);
+        // Hit counts summed over blocks of 8x6 pixels, and how far those of two accumulations
+        // are apart relative to the second.
+        let blocks = |hits: &[f32]| {
+            let mut blocks = vec![0.0; 36];
+            for (i, &hits) in hits.iter().enumerate() {
+                blocks[i / 48 / 6 * 6 + i % 48 / 8] += hits as f64;
+            }
+            blocks
+        };
+        let apart = |a: &Accumulation, b: &Accumulation| {
+            let (a, b) = (blocks(&a.0), blocks(&b.0));
+            a.iter()
+                .zip(&b)
+                .map(|(a, b)| (a - b).abs() / b.max(1.0))
+                .fold(0.0, f64::max)
+        };
+        let started = |a: &Accumulation| a.1.iter().map(|cell| cell.0 as u64).sum::<u64>();
+        for sampler in [None, warm.as_ref()] {
+            let cpu = cpu_chunks(&params, 0..chunks, total, sampler, CELLS);
+            let gpu = gpu_chunks(&params, 0..chunks, total, sampler, CELLS).unwrap();
+            // The same number of starting points from other seeds on the CPU show the noise.
+            let other = cpu_chunks(&params, chunks..2 * chunks, 2 * total, sampler, CELLS);
+            assert_eq!(started(&gpu), total);
+            assert!(apart(&gpu, &cpu) < 1.5 * apart(&other, &cpu));
+            let sum = |a: &Accumulation| a.0.iter().map(|&hits| hits as f64).sum::<f64>();
+            assert!((sum(&gpu) / sum(&cpu) - 1.0).abs() < 0.03);
+        }
+    }
+
+    #[test]
+    fn accumulation_is_deterministic_and_symmetric() {
+        let params = RenderParams::builder()
+            .fractal(Fractal::Buddhabrot {
+                samples_per_pixel: 4,
+            })
+            .size(40, 30)
+            .iterations(50)
+            .build()
+            .unwrap();
+        let (a, _) = accumulate(&params, 4, None);
+        assert_eq!(a, accumulate(&params, 4, None).0);
+        assert!(a.iter().any(|v| *v > 0.0));
+        // The default view is symmetric about the real axis, the hit counts roughly are too.
+        let top = a[..40 * 15].iter().sum::<f32>();
+        let bottom = a[40 * 15..].iter().sum::<f32>();
+        assert!((top - bottom).abs() / (top + bottom) < 0.1);
+    }
+
+    #[test]
+    fn orbit_lengths_filter_the_channels() {
+        let base = RenderParams::builder()
+            .fractal(Fractal::Buddhabrot {
+                samples_per_pixel: 4,
+            })
+            .size(40, 30)
+            .iterations(100)
+            .build()
+            .unwrap();
+        let all = accumulate(&base, 4, None).0;
+        let nebulabrot = Orbits::parse(":,10:,:9").unwrap();
+        let params = base.to_builder().orbits(nebulabrot).build().unwrap();
+        let channels = accumulate(&params, 4, None).0;
+        assert_eq!(channels.len(), 3 * all.len());
+        let channel = |k: usize| channels.iter().skip(k).step_by(3).copied();
+        assert!(channel(0).eq(all.iter().copied()));
+        // The short and the long orbits split the whole between them.
+        assert!(channel(1)
+            .zip(channel(2))
+            .map(|(a, b)| a + b)
+            .eq(all.iter().copied()));
+        assert!(channel(1).sum::<f32>() > 0.0 && channel(2).sum::<f32>() > 0.0);
+        let frame = crate::render::accumulation_frame(params, channels);
+        assert_eq!(frame.rgb.len(), 3 * 40 * 30);
+        assert!(frame.rgb.chunks(3).any(|rgb| rgb[1] != rgb[2]));
+        for invalid in ["5", "9:2", ":1,:2", "a:"] {
+            assert!(Orbits::parse(invalid).is_err(), "{invalid}");
+        }
+        assert_eq!(Orbits::from_json(&nebulabrot.to_json()), Ok(nebulabrot));
+    }
+
+    #[test]
+    fn passes_add_up_to_the_whole_accumulation() {
+        let params = RenderParams::builder()
+            .fractal(Fractal::Buddhabrot {
+                samples_per_pixel: 3,
+            })
+            .size(128, 128)
+            .iterations(50)
+            .build()
+            .unwrap();
+        assert!(Progressive::new(RenderParams::builder().build().unwrap(), None).is_none());
+        let mut progressive = Progressive::new(params, None).unwrap();
+        assert_eq!(progressive.passes(), (0, 3));
+        assert_eq!(progressive.remaining(), None);
+        progressive.pass();
+        assert_eq!(progressive.passes(), (1, 3));
+        assert!(progressive.status().starts_with("pass 1/3 "));
+        while progressive.running() {
+            progressive.pass();
+        }
+        assert_eq!(progressive.samples(), 3 * 128 * 128);
+        assert_eq!(progressive.remaining(), Some(Duration::ZERO));
+        let (hits, learned) = accumulate(&params, 3, None);
+        assert_eq!(progressive.accumulation(), hits);
+        assert_eq!(progressive.learned(), learned);
+    }
+
+    #[test]
+    fn warm_starts_converge_faster() {
+        let params = RenderParams::builder()
+            .fractal(Fractal::Buddhabrot {
+                samples_per_pixel: 4,
+            })
+            .viewport(Viewport::new(Complex::new(-0.1, 0.75), 0.4, 0.3))
+            .size(32, 24)
+            .iterations(100)
+            .build()
+            .unwrap();
+        let (reference, learned) = accumulate(&params, 256, None);
+        let (cold, _) = accumulate(&params, 4, None);
+        let (warm, _) = accumulate(&params, 4, Some(&learned));
+        let scale = 4.0 / 256.0;
+        let error = |hits: &[f32]| {
+            hits.iter()
+                .zip(&reference)
+                .map(|(a, b)| (a - b * scale).powi(2))
+                .sum::<f32>()
+        };
+        assert!(
+            error(&warm) < 0.5 * error(&cold),
+            "warm {} cold {}",
+            error(&warm),
+            error(&cold)
+        );
+        // Unbiased, the total stays the same.
+        let total = |hits: &[f32]| hits.iter().sum::<f32>();
+        assert!((total(&warm) / (total(&reference) * scale) - 1.0).abs() < 0.1);
+
+        let path = std::env::temp_dir().join(format!("importance-{}.pgm", std::process::id()));
+        learned.save(&path).unwrap();
+        let loaded = Importance::load(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(loaded.size, CELLS);
+        let max = learned.cells.iter().fold(0f32, |max, &cell| max.max(cell));
+        for (a, b) in loaded.cells.iter().zip(&learned.cells) {
+            assert!((a - b / max).abs() < 1e-4);
+        }
+        assert!(Importance::load(Path::new("readme.md")).is_err());
+    }
+}
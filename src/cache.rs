@@ -0,0 +1,189 @@
+use crate::json::{Json, ToJson};
+use crate::kernel;
+use crate::params::RenderParams;
+use crate::render::{self, Frame};
+use crate::subdivide;
+use std::fs;
+use std::io::Write;
+use std::path::PathBuf;
+use std::time::{Duration, Instant, SystemTime};
+
+const MAGIC: &[u8; 4] = b"MBC1";
+
+// Content-addressed store of finished RGB frames, keyed by `key`.
+pub struct RenderCache {
+    dir: PathBuf,
+    max_bytes: u64,
+    // Frames that were cheaper than this to compute are not worth the disk write.
+    min_compute_time: Duration,
+}
+
+impl RenderCache {
+    pub fn new(dir: PathBuf, max_bytes: u64) -> Result<Self, String> {
+        fs::create_dir_all(&dir).map_err(|e| format!("{}: {e}", dir.display()))?;
+        Ok(RenderCache {
+            dir,
+            max_bytes,
+            min_compute_time: Duration::from_millis(250),
+        })
+    }
+
+    pub fn default_dir() -> PathBuf {
+        std::env::var_os("XDG_CACHE_HOME")
+            .map(PathBuf::from)
+            .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".cache")))
+            .unwrap_or_else(std::env::temp_dir)
+            .join("mandelbrot-explorer")
+    }
+
+    fn path(&self, params: &RenderParams) -> PathBuf {
+        self.dir.join(format!("{:016x}.bin", key(params)))
+    }
+
+    pub fn get(&self, params: &RenderParams) -> Option<Vec<u8>> {
+        let path = self.path(params);
+        let bytes = fs::read(&path).ok()?;
+        let expected_len = (params.width * params.height * 3) as usize;
+        let (header, data) = bytes.split_at_checked(MAGIC.len() + 8)?;
+        if &header[..4] != MAGIC
+            || header[4..] != key(params).to_le_bytes()
+            || data.len() != expected_len
+        {
+            return None;
+        }
+        // Refresh the modification time so garbage collection evicts least recently used
+        // entries first.
+        if let Ok(file) = fs::File::options().append(true).open(&path) {
+            let _ = file.set_modified(SystemTime::now());
+        }
+        Some(data.to_vec())
+    }
+
+    pub fn put(&self, params: &RenderParams, data: &[u8]) -> Result<(), String> {
+        let path = self.path(params);
+        let tmp = path.with_extension("tmp");
+        let mut file = fs::File::create(&tmp).map_err(|e| e.to_string())?;
+        file.write_all(MAGIC)
+            .and_then(|_| file.write_all(&key(params).to_le_bytes()))
+            .and_then(|_| file.write_all(data))
+            .map_err(|e| e.to_string())?;
+        fs::rename(&tmp, &path).map_err(|e| e.to_string())?;
+        self.gc()
+    }
+
+    // Deletes least recently used entries until the cache fits into `max_bytes`.
+    pub fn gc(&self) -> Result<(), String> {
+        let mut entries = fs::read_dir(&self.dir)
+            .map_err(|e| e.to_string())?
+            .filter_map(|entry| {
+                let entry = entry.ok()?;
+                let metadata = entry.metadata().ok()?;
+                let is_entry = entry.path().extension().is_some_and(|ext| ext == "bin");
+                (metadata.is_file() && is_entry).then(|| {
+                    let modified = metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH);
+                    (modified, metadata.len(), entry.path())
+                })
+            })
+            .collect::<Vec<_>>();
+        let mut total = entries.iter().map(|(_, len, _)| len).sum::<u64>();
+        entries.sort();
+        for (_, len, path) in entries {
+            if total <= self.max_bytes {
+                break;
+            }
+            fs::remove_file(&path).map_err(|e| e.to_string())?;
+            total -= len;
+        }
+        Ok(())
+    }
+
+    pub fn render(&self, params: RenderParams) -> Frame {
+        if let Some(data) = self.get(&params) {
+            println!("Using cached frame {:016x}", key(&params));
+            return Frame::from_rgb(params.width, params.height, data);
+        }
+        let stamp = Instant::now();
+        let frame = render::render_frame(params);
+        if stamp.elapsed() >= self.min_compute_time {
+            if let Err(e) = self.put(&params, &frame.rgb) {
+                println!("Failed to store frame in cache: {e}");
+            }
+        }
+        frame
+    }
+}
+
+// The hash of the params and of the switches of the whole run that change frames: the
+// subdivision, which can miss filaments thinner than a pixel, and the periodicity checking.
+pub fn key(params: &RenderParams) -> u64 {
+    Json::object([
+        ("params", params.to_json()),
+        ("subdivision", Json::Bool(subdivide::enabled())),
+        ("periodicity", Json::Bool(kernel::periodicity())),
+    ])
+    .content_hash()
+}
+
+// The frame of `params`, through the cache unless it is off.
+pub fn render(cache: Option<&RenderCache>, params: RenderParams) -> Frame {
+    match cache {
+        Some(cache) => cache.render(params),
+        None => render::render_frame(params),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_cache(name: &str, max_bytes: u64) -> RenderCache {
+        let dir = std::env::temp_dir().join(format!(
+            "mandelbrot-cache-test-{name}-{}",
+            std::process::id()
+        ));
+        let _ = fs::remove_dir_all(&dir);
+        RenderCache::new(dir, max_bytes).unwrap()
+    }
+
+    #[test]
+    fn put_then_get() {
+        let cache = temp_cache("roundtrip", u64::MAX);
+        let params = RenderParams::builder().size(4, 2).build().unwrap();
+        assert_eq!(cache.get(&params), None);
+        let data = (0..24).collect::<Vec<u8>>();
+        cache.put(&params, &data).unwrap();
+        assert_eq!(cache.get(&params), Some(data));
+        let other = params.to_builder().iterations(7).build().unwrap();
+        assert_eq!(cache.get(&other), None);
+        fs::remove_dir_all(&cache.dir).unwrap();
+    }
+
+    #[test]
+    fn gc_evicts_oldest_entries() {
+        let cache = temp_cache("gc", 2 * (12 + 24));
+        let frames = (1..=3)
+            .map(|i| {
+                RenderParams::builder()
+                    .size(4, 2)
+                    .iterations(i)
+                    .build()
+                    .unwrap()
+            })
+            .collect::<Vec<_>>();
+        for params in &frames {
+            cache.put(params, &[0; 24]).unwrap();
+            let file = fs::File::options()
+                .append(true)
+                .open(cache.path(params))
+                .unwrap();
+            // Spread modification times apart, filesystem timestamps can be coarse.
+            let age = Duration::from_secs(100 * (4 - params.iterations as u64));
+            file.set_modified(SystemTime::now() - age).unwrap();
+        }
+        cache.gc().unwrap();
+        assert_eq!(cache.get(&frames[0]), None);
+        assert!(cache.get(&frames[1]).is_some());
+        assert!(cache.get(&frames[2]).is_some());
+        fs::remove_dir_all(&cache.dir).unwrap();
+    }
+}
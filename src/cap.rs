@@ -0,0 +1,121 @@
+use crate::params::{Fractal, RenderParams};
+use crate::render;
+
+// Columns of the grid of points sampled across the view, its rows going by the aspect ratio.
+const COLUMNS: u32 = 64;
+// The probing cap as a multiple of the current one, and its bounds.
+const PROBE_FACTOR: u32 = 16;
+const MIN_PROBE: u32 = 10_000;
+const MAX_PROBE: u32 = 1 << 20;
+// The suggested cap clears the 99.9th percentile of the escape times by a quarter.
+const PERCENTILE: f64 = 99.9;
+const MARGIN: f64 = 1.25;
+const MIN_CAP: u32 = 50;
+pub const QUANTILES: [f64; 5] = [50.0, 90.0, 99.0, PERCENTILE, 100.0];
+
+// Escape times of a sparse grid over the view, iterated far past the current cap: how many
+// iterations the escaping points of this view actually take, unlike deepening blindly with the
+// zoom.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Suggestion {
+    pub sampled: usize,
+    pub probe: u32,
+    // The points escaping before the probing cap, by the iterations they take, ascending.
+    escapes: Vec<u32>,
+    // Those among them drawn as inside the set at the current cap.
+    pub missed: usize,
+    pub cap: Option<u32>,
+}
+
+impl Suggestion {
+    // Iterations taken by `percent` percent of the escaping points (nearest rank).
+    pub fn quantile(&self, percent: f64) -> Option<u32> {
+        let rank = (percent / 100.0 * self.escapes.len() as f64).ceil() as usize;
+        self.escapes
+            .get(rank.clamp(1, self.escapes.len().max(1)) - 1)
+            .copied()
+    }
+
+    pub fn escaped(&self) -> usize {
+        self.escapes.len()
+    }
+}
+
+pub fn suggest(params: &RenderParams) -> Result<Suggestion, String> {
+    if let Fractal::Buddhabrot { .. } = params.fractal {
+        return Err("The Buddhabrot draws orbits rather than escape times".to_string());
+    }
+    let rows = (COLUMNS as f64 * params.height as f64 / params.width as f64).round() as u32;
+    let probe = params
+        .iterations
+        .saturating_mul(PROBE_FACTOR)
+        .clamp(MIN_PROBE, MAX_PROBE);
+    let grid = params
+        .to_builder()
+        .size(COLUMNS, rows.max(1))
+        .iterations(probe)
+        .antialias(1)
+        .effect(None)
+        .bloom(None)
+        .build()?;
+    let samples = render::escape_time_frame(grid).samples;
+    let mut escapes = samples
+        .iter()
+        .filter_map(|sample| sample.escape.map(|i| i + 1))
+        .collect::<Vec<_>>();
+    escapes.sort_unstable();
+    let missed = escapes.iter().filter(|&&n| n > params.iterations).count();
+    let mut suggestion = Suggestion {
+        sampled: samples.len(),
+        probe,
+        escapes,
+        missed,
+        cap: None,
+    };
+    suggestion.cap = suggestion
+        .quantile(PERCENTILE)
+        .map(|n| ((n as f64 * MARGIN).ceil() as u32).max(MIN_CAP));
+    Ok(suggestion)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::viewport::Viewport;
+    use num::complex::Complex;
+
+    #[test]
+    fn suggests_a_cap_from_the_slowest_escapes() {
+        // Near the neck of the seahorse valley escapes take far more than 100 iterations.
+        let params = RenderParams::builder()
+            .viewport(Viewport::new(Complex::new(-0.7435, 0.1314), 0.004, 0.003))
+            .size(320, 240)
+            .iterations(100)
+            .build()
+            .unwrap();
+        let suggestion = suggest(&params).unwrap();
+        assert_eq!(suggestion.sampled, 64 * 48);
+        assert_eq!(suggestion.probe, MIN_PROBE);
+        assert!(suggestion.missed > 0);
+        let quantiles = QUANTILES.map(|percent| suggestion.quantile(percent).unwrap());
+        assert!(quantiles.windows(2).all(|pair| pair[0] <= pair[1]));
+        let cap = suggestion.cap.unwrap();
+        assert!(cap > 100 && cap <= MIN_PROBE * 5 / 4, "{cap}");
+        // Away from the set every point escapes at once.
+        let outside = RenderParams::builder()
+            .viewport(Viewport::new(Complex::new(3.0, 3.0), 0.4, 0.3))
+            .size(320, 240)
+            .build()
+            .unwrap();
+        let suggestion = suggest(&outside).unwrap();
+        assert_eq!(suggestion.escaped(), suggestion.sampled);
+        assert_eq!(suggestion.cap, Some(MIN_CAP));
+        let buddhabrot = RenderParams::builder()
+            .fractal(Fractal::Buddhabrot {
+                samples_per_pixel: 1,
+            })
+            .build()
+            .unwrap();
+        assert!(suggest(&buddhabrot).is_err());
+    }
+}
@@ -0,0 +1,94 @@
+use crate::params::Fractal;
+use num::complex::Complex;
+
+// Color of the pixels whose classification the floating point computation cannot vouch for.
+pub const UNCERTAIN: [u8; 3] = [255, 0, 255];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Certainty {
+    // The whole error ball left the bailout radius at this iteration.
+    Escaped(u32),
+    // The whole error ball stayed within the bailout radius for all the iterations.
+    Bounded,
+    Uncertain,
+}
+
+// Iterates the orbit as a ball: the computed value together with a bound of its distance from
+// the exact orbit of the exact point, grown by the rounding error of every operation.
+pub fn classify(fractal: Fractal, point: Complex<f64>, iterations: u32) -> Certainty {
+    // The rounding of an arbitrary formula is not bounded.
+    if let Fractal::Formula { .. } = fractal {
+        return Certainty::Uncertain;
+    }
+    let (mut z, c) = fractal.start(point);
+    // The point itself is only known up to the rounding of its pixel position.
+    let (mut r, rc) = match fractal {
+        Fractal::Julia { .. } => (f64::EPSILON * point.norm(), 0.0),
+        _ => (0.0, f64::EPSILON * point.norm()),
+    };
+    let d = fractal.degree() as i32;
+    let mut straddled = false;
+    for i in 0..iterations {
+        let norm = z.norm();
+        // A complex multiplication and an addition cost a few ulps each.
+        let rounding = 2.0 * d as f64 * f64::EPSILON * (norm.powi(d) + c.norm());
+        // |(z + e)^d - z^d| <= (|z| + r)^d - |z|^d, that is (2 |z| + r) r for squares.
+        let growth = match d {
+            2 => (2.0 * norm + r) * r,
+            _ => (norm + r).powi(d) - norm.powi(d),
+        };
+        r = growth + rc + rounding;
+        // Folding and conjugation move no two points further apart, the Burning Ship and the
+        // Tricorn keep the same bound.
+        z = fractal.step(z, c);
+        let norm = z.norm();
+        if norm - r > 2.0 {
+            return if straddled {
+                Certainty::Uncertain
+            } else {
+                Certainty::Escaped(i)
+            };
+        }
+        if norm + r > 2.0 {
+            // Some of the ball is outside already: whether and when it escapes is undecided.
+            straddled = true;
+        }
+        if norm > 2.0 {
+            return Certainty::Uncertain;
+        }
+    }
+    if straddled {
+        Certainty::Uncertain
+    } else {
+        Certainty::Bounded
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn classifies_clear_and_borderline_points() {
+        let mandelbrot = Fractal::MANDELBROT;
+        assert_eq!(
+            classify(mandelbrot, Complex::new(1.0, 1.0), 100),
+            Certainty::Escaped(1)
+        );
+        assert_eq!(
+            classify(mandelbrot, Complex::new(-0.1, 0.1), 1000),
+            Certainty::Bounded
+        );
+        // The tip of the antenna sits at the bailout radius forever.
+        assert_eq!(
+            classify(mandelbrot, Complex::new(-2.0, 0.0), 100),
+            Certainty::Uncertain
+        );
+        // On the chaotic part of the real axis the rounding errors grow until they decide
+        // nothing.
+        assert_eq!(
+            classify(mandelbrot, Complex::new(-1.9, 0.0), 10_000),
+            Certainty::Uncertain
+        );
+    }
+}
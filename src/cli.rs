@@ -0,0 +1,1139 @@
+use mandelbrot_explorer::buddhabrot::Orbits;
+use mandelbrot_explorer::config::Config;
+use mandelbrot_explorer::contact::ContactSheet;
+use mandelbrot_explorer::formula;
+use mandelbrot_explorer::gallery::Gallery;
+use mandelbrot_explorer::gamut::Gamut;
+use mandelbrot_explorer::kernel::Kernel;
+use mandelbrot_explorer::keys::KeyScheme;
+use mandelbrot_explorer::locale::Separator;
+use mandelbrot_explorer::location;
+use mandelbrot_explorer::orbit::OrbitBatch;
+use mandelbrot_explorer::pack::{Direction, Exchange};
+use mandelbrot_explorer::palette::{self, Palette};
+use mandelbrot_explorer::params::Fractal;
+use mandelbrot_explorer::poster::Poster;
+use mandelbrot_explorer::schedule::IterationSchedule;
+use mandelbrot_explorer::style::Theme;
+use mandelbrot_explorer::tiles::ScanOrder;
+use mandelbrot_explorer::tour::{Easing, Tour};
+use mandelbrot_explorer::video::{self, Interpolation, ZoomVideo};
+use mandelbrot_explorer::wallpaper::Wallpaper;
+use num::complex::Complex;
+use std::path::{Path, PathBuf};
+
+pub const USAGE: &str = "\
+usage: mandelbrot-explorer [OPTIONS] [VIEWPORT.json]
+       mandelbrot-explorer --headless [HEADLESS OPTIONS]
+       mandelbrot-explorer zoomvideo --center RE,IM --depth ZOOM [VIDEO OPTIONS]
+       mandelbrot-explorer tour BOOKMARKS.json | --from VIEW --to VIEW [TOUR OPTIONS]
+       mandelbrot-explorer contactsheet BOOKMARKS.json [CONTACT SHEET OPTIONS]
+       mandelbrot-explorer orbits POINTS.json [ORBIT OPTIONS]
+       mandelbrot-explorer bookmarks export|import PACK.json [PACK OPTIONS]
+       mandelbrot-explorer gallery build BOOKMARKS.json [GALLERY OPTIONS]
+       mandelbrot-explorer wallpaper [WALLPAPER OPTIONS]
+       mandelbrot-explorer doctor
+
+options:
+  --julia NAME         start with the Julia set of a famous constant
+  --z0 RE,IM           starting value of the orbits of the Mandelbrot set instead of 0,
+                       giving a generalized set
+  --list-julia         list the built-in Julia constants
+  --orbits MIN:MAX[,MIN:MAX,MIN:MAX]
+                       lengths of the orbits the Buddhabrot (U) accumulates, either bound
+                       left out for none; three ranges for the red, green and blue channels
+                       of a Nebulabrot, e.g. :5000,:500,:50
+  --fractal NAME       start with another fractal than the Mandelbrot set: burning-ship,
+                       tricorn or multibrot
+  --exponent D         exponent of the Multibrot sets (default 3)
+  --formula TEXT       start with the fractal of a step of z and c, such as \"z^3 + c*z + c\",
+                       with + - * / ^, i, exp, log, sqrt, sin, cos, conj and a second
+                       parameter p
+  --bookmarks FILE     bookmarks saved and recalled in the window (default bookmarks.json)
+  --palette NAME       start with a named palette or one of --palettes
+  --config FILE        defaults of the explorer, written by the setup of the first run
+                       (default config.json)
+  --no-setup           start without the setup while there is no configuration yet
+  --profile NAME       start with the antialiasing, iterations, kernel and motion quality of
+                       a profile: interactive, quality, poster or one of the profiles file
+  --profiles FILE      profiles of their own, instead of the profiles object of the config
+                       file or else profiles.json, if it exists
+  --width N            initial width of the window (default 800), which can be resized
+  --height N           initial height of the window (default 600)
+  --no-cache           do not read or write the on-disk render cache
+  --cache-dir DIR      directory of the render cache
+  --cache-size MB      maximal size of the render cache (default 256)
+  --super-resolution MB
+                       memory for rendering the view at a higher resolution while idle
+                       (default 64), 0 turns it off
+  --iterations-schedule FILE
+                       JSON iteration schedule followed while zooming, either a table of
+                       [zoom, iterations] points or {\"base\": N, \"per_octave\": N}
+  --auto-iterations BASE,PER_OCTAVE
+                       start with the automatic iteration count on, BASE iterations at the
+                       initial view and PER_OCTAVE more per zoom doubling (default 200,50)
+  --params FILE        render the image described by an export manifest (or render
+                       parameters) again, into the file given by --output
+  --output FILE        .png or .ppm image rendered with --params
+  --palettes FILE      JSON object of named palettes, each an array of five #rrggbb stops,
+                       cycled through with F after the built-in ones
+  --theme NAME         colors of the overlays: dark (default), light or high-contrast
+  --keys SCHEME        keys that pan the view: arrows (default) or vi, which adds hjkl
+  --decimal SEPARATOR  decimal separator of the coordinates shown and copied: point or comma,
+                       by default that of the locale
+  --gamut GAMUT        color space of the monitor the window is shown on: srgb (default),
+                       display-p3, or nine comma separated coefficients of a matrix from
+                       linear sRGB
+  --dive-screenshots N save a screenshot with its manifest every N zoom doublings of the
+                       dives started with Z, into dive-screenshots/
+  --kernel NAME        escape-time loop to use instead of the best one the CPU supports:
+                       scalar, neon, avx2 or avx512, e.g. to compare their speed
+  --no-periodicity     iterate interior points for the full iteration count instead of
+                       stopping once their orbit is found back on itself
+  --no-subdivision     compute every pixel instead of filling rectangles bordered by
+                       interior points
+  --scan-order ORDER   order of computing the missing tiles of a view, shown as they come:
+                       rows (default), hilbert, filling it in as compact patches, or
+                       error, coarse first and then the busiest tiles
+  --animated-noise     dither the Buddhabrot with new noise at every pass while it
+                       accumulates, instead of the same noise in every frame
+  --deterministic      bit-identical frames for the same parameters: no reduced quality
+                       while zooming, no tiles, no temporal contrast smoothing
+  -h, --help           print this help
+
+headless options, rendering one image without opening a window:
+  --width N            image width (default 1920)
+  --height N           image height (default 1080)
+  --center RE,IM       center of the view (default 0,0)
+  --zoom ZOOM          magnification over the initial view (default 1)
+  --iterations N       iteration count (default 1000)
+  --palette COLORS     five comma separated #rrggbb stops of the exterior gradient
+  --output FILE        .png or .ppm image (default mandelbrot.png)
+  --dump FILE          also write the escape results as an iteration dump
+
+video options:
+  --center RE,IM       point to zoom into
+  --depth ZOOM         final magnification, e.g. 1e12
+  --duration SECONDS   length of the video (default 30)
+  --fps N              frames per second (default 30)
+  --size WxH           video resolution (default 1280x720)
+  --iterations N       iteration count (default 1000)
+  --iterations-schedule FILE
+                       iteration count depending on the zoom, overrides --iterations
+  --antialias N        supersampling of the keyframes per axis (default 2)
+  --output PATH        .mp4/.mkv/.mov/.webm file encoded by ffmpeg, or a directory that
+                       receives PPM frames (default zoom.mp4)
+  --resume             continue an interrupted export into a frame directory, keeping the
+                       frames whose parameters still match
+  --preview            show the frames in a small window as they complete
+  --interpolation MODE how frames are derived from the keyframes: nearest, box (default),
+                       bilinear, bicubic, or render to render every frame directly
+  --shutter N          average N sub-frames spread over each frame interval (motion blur)
+  --events FILE        JSON list of timestamped zoom speed and hue changes, e.g.
+                       [{\"time\": 1.5, \"speed\": 2}, {\"time\": 3, \"hue\": 90}]
+
+tour options, flying through the bookmarks of a file in their order or between two views:
+  --from VIEW          start view, \"RE IM HEIGHT\" or any other shape goto reads
+  --to VIEW            end view
+  --iterations N       iteration count of --from and --to (default 1000); bookmarks have
+                       their own, followed from one to the next
+  --duration SECONDS   length of the video (default 20)
+  --fps N              frames per second (default 30)
+  --size WxH           video resolution (default 1280x720)
+  --easing NAME        speed along every leg between two keyframes: linear, in, out or
+                       in-out (default), slowing down into and out of every keyframe
+  --output PATH        .mp4/.mkv/.mov/.webm file encoded by ffmpeg, or a directory that
+                       receives PNG frames (default tour.mp4)
+
+contact sheet options:
+  --output FILE        .png image of the thumbnails (default contact-sheet.png)
+  --columns N          thumbnails per row (default 4)
+  --thumbnail WxH      size of a thumbnail (default 240x160)
+
+orbit options:
+  --iterations N       iterations of the bounded orbits (default 1000)
+  --julia RE,IM        orbits of the points in the Julia set of this constant instead of
+                       the critical orbits of the Mandelbrot set parameters
+  --output FILE        JSON array of the orbits, each of [re, im] pairs (default orbits.json)
+
+pack options, exporting a bookmarks file with thumbnails to share or merging a pack into one:
+  --bookmarks FILE     bookmarks file to export, or to add the bookmarks of the pack to that
+                       are not in it yet (default bookmarks.json)
+  --thumbnail WxH      size of the exported thumbnails (default 128x96)
+
+gallery options:
+  --output DIR         directory of the web page, index.html, with the images and their
+                       manifests (default gallery)
+  --size WxH           size of the images (default 640x480)
+
+wallpaper options, rendering a random interesting place:
+  --size WxH           image size (default the resolution of the desktop)
+  --iterations N       iteration count (default 2000)
+  --seed N             the same place and palette for the same seed instead of new ones
+  --output FILE        .png image (default wallpaper.png)
+  --set                set the image as the desktop background (GNOME, macOS or Windows),
+                       in builds with the wallpaper feature
+  --schedule FILE      write a crontab (or on Windows a schtasks command) rendering and
+                       setting a new wallpaper every day at 8:00";
+
+#[derive(Debug, PartialEq)]
+pub struct Options {
+    pub viewport_file: Option<String>,
+    pub julia: Option<String>,
+    pub z0: Complex<f64>,
+    pub orbits: Orbits,
+    pub fractal: Option<Fractal>,
+    pub exponent: u32,
+    pub list_julia: bool,
+    pub width: u32,
+    pub height: u32,
+    pub cache: bool,
+    pub cache_dir: Option<PathBuf>,
+    pub cache_size_mb: u64,
+    pub super_resolution_mb: u64,
+    pub bookmarks: PathBuf,
+    pub profile: Option<String>,
+    // The built-in gradient with `None`.
+    pub palette: Option<String>,
+    pub config: PathBuf,
+    pub setup: bool,
+    pub profiles: Option<PathBuf>,
+    pub iterations_schedule: Option<IterationSchedule>,
+    pub deterministic: bool,
+    pub palettes: Vec<(String, Palette)>,
+    pub theme: Theme,
+    pub keys: KeyScheme,
+    pub gamut: Gamut,
+    pub dive_screenshots: Option<f64>,
+    pub params: Option<PathBuf>,
+    pub output: Option<PathBuf>,
+    pub kernel: Option<Kernel>,
+    pub periodicity: bool,
+    pub subdivision: bool,
+    pub scan_order: ScanOrder,
+    pub animated_noise: bool,
+    // The decimal separator of the locale with `None`.
+    pub decimal: Option<Separator>,
+    pub help: bool,
+    // Set by the `zoomvideo` subcommand.
+    pub zoom_video: Option<ZoomVideo>,
+    // Set by the `tour` subcommand.
+    pub tour: Option<Tour>,
+    // Set by the `contactsheet` subcommand.
+    pub contact_sheet: Option<ContactSheet>,
+    // Set by the `orbits` subcommand.
+    pub orbit_batch: Option<OrbitBatch>,
+    // Set by the `bookmarks` subcommand.
+    pub exchange: Option<Exchange>,
+    // Set by the `gallery build` subcommand.
+    pub gallery: Option<Gallery>,
+    // Set by the `wallpaper` subcommand.
+    pub wallpaper: Option<Wallpaper>,
+    // Set by the `doctor` subcommand.
+    pub doctor: bool,
+    // Set by `--headless`.
+    pub poster: Option<Poster>,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Options {
+            viewport_file: None,
+            julia: None,
+            z0: Complex::new(0.0, 0.0),
+            orbits: Orbits::ALL,
+            fractal: None,
+            exponent: 3,
+            list_julia: false,
+            width: 800,
+            height: 600,
+            cache: true,
+            cache_dir: None,
+            cache_size_mb: 256,
+            super_resolution_mb: 64,
+            bookmarks: PathBuf::from("bookmarks.json"),
+            profile: None,
+            palette: None,
+            config: PathBuf::from("config.json"),
+            setup: true,
+            profiles: None,
+            iterations_schedule: None,
+            deterministic: false,
+            palettes: Vec::new(),
+            theme: Theme::Dark,
+            keys: KeyScheme::Arrows,
+            gamut: Gamut::Srgb,
+            dive_screenshots: None,
+            params: None,
+            output: None,
+            kernel: None,
+            periodicity: true,
+            subdivision: true,
+            scan_order: ScanOrder::Rows,
+            animated_noise: false,
+            decimal: None,
+            help: false,
+            zoom_video: None,
+            tour: None,
+            contact_sheet: None,
+            orbit_batch: None,
+            exchange: None,
+            gallery: None,
+            wallpaper: None,
+            doctor: false,
+            poster: None,
+        }
+    }
+}
+
+impl Options {
+    pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Options, String> {
+        Options::parse_over(Options::default(), args)
+    }
+
+    // The defaults of the configuration, which the flags override.
+    pub fn from_config(config: &Config) -> Options {
+        Options {
+            kernel: config.kernel,
+            palette: config.palette.clone(),
+            width: config.width,
+            height: config.height,
+            keys: config.keys,
+            ..Options::default()
+        }
+    }
+
+    // Whether the options open the explorer window rather than run a subcommand.
+    pub fn explorer(&self) -> bool {
+        let batch = self.zoom_video.is_some()
+            || self.tour.is_some()
+            || self.contact_sheet.is_some()
+            || self.orbit_batch.is_some()
+            || self.exchange.is_some()
+            || self.gallery.is_some()
+            || self.wallpaper.is_some()
+            || self.poster.is_some()
+            || self.params.is_some()
+            || self.doctor;
+        !(batch || self.help || self.list_julia)
+    }
+
+    pub fn parse_over(
+        mut options: Options,
+        args: impl IntoIterator<Item = String>,
+    ) -> Result<Options, String> {
+        let mut args = args.into_iter().peekable();
+        if args.peek().map(String::as_str) == Some("zoomvideo") {
+            args.next();
+            options.zoom_video = Some(parse_zoom_video(&mut options, args)?);
+            return Ok(options);
+        }
+        if args.peek().map(String::as_str) == Some("tour") {
+            args.next();
+            options.tour = Some(parse_tour(&mut options, args)?);
+            return Ok(options);
+        }
+        if args.peek().map(String::as_str) == Some("contactsheet") {
+            args.next();
+            options.contact_sheet = Some(parse_contact_sheet(&mut options, args)?);
+            return Ok(options);
+        }
+        if args.peek().map(String::as_str) == Some("--headless") {
+            args.next();
+            options.poster = Some(parse_poster(&mut options, args)?);
+            return Ok(options);
+        }
+        if args.peek().map(String::as_str) == Some("orbits") {
+            args.next();
+            options.orbit_batch = Some(parse_orbit_batch(&mut options, args)?);
+            return Ok(options);
+        }
+        if args.peek().map(String::as_str) == Some("bookmarks") {
+            args.next();
+            options.exchange = Some(parse_exchange(&mut options, args)?);
+            return Ok(options);
+        }
+        if args.peek().map(String::as_str) == Some("gallery") {
+            args.next();
+            options.gallery = Some(parse_gallery(&mut options, args)?);
+            return Ok(options);
+        }
+        if args.peek().map(String::as_str) == Some("wallpaper") {
+            args.next();
+            options.wallpaper = Some(parse_wallpaper(&mut options, args)?);
+            return Ok(options);
+        }
+        if args.peek().map(String::as_str) == Some("doctor") {
+            args.next();
+            if let Some(arg) = args.next() {
+                return Err(format!("Unknown doctor option {arg}"));
+            }
+            options.doctor = true;
+            return Ok(options);
+        }
+        let mut fractal = None;
+        while let Some(arg) = args.next() {
+            let mut value = |name: &str| {
+                args.next()
+                    .ok_or_else(|| format!("Missing value for {name}"))
+            };
+            match arg.as_str() {
+                "--julia" => options.julia = Some(value(&arg)?),
+                "--fractal" => fractal = Some(value(&arg)?),
+                "--formula" => options.fractal = Some(formula::fractal(&value(&arg)?)?),
+                "--exponent" => options.exponent = parse_number(&arg, &value(&arg)?)?,
+                "--z0" => {
+                    let (re, im) = parse_pair(&arg, &value(&arg)?, ',')?;
+                    options.z0 = Complex::new(re, im);
+                }
+                "--orbits" => options.orbits = Orbits::parse(&value(&arg)?)?,
+                "--list-julia" => options.list_julia = true,
+                "--width" => options.width = parse_number(&arg, &value(&arg)?)?,
+                "--height" => options.height = parse_number(&arg, &value(&arg)?)?,
+                "--no-cache" => options.cache = false,
+                "--no-periodicity" => options.periodicity = false,
+                "--no-subdivision" => options.subdivision = false,
+                "--animated-noise" => options.animated_noise = true,
+                "--deterministic" => options.deterministic = true,
+                "--theme" => {
+                    let name = value(&arg)?;
+                    options.theme =
+                        Theme::from_name(&name).ok_or_else(|| format!("Unknown theme {name}"))?
+                }
+                "--keys" => {
+                    let name = value(&arg)?;
+                    options.keys = KeyScheme::from_name(&name)
+                        .ok_or_else(|| format!("Unknown key scheme {name}"))?
+                }
+                "--decimal" => {
+                    let name = value(&arg)?;
+                    options.decimal = Some(
+                        Separator::from_name(&name)
+                            .ok_or_else(|| format!("Unknown decimal separator {name}"))?,
+                    );
+                }
+                "--palettes" => options.palettes = palette::load(Path::new(&value(&arg)?))?,
+                "--cache-dir" => options.cache_dir = Some(PathBuf::from(value(&arg)?)),
+                "--bookmarks" => options.bookmarks = PathBuf::from(value(&arg)?),
+                "--profile" => options.profile = Some(value(&arg)?),
+                "--palette" => options.palette = Some(value(&arg)?),
+                "--config" => options.config = PathBuf::from(value(&arg)?),
+                "--no-setup" => options.setup = false,
+                "--profiles" => options.profiles = Some(PathBuf::from(value(&arg)?)),
+                "--dive-screenshots" => {
+                    let every: f64 = value(&arg)?
+                        .parse()
+                        .map_err(|e| format!("Invalid {arg}: {e}"))?;
+                    if !every.is_finite() || every <= 0.0 {
+                        return Err(format!("Invalid {arg}: {every}"));
+                    }
+                    options.dive_screenshots = Some(every);
+                }
+                "--gamut" => options.gamut = Gamut::parse(&value(&arg)?)?,
+                "--params" => options.params = Some(PathBuf::from(value(&arg)?)),
+                "--output" => options.output = Some(PathBuf::from(value(&arg)?)),
+                "--kernel" => {
+                    let name = value(&arg)?;
+                    options.kernel = Some(
+                        Kernel::from_name(&name).ok_or_else(|| format!("Unknown kernel {name}"))?,
+                    )
+                }
+                "--scan-order" => {
+                    let name = value(&arg)?;
+                    options.scan_order = ScanOrder::from_name(&name)
+                        .ok_or_else(|| format!("Unknown scan order {name}"))?
+                }
+                "--cache-size" => {
+                    options.cache_size_mb = value(&arg)?
+                        .parse()
+                        .map_err(|e| format!("Invalid {arg}: {e}"))?
+                }
+                "--super-resolution" => {
+                    options.super_resolution_mb = value(&arg)?
+                        .parse()
+                        .map_err(|e| format!("Invalid {arg}: {e}"))?
+                }
+                "--iterations-schedule" => {
+                    options.iterations_schedule =
+                        Some(IterationSchedule::load(Path::new(&value(&arg)?))?)
+                }
+                "--auto-iterations" => {
+                    let (base, per_octave) = parse_pair(&arg, &value(&arg)?, ',')?;
+                    options.iterations_schedule = Some(IterationSchedule::Formula {
+                        base,
+                        per_octave,
+                        max: None,
+                    });
+                }
+                "-h" | "--help" => options.help = true,
+                flag if flag.starts_with('-') => return Err(format!("Unknown option {flag}")),
+                _ if options.viewport_file.is_none() => options.viewport_file = Some(arg),
+                _ => return Err(format!("Unexpected argument {arg}")),
+            }
+        }
+        if options.exponent < 2 {
+            return Err(format!("Invalid --exponent {}", options.exponent));
+        }
+        if let Some(name) = fractal {
+            if options.fractal.is_some() {
+                return Err("Use either --fractal or --formula".to_string());
+            }
+            options.fractal = Some(
+                Fractal::from_name(&name, options.exponent)
+                    .ok_or_else(|| format!("Unknown fractal {name}"))?,
+            );
+        }
+        if options.width == 0 || options.height == 0 {
+            return Err("The window needs a positive --width and --height".to_string());
+        }
+        Ok(options)
+    }
+}
+
+fn parse_number<T: std::str::FromStr>(name: &str, text: &str) -> Result<T, String>
+where
+    T::Err: std::fmt::Display,
+{
+    text.trim()
+        .parse()
+        .map_err(|e| format!("Invalid {name} {text}: {e}"))
+}
+
+fn parse_pair<T: std::str::FromStr>(
+    name: &str,
+    text: &str,
+    separator: char,
+) -> Result<(T, T), String>
+where
+    T::Err: std::fmt::Display,
+{
+    let (a, b) = text
+        .split_once(separator)
+        .ok_or_else(|| format!("Invalid {name} {text}, expected A{separator}B"))?;
+    Ok((parse_number(name, a)?, parse_number(name, b)?))
+}
+
+fn parse_zoom_video(
+    options: &mut Options,
+    mut args: impl Iterator<Item = String>,
+) -> Result<ZoomVideo, String> {
+    let mut job = ZoomVideo::default();
+    let (mut center, mut depth) = (false, false);
+    while let Some(arg) = args.next() {
+        let switch = match arg.as_str() {
+            "--resume" => Some(&mut job.resume),
+            "--preview" => Some(&mut job.preview),
+            _ => None,
+        };
+        if let Some(switch) = switch {
+            *switch = true;
+            continue;
+        }
+        let value = args
+            .next()
+            .ok_or_else(|| format!("Missing value for {arg}"));
+        match arg.as_str() {
+            "--center" => {
+                let (re, im) = parse_pair(&arg, &value?, ',')?;
+                job.center = Complex::new(re, im);
+                center = true;
+            }
+            "--depth" => {
+                job.depth = parse_number(&arg, &value?)?;
+                depth = true;
+            }
+            "--duration" => job.duration = parse_number(&arg, &value?)?,
+            "--fps" => job.fps = parse_number(&arg, &value?)?,
+            "--size" => (job.width, job.height) = parse_pair(&arg, &value?, 'x')?,
+            "--iterations" => job.iterations = parse_number(&arg, &value?)?,
+            "--iterations-schedule" => {
+                job.iterations_schedule = Some(IterationSchedule::load(Path::new(&value?))?)
+            }
+            "--antialias" => job.antialias = parse_number(&arg, &value?)?,
+            "--output" => job.output = PathBuf::from(value?),
+            "--events" => job.events = video::load_events(Path::new(&value?))?,
+            "--interpolation" => {
+                let name = value?;
+                job.interpolation = Interpolation::from_name(&name)
+                    .ok_or_else(|| format!("Unknown interpolation {name}"))?;
+            }
+            "--shutter" => job.shutter = parse_number(&arg, &value?)?,
+            "-h" | "--help" => {
+                options.help = true;
+                return Ok(job);
+            }
+            _ => return Err(format!("Unknown zoomvideo option {arg}")),
+        }
+    }
+    if !(center && depth) {
+        return Err("zoomvideo needs --center and --depth".to_string());
+    }
+    Ok(job)
+}
+
+fn parse_tour(
+    options: &mut Options,
+    mut args: impl Iterator<Item = String>,
+) -> Result<Tour, String> {
+    let mut job = Tour::default();
+    while let Some(arg) = args.next() {
+        if matches!(arg.as_str(), "-h" | "--help") {
+            options.help = true;
+            return Ok(job);
+        }
+        if !arg.starts_with('-') && job.bookmarks.is_none() {
+            job.bookmarks = Some(PathBuf::from(arg));
+            continue;
+        }
+        let value = args
+            .next()
+            .ok_or_else(|| format!("Missing value for {arg}"));
+        match arg.as_str() {
+            "--from" => job.from = Some(location::parse(&value?)?),
+            "--to" => job.to = Some(location::parse(&value?)?),
+            "--iterations" => job.iterations = parse_number(&arg, &value?)?,
+            "--duration" => job.duration = parse_number(&arg, &value?)?,
+            "--fps" => job.fps = parse_number(&arg, &value?)?,
+            "--size" => (job.width, job.height) = parse_pair(&arg, &value?, 'x')?,
+            "--easing" => {
+                let name = value?;
+                job.easing =
+                    Easing::from_name(&name).ok_or_else(|| format!("Unknown easing {name}"))?;
+            }
+            "--output" => job.output = PathBuf::from(value?),
+            _ => return Err(format!("Unknown tour option {arg}")),
+        }
+    }
+    let views = (job.from.is_some(), job.to.is_some());
+    match (job.bookmarks.is_some(), views) {
+        (true, (false, false)) | (false, (true, true)) => Ok(job),
+        _ => Err("tour needs a bookmarks file or both --from and --to".to_string()),
+    }
+}
+
+fn parse_contact_sheet(
+    options: &mut Options,
+    mut args: impl Iterator<Item = String>,
+) -> Result<ContactSheet, String> {
+    let mut job = ContactSheet::default();
+    let mut bookmarks = false;
+    while let Some(arg) = args.next() {
+        if matches!(arg.as_str(), "-h" | "--help") {
+            options.help = true;
+            return Ok(job);
+        }
+        if !arg.starts_with('-') && !bookmarks {
+            job.bookmarks = PathBuf::from(arg);
+            bookmarks = true;
+            continue;
+        }
+        let value = args
+            .next()
+            .ok_or_else(|| format!("Missing value for {arg}"));
+        match arg.as_str() {
+            "--output" => job.output = PathBuf::from(value?),
+            "--columns" => job.columns = parse_number(&arg, &value?)?,
+            "--thumbnail" => {
+                (job.thumbnail_width, job.thumbnail_height) = parse_pair(&arg, &value?, 'x')?
+            }
+            _ => return Err(format!("Unknown contactsheet option {arg}")),
+        }
+    }
+    if !bookmarks {
+        return Err("contactsheet needs a bookmarks file".to_string());
+    }
+    if job.columns == 0 || job.thumbnail_width == 0 || job.thumbnail_height == 0 {
+        return Err("contactsheet needs positive --columns and --thumbnail sizes".to_string());
+    }
+    Ok(job)
+}
+
+fn parse_poster(
+    options: &mut Options,
+    mut args: impl Iterator<Item = String>,
+) -> Result<Poster, String> {
+    let mut job = Poster::default();
+    while let Some(arg) = args.next() {
+        if matches!(arg.as_str(), "-h" | "--help") {
+            options.help = true;
+            return Ok(job);
+        }
+        let value = args
+            .next()
+            .ok_or_else(|| format!("Missing value for {arg}"));
+        match arg.as_str() {
+            "--width" => job.width = parse_number(&arg, &value?)?,
+            "--height" => job.height = parse_number(&arg, &value?)?,
+            "--center" => {
+                let (re, im) = parse_pair(&arg, &value?, ',')?;
+                job.center = Complex::new(re, im);
+            }
+            "--zoom" => job.zoom = parse_number(&arg, &value?)?,
+            "--iterations" => job.iterations = parse_number(&arg, &value?)?,
+            "--palette" => job.palette = Some(Palette::parse(&value?)?),
+            "--output" => job.output = PathBuf::from(value?),
+            "--dump" => job.dump = Some(PathBuf::from(value?)),
+            _ => return Err(format!("Unknown headless option {arg}")),
+        }
+    }
+    Ok(job)
+}
+
+fn parse_orbit_batch(
+    options: &mut Options,
+    mut args: impl Iterator<Item = String>,
+) -> Result<OrbitBatch, String> {
+    let mut job = OrbitBatch::default();
+    let mut points = false;
+    while let Some(arg) = args.next() {
+        if matches!(arg.as_str(), "-h" | "--help") {
+            options.help = true;
+            return Ok(job);
+        }
+        if !arg.starts_with('-') && !points {
+            job.points = PathBuf::from(arg);
+            points = true;
+            continue;
+        }
+        let value = args
+            .next()
+            .ok_or_else(|| format!("Missing value for {arg}"));
+        match arg.as_str() {
+            "--iterations" => job.iterations = parse_number(&arg, &value?)?,
+            "--julia" => {
+                let (re, im) = parse_pair(&arg, &value?, ',')?;
+                job.julia = Some(Complex::new(re, im));
+            }
+            "--output" => job.output = PathBuf::from(value?),
+            _ => return Err(format!("Unknown orbits option {arg}")),
+        }
+    }
+    if !points {
+        return Err("orbits needs a points file".to_string());
+    }
+    Ok(job)
+}
+
+fn parse_exchange(
+    options: &mut Options,
+    mut args: impl Iterator<Item = String>,
+) -> Result<Exchange, String> {
+    let direction = match args.next().as_deref() {
+        Some("export") => Direction::Export,
+        Some("import") => Direction::Import,
+        Some("-h" | "--help") => {
+            options.help = true;
+            return Ok(Exchange::default());
+        }
+        _ => return Err("Expected bookmarks export|import".to_string()),
+    };
+    let mut job = Exchange {
+        direction,
+        ..Exchange::default()
+    };
+    let mut pack = false;
+    while let Some(arg) = args.next() {
+        if matches!(arg.as_str(), "-h" | "--help") {
+            options.help = true;
+            return Ok(job);
+        }
+        if !arg.starts_with('-') && !pack {
+            job.pack = PathBuf::from(arg);
+            pack = true;
+            continue;
+        }
+        let value = args
+            .next()
+            .ok_or_else(|| format!("Missing value for {arg}"));
+        match arg.as_str() {
+            "--bookmarks" => job.bookmarks = PathBuf::from(value?),
+            "--thumbnail" => {
+                (job.thumbnail_width, job.thumbnail_height) = parse_pair(&arg, &value?, 'x')?
+            }
+            _ => return Err(format!("Unknown bookmarks option {arg}")),
+        }
+    }
+    if !pack {
+        return Err("bookmarks needs a pack file".to_string());
+    }
+    if job.thumbnail_width == 0 || job.thumbnail_height == 0 {
+        return Err("bookmarks needs a positive --thumbnail size".to_string());
+    }
+    Ok(job)
+}
+
+fn parse_gallery(
+    options: &mut Options,
+    mut args: impl Iterator<Item = String>,
+) -> Result<Gallery, String> {
+    let mut job = Gallery::default();
+    match args.next().as_deref() {
+        Some("build") => {}
+        Some("-h" | "--help") => {
+            options.help = true;
+            return Ok(job);
+        }
+        _ => return Err("Expected gallery build".to_string()),
+    }
+    let mut bookmarks = false;
+    while let Some(arg) = args.next() {
+        if matches!(arg.as_str(), "-h" | "--help") {
+            options.help = true;
+            return Ok(job);
+        }
+        if !arg.starts_with('-') && !bookmarks {
+            job.bookmarks = PathBuf::from(arg);
+            bookmarks = true;
+            continue;
+        }
+        let value = args
+            .next()
+            .ok_or_else(|| format!("Missing value for {arg}"));
+        match arg.as_str() {
+            "--output" => job.output = PathBuf::from(value?),
+            "--size" => (job.width, job.height) = parse_pair(&arg, &value?, 'x')?,
+            _ => return Err(format!("Unknown gallery option {arg}")),
+        }
+    }
+    if !bookmarks {
+        return Err("gallery build needs a bookmarks file".to_string());
+    }
+    if job.width == 0 || job.height == 0 {
+        return Err("gallery build needs a positive --size".to_string());
+    }
+    Ok(job)
+}
+
+fn parse_wallpaper(
+    options: &mut Options,
+    mut args: impl Iterator<Item = String>,
+) -> Result<Wallpaper, String> {
+    let mut job = Wallpaper::default();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-h" | "--help" => {
+                options.help = true;
+                return Ok(job);
+            }
+            "--set" => {
+                job.set = true;
+                continue;
+            }
+            _ => {}
+        }
+        let value = args
+            .next()
+            .ok_or_else(|| format!("Missing value for {arg}"));
+        match arg.as_str() {
+            "--size" => job.size = Some(parse_pair(&arg, &value?, 'x')?),
+            "--iterations" => job.iterations = parse_number(&arg, &value?)?,
+            "--seed" => job.seed = Some(parse_number(&arg, &value?)?),
+            "--output" => job.output = PathBuf::from(value?),
+            "--schedule" => job.schedule = Some(PathBuf::from(value?)),
+            _ => return Err(format!("Unknown wallpaper option {arg}")),
+        }
+    }
+    if job
+        .size
+        .is_some_and(|(width, height)| width == 0 || height == 0)
+    {
+        return Err("wallpaper needs a positive --size".to_string());
+    }
+    Ok(job)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Options, String> {
+        Options::parse(args.iter().map(|arg| arg.to_string()))
+    }
+
+    #[test]
+    fn defaults() {
+        assert_eq!(parse(&[]), Ok(Options::default()));
+    }
+
+    #[test]
+    fn starting_value() {
+        assert_eq!(
+            parse(&["--z0", "0.5,-0.25"]).unwrap().z0,
+            Complex::new(0.5, -0.25)
+        );
+        assert!(parse(&["--z0", "0.5"]).is_err());
+        assert_eq!(
+            parse(&["--orbits", "20:,:500,:50"])
+                .unwrap()
+                .orbits
+                .channels()[2]
+                .max,
+            50
+        );
+        assert!(parse(&["--orbits", "500:50"]).is_err());
+        assert!(parse(&["--orbits", ":5,:6"]).is_err());
+    }
+
+    #[test]
+    fn window_size() {
+        let options = parse(&["--width", "1920", "--height", "1080"]).unwrap();
+        assert_eq!((options.width, options.height), (1920, 1080));
+        assert!(parse(&["--width", "0"]).is_err());
+    }
+
+    #[test]
+    fn cache_options() {
+        let options =
+            parse(&["--no-cache", "--cache-dir", "/tmp/x", "--cache-size", "16"]).unwrap();
+        assert!(!options.cache);
+        assert_eq!(options.cache_dir, Some(PathBuf::from("/tmp/x")));
+        assert_eq!(options.cache_size_mb, 16);
+        let options = parse(&["--super-resolution", "0"]).unwrap();
+        assert_eq!(options.super_resolution_mb, 0);
+        let options = parse(&["--bookmarks", "spots.json"]).unwrap();
+        assert_eq!(options.bookmarks, PathBuf::from("spots.json"));
+        let options = parse(&["--profile", "poster", "--profiles", "mine.json"]).unwrap();
+        let config = Config {
+            kernel: Some(Kernel::Scalar),
+            palette: Some("fire".to_string()),
+            width: 1280,
+            height: 720,
+            keys: KeyScheme::Vi,
+        };
+        let args = ["--width", "640", "--config", "mine.json"].map(String::from);
+        let configured = Options::parse_over(Options::from_config(&config), args).unwrap();
+        assert_eq!((configured.width, configured.height), (640, 720));
+        assert_eq!(configured.kernel, Some(Kernel::Scalar));
+        assert_eq!(configured.palette.as_deref(), Some("fire"));
+        assert_eq!(configured.keys, KeyScheme::Vi);
+        assert_eq!(parse(&["--keys", "vi"]).unwrap().keys, KeyScheme::Vi);
+        assert!(parse(&["--keys", "emacs"]).is_err());
+        assert_eq!(configured.config, PathBuf::from("mine.json"));
+        assert!(!parse(&["--no-setup"]).unwrap().setup);
+        assert!(configured.explorer());
+        assert!(!parse(&["contactsheet", "saved.json"]).unwrap().explorer());
+        // Diagnosing a machine without a display must not open the setup first.
+        assert!(!parse(&["doctor"]).unwrap().explorer());
+        assert_eq!(options.profile.as_deref(), Some("poster"));
+        assert_eq!(options.profiles, Some(PathBuf::from("mine.json")));
+        assert!(parse(&["--super-resolution", "lots"]).is_err());
+        assert!(parse(&["--deterministic"]).unwrap().deterministic);
+        assert!(!parse(&["--no-periodicity"]).unwrap().periodicity);
+        assert!(!parse(&["--no-subdivision"]).unwrap().subdivision);
+        assert!(parse(&["--animated-noise"]).unwrap().animated_noise);
+        assert_eq!(
+            parse(&["--theme", "high-contrast"]).unwrap().theme,
+            Theme::HighContrast
+        );
+        assert!(parse(&["--theme", "sepia"]).is_err());
+        assert_eq!(
+            parse(&["--decimal", "comma"]).unwrap().decimal,
+            Some(Separator::Comma)
+        );
+        assert!(parse(&["--decimal", "semicolon"]).is_err());
+        assert_eq!(
+            parse(&["--kernel", "scalar"]).unwrap().kernel,
+            Some(Kernel::Scalar)
+        );
+        assert!(parse(&["--kernel", "mmx"]).is_err());
+        assert_eq!(
+            parse(&["--scan-order", "hilbert"]).unwrap().scan_order,
+            ScanOrder::Hilbert
+        );
+        assert_eq!(
+            parse(&["--scan-order", "error"]).unwrap().scan_order,
+            ScanOrder::Error
+        );
+        assert!(parse(&["--scan-order", "spiral"]).is_err());
+        assert_eq!(
+            parse(&["--exponent", "5", "--fractal", "multibrot"])
+                .unwrap()
+                .fractal,
+            Some(Fractal::Multibrot { exponent: 5 })
+        );
+        assert!(parse(&["--fractal", "multibrot", "--exponent", "1"]).is_err());
+        assert!(parse(&["--fractal", "newton"]).is_err());
+        let formula = parse(&["--formula", "z^3 + c*z + c"]).unwrap().fractal;
+        assert_eq!(formula, Some(formula::fractal("z^3 + c*z + c").unwrap()));
+        assert!(parse(&["--formula", "z^ + c"]).is_err());
+        assert!(parse(&["--formula", "z*z + c", "--fractal", "tricorn"]).is_err());
+        assert_eq!(
+            parse(&["--auto-iterations", "100,80"])
+                .unwrap()
+                .iterations_schedule,
+            Some(IterationSchedule::Formula {
+                base: 100.0,
+                per_octave: 80.0,
+                max: None
+            })
+        );
+        assert_eq!(
+            parse(&["--gamut", "display-p3"]).unwrap().gamut,
+            Gamut::DisplayP3
+        );
+    }
+
+    #[test]
+    fn rejects_bad_arguments() {
+        assert!(parse(&["--cache-size"]).is_err());
+        assert!(parse(&["--cache-size", "lots"]).is_err());
+        assert!(parse(&["--frobnicate"]).is_err());
+        assert!(parse(&["a.json", "b.json"]).is_err());
+    }
+
+    #[test]
+    fn zoom_video_subcommand() {
+        let options = parse(&[
+            "zoomvideo",
+            "--center",
+            "-0.75,0.1",
+            "--depth",
+            "1e9",
+            "--size",
+            "640x360",
+            "--output",
+            "frames",
+            "--interpolation",
+            "bicubic",
+        ])
+        .unwrap();
+        let job = options.zoom_video.unwrap();
+        assert_eq!(job.center, Complex::new(-0.75, 0.1));
+        assert_eq!(job.depth, 1e9);
+        assert_eq!((job.width, job.height), (640, 360));
+        assert_eq!(job.output, PathBuf::from("frames"));
+        assert_eq!(job.interpolation, Interpolation::Bicubic);
+        assert_eq!(job.fps, ZoomVideo::default().fps);
+        assert!(parse(&["zoomvideo", "--depth", "1e9"]).is_err());
+        assert!(parse(&["zoomvideo", "--center", "1;2", "--depth", "9"]).is_err());
+    }
+
+    #[test]
+    fn tour_subcommand() {
+        let options = parse(&[
+            "tour",
+            "saved.json",
+            "--easing",
+            "linear",
+            "--size",
+            "640x360",
+        ]);
+        let job = options.unwrap().tour.unwrap();
+        assert_eq!(job.bookmarks, Some(PathBuf::from("saved.json")));
+        assert_eq!(job.easing, Easing::Linear);
+        assert_eq!((job.width, job.height), (640, 360));
+        let options = parse(&["tour", "--from", "-0.5 0 3", "--to", "-0.75 0.1 1e-3"]);
+        let job = options.unwrap().tour.unwrap();
+        assert_eq!(job.to.unwrap().height, Some(1e-3));
+        assert_eq!(job.output, PathBuf::from("tour.mp4"));
+        assert!(parse(&["tour", "--from", "-0.5 0 3"]).is_err());
+        assert!(parse(&["tour", "saved.json", "--to", "0 0 1"]).is_err());
+        assert!(parse(&["tour", "saved.json", "--easing", "bounce"]).is_err());
+    }
+
+    #[test]
+    fn contact_sheet_subcommand() {
+        let options = parse(&[
+            "contactsheet",
+            "saved.json",
+            "--columns",
+            "6",
+            "--thumbnail",
+            "120x80",
+        ])
+        .unwrap();
+        let job = options.contact_sheet.unwrap();
+        assert_eq!(job.bookmarks, PathBuf::from("saved.json"));
+        assert_eq!(
+            (job.columns, job.thumbnail_width, job.thumbnail_height),
+            (6, 120, 80)
+        );
+        assert_eq!(job.output, ContactSheet::default().output);
+        assert!(parse(&["contactsheet"]).is_err());
+        assert!(parse(&["contactsheet", "saved.json", "--columns", "0"]).is_err());
+    }
+
+    #[test]
+    fn bookmarks_subcommand() {
+        let options = parse(&[
+            "bookmarks",
+            "import",
+            "shared.json",
+            "--bookmarks",
+            "mine.json",
+        ]);
+        let job = options.unwrap().exchange.unwrap();
+        assert_eq!(job.direction, Direction::Import);
+        assert_eq!(job.pack, PathBuf::from("shared.json"));
+        assert_eq!(job.bookmarks, PathBuf::from("mine.json"));
+        let options = parse(&["bookmarks", "export", "out.json", "--thumbnail", "64x48"]);
+        let job = options.unwrap().exchange.unwrap();
+        assert_eq!(job.direction, Direction::Export);
+        assert_eq!((job.thumbnail_width, job.thumbnail_height), (64, 48));
+        assert!(parse(&["bookmarks", "share", "out.json"]).is_err());
+        assert!(parse(&["bookmarks", "export"]).is_err());
+    }
+
+    #[test]
+    fn gallery_subcommand() {
+        let options = parse(&["gallery", "build", "saved.json", "--size", "320x200"]);
+        let job = options.unwrap().gallery.unwrap();
+        assert_eq!(job.bookmarks, PathBuf::from("saved.json"));
+        assert_eq!((job.width, job.height), (320, 200));
+        assert_eq!(job.output, Gallery::default().output);
+        assert!(parse(&["gallery", "saved.json"]).is_err());
+        assert!(parse(&["gallery", "build"]).is_err());
+    }
+
+    #[test]
+    fn wallpaper_subcommand() {
+        let options = parse(&["wallpaper", "--seed", "7", "--set", "--size", "2560x1440"]);
+        let job = options.unwrap().wallpaper.unwrap();
+        assert_eq!((job.seed, job.set), (Some(7), true));
+        assert_eq!(job.size, Some((2560, 1440)));
+        assert_eq!(parse(&["wallpaper"]).unwrap().wallpaper.unwrap().size, None);
+        assert!(parse(&["wallpaper", "--size", "0x0"]).is_err());
+    }
+
+    #[test]
+    fn doctor_subcommand() {
+        assert!(parse(&["doctor"]).unwrap().doctor);
+        assert!(!parse(&[]).unwrap().doctor);
+        assert!(parse(&["doctor", "--verbose"]).is_err());
+    }
+
+    #[test]
+    fn headless_mode() {
+        let options = parse(&[
+            "--headless",
+            "--width",
+            "640",
+            "--center",
+            "-0.75,0.1",
+            "--zoom",
+            "1e3",
+            "--output",
+            "poster.png",
+            "--dump",
+            "poster.mbd",
+        ])
+        .unwrap();
+        let job = options.poster.unwrap();
+        assert_eq!(job.dump, Some(PathBuf::from("poster.mbd")));
+        assert_eq!((job.width, job.height), (640, Poster::default().height));
+        assert_eq!((job.center, job.zoom), (Complex::new(-0.75, 0.1), 1e3));
+        assert_eq!(job.output, PathBuf::from("poster.png"));
+        assert!(parse(&["--headless", "--palette", "#ffffff"]).is_err());
+        assert!(parse(&["--headless", "--depth", "2"]).is_err());
+        assert_eq!(parse(&[]).unwrap().poster, None);
+    }
+
+    #[test]
+    fn orbits_subcommand() {
+        let options = parse(&["orbits", "points.json", "--julia", "-0.8,0.156"]).unwrap();
+        let job = options.orbit_batch.unwrap();
+        assert_eq!(job.julia, Some(Complex::new(-0.8, 0.156)));
+        assert_eq!(job.iterations, OrbitBatch::default().iterations);
+        assert!(parse(&["orbits", "--iterations", "10"]).is_err());
+    }
+}
@@ -0,0 +1,74 @@
+use std::time::{Duration, Instant};
+
+// Input that arrives closer together than this is rendered once, for the view it ends at.
+const QUIET: Duration = Duration::from_millis(40);
+// Longest a stream of input holds the render back, so a held key still shows progress.
+const MAX_DELAY: Duration = Duration::from_millis(150);
+
+// Coalesces bursts of navigation input (wheel flicks, key repeats): every event changes the
+// view at once, but the render waits until the input pauses.
+#[derive(Default)]
+pub struct Coalescer {
+    // First and last input since the previous render.
+    pending: Option<(Instant, Instant)>,
+    events: u32,
+}
+
+impl Coalescer {
+    pub fn input(&mut self, now: Instant) {
+        let first = self.pending.map_or(now, |(first, _)| first);
+        self.pending = Some((first, now));
+        self.events += 1;
+    }
+
+    // Whether a render wanted now should start rather than wait for more input.
+    pub fn ready(&self, now: Instant) -> bool {
+        match self.pending {
+            None => true,
+            Some((first, last)) => now - last >= QUIET || now - first >= MAX_DELAY,
+        }
+    }
+
+    // Wait before the render is ready.
+    pub fn wait(&self, now: Instant) -> Duration {
+        match self.pending {
+            None => Duration::ZERO,
+            Some((first, last)) => (last + QUIET)
+                .min(first + MAX_DELAY)
+                .saturating_duration_since(now),
+        }
+    }
+
+    // Starts the render, returning the number of input events it covers.
+    pub fn rendered(&mut self) -> u32 {
+        self.pending = None;
+        std::mem::take(&mut self.events)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bursts_render_once() {
+        let start = Instant::now();
+        let at = |ms: u64| start + Duration::from_millis(ms);
+        let mut coalescer = Coalescer::default();
+        assert!(coalescer.ready(at(0)));
+        for ms in [0, 10, 20, 30] {
+            coalescer.input(at(ms));
+            assert!(!coalescer.ready(at(ms)));
+        }
+        assert_eq!(coalescer.wait(at(30)), QUIET);
+        assert!(coalescer.ready(at(70)));
+        assert_eq!(coalescer.rendered(), 4);
+        assert!(coalescer.ready(at(70)));
+        // A continuous stream is rendered at least every MAX_DELAY.
+        for ms in (100..=250).step_by(20) {
+            coalescer.input(at(ms));
+        }
+        assert!(coalescer.ready(at(250)));
+        assert_eq!(coalescer.wait(at(250)), Duration::ZERO);
+    }
+}
@@ -0,0 +1,138 @@
+use crate::json::{FromJson, Json, ToJson};
+use crate::kernel::Kernel;
+use crate::keys::KeyScheme;
+use crate::params::Fractal;
+use crate::viewport::Viewport;
+use num::complex::Complex;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+// Defaults of the explorer, written by the setup of the first run. Flags given on the command
+// line take precedence.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Config {
+    // Detected with `None`.
+    pub kernel: Option<Kernel>,
+    // A named palette or one of --palettes, the built-in gradient with `None`.
+    pub palette: Option<String>,
+    pub width: u32,
+    pub height: u32,
+    pub keys: KeyScheme,
+}
+
+impl ToJson for Config {
+    fn to_json(&self) -> Json {
+        let name = |name: Option<&str>| name.map_or(Json::Null, |name| Json::String(name.into()));
+        Json::object([
+            ("kernel", name(self.kernel.as_ref().map(Kernel::name))),
+            ("palette", name(self.palette.as_deref())),
+            ("width", Json::Number(self.width as f64)),
+            ("height", Json::Number(self.height as f64)),
+            ("keys", Json::String(self.keys.name().into())),
+        ])
+    }
+}
+
+impl FromJson for Config {
+    fn from_json(json: &Json) -> Result<Self, String> {
+        let name = |key: &str| match json.get(key) {
+            None | Some(Json::Null) => Ok(None),
+            Some(Json::String(name)) => Ok(Some(name.clone())),
+            Some(other) => Err(format!("Expected a name for {key}, got {other}")),
+        };
+        let kernel = match name("kernel")? {
+            Some(name) => {
+                Some(Kernel::from_name(&name).ok_or_else(|| format!("Unknown kernel {name}"))?)
+            }
+            None => None,
+        };
+        let size = |key: &str| match json.field(key)?.as_f64()? {
+            n if n >= 1.0 && n <= u32::MAX as f64 => Ok(n as u32),
+            n => Err(format!("Invalid {key} {n}")),
+        };
+        // The arrows in files written before there was a choice.
+        let keys = match name("keys")? {
+            Some(name) => {
+                KeyScheme::from_name(&name).ok_or_else(|| format!("Unknown key scheme {name}"))?
+            }
+            None => KeyScheme::Arrows,
+        };
+        Ok(Config {
+            kernel,
+            palette: name("palette")?,
+            width: size("width")?,
+            height: size("height")?,
+            keys,
+        })
+    }
+}
+
+// The configuration in the file, `None` while there is none yet.
+pub fn load(path: &Path) -> Result<Option<Config>, String> {
+    if !path.exists() {
+        return Ok(None);
+    }
+    let text = std::fs::read_to_string(path).map_err(|e| format!("{}: {e}", path.display()))?;
+    Config::from_json(&Json::parse(&text)?)
+        .map(Some)
+        .map_err(|e| format!("{}: {e}", path.display()))
+}
+
+pub fn save(path: &Path, config: &Config) -> Result<(), String> {
+    std::fs::write(path, config.to_json().to_string())
+        .map_err(|e| format!("{}: {e}", path.display()))
+}
+
+// Time each kernel the CPU supports takes for the same points, the fastest first.
+pub fn benchmark() -> Vec<(Kernel, Duration)> {
+    const COLUMNS: u32 = 256;
+    const ROWS: u32 = 192;
+    // Near the seahorse valley, outside the main cardioid that the scalar loop answers
+    // without iterating.
+    let viewport = Viewport::new(Complex::new(-0.7435, 0.1314), 0.004, 0.003);
+    let points = (0..COLUMNS * ROWS)
+        .map(|i| {
+            let (x, y) = ((i % COLUMNS) as f64, (i / COLUMNS) as f64);
+            viewport.pixel_to_complex(x, y, (COLUMNS, ROWS))
+        })
+        .collect::<Vec<_>>();
+    let mut timings = Kernel::ALL
+        .into_iter()
+        .filter(Kernel::supported)
+        .map(|kernel| {
+            let stamp = Instant::now();
+            kernel.escape_time(Fractal::MANDELBROT, &points, 500);
+            (kernel, stamp.elapsed())
+        })
+        .collect::<Vec<_>>();
+    timings.sort_by_key(|&(_, elapsed)| elapsed);
+    timings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trips_through_the_file() {
+        let path = std::env::temp_dir().join(format!("config-{}.json", std::process::id()));
+        assert_eq!(load(&path), Ok(None));
+        let config = Config {
+            kernel: Some(Kernel::Scalar),
+            palette: Some("fire".to_string()),
+            width: 1280,
+            height: 720,
+            keys: KeyScheme::Vi,
+        };
+        save(&path, &config).unwrap();
+        assert_eq!(load(&path), Ok(Some(config)));
+        std::fs::write(&path, r#"{"width": 800, "height": 600}"#).unwrap();
+        assert_eq!(load(&path).unwrap().unwrap().keys, KeyScheme::Arrows);
+        std::fs::write(&path, r#"{"kernel": "sse9", "width": 800, "height": 600}"#).unwrap();
+        assert!(load(&path).unwrap_err().contains("sse9"));
+        std::fs::remove_file(&path).unwrap();
+        let timings = benchmark();
+        assert!(timings.iter().any(|&(kernel, _)| kernel == Kernel::Scalar));
+        assert!(timings.windows(2).all(|pair| pair[0].1 <= pair[1].1));
+    }
+}
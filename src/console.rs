@@ -0,0 +1,592 @@
+use mandelbrot_explorer::annotate::{Annotation, Edit};
+use mandelbrot_explorer::buddhabrot::Orbits;
+use mandelbrot_explorer::formula;
+use mandelbrot_explorer::locale;
+use mandelbrot_explorer::location::{self, Location};
+use mandelbrot_explorer::nudge::Nudge;
+use mandelbrot_explorer::params::{Coloring, Fractal};
+use mandelbrot_explorer::render::Frame;
+use mandelbrot_explorer::style::{Style, Theme};
+use mandelbrot_explorer::text;
+use num::complex::Complex;
+use std::path::{Path, PathBuf};
+
+// Output lines kept for display.
+const SCROLLBACK: usize = 8;
+// Nesting limit of `source`, which would otherwise loop on scripts sourcing themselves.
+const MAX_SOURCE_DEPTH: usize = 8;
+const SCALE: u32 = 2;
+
+// Effect of a console command on the explorer, applied by the main loop.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Action {
+    SetIterations(u32),
+    SetAntialias(u32),
+    SetExposure(f32),
+    SetTheme(Theme),
+    SetColoring(Coloring),
+    // The interior colored like the exterior with `None`.
+    SetInterior(Option<Coloring>),
+    // Starting value of the orbits of the Mandelbrot set.
+    SetZ0(Complex<f64>),
+    // Which critical point the orbits of a formula start from.
+    SetCritical(u32),
+    // The second parameter of a formula in the plane of c.
+    SetP(Complex<f64>),
+    // Lengths of the orbits the Buddhabrot accumulates.
+    SetOrbits(Orbits),
+    // The fractal of a custom formula.
+    SetFractal(Fractal),
+    // A palette of `F` by name, `built-in` for the gradient, and the palettes of a file added
+    // to them.
+    SetPalette(String),
+    LoadPalettes(PathBuf),
+    // View centered at the location, with the given height of the complex plane.
+    Goto(Box<Location>),
+    // The annotations are drawn into the exported frame if `annotated`. With `manifest` the
+    // frame is rendered again from its parameters, which are written next to it.
+    Export {
+        path: PathBuf,
+        annotated: bool,
+        manifest: bool,
+    },
+    Annotations(Edit),
+    // The last frame replaced by its difference to a render with the parameter nudged.
+    Nudge(Nudge),
+    // The importance map learned by the last Buddhabrot render written as a PGM image, or one
+    // read to warm-start the next renders, or none used.
+    SaveImportance(PathBuf),
+    LoadImportance(PathBuf),
+    ClearImportance,
+    // The view saved as a bookmark of the name, replacing one of that name, and the bookmarks
+    // recalled, deleted by name or number, or listed.
+    SaveBookmark(String),
+    GotoBookmark(String),
+    DeleteBookmark(String),
+    ListBookmarks,
+    // The view sampled for the iteration cap it needs, which is set with `apply`.
+    SuggestCap {
+        apply: bool,
+    },
+    Print(String),
+}
+
+pub struct Command {
+    pub name: &'static str,
+    pub usage: &'static str,
+    // Completions of the first argument.
+    pub keywords: &'static [&'static str],
+    run: fn(&[&str]) -> Result<Vec<Action>, String>,
+}
+
+pub const COMMANDS: &[Command] = &[
+    Command {
+        name: "set",
+        usage: "set iterations N | antialias N | exposure X | theme NAME | coloring NAME [ARGS] \
+                | interior NAME [ARGS] | interior same | z0 RE IM | critical N \
+                | p RE IM | orbits MIN:MAX[,..]",
+        keywords: &[
+            "iterations",
+            "antialias",
+            "exposure",
+            "theme",
+            "coloring",
+            "interior",
+            "z0",
+            "critical",
+            "p",
+            "orbits",
+        ],
+        run: |args| match args {
+            ["iterations", n] => match parse(n)? {
+                0 => Err("Iteration count must be positive".to_string()),
+                n => Ok(vec![Action::SetIterations(n)]),
+            },
+            ["antialias", n] => match parse(n)? {
+                n @ 1..=8 => Ok(vec![Action::SetAntialias(n)]),
+                n => Err(format!("Anti-aliasing {n} outside 1 to 8")),
+            },
+            ["exposure", x] => match parse::<f32>(x)? {
+                x if x.is_finite() && x > 0.0 => Ok(vec![Action::SetExposure(x)]),
+                x => Err(format!("Exposure {x} must be positive")),
+            },
+            ["theme", name] => match Theme::from_name(name) {
+                Some(theme) => Ok(vec![Action::SetTheme(theme)]),
+                None => Err(format!(
+                    "Unknown theme {name}, expected dark|light|high-contrast"
+                )),
+            },
+            ["coloring", coloring @ ..] => {
+                Ok(vec![Action::SetColoring(Coloring::parse(coloring)?)])
+            }
+            ["interior", "same"] => Ok(vec![Action::SetInterior(None)]),
+            ["interior", coloring @ ..] => {
+                Ok(vec![Action::SetInterior(Some(Coloring::parse(coloring)?))])
+            }
+            ["z0", re, im] => Ok(vec![Action::SetZ0(Complex::new(parse(re)?, parse(im)?))]),
+            ["critical", n] => Ok(vec![Action::SetCritical(parse(n)?)]),
+            ["p", re, im] => Ok(vec![Action::SetP(Complex::new(parse(re)?, parse(im)?))]),
+            ["orbits", orbits] => Ok(vec![Action::SetOrbits(Orbits::parse(orbits)?)]),
+            _ => Err(
+                "Expected set iterations|antialias|exposure|theme|coloring|interior|z0|critical\
+                 |p|orbits VALUE"
+                    .to_string(),
+            ),
+        },
+    },
+    Command {
+        name: "nudge",
+        usage: "nudge z0 DRE DIM | c DRE DIM | iterations N",
+        keywords: &["z0", "c", "iterations"],
+        run: |args| match args {
+            ["z0", re, im] => Ok(vec![Action::Nudge(Nudge::Z0(Complex::new(
+                parse(re)?,
+                parse(im)?,
+            )))]),
+            ["c", re, im] => Ok(vec![Action::Nudge(Nudge::C(Complex::new(
+                parse(re)?,
+                parse(im)?,
+            )))]),
+            ["iterations", n] => Ok(vec![Action::Nudge(Nudge::Iterations(parse(n)?))]),
+            _ => Err("Expected nudge z0 DRE DIM|c DRE DIM|iterations N".to_string()),
+        },
+    },
+    Command {
+        name: "goto",
+        usage: "goto RE IM [HEIGHT] | RE + IM i",
+        keywords: &[],
+        // Coordinates as pasted in any of the shapes of `location::parse`.
+        run: |args| {
+            let location = location::parse(&args.join(" "))?;
+            Ok(vec![Action::Goto(Box::new(Location {
+                height: Some(location.height.unwrap_or(3.0)),
+                ..location
+            }))])
+        },
+    },
+    Command {
+        name: "export",
+        usage: "export FILE.png|FILE.ppm [annotated | manifest]",
+        keywords: &[],
+        run: |args| match args {
+            [path] | [path, "annotated" | "manifest"] => Ok(vec![Action::Export {
+                path: PathBuf::from(path),
+                annotated: args.get(1) == Some(&"annotated"),
+                manifest: args.get(1) == Some(&"manifest"),
+            }]),
+            _ => Err("Expected export FILE [annotated|manifest]".to_string()),
+        },
+    },
+    Command {
+        name: "label",
+        usage: "label RE IM TEXT",
+        keywords: &[],
+        run: |args| match args {
+            [re, im, text @ ..] if !text.is_empty() => {
+                Ok(vec![Action::Annotations(Edit::Add(Annotation::Label {
+                    at: Complex::new(parse(re)?, parse(im)?),
+                    text: text.join(" "),
+                }))])
+            }
+            _ => Err("Expected label RE IM TEXT".to_string()),
+        },
+    },
+    Command {
+        name: "annotations",
+        usage: "annotations undo | clear | save FILE | load FILE",
+        keywords: &["undo", "clear", "save", "load"],
+        run: |args| {
+            let edit = match args {
+                ["undo"] => Edit::Undo,
+                ["clear"] => Edit::Clear,
+                ["save", path] => Edit::Save(PathBuf::from(path)),
+                ["load", path] => Edit::Load(PathBuf::from(path)),
+                _ => return Err("Expected annotations undo|clear|save FILE|load FILE".to_string()),
+            };
+            Ok(vec![Action::Annotations(edit)])
+        },
+    },
+    Command {
+        name: "formula",
+        usage: "formula TEXT",
+        keywords: &[],
+        run: |args| match args {
+            [] => Err("Expected formula TEXT".to_string()),
+            _ => Ok(vec![Action::SetFractal(formula::fractal(&args.join(" "))?)]),
+        },
+    },
+    Command {
+        name: "importance",
+        usage: "importance save FILE.pgm | load FILE.pgm | clear",
+        keywords: &["save", "load", "clear"],
+        run: |args| match args {
+            ["save", path] => Ok(vec![Action::SaveImportance(PathBuf::from(path))]),
+            ["load", path] => Ok(vec![Action::LoadImportance(PathBuf::from(path))]),
+            ["clear"] => Ok(vec![Action::ClearImportance]),
+            _ => Err("Expected importance save FILE|load FILE|clear".to_string()),
+        },
+    },
+    Command {
+        name: "palette",
+        usage: "palette NAME | built-in | load FILE",
+        keywords: &["load", "built-in", "fire", "ocean", "grayscale", "rainbow"],
+        run: |args| match args {
+            ["load", path] => Ok(vec![Action::LoadPalettes(PathBuf::from(path))]),
+            [name] if *name != "load" => Ok(vec![Action::SetPalette(name.to_string())]),
+            _ => Err("Expected palette NAME|built-in|load FILE".to_string()),
+        },
+    },
+    Command {
+        name: "bookmark",
+        usage: "bookmark save NAME | go NAME|N | delete NAME|N | list",
+        keywords: &["save", "go", "delete", "list"],
+        run: |args| match args {
+            ["save", name @ ..] if !name.is_empty() => {
+                Ok(vec![Action::SaveBookmark(name.join(" "))])
+            }
+            ["go", key @ ..] if !key.is_empty() => Ok(vec![Action::GotoBookmark(key.join(" "))]),
+            ["delete", key @ ..] if !key.is_empty() => {
+                Ok(vec![Action::DeleteBookmark(key.join(" "))])
+            }
+            ["list"] => Ok(vec![Action::ListBookmarks]),
+            _ => Err("Expected bookmark save NAME|go NAME|delete NAME|list".to_string()),
+        },
+    },
+    Command {
+        name: "cap",
+        usage: "cap [apply]",
+        keywords: &["apply"],
+        run: |args| match args {
+            [] => Ok(vec![Action::SuggestCap { apply: false }]),
+            ["apply"] => Ok(vec![Action::SuggestCap { apply: true }]),
+            _ => Err("Expected cap [apply]".to_string()),
+        },
+    },
+    Command {
+        name: "source",
+        usage: "source FILE",
+        keywords: &[],
+        // Expanded by `execute`, which tracks the nesting.
+        run: |_| Ok(Vec::new()),
+    },
+    Command {
+        name: "help",
+        usage: "help",
+        keywords: &[],
+        run: |_| {
+            let usages = COMMANDS.iter().map(|command| command.usage);
+            Ok(usages
+                .map(|usage| Action::Print(usage.to_string()))
+                .collect())
+        },
+    },
+];
+
+fn parse<T: std::str::FromStr>(text: &str) -> Result<T, String>
+where
+    T::Err: std::fmt::Display,
+{
+    locale::normalize(text)
+        .parse()
+        .map_err(|e| format!("Invalid value {text}: {e}"))
+}
+
+// Runs a command line, or every line of the sourced files, into the actions to apply. Empty
+// lines and lines starting with `#` are ignored, so scripts can be commented.
+pub fn execute(line: &str) -> Result<Vec<Action>, String> {
+    execute_nested(line, 0)
+}
+
+fn execute_nested(line: &str, depth: usize) -> Result<Vec<Action>, String> {
+    let words = line.split_whitespace().collect::<Vec<_>>();
+    let Some((&name, args)) = words.split_first() else {
+        return Ok(Vec::new());
+    };
+    if name.starts_with('#') {
+        return Ok(Vec::new());
+    }
+    let command = COMMANDS
+        .iter()
+        .find(|command| command.name == name)
+        .ok_or_else(|| format!("Unknown command {name}, try help"))?;
+    if command.name != "source" {
+        return (command.run)(args);
+    }
+    let [path] = args else {
+        return Err("Expected source FILE".to_string());
+    };
+    if depth >= MAX_SOURCE_DEPTH {
+        return Err(format!("{path}: sourced too deeply"));
+    }
+    let script = std::fs::read_to_string(Path::new(path)).map_err(|e| format!("{path}: {e}"))?;
+    let mut actions = Vec::new();
+    for (number, line) in script.lines().enumerate() {
+        let nested =
+            execute_nested(line, depth + 1).map_err(|e| format!("{path}:{}: {e}", number + 1))?;
+        actions.extend(nested);
+    }
+    Ok(actions)
+}
+
+// Longest common prefix of the candidates starting with `word`, and the candidates themselves.
+fn complete_word<'a>(word: &str, candidates: &[&'a str]) -> (String, Vec<&'a str>) {
+    let matches = candidates
+        .iter()
+        .copied()
+        .filter(|candidate| candidate.starts_with(word))
+        .collect::<Vec<_>>();
+    let Some(first) = matches.first() else {
+        return (word.to_string(), matches);
+    };
+    let common = matches.iter().fold(first.len(), |len, candidate| {
+        first
+            .bytes()
+            .zip(candidate.bytes())
+            .take(len)
+            .take_while(|(a, b)| a == b)
+            .count()
+    });
+    (first[..common].to_string(), matches)
+}
+
+// The line editor behind the backtick key: input with history and tab completion above a few
+// lines of output.
+#[derive(Default)]
+pub struct Console {
+    pub open: bool,
+    input: String,
+    history: Vec<String>,
+    // Position while browsing the history, `history.len()` for the line being edited.
+    browsing: usize,
+    output: Vec<String>,
+}
+
+impl Console {
+    pub fn type_text(&mut self, text: &str) {
+        self.input.extend(text.chars().filter(|c| *c != '`'));
+    }
+
+    pub fn backspace(&mut self) {
+        self.input.pop();
+    }
+
+    pub fn print(&mut self, line: impl Into<String>) {
+        self.output.push(line.into());
+        let excess = self.output.len().saturating_sub(SCROLLBACK);
+        self.output.drain(..excess);
+    }
+
+    // Completes the command name, or the first argument from the command's keywords. Ambiguous
+    // completions extend to the common prefix and list the candidates.
+    pub fn complete(&mut self) {
+        let words = self.input.split_whitespace().collect::<Vec<_>>();
+        let editing_new_word = self.input.is_empty() || self.input.ends_with(' ');
+        let (done, word) = match (words.split_last(), editing_new_word) {
+            (None, _) => (&words[..], ""),
+            (Some(_), true) => (&words[..], ""),
+            (Some((last, done)), false) => (done, *last),
+        };
+        let candidates = match done {
+            [] => COMMANDS.iter().map(|command| command.name).collect(),
+            [name] => COMMANDS
+                .iter()
+                .find(|command| command.name == *name)
+                .map_or(Vec::new(), |command| command.keywords.to_vec()),
+            _ => Vec::new(),
+        };
+        let (completed, matches) = complete_word(word, &candidates);
+        let mut line = done.join(" ");
+        if !done.is_empty() {
+            line.push(' ');
+        }
+        line.push_str(&completed);
+        if matches.len() == 1 {
+            line.push(' ');
+        } else if matches.len() > 1 {
+            self.print(matches.join("  "));
+        }
+        self.input = line;
+    }
+
+    // Steps through the history, -1 towards older lines.
+    pub fn browse(&mut self, step: i32) {
+        let position = (self.browsing as i64 + step as i64).clamp(0, self.history.len() as i64);
+        self.browsing = position as usize;
+        self.input = self.history.get(self.browsing).cloned().unwrap_or_default();
+    }
+
+    // Takes the entered line into the history and returns it for execution.
+    pub fn submit(&mut self) -> String {
+        let line = std::mem::take(&mut self.input);
+        if !line.trim().is_empty() && self.history.last() != Some(&line) {
+            self.history.push(line.clone());
+        }
+        self.browsing = self.history.len();
+        self.print(format!("> {line}"));
+        line
+    }
+
+    // Draws the output and the prompt over the bottom of the frame.
+    pub fn overlay(&self, frame: &mut Frame, style: &Style) {
+        let line_height = (text::LINE_HEIGHT * SCALE) as i32;
+        let lines = self.output.len() as i32 + 1;
+        let top = frame.height as i32 - lines * line_height - line_height / 2;
+        let size = (frame.width, frame.height.saturating_sub(top.max(0) as u32));
+        style.panel(frame, (0, top), size);
+        let prompt = format!("> {}_", self.input);
+        let rows = self
+            .output
+            .iter()
+            .map(String::as_str)
+            .chain([prompt.as_str()]);
+        for (i, line) in rows.enumerate() {
+            let y = top + line_height / 4 + i as i32 * line_height;
+            text::draw(frame, 8, y, SCALE, style.text, line);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mandelbrot_explorer::viewport::NO_TAIL;
+
+    #[test]
+    fn executes_commands() {
+        assert_eq!(
+            execute("set iterations 5000"),
+            Ok(vec![Action::SetIterations(5000)])
+        );
+        assert_eq!(
+            execute("  goto -0.75 0.25 1e-8 "),
+            Ok(vec![Action::Goto(Box::new(Location {
+                center: Complex::new(-0.75, 0.25),
+                center_low: Complex::new(0.0, 0.0),
+                center_tail: NO_TAIL,
+                height: Some(1e-8)
+            }))])
+        );
+        assert_eq!(
+            execute("label -1 0 period 2 bulb"),
+            Ok(vec![Action::Annotations(Edit::Add(Annotation::Label {
+                at: Complex::new(-1.0, 0.0),
+                text: "period 2 bulb".to_string()
+            }))])
+        );
+        assert_eq!(
+            execute("set coloring stripes 5 0.5"),
+            Ok(vec![Action::SetColoring(Coloring::Stripes {
+                period: 5,
+                duty: 0.5
+            })])
+        );
+        assert_eq!(
+            execute("set interior period"),
+            Ok(vec![Action::SetInterior(Some(Coloring::Period))])
+        );
+        assert_eq!(
+            execute("set z0 0.5 -0.25"),
+            Ok(vec![Action::SetZ0(Complex::new(0.5, -0.25))])
+        );
+        assert_eq!(execute("set critical 1"), Ok(vec![Action::SetCritical(1)]));
+        assert_eq!(
+            execute("set p -0.5 0.25"),
+            Ok(vec![Action::SetP(Complex::new(-0.5, 0.25))])
+        );
+        assert_eq!(
+            execute("nudge iterations -50"),
+            Ok(vec![Action::Nudge(Nudge::Iterations(-50))])
+        );
+        assert_eq!(
+            execute("formula z^3 + c"),
+            Ok(vec![Action::SetFractal(
+                formula::fractal("z^3 + c").unwrap()
+            )])
+        );
+        assert!(execute("formula z +").is_err());
+        assert_eq!(
+            execute("palette ocean"),
+            Ok(vec![Action::SetPalette("ocean".to_string())])
+        );
+        assert_eq!(
+            execute("palette load mine.json"),
+            Ok(vec![Action::LoadPalettes(PathBuf::from("mine.json"))])
+        );
+        assert!(execute("palette load").is_err());
+        assert_eq!(
+            execute("bookmark save seahorse valley"),
+            Ok(vec![Action::SaveBookmark("seahorse valley".to_string())])
+        );
+        assert_eq!(
+            execute("bookmark go 2"),
+            Ok(vec![Action::GotoBookmark("2".to_string())])
+        );
+        assert!(execute("bookmark go").is_err());
+        assert_eq!(
+            execute("cap apply"),
+            Ok(vec![Action::SuggestCap { apply: true }])
+        );
+        assert_eq!(
+            execute("goto -0,75 + 0,25i"),
+            Ok(vec![Action::Goto(Box::new(Location {
+                center: Complex::new(-0.75, 0.25),
+                center_low: Complex::new(0.0, 0.0),
+                center_tail: NO_TAIL,
+                height: Some(3.0)
+            }))])
+        );
+        assert_eq!(execute("# comment"), Ok(Vec::new()));
+        assert!(execute("set antialias 12").is_err());
+        for invalid in ["iterations 0", "exposure 0", "exposure -1", "exposure NaN"] {
+            assert!(execute(&format!("set {invalid}")).is_err(), "{invalid}");
+        }
+        assert!(execute("goto 1").is_err());
+        assert!(execute("frobnicate").is_err());
+    }
+
+    #[test]
+    fn sources_scripts() {
+        let path = std::env::temp_dir().join(format!("mandelbrot-script-{}", std::process::id()));
+        let script = path.display().to_string();
+        std::fs::write(&path, "set exposure 2\n\nexport a.png manifest\n").unwrap();
+        assert_eq!(
+            execute(&format!("source {script}")),
+            Ok(vec![
+                Action::SetExposure(2.0),
+                Action::Export {
+                    path: PathBuf::from("a.png"),
+                    annotated: false,
+                    manifest: true
+                }
+            ])
+        );
+        std::fs::write(&path, format!("source {script}\n")).unwrap();
+        assert!(execute(&format!("source {script}")).is_err());
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn completes_and_remembers_lines() {
+        let mut console = Console::default();
+        console.type_text("s");
+        console.complete();
+        assert_eq!(console.input, "s");
+        assert_eq!(console.output, ["set  source"]);
+        console.type_text("e");
+        console.complete();
+        assert_eq!(console.input, "set ");
+        console.type_text("it");
+        console.complete();
+        assert_eq!(console.input, "set iterations ");
+        console.type_text("300");
+        assert_eq!(console.submit(), "set iterations 300");
+        console.type_text("help`");
+        assert_eq!(console.submit(), "help");
+        console.browse(-1);
+        console.browse(-1);
+        assert_eq!(console.input, "set iterations 300");
+        console.browse(1);
+        console.browse(1);
+        assert_eq!(console.input, "");
+    }
+}
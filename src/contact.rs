@@ -0,0 +1,185 @@
+use crate::bookmarks::{self, Bookmark};
+use crate::cache::{self, RenderCache};
+use crate::params::RenderParams;
+use crate::png;
+use crate::render::Frame;
+use crate::text;
+use std::path::PathBuf;
+use std::time::Instant;
+
+const GAP: u32 = 8;
+const LABEL_HEIGHT: u32 = text::LINE_HEIGHT + 6;
+const BACKGROUND: [u8; 3] = [24, 24, 24];
+const LABEL: [u8; 3] = [230, 230, 230];
+
+// The `contactsheet` subcommand: thumbnails of all the bookmarks of a file in one image.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ContactSheet {
+    pub bookmarks: PathBuf,
+    pub output: PathBuf,
+    pub columns: u32,
+    pub thumbnail_width: u32,
+    pub thumbnail_height: u32,
+}
+
+impl Default for ContactSheet {
+    fn default() -> Self {
+        ContactSheet {
+            bookmarks: PathBuf::from("bookmarks.json"),
+            output: PathBuf::from("contact-sheet.png"),
+            columns: 4,
+            thumbnail_width: 240,
+            thumbnail_height: 160,
+        }
+    }
+}
+
+impl ContactSheet {
+    fn thumbnail_params(&self, params: RenderParams) -> Result<RenderParams, String> {
+        bookmarks::resized(params, self.thumbnail_width, self.thumbnail_height)
+    }
+
+    // Lays the thumbnails out row by row, each with its label underneath.
+    fn compose(&self, thumbnails: &[(&str, Frame)]) -> Frame {
+        let (width, height) = (self.thumbnail_width, self.thumbnail_height);
+        let columns = self.columns.min(thumbnails.len() as u32).max(1);
+        let rows = (thumbnails.len() as u32).div_ceil(columns);
+        let (cell_width, cell_height) = (width + GAP, height + LABEL_HEIGHT + GAP);
+        let sheet_width = columns * cell_width + GAP;
+        let mut sheet = Frame::from_rgb(
+            sheet_width,
+            rows * cell_height + GAP,
+            BACKGROUND.repeat((sheet_width * (rows * cell_height + GAP)) as usize),
+        );
+        for (i, (name, thumbnail)) in thumbnails.iter().enumerate() {
+            let (x, y) = (
+                GAP + i as u32 % columns * cell_width,
+                GAP + i as u32 / columns * cell_height,
+            );
+            for row in 0..height {
+                let from = (row * width * 3) as usize;
+                let to = (((y + row) * sheet_width + x) * 3) as usize;
+                sheet.rgb[to..to + (width * 3) as usize]
+                    .copy_from_slice(&thumbnail.rgb[from..from + (width * 3) as usize]);
+            }
+            text::draw(
+                &mut sheet,
+                x as i32,
+                (y + height + 4) as i32,
+                1,
+                LABEL,
+                &label(name, (width / text::ADVANCE) as usize),
+            );
+        }
+        sheet
+    }
+}
+
+// The name cut to `length` characters.
+fn label(name: &str, length: usize) -> String {
+    if name.chars().count() <= length {
+        return name.to_string();
+    }
+    let kept = name
+        .chars()
+        .take(length.saturating_sub(3))
+        .collect::<String>();
+    format!("{kept}...")
+}
+
+pub fn run(job: &ContactSheet, cache: Option<&RenderCache>) -> Result<(), String> {
+    let stamp = Instant::now();
+    let bookmarks = bookmarks::load(&job.bookmarks)?;
+    if bookmarks.is_empty() {
+        return Err(format!("{}: no bookmarks", job.bookmarks.display()));
+    }
+    let mut thumbnails = Vec::new();
+    for (i, Bookmark { name, params }) in bookmarks.iter().enumerate() {
+        println!("Thumbnail {}/{}: {name}", i + 1, bookmarks.len());
+        let params = job.thumbnail_params(*params)?;
+        thumbnails.push((name.as_str(), cache::render(cache, params)));
+    }
+    let sheet = job.compose(&thumbnails);
+    png::write(&job.output, sheet.width, sheet.height, &sheet.rgb)?;
+    println!(
+        "Wrote {} ({}x{}) in {:?}",
+        job.output.display(),
+        sheet.width,
+        sheet.height,
+        Instant::now() - stamp
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::json::{Json, ToJson};
+    use crate::viewport::Viewport;
+    use num::complex::Complex;
+
+    #[test]
+    fn sheet_of_bookmarks() {
+        let params = RenderParams::builder()
+            .size(800, 800)
+            .iterations(100)
+            .build()
+            .unwrap();
+        let zoomed = params
+            .to_builder()
+            .viewport(Viewport::new(Complex::new(-0.75, 0.1), 0.1, 0.1))
+            .build()
+            .unwrap();
+        let bookmarks = [
+            Bookmark {
+                name: "home".to_string(),
+                params,
+            },
+            Bookmark {
+                name: "seahorse valley, a rather long name".to_string(),
+                params: zoomed,
+            },
+            Bookmark {
+                name: "again".to_string(),
+                params,
+            },
+        ];
+        let dir = std::env::temp_dir().join(format!("mandelbrot-sheet-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let file = dir.join("bookmarks.json");
+        let json = Json::Array(bookmarks.iter().map(Bookmark::to_json).collect());
+        std::fs::write(&file, json.to_string()).unwrap();
+        assert_eq!(bookmarks::load(&file).unwrap(), bookmarks);
+
+        let job = ContactSheet {
+            bookmarks: file,
+            output: dir.join("sheet.png"),
+            columns: 2,
+            thumbnail_width: 60,
+            thumbnail_height: 40,
+        };
+        let thumbnail = job.thumbnail_params(zoomed).unwrap();
+        assert_eq!((thumbnail.width, thumbnail.height), (60, 40));
+        assert!((thumbnail.viewport.width - 0.15).abs() < 1e-12);
+        run(&job, None).unwrap();
+        assert!(job.output.exists());
+        let frames = bookmarks
+            .iter()
+            .map(|b| {
+                (
+                    b.name.as_str(),
+                    Frame::from_rgb(60, 40, vec![255; 60 * 40 * 3]),
+                )
+            })
+            .collect::<Vec<_>>();
+        let sheet = job.compose(&frames);
+        assert_eq!((sheet.width, sheet.height), (2 * 68 + 8, 2 * 63 + 8));
+        // The last cell stays empty.
+        let empty = ((8 + 63 + 10) * sheet.width + 8 + 68 + 10) as usize * 3;
+        assert_eq!(sheet.rgb[empty..empty + 3], BACKGROUND);
+        let filled = ((8 + 63 + 10) * sheet.width + 10) as usize * 3;
+        assert_eq!(sheet.rgb[filled..filled + 3], [255, 255, 255]);
+        assert_eq!(label("seahorse valley", 10), "seahors...");
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,148 @@
+use crate::palette::{self, Palette};
+use crate::params::RenderParams;
+use crate::render::{self, Frame, Sample};
+use rayon::prelude::*;
+
+// Share of the exterior pixels clipped at each end of the palette.
+const CLIP: f64 = 0.01;
+// Weight of the new frame's range in the running range.
+const SMOOTHING: f64 = 0.35;
+
+// Continuous escape time for the bailout radius 2 of the renderer, `None` inside the set.
+pub fn smooth_value(sample: &Sample) -> Option<f64> {
+    let escape = sample.escape?;
+    let log = sample.z.norm().ln().max(f64::MIN_POSITIVE);
+    Some(escape as f64 + 2.0 - (log / 2f64.ln()).log2())
+}
+
+// The `CLIP` and `1 - CLIP` quantiles of the smooth iteration values of the frame.
+pub fn percentile_range(samples: &[Sample]) -> Option<(f64, f64)> {
+    let mut values = samples.iter().filter_map(smooth_value).collect::<Vec<_>>();
+    if values.is_empty() {
+        return None;
+    }
+    let last = values.len() - 1;
+    let (low, high) = (
+        (last as f64 * CLIP).round() as usize,
+        (last as f64 * (1.0 - CLIP)).round() as usize,
+    );
+    let high = *values.select_nth_unstable_by(high, f64::total_cmp).1;
+    let low = *values.select_nth_unstable_by(low, f64::total_cmp).1;
+    Some((low, high))
+}
+
+// Maps the palette over the smooth iteration range of recent frames, smoothed over time so
+// the contrast does not flicker while zooming.
+pub struct AutoContrast {
+    range: Option<(f64, f64)>,
+    smoothing: f64,
+}
+
+impl Default for AutoContrast {
+    fn default() -> Self {
+        AutoContrast {
+            range: None,
+            smoothing: SMOOTHING,
+        }
+    }
+}
+
+impl AutoContrast {
+    // Uses the range of every frame as is, so the colors depend on nothing but the frame.
+    pub fn unsmoothed() -> Self {
+        AutoContrast {
+            range: None,
+            smoothing: 1.0,
+        }
+    }
+
+    // Recolors the frame from its samples and re-applies the post-processing of `params`.
+    // Returns false while the running range is still converging to the frame's own range, in
+    // which case the caller should draw again. Frames without samples (restored from the disk
+    // cache) or with an accumulation buffer are left alone.
+    pub fn apply(&mut self, params: RenderParams, frame: &mut Frame) -> bool {
+        if frame.samples.is_empty() || frame.accumulation.is_some() {
+            return true;
+        }
+        let Some(target) = percentile_range(&frame.samples) else {
+            return true;
+        };
+        let (low, high) = match self.range {
+            Some((low, high)) => (
+                low + self.smoothing * (target.0 - low),
+                high + self.smoothing * (target.1 - high),
+            ),
+            None => target,
+        };
+        self.range = Some((low, high));
+        colorize(frame, (low, high), params.palette);
+        render::finish(params, frame);
+        let tolerance = 0.01 * (target.1 - target.0).max(1.0);
+        (low - target.0).abs() < tolerance && (high - target.1).abs() < tolerance
+    }
+}
+
+fn colorize(frame: &mut Frame, (low, high): (f64, f64), palette: Option<Palette>) {
+    let span = (high - low).max(1e-9);
+    frame
+        .rgb
+        .par_chunks_mut(3)
+        .zip(frame.samples.par_iter())
+        .for_each(|(pixel, sample)| {
+            let rgb = match smooth_value(sample) {
+                Some(value) => palette::shade(palette, (value - low) / span),
+                None => [0, 0, 0],
+            };
+            pixel.copy_from_slice(&rgb);
+        });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use num::complex::Complex;
+
+    fn sample(escape: u32) -> Sample {
+        Sample {
+            escape: Some(escape),
+            z: Complex::new(3.0, 0.0),
+        }
+    }
+
+    #[test]
+    fn range_ignores_outliers_and_interior() {
+        let mut samples = (0..1000).map(|i| sample(100 + i % 10)).collect::<Vec<_>>();
+        samples[0] = sample(0);
+        samples[1] = sample(5000);
+        samples.push(Sample {
+            escape: None,
+            z: Complex::new(0.0, 0.0),
+        });
+        let (low, high) = percentile_range(&samples).unwrap();
+        assert!(low > 99.0 && high < 111.0, "{low}..{high}");
+    }
+
+    #[test]
+    fn running_range_converges() {
+        let params = RenderParams::builder().size(64, 48).build().unwrap();
+        let mut contrast = AutoContrast::default();
+        let mut frame = render::render_frame(params);
+        assert!(
+            contrast.apply(params, &mut frame),
+            "first frame sets the range"
+        );
+        let zoomed = params
+            .to_builder()
+            .viewport(crate::viewport::Viewport::new(
+                Complex::new(-0.75, 0.1),
+                0.04,
+                0.03,
+            ))
+            .build()
+            .unwrap();
+        let converged = (0..30)
+            .map(|_| contrast.apply(zoomed, &mut render::render_frame(zoomed)))
+            .collect::<Vec<_>>();
+        assert!(!converged[0] && *converged.last().unwrap());
+    }
+}
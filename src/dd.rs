@@ -0,0 +1,217 @@
+use crate::params::Fractal;
+use crate::render::Sample;
+use num::complex::Complex;
+use std::ops::{Add, Mul, Neg, Sub};
+
+// Double-double numbers: the unevaluated sum of two f64, the low word holding the rounding
+// error of the high one, for about 106 bits of mantissa with the exponent range of f64. Deep
+// zooms render in them once pixels get smaller than f64 resolves, down to a zoom of about 1e30
+// where those bits run out. Reference orbits go on in `fixed` point from there, the fractals
+// perturbation does not apply to stop.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Dd {
+    pub hi: f64,
+    pub lo: f64,
+}
+
+// The sum of two f64 and its rounding error (Knuth).
+fn two_sum(a: f64, b: f64) -> Dd {
+    let hi = a + b;
+    let b_virtual = hi - a;
+    let lo = (a - (hi - b_virtual)) + (b - b_virtual);
+    Dd { hi, lo }
+}
+
+// The same for `|a| >= |b|` in fewer operations.
+fn quick_two_sum(a: f64, b: f64) -> Dd {
+    let hi = a + b;
+    Dd {
+        hi,
+        lo: b - (hi - a),
+    }
+}
+
+impl Dd {
+    pub fn new(hi: f64, lo: f64) -> Dd {
+        quick_two_sum(hi, lo)
+    }
+
+    pub fn abs(self) -> Dd {
+        if self.hi < 0.0 {
+            -self
+        } else {
+            self
+        }
+    }
+
+    pub fn sqr(self) -> Dd {
+        let hi = self.hi * self.hi;
+        let lo = self.hi.mul_add(self.hi, -hi) + 2.0 * self.hi * self.lo;
+        quick_two_sum(hi, lo)
+    }
+}
+
+impl From<f64> for Dd {
+    fn from(hi: f64) -> Dd {
+        Dd { hi, lo: 0.0 }
+    }
+}
+
+impl Add for Dd {
+    type Output = Dd;
+
+    fn add(self, other: Dd) -> Dd {
+        let sum = two_sum(self.hi, other.hi);
+        let error = two_sum(self.lo, other.lo);
+        let sum = quick_two_sum(sum.hi, sum.lo + error.hi);
+        quick_two_sum(sum.hi, sum.lo + error.lo)
+    }
+}
+
+impl Add<f64> for Dd {
+    type Output = Dd;
+
+    fn add(self, other: f64) -> Dd {
+        let sum = two_sum(self.hi, other);
+        quick_two_sum(sum.hi, sum.lo + self.lo)
+    }
+}
+
+impl Neg for Dd {
+    type Output = Dd;
+
+    fn neg(self) -> Dd {
+        Dd {
+            hi: -self.hi,
+            lo: -self.lo,
+        }
+    }
+}
+
+impl Sub for Dd {
+    type Output = Dd;
+
+    fn sub(self, other: Dd) -> Dd {
+        self + -other
+    }
+}
+
+impl Mul for Dd {
+    type Output = Dd;
+
+    fn mul(self, other: Dd) -> Dd {
+        let hi = self.hi * other.hi;
+        let lo = self.hi.mul_add(other.hi, -hi) + (self.hi * other.lo + self.lo * other.hi);
+        quick_two_sum(hi, lo)
+    }
+}
+
+// A complex number as its real and imaginary part.
+pub type DdComplex = (Dd, Dd);
+
+pub fn rounded((re, im): DdComplex) -> Complex<f64> {
+    Complex::new(re.hi, im.hi)
+}
+
+// The starting value and the constant of the orbit of the point at `offset` from a center
+// given in double-double as its high and low parts.
+pub fn start(
+    fractal: Fractal,
+    center: (Complex<f64>, Complex<f64>),
+    offset: Complex<f64>,
+) -> (DdComplex, DdComplex) {
+    let point = (
+        Dd::new(center.0.re, center.1.re) + offset.re,
+        Dd::new(center.0.im, center.1.im) + offset.im,
+    );
+    let lift = |z: Complex<f64>| (Dd::from(z.re), Dd::from(z.im));
+    match fractal {
+        Fractal::Mandelbrot { z0 } => (lift(z0), point),
+        Fractal::Julia { c } => (point, lift(c)),
+        Fractal::Buddhabrot { .. }
+        | Fractal::BurningShip
+        | Fractal::Tricorn
+        | Fractal::Multibrot { .. }
+        | Fractal::Formula { .. } => (lift(Complex::new(0.0, 0.0)), point),
+    }
+}
+
+pub fn step((re, im): DdComplex, c: DdComplex) -> DdComplex {
+    (re.sqr() - im.sqr() + c.0, (re * im) * Dd::from(2.0) + c.1)
+}
+
+// The step of any fractal of `Fractal::step`.
+fn step_of(fractal: Fractal, (re, im): DdComplex, c: DdComplex) -> DdComplex {
+    match fractal {
+        Fractal::BurningShip => step((re.abs(), im.abs()), c),
+        Fractal::Tricorn => step((re, -im), c),
+        Fractal::Multibrot { exponent } => {
+            let mut power = (re, im);
+            for _ in 1..exponent {
+                power = (power.0 * re - power.1 * im, power.0 * im + power.1 * re);
+            }
+            (power.0 + c.0, power.1 + c.1)
+        }
+        _ => step((re, im), c),
+    }
+}
+
+// Escape time of the point, iterated in double-double throughout.
+pub fn escape_time(
+    fractal: Fractal,
+    center: (Complex<f64>, Complex<f64>),
+    offset: Complex<f64>,
+    iterations: u32,
+) -> Sample {
+    let (mut z, c) = start(fractal, center, offset);
+    for i in 0..iterations {
+        z = step_of(fractal, z, c);
+        if rounded(z).norm_sqr() > 4.0 {
+            return Sample {
+                escape: Some(i),
+                z: rounded(z),
+            };
+        }
+    }
+    Sample {
+        escape: None,
+        z: rounded(z),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::render;
+    use itertools::Itertools;
+
+    #[test]
+    fn keeps_the_bits_f64_rounds_away() {
+        let nudged = Dd::from(1.0) + Dd::new(2f64.powi(-60), 0.0);
+        assert_eq!((nudged.hi, nudged.lo), (1.0, 2f64.powi(-60)));
+        let square = nudged.sqr();
+        assert_eq!((square.hi, square.lo), (1.0, 2f64.powi(-59)));
+        assert_eq!(nudged * nudged, square);
+        assert_eq!(nudged - nudged, Dd::from(0.0));
+        // Shallow points escape at the same iteration as in f64.
+        let fractals = [
+            Fractal::MANDELBROT,
+            Fractal::BurningShip,
+            Fractal::Tricorn,
+            Fractal::Multibrot { exponent: 4 },
+        ];
+        for (fractal, point) in fractals
+            .into_iter()
+            .cartesian_product([Complex::new(-0.75, 0.1), Complex::new(0.3, 0.5)])
+        {
+            let sample = escape_time(
+                fractal,
+                (point, Complex::new(0.0, 0.0)),
+                Complex::new(0.0, 0.0),
+                500,
+            );
+            let expected = render::escape_time(fractal, point, 500);
+            assert_eq!(sample.escape, expected.escape, "{fractal:?} {point}");
+        }
+    }
+}
@@ -0,0 +1,202 @@
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+// Whether files are chosen in dialogs, in builds with the dialogs feature.
+pub const ENABLED: bool = cfg!(feature = "dialogs");
+
+// What a file is chosen for. Each purpose starts in the working directory, where the files
+// went without dialogs, and then in the directory of its last choice.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Purpose {
+    Screenshot,
+    Palettes,
+    Params,
+}
+
+impl Purpose {
+    fn title(self) -> &'static str {
+        match self {
+            Purpose::Screenshot => "Save screenshot",
+            Purpose::Palettes => "Open palettes",
+            Purpose::Params => "Open render parameters",
+        }
+    }
+
+    fn extension(self) -> &'static str {
+        match self {
+            Purpose::Screenshot => "png",
+            Purpose::Palettes | Purpose::Params => "json",
+        }
+    }
+}
+
+// The native file dialogs of the desktop, shown by its own dialog tool: zenity or kdialog,
+// AppleScript on macOS and Windows Forms through PowerShell. They are modal.
+#[derive(Debug, Default)]
+pub struct Dialogs {
+    directories: Vec<(Purpose, PathBuf)>,
+}
+
+impl Dialogs {
+    pub fn directory(&self, purpose: Purpose) -> PathBuf {
+        self.directories
+            .iter()
+            .find(|(remembered, _)| *remembered == purpose)
+            .map_or_else(
+                || std::env::current_dir().unwrap_or_else(|_| PathBuf::from(".")),
+                |(_, directory)| directory.clone(),
+            )
+    }
+
+    fn remember(&mut self, purpose: Purpose, path: &Path) {
+        let Some(directory) = path.parent() else {
+            return;
+        };
+        self.directories
+            .retain(|(remembered, _)| *remembered != purpose);
+        self.directories.push((purpose, directory.to_path_buf()));
+    }
+
+    // A path to save to, suggested `name` in the directory of the purpose, given the
+    // extension of the purpose unless it has one. `None` when the dialog is cancelled.
+    pub fn save(&mut self, purpose: Purpose, name: &str) -> Result<Option<PathBuf>, String> {
+        let chosen = self.choose(purpose, Some(name))?;
+        Ok(chosen.map(|path| match path.extension() {
+            Some(_) => path,
+            None => path.with_extension(purpose.extension()),
+        }))
+    }
+
+    pub fn open(&mut self, purpose: Purpose) -> Result<Option<PathBuf>, String> {
+        self.choose(purpose, None)
+    }
+
+    fn choose(&mut self, purpose: Purpose, name: Option<&str>) -> Result<Option<PathBuf>, String> {
+        let chosen = show(commands(purpose, &self.directory(purpose), name))?;
+        if let Some(path) = &chosen {
+            self.remember(purpose, path);
+        }
+        Ok(chosen)
+    }
+}
+
+// The commands showing the dialog, the first tool found on the system is used. Saving when
+// there is a suggested name, opening otherwise.
+fn commands(purpose: Purpose, directory: &Path, name: Option<&str>) -> Vec<Command> {
+    let (title, extension) = (purpose.title(), purpose.extension());
+    let start = directory.join(name.unwrap_or(""));
+    let start = start.to_string_lossy();
+    if cfg!(target_os = "macos") {
+        let quote = |text: &str| text.replace('\\', "\\\\").replace('"', "\\\"");
+        let location = format!(
+            "default location POSIX file \"{}\"",
+            quote(&directory.to_string_lossy())
+        );
+        let script = match name {
+            Some(name) => format!(
+                "POSIX path of (choose file name with prompt \"{title}\" {location} \
+                 default name \"{}\")",
+                quote(name)
+            ),
+            None => format!(
+                "POSIX path of (choose file with prompt \"{title}\" {location} \
+                 of type {{\"{extension}\"}})"
+            ),
+        };
+        let mut osascript = Command::new("osascript");
+        osascript.args(["-e", &script]);
+        vec![osascript]
+    } else if cfg!(windows) {
+        let quote = |text: &str| text.replace('\'', "''");
+        let kind = if name.is_some() { "Save" } else { "Open" };
+        let script = format!(
+            "Add-Type -AssemblyName System.Windows.Forms; \
+             $dialog = New-Object System.Windows.Forms.{kind}FileDialog; \
+             $dialog.Title = '{title}'; \
+             $dialog.InitialDirectory = '{}'; \
+             $dialog.FileName = '{}'; \
+             $dialog.Filter = '{extension} files (*.{extension})|*.{extension}'; \
+             if ($dialog.ShowDialog() -eq 'OK') {{ $dialog.FileName }}",
+            quote(&directory.to_string_lossy()),
+            quote(name.unwrap_or(""))
+        );
+        let mut powershell = Command::new("powershell");
+        powershell.args(["-NoProfile", "-Command", &script]);
+        vec![powershell]
+    } else {
+        let mut zenity = Command::new("zenity");
+        zenity.args([
+            "--file-selection".to_string(),
+            format!("--title={title}"),
+            format!("--filename={start}"),
+            format!("--file-filter=*.{extension}"),
+        ]);
+        let mut kdialog = Command::new("kdialog");
+        kdialog.args(["--title", title]);
+        match name {
+            Some(_) => {
+                zenity.args(["--save", "--confirm-overwrite"]);
+                kdialog.arg("--getsavefilename");
+            }
+            None => {
+                kdialog.arg("--getopenfilename");
+            }
+        }
+        kdialog.args([start.as_ref(), &format!("*.{extension}")]);
+        vec![zenity, kdialog]
+    }
+}
+
+// The path chosen, `None` when the dialog is cancelled, which the tools tell by their exit
+// status.
+#[cfg(feature = "dialogs")]
+fn show(commands: Vec<Command>) -> Result<Option<PathBuf>, String> {
+    let mut tools = Vec::new();
+    for mut command in commands {
+        tools.push(command.get_program().to_string_lossy().into_owned());
+        match command.output() {
+            Ok(output) => {
+                let chosen = String::from_utf8_lossy(&output.stdout).trim().to_string();
+                return Ok(
+                    (output.status.success() && !chosen.is_empty()).then(|| PathBuf::from(chosen))
+                );
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+            Err(e) => return Err(format!("{}: {e}", tools.last().unwrap())),
+        }
+    }
+    Err(format!("No file dialog tool found ({})", tools.join(", ")))
+}
+
+#[cfg(not(feature = "dialogs"))]
+fn show(_: Vec<Command>) -> Result<Option<PathBuf>, String> {
+    Err("File dialogs need a build with the dialogs feature".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn starts_where_the_last_file_was_chosen() {
+        let mut dialogs = Dialogs::default();
+        let working = std::env::current_dir().unwrap();
+        assert_eq!(dialogs.directory(Purpose::Screenshot), working);
+        dialogs.remember(Purpose::Screenshot, Path::new("/tmp/shots/seahorse.png"));
+        dialogs.remember(Purpose::Screenshot, Path::new("/tmp/more/spiral.png"));
+        assert_eq!(
+            dialogs.directory(Purpose::Screenshot),
+            PathBuf::from("/tmp/more")
+        );
+        assert_eq!(dialogs.directory(Purpose::Params), working);
+        let commands = commands(Purpose::Screenshot, Path::new("/tmp/more"), Some("a.png"));
+        let arguments = commands[0]
+            .get_args()
+            .map(|arg| arg.to_string_lossy().into_owned())
+            .collect::<Vec<_>>()
+            .join(" ");
+        #[cfg(target_os = "linux")]
+        assert!(arguments.contains("--filename=/tmp/more/a.png") && arguments.contains("--save"));
+        assert!(arguments.contains("a.png"), "{arguments}");
+    }
+}
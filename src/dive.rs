@@ -0,0 +1,258 @@
+use crate::embedded;
+use crate::viewport::Viewport;
+use num::complex::Complex;
+use std::path::PathBuf;
+
+// Frames of the animated dive per halving of the view height.
+const FRAMES_PER_OCTAVE: f64 = 6.0;
+// Frames of a zoom glide, whatever it zooms by.
+const GLIDE_FRAMES: u32 = 12;
+
+// The minibrot found by `Z` and the view framing it like the default view frames the whole set.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Target {
+    pub period: u32,
+    pub nucleus: Complex<f64>,
+    pub viewport: Viewport,
+}
+
+// Period of the lowest period nucleus inside the view: the orbit of a ball around the view
+// center, bounded to first order through the derivative, is iterated until it contains zero.
+pub fn period(viewport: &Viewport, iterations: u32) -> Option<u32> {
+    let c = viewport.center;
+    let radius = 0.5 * viewport.width.hypot(viewport.height);
+    let (mut z, mut dz) = (Complex::new(0.0, 0.0), Complex::new(0.0, 0.0));
+    for n in 1..=iterations {
+        dz = 2.0 * z * dz + 1.0;
+        z = z * z + c;
+        if z.norm() <= dz.norm() * radius {
+            return Some(n);
+        }
+        if z.norm_sqr() > 4.0 {
+            return None;
+        }
+    }
+    None
+}
+
+pub fn find(viewport: &Viewport, iterations: u32) -> Option<Target> {
+    let period = period(viewport, iterations)?;
+    let nucleus = embedded::nucleus(viewport.center, period)?;
+    let size = embedded::minibrot_size(nucleus, period);
+    if !size.is_normal() {
+        return None;
+    }
+    let framing = Viewport::default();
+    let aspect = viewport.width / viewport.height;
+    let height = size * framing.height;
+    Some(Target {
+        period,
+        nucleus,
+        viewport: Viewport::new(nucleus, height * aspect, height),
+    })
+}
+
+// Animated zoom from one view to another, at a constant rate in the logarithm of the height.
+// The center approaches the target as fast as the height does, so the target drifts steadily
+// to the middle of the window instead of racing off-screen first.
+pub struct Dive {
+    from: Viewport,
+    to: Viewport,
+    frame: u32,
+    frames: u32,
+}
+
+impl Dive {
+    pub fn new(from: Viewport, to: Viewport) -> Self {
+        let octaves = (from.height / to.height).log2().abs();
+        Dive {
+            from,
+            to,
+            frame: 0,
+            frames: (octaves * FRAMES_PER_OCTAVE).ceil().max(1.0) as u32,
+        }
+    }
+
+    pub fn start(&self) -> &Viewport {
+        &self.from
+    }
+}
+
+impl Iterator for Dive {
+    type Item = Viewport;
+
+    // The view of the next frame, `None` once the target has been reached.
+    fn next(&mut self) -> Option<Viewport> {
+        if self.frame >= self.frames {
+            return None;
+        }
+        self.frame += 1;
+        if self.frame == self.frames {
+            return Some(self.to);
+        }
+        let t = self.frame as f64 / self.frames as f64;
+        let height = self.from.height * (self.to.height / self.from.height).powf(t);
+        let progress = if self.from.height == self.to.height {
+            t
+        } else {
+            (self.from.height - height) / (self.from.height - self.to.height)
+        };
+        Some(Viewport::new(
+            self.from.center + (self.to.center - self.from.center) * progress,
+            height * self.to.width / self.to.height,
+            height,
+        ))
+    }
+}
+
+// Animated zoom of a click or a wheel notch: the view zooms about the point at `offset` from
+// its center over a few frames, fast at first and slowing down into the target, the point
+// staying under the cursor all along. Unlike the dive it keeps the rotation and the low part of
+// the center.
+pub struct Glide {
+    from: Viewport,
+    offset: Complex<f64>,
+    factor: f64,
+    frame: u32,
+}
+
+impl Glide {
+    pub fn new(from: Viewport, offset: Complex<f64>, factor: f64) -> Self {
+        Glide {
+            from,
+            offset,
+            factor,
+            frame: 0,
+        }
+    }
+
+    pub fn target(&self) -> Viewport {
+        let mut target = self.from;
+        target.zoom_at(self.offset, self.factor);
+        target
+    }
+
+    pub fn finished(&self) -> bool {
+        self.frame >= GLIDE_FRAMES
+    }
+}
+
+impl Iterator for Glide {
+    type Item = Viewport;
+
+    fn next(&mut self) -> Option<Viewport> {
+        if self.finished() {
+            return None;
+        }
+        self.frame += 1;
+        let t = self.frame as f64 / GLIDE_FRAMES as f64;
+        let eased = 1.0 - (1.0 - t) * (1.0 - t);
+        let mut view = self.from;
+        view.zoom_at(self.offset, self.factor.powf(eased));
+        Some(view)
+    }
+}
+
+// Screenshots taken every `every` halvings of the view height during dives, numbered by the
+// dive and the shot.
+pub struct ScreenshotSeries {
+    every: f64,
+    dir: PathBuf,
+    dives: u32,
+    taken: u32,
+}
+
+impl ScreenshotSeries {
+    pub fn new(every: f64, dir: PathBuf) -> Self {
+        ScreenshotSeries {
+            every,
+            dir,
+            dives: 0,
+            taken: 0,
+        }
+    }
+
+    pub fn start(&mut self) {
+        self.dives += 1;
+        self.taken = 0;
+    }
+
+    // Path of the next screenshot once the dive has gone deep enough for it.
+    pub fn due(&mut self, start: &Viewport, viewport: &Viewport) -> Option<PathBuf> {
+        let octaves = (start.height / viewport.height).log2();
+        if octaves < (self.taken + 1) as f64 * self.every {
+            return None;
+        }
+        self.taken += 1;
+        Some(
+            self.dir
+                .join(format!("dive-{:03}-{:04}.png", self.dives, self.taken)),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_nearest_minibrot() {
+        // A view of the period 3 minibrot on the real axis, seen slightly off its nucleus.
+        let viewport = Viewport::new(Complex::new(-1.7545, 0.0005), 0.04, 0.03);
+        assert_eq!(period(&viewport, 1000), Some(3));
+        let target = find(&viewport, 1000).unwrap();
+        assert!((target.nucleus - Complex::new(-1.754877666246693, 0.0)).norm() < 1e-12);
+        assert!(target.viewport.height > 0.03 && target.viewport.height < 0.09);
+        // Centered on it, the nucleus is its own target.
+        let again = find(&target.viewport, 1000).unwrap();
+        assert_eq!(again.period, 3);
+        assert!((again.nucleus - target.nucleus).norm() < 1e-12);
+    }
+
+    #[test]
+    fn dive_ends_at_the_target() {
+        let from = Viewport::default();
+        let to = Viewport::new(Complex::new(-1.754877666246693, 0.0), 0.08, 0.06);
+        let steps: Vec<Viewport> = std::iter::from_fn({
+            let mut dive = Dive::new(from, to);
+            move || dive.next()
+        })
+        .collect();
+        assert!(steps.len() > 20);
+        assert_eq!(*steps.last().unwrap(), to);
+        assert!(steps.windows(2).all(|w| w[1].height < w[0].height));
+        assert!(steps
+            .windows(2)
+            .all(|w| (w[1].center - to.center).norm() <= (w[0].center - to.center).norm()));
+        // A screenshot every two of the dive's almost six octaves.
+        let mut series = ScreenshotSeries::new(2.0, PathBuf::from("shots"));
+        series.start();
+        let shots: Vec<PathBuf> = steps
+            .iter()
+            .filter_map(|step| series.due(&from, step))
+            .collect();
+        assert_eq!(shots.len(), 2);
+        assert_eq!(shots[1], PathBuf::from("shots/dive-001-0002.png"));
+    }
+
+    #[test]
+    fn glide_keeps_the_point_under_the_cursor() {
+        let mut from = Viewport::new(Complex::new(-0.75, 0.1), 0.4, 0.3);
+        from.rotate(0.3);
+        let offset = from.offset(300.0, 100.0, (400, 300));
+        let point = from.center + offset;
+        let mut glide = Glide::new(from, offset, 0.5);
+        let steps = glide.by_ref().collect::<Vec<_>>();
+        assert_eq!(steps.len(), GLIDE_FRAMES as usize);
+        assert!(glide.finished());
+        assert_eq!(*steps.last().unwrap(), glide.target());
+        assert!((glide.target().height - 0.15).abs() < 1e-12);
+        // The first frames cover more of the zoom than the last ones.
+        assert!(steps[0].height - steps[1].height > steps[10].height - steps[11].height);
+        for step in &steps {
+            assert_eq!(step.rotation, from.rotation);
+            let (x, y) = step.complex_to_pixel(point, (400, 300));
+            assert!((x - 300.0).abs() < 1e-9 && (y - 100.0).abs() < 1e-9);
+        }
+    }
+}
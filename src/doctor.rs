@@ -0,0 +1,157 @@
+use crate::kernel::{self, Kernel};
+use crate::params::{Fractal, RenderParams};
+use crate::scheduler::Scheduler;
+use crate::viewport::Viewport;
+use num::complex::Complex;
+use std::time::{Duration, Instant};
+
+// Side of the view rendered by the benchmarks, in pixels, and its iteration count.
+const BENCHMARK_SIDE: u32 = 128;
+const BENCHMARK_ITERATIONS: u32 = 1000;
+
+// The fixed view every benchmark renders: the seahorse valley, mostly slowly escaping points.
+fn benchmark_params() -> RenderParams {
+    RenderParams::builder()
+        .viewport(Viewport::new(Complex::new(-0.75, 0.1), 0.05, 0.05))
+        .size(BENCHMARK_SIDE, BENCHMARK_SIDE)
+        .iterations(BENCHMARK_ITERATIONS)
+        .build()
+        .expect("valid benchmark params")
+}
+
+// Extensions of the instruction set the escape-time kernels can use.
+pub fn cpu_features() -> Vec<&'static str> {
+    let mut features = Vec::new();
+    #[cfg(target_arch = "x86_64")]
+    {
+        let detected = [
+            ("sse2", is_x86_feature_detected!("sse2")),
+            ("avx", is_x86_feature_detected!("avx")),
+            ("avx2", is_x86_feature_detected!("avx2")),
+            ("fma", is_x86_feature_detected!("fma")),
+            ("avx512f", is_x86_feature_detected!("avx512f")),
+        ];
+        features.extend(detected.iter().filter(|(_, on)| *on).map(|(name, _)| *name));
+    }
+    #[cfg(target_arch = "aarch64")]
+    if std::arch::is_aarch64_feature_detected!("neon") {
+        features.push("neon");
+    }
+    features
+}
+
+// Points per second of a kernel over the benchmark view.
+fn kernel_speed(kernel: Kernel) -> (Duration, f64) {
+    let params = benchmark_params();
+    let points = (0..BENCHMARK_SIDE)
+        .flat_map(|y| (0..BENCHMARK_SIDE).map(move |x| (x, y)))
+        .map(|(x, y)| {
+            let offset = params
+                .viewport
+                .offset(x as f64, y as f64, (params.width, params.height));
+            params.viewport.center + offset
+        })
+        .collect::<Vec<_>>();
+    let stamp = Instant::now();
+    kernel.escape_time(Fractal::MANDELBROT, &points, params.iterations);
+    let elapsed = stamp.elapsed();
+    (elapsed, points.len() as f64 / elapsed.as_secs_f64())
+}
+
+// The lines of the `doctor` report about the machine: CPU, threads, kernels and backends, the
+// kernels and backends each timed on a small fixed view.
+pub fn report() -> Vec<String> {
+    let mut lines = vec![
+        format!("CPU architecture {}", std::env::consts::ARCH),
+        format!("CPU features: {}", cpu_features().join(" ")),
+        format!("Threads: {}", rayon::current_num_threads()),
+        format!(
+            "Periodicity checking {}",
+            if kernel::periodicity() { "on" } else { "off" }
+        ),
+    ];
+    let side = BENCHMARK_SIDE;
+    // The vector kernels are measured against the scalar one, which is timed first.
+    let mut scalar = None;
+    for kernel in Kernel::ALL {
+        if !kernel.supported() {
+            lines.push(format!("Kernel {}: not supported", kernel.name()));
+            continue;
+        }
+        let (elapsed, speed) = kernel_speed(kernel);
+        let current = if kernel == kernel::current() {
+            " (selected)"
+        } else {
+            ""
+        };
+        let scalar = *scalar.get_or_insert(speed);
+        let speedup = if kernel == Kernel::Scalar {
+            String::new()
+        } else {
+            format!(", {:.1}x scalar", speed / scalar)
+        };
+        lines.push(format!(
+            "Kernel {}{current}: {side}x{side} in {elapsed:?}, {:.1} Mpoints/s{speedup}, one \
+             thread",
+            kernel.name(),
+            speed / 1e6
+        ));
+    }
+    let scheduler = Scheduler::detect();
+    for worker in scheduler.workers() {
+        let stamp = Instant::now();
+        worker.render(&[benchmark_params()]);
+        lines.push(format!(
+            "Backend {}: up to {} precision, {side}x{side} tile in {:?}",
+            worker.backend().name(),
+            worker.precision().name(),
+            stamp.elapsed()
+        ));
+    }
+    lines.push(gpu());
+    lines
+}
+
+#[cfg(feature = "gpu")]
+fn gpu() -> String {
+    match crate::gpu::device() {
+        Ok(device) => format!("GPU {}", device.renderer),
+        Err(e) => format!("No GPU: {e}"),
+    }
+}
+
+#[cfg(not(feature = "gpu"))]
+fn gpu() -> String {
+    "No GPU backend in this build".to_string()
+}
+
+// Problems worth a line at startup, which slow all the rendering down.
+pub fn warnings() -> Vec<String> {
+    let mut warnings = Vec::new();
+    if rayon::current_num_threads() == 1 {
+        warnings.push("Only one thread available, tiles are rendered one at a time".to_string());
+    }
+    if Kernel::detect() == Kernel::Scalar {
+        warnings.push("No vector kernel for this CPU, points are iterated one at a time".into());
+    }
+    warnings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn report_times_every_supported_kernel() {
+        let report = report();
+        let supported = Kernel::ALL.iter().filter(|kernel| kernel.supported());
+        let timed = report.iter().filter(|line| line.contains("Mpoints/s"));
+        assert_eq!(timed.count(), supported.count());
+        let vector = Kernel::ALL[1..].iter().filter(|kernel| kernel.supported());
+        let compared = report.iter().filter(|line| line.contains("x scalar"));
+        assert_eq!(compared.count(), vector.count());
+        assert!(report
+            .iter()
+            .any(|line| line.starts_with("Backend cpu: up to fixed-point")));
+    }
+}
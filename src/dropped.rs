@@ -0,0 +1,96 @@
+use crate::formula;
+use crate::json::{FromJson, Json};
+use crate::manifest::Manifest;
+use crate::palette::{self, Palette};
+use crate::params::{Fractal, RenderParams};
+use crate::viewport::Viewport;
+use std::path::Path;
+
+// What a file dropped onto the window brings.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Dropped {
+    // From a manifest, bare render parameters or a screenshot.
+    Params(RenderParams),
+    Viewport(Viewport),
+    Palettes(Vec<(String, Palette)>),
+    // From a formula script: the step of a custom fractal, `#` starting a comment.
+    Fractal(Fractal),
+}
+
+// Tells the kind of the file by its extension and, for JSON, by its fields.
+pub fn open(path: &Path) -> Result<Dropped, String> {
+    let extension = path.extension().and_then(|extension| extension.to_str());
+    let is = |name: &str| extension.is_some_and(|extension| extension.eq_ignore_ascii_case(name));
+    if is("png") {
+        return Ok(Dropped::Params(Manifest::from_png(path)?.params));
+    }
+    let text = std::fs::read_to_string(path).map_err(|e| format!("{}: {e}", path.display()))?;
+    if !is("json") {
+        let formula = text
+            .lines()
+            .map(|line| line.split('#').next().unwrap_or("").trim())
+            .filter(|line| !line.is_empty())
+            .collect::<Vec<_>>()
+            .join(" ");
+        return formula::fractal(&formula)
+            .map(Dropped::Fractal)
+            .map_err(|e| format!("{}: {e}", path.display()));
+    }
+    let json = Json::parse(&text).map_err(|e| format!("{}: {e}", path.display()))?;
+    if json.get("center_re").is_some() {
+        return Ok(Dropped::Viewport(Viewport::from_json(&json)?));
+    }
+    if json.get("params").is_some() || json.get("fractal").is_some() {
+        return Ok(Dropped::Params(Manifest::load(path)?.params));
+    }
+    palette::load(path).map(Dropped::Palettes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::json::ToJson;
+    use std::time::Duration;
+
+    #[test]
+    fn tells_the_kinds_of_files_apart() {
+        let dir = std::env::temp_dir().join(format!("dropped-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let file = |name: &str, contents: &str| {
+            let path = dir.join(name);
+            std::fs::write(&path, contents).unwrap();
+            open(&path)
+        };
+        let params = RenderParams::builder().size(8, 6).build().unwrap();
+        let manifest = Manifest::new(params, Duration::ZERO);
+        let text = manifest.to_json().to_string();
+        assert_eq!(file("view.json", &text), Ok(Dropped::Params(params)));
+        assert_eq!(
+            file("bare.json", &params.to_json().to_string()),
+            Ok(Dropped::Params(params))
+        );
+        let viewport = Viewport::default();
+        assert_eq!(
+            file("viewport.JSON", &viewport.to_json().to_string()),
+            Ok(Dropped::Viewport(viewport))
+        );
+        let fire = palette::NAMED[0].1.describe().replace(' ', "\", \"");
+        assert_eq!(
+            file("mine.json", &format!("{{\"mine\": [\"{fire}\"]}}")),
+            Ok(Dropped::Palettes(vec![(
+                "mine".to_string(),
+                palette::NAMED[0].1
+            )]))
+        );
+        assert_eq!(
+            file("cubic.txt", "# A cubic\nz^3 + c\n"),
+            Ok(Dropped::Fractal(formula::fractal("z^3 + c").unwrap()))
+        );
+        let image = dir.join("shot.png");
+        manifest.write_png(&image, 1, 1, &[0, 0, 0]).unwrap();
+        assert_eq!(open(&image), Ok(Dropped::Params(params)));
+        assert!(file("notes.txt", "not a formula (").is_err());
+        assert!(file("list.json", "[1, 2]").is_err());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,86 @@
+use crate::params::{Fractal, RenderParams};
+use crate::render::{self, Frame};
+
+// The dual view: the Mandelbrot set and the Burning Ship side by side, both halves showing the
+// same center and scale, so that they pan and zoom together.
+
+// The fractal shown next to `fractal`, `None` for those without a counterpart.
+pub fn partner(fractal: Fractal) -> Option<Fractal> {
+    match fractal {
+        Fractal::Mandelbrot { .. } => Some(Fractal::BurningShip),
+        Fractal::BurningShip => Some(Fractal::MANDELBROT),
+        _ => None,
+    }
+}
+
+// Params of the left and the right half.
+pub fn halves(params: RenderParams) -> Option<[RenderParams; 2]> {
+    let other = partner(params.fractal)?;
+    let left = params.width / 2;
+    let half = |fractal, width| RenderParams {
+        fractal,
+        width,
+        viewport: params.viewport.fit((width, params.height)),
+        ..params
+    };
+    Some([half(params.fractal, left), half(other, params.width - left)])
+}
+
+// Both halves joined into one frame. It keeps no samples, the points of the halves are not
+// those of the undivided view, so recoloring and inspection render it again.
+pub fn render(params: RenderParams) -> Option<Frame> {
+    let [left, right] = halves(params)?.map(render::render_frame);
+    let rgb = left
+        .rgb
+        .chunks(3 * left.width as usize)
+        .zip(right.rgb.chunks(3 * right.width as usize))
+        .flat_map(|(left, right)| left.iter().chain(right).copied())
+        .collect();
+    Some(Frame::from_rgb(params.width, params.height, rgb))
+}
+
+// The column of the undivided view showing the point under column `x` of either half.
+pub fn unsplit(x: f64, width: u32) -> f64 {
+    let left = (width / 2) as f64;
+    let center = width as f64 / 2.0;
+    if x < left {
+        x - left / 2.0 + center
+    } else {
+        x - left - (width as f64 - left) / 2.0 + center
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::viewport::Viewport;
+
+    #[test]
+    fn halves_render_the_same_view() {
+        let params = RenderParams::builder()
+            .viewport(Viewport::default().fit((40, 15)))
+            .size(40, 15)
+            .iterations(100)
+            .build()
+            .unwrap();
+        let [left, right] = halves(params).unwrap();
+        assert_eq!(right.fractal, Fractal::BurningShip);
+        assert_eq!(left.viewport.center, right.viewport.center);
+        assert_eq!(left.viewport.pixel_to_complex(5.0, 3.0, (20, 15)), {
+            let x = unsplit(5.0, 40);
+            params.viewport.pixel_to_complex(x, 3.0, (40, 15))
+        });
+        assert_eq!(unsplit(25.0, 40), unsplit(5.0, 40));
+        let frame = render(params).unwrap();
+        let ship = render::render_frame(right);
+        assert_eq!(frame.rgb[..60], render::render_frame(left).rgb[..60]);
+        assert_eq!(frame.rgb[60..120], ship.rgb[..60]);
+        assert!(render(RenderParams {
+            fractal: Fractal::Julia {
+                c: num::complex::Complex::new(0.0, 0.0)
+            },
+            ..params
+        })
+        .is_none());
+    }
+}
@@ -0,0 +1,470 @@
+use crate::json::{FromJson, Json, ToJson};
+use crate::params::RenderParams;
+use crate::render::{Frame, Sample};
+use crate::stats::OrbitStats;
+use num::complex::Complex;
+use rayon::prelude::*;
+use std::fs::File;
+use std::io::{BufReader, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+
+const MAGIC: &[u8; 4] = b"MBD1";
+pub const TILE_SIZE: u32 = 64;
+
+// Per-pixel values of a layer, row by row.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Data {
+    U32(Vec<u32>),
+    F32(Vec<f32>),
+}
+
+impl Data {
+    fn kind(&self) -> u8 {
+        match self {
+            Data::U32(_) => 0,
+            Data::F32(_) => 1,
+        }
+    }
+
+    fn len(&self) -> usize {
+        match self {
+            Data::U32(values) => values.len(),
+            Data::F32(values) => values.len(),
+        }
+    }
+
+    // Raw bits of the value of a pixel.
+    fn bits(&self, i: usize) -> u32 {
+        match self {
+            Data::U32(values) => values[i],
+            Data::F32(values) => values[i].to_bits(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Layer {
+    pub name: String,
+    pub data: Data,
+}
+
+// Iteration dumps: named per-pixel buffers of a view together with its parameters, to color,
+// analyse or continue later without iterating again. The file holds the layers tile by tile,
+// each tile compressed on its own and found through an index, so readers load only the tiles
+// they need:
+//
+//     "MBD1", width, height, tile size, params JSON length, params JSON,
+//     layer count, per layer its kind (0 u32, 1 f32), name length and name,
+//     per tile in row-major order its offset in the file and byte length,
+//     the tiles
+//
+// All integers little-endian u32 but the u64 tile offsets. The layers of a tile follow each
+// other, their values in row-major order within the tile, delta-coded (u32 as the zigzag-coded
+// difference to the previous value, f32 as the bits XORed with those of the previous value,
+// both small for neighbouring pixels) into LEB128 varints.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Dump {
+    pub params: RenderParams,
+    pub layers: Vec<Layer>,
+}
+
+impl Dump {
+    // The escape results of a frame: the escape iteration plus one, 0 inside the set, and the
+    // last orbit value.
+    pub fn from_frame(params: RenderParams, frame: &Frame) -> Dump {
+        let samples = &frame.samples;
+        let z = |part: fn(&Complex<f64>) -> f64| samples.iter().map(move |s| part(&s.z) as f32);
+        Dump {
+            params,
+            layers: vec![
+                Layer {
+                    name: "escape".to_string(),
+                    data: Data::U32(
+                        samples
+                            .iter()
+                            .map(|s| s.escape.map_or(0, |e| e + 1))
+                            .collect(),
+                    ),
+                },
+                Layer {
+                    name: "z re".to_string(),
+                    data: Data::F32(z(|z| z.re).collect()),
+                },
+                Layer {
+                    name: "z im".to_string(),
+                    data: Data::F32(z(|z| z.im).collect()),
+                },
+            ],
+        }
+    }
+
+    // The layers of the statistics export.
+    pub fn from_stats(params: RenderParams, stats: &[OrbitStats]) -> Dump {
+        let layer = |name: &str, f: fn(&OrbitStats) -> f64| Layer {
+            name: name.to_string(),
+            data: Data::F32(stats.iter().map(|s| f(s) as f32).collect()),
+        };
+        Dump {
+            params,
+            layers: vec![
+                layer("smooth iteration", |s| s.smooth),
+                layer("distance estimate", |s| s.distance),
+                layer("final angle", |s| s.angle),
+                layer("orbit trap distance", |s| s.trap),
+            ],
+        }
+    }
+
+    pub fn layer(&self, name: &str) -> Option<&Data> {
+        self.layers
+            .iter()
+            .find(|layer| layer.name == name)
+            .map(|layer| &layer.data)
+    }
+
+    // A frame of the samples of a dump made by `from_frame`, without colors; `render::recolor`
+    // colors it.
+    pub fn frame(&self) -> Result<Frame, String> {
+        let (Some(Data::U32(escape)), Some(Data::F32(re)), Some(Data::F32(im))) =
+            (self.layer("escape"), self.layer("z re"), self.layer("z im"))
+        else {
+            return Err("The dump has no escape results".to_string());
+        };
+        let mut frame = Frame::from_rgb(self.params.width, self.params.height, Vec::new());
+        frame.samples = escape
+            .iter()
+            .zip(re.iter().zip(im))
+            .map(|(&escape, (&re, &im))| Sample {
+                escape: escape.checked_sub(1),
+                z: Complex::new(re as f64, im as f64),
+            })
+            .collect();
+        Ok(frame)
+    }
+}
+
+// Position and size of a tile.
+type Tile = (u32, u32, u32, u32);
+
+// Tiles covering the image in row-major order.
+fn tiles(width: u32, height: u32) -> Vec<Tile> {
+    (0..height.div_ceil(TILE_SIZE))
+        .flat_map(|ty| {
+            (0..width.div_ceil(TILE_SIZE)).map(move |tx| {
+                let (x, y) = (tx * TILE_SIZE, ty * TILE_SIZE);
+                (x, y, TILE_SIZE.min(width - x), TILE_SIZE.min(height - y))
+            })
+        })
+        .collect()
+}
+
+fn push_varint(out: &mut Vec<u8>, mut value: u32) {
+    while value >= 0x80 {
+        out.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+    out.push(value as u8);
+}
+
+fn read_varint(bytes: &[u8], at: &mut usize) -> Result<u32, String> {
+    let mut value = 0u32;
+    for shift in (0..35).step_by(7) {
+        let byte = *bytes.get(*at).ok_or("Truncated tile")?;
+        *at += 1;
+        value |= ((byte & 0x7f) as u32) << shift;
+        if byte < 0x80 {
+            return Ok(value);
+        }
+    }
+    Err("Invalid varint".to_string())
+}
+
+fn encode_tile(width: u32, layers: &[Layer], (x, y, w, h): Tile) -> Vec<u8> {
+    let mut out = Vec::new();
+    for layer in layers {
+        let xor = matches!(layer.data, Data::F32(_));
+        let mut previous = 0u32;
+        for py in y..y + h {
+            for px in x..x + w {
+                let bits = layer.data.bits((py * width + px) as usize);
+                push_varint(
+                    &mut out,
+                    match xor {
+                        true => bits ^ previous,
+                        false => {
+                            let delta = bits.wrapping_sub(previous) as i32;
+                            ((delta << 1) ^ (delta >> 31)) as u32
+                        }
+                    },
+                );
+                previous = bits;
+            }
+        }
+    }
+    out
+}
+
+// The values of every layer in a tile, in row-major order within the tile.
+fn decode_tile(kinds: &[u8], pixels: usize, bytes: &[u8]) -> Result<Vec<Data>, String> {
+    let mut at = 0;
+    kinds
+        .iter()
+        .map(|&kind| {
+            let mut previous = 0u32;
+            let mut bits = Vec::with_capacity(pixels);
+            for _ in 0..pixels {
+                let delta = read_varint(bytes, &mut at)?;
+                previous = match kind {
+                    0 => {
+                        previous.wrapping_add(((delta >> 1) as i32 ^ -((delta & 1) as i32)) as u32)
+                    }
+                    _ => previous ^ delta,
+                };
+                bits.push(previous);
+            }
+            Ok(match kind {
+                0 => Data::U32(bits),
+                _ => Data::F32(bits.into_iter().map(f32::from_bits).collect()),
+            })
+        })
+        .collect()
+}
+
+pub fn write(path: &Path, dump: &Dump) -> Result<(), String> {
+    let (width, height) = (dump.params.width, dump.params.height);
+    let pixels = (width * height) as usize;
+    if let Some(layer) = dump.layers.iter().find(|layer| layer.data.len() != pixels) {
+        return Err(format!(
+            "Layer {} does not have {pixels} values",
+            layer.name
+        ));
+    }
+    let tiles = tiles(width, height)
+        .into_par_iter()
+        .map(|tile| encode_tile(width, &dump.layers, tile))
+        .collect::<Vec<_>>();
+    let mut header = MAGIC.to_vec();
+    let params = dump.params.to_json().to_string();
+    header.extend(
+        [width, height, TILE_SIZE, params.len() as u32]
+            .map(u32::to_le_bytes)
+            .concat(),
+    );
+    header.extend(params.as_bytes());
+    header.extend((dump.layers.len() as u32).to_le_bytes());
+    for layer in &dump.layers {
+        header.push(layer.data.kind());
+        header.extend((layer.name.len() as u32).to_le_bytes());
+        header.extend(layer.name.as_bytes());
+    }
+    let mut offset = (header.len() + tiles.len() * 12) as u64;
+    for tile in &tiles {
+        header.extend(offset.to_le_bytes());
+        header.extend((tile.len() as u32).to_le_bytes());
+        offset += tile.len() as u64;
+    }
+    let mut file = std::io::BufWriter::new(
+        File::create(path).map_err(|e| format!("{}: {e}", path.display()))?,
+    );
+    std::iter::once(&header)
+        .chain(&tiles)
+        .try_for_each(|bytes| file.write_all(bytes))
+        .and_then(|_| file.flush())
+        .map_err(|e| format!("{}: {e}", path.display()))
+}
+
+// An open dump, with its header read and the tiles read on demand.
+pub struct Reader {
+    file: BufReader<File>,
+    pub params: RenderParams,
+    // Names and kinds of the layers.
+    layers: Vec<(String, u8)>,
+    // Tile positions and sizes, offsets and byte lengths.
+    index: Vec<(Tile, u64, u32)>,
+}
+
+fn read_u32(file: &mut impl Read) -> Result<u32, String> {
+    let mut bytes = [0; 4];
+    file.read_exact(&mut bytes).map_err(|e| e.to_string())?;
+    Ok(u32::from_le_bytes(bytes))
+}
+
+fn read_bytes(file: &mut impl Read, length: u32) -> Result<Vec<u8>, String> {
+    let mut bytes = vec![0; length as usize];
+    file.read_exact(&mut bytes).map_err(|e| e.to_string())?;
+    Ok(bytes)
+}
+
+impl Reader {
+    pub fn open(path: &Path) -> Result<Reader, String> {
+        let open = || -> Result<Reader, String> {
+            let mut file = BufReader::new(File::open(path).map_err(|e| e.to_string())?);
+            if read_bytes(&mut file, 4)? != MAGIC {
+                return Err("Not an iteration dump".to_string());
+            }
+            let (width, height, tile_size) = (
+                read_u32(&mut file)?,
+                read_u32(&mut file)?,
+                read_u32(&mut file)?,
+            );
+            if tile_size != TILE_SIZE {
+                return Err(format!("Unsupported tile size {tile_size}"));
+            }
+            let length = read_u32(&mut file)?;
+            let text =
+                String::from_utf8(read_bytes(&mut file, length)?).map_err(|e| e.to_string())?;
+            let params = RenderParams::from_json(&Json::parse(&text)?)?;
+            if (params.width, params.height) != (width, height) {
+                return Err("The image size does not match the parameters".to_string());
+            }
+            let mut layers = Vec::new();
+            for _ in 0..read_u32(&mut file)? {
+                let kind = read_bytes(&mut file, 1)?[0];
+                if kind > 1 {
+                    return Err(format!("Unknown layer kind {kind}"));
+                }
+                let length = read_u32(&mut file)?;
+                let name =
+                    String::from_utf8(read_bytes(&mut file, length)?).map_err(|e| e.to_string())?;
+                layers.push((name, kind));
+            }
+            let index = tiles(width, height)
+                .into_iter()
+                .map(|tile| {
+                    let offset = read_bytes(&mut file, 8)?;
+                    let offset = u64::from_le_bytes(offset.try_into().unwrap());
+                    Ok((tile, offset, read_u32(&mut file)?))
+                })
+                .collect::<Result<Vec<_>, String>>()?;
+            Ok(Reader {
+                file,
+                params,
+                layers,
+                index,
+            })
+        };
+        open().map_err(|e| format!("{}: {e}", path.display()))
+    }
+
+    pub fn layer_names(&self) -> impl Iterator<Item = &str> {
+        self.layers.iter().map(|(name, _)| name.as_str())
+    }
+
+    // Position and size of every tile, in the order of `tile`.
+    pub fn tiles(&self) -> impl Iterator<Item = Tile> + '_ {
+        self.index.iter().map(|(tile, _, _)| *tile)
+    }
+
+    // The values of every layer in the tile, row by row within it.
+    pub fn tile(&mut self, i: usize) -> Result<Vec<Data>, String> {
+        let ((_, _, w, h), offset, length) = *self.index.get(i).ok_or("No such tile")?;
+        self.file
+            .seek(SeekFrom::Start(offset))
+            .map_err(|e| e.to_string())?;
+        let bytes = read_bytes(&mut self.file, length)?;
+        let kinds = self
+            .layers
+            .iter()
+            .map(|(_, kind)| *kind)
+            .collect::<Vec<_>>();
+        decode_tile(&kinds, (w * h) as usize, &bytes)
+    }
+
+    // Every tile assembled into whole layers.
+    pub fn read_all(&mut self) -> Result<Dump, String> {
+        let width = self.params.width;
+        let pixels = (width * self.params.height) as usize;
+        let mut bits = vec![vec![0u32; pixels]; self.layers.len()];
+        for i in 0..self.index.len() {
+            let (x, y, w, _) = self.index[i].0;
+            for (layer, data) in bits.iter_mut().zip(self.tile(i)?) {
+                for j in 0..data.len() {
+                    let (px, py) = (x + j as u32 % w, y + j as u32 / w);
+                    layer[(py * width + px) as usize] = data.bits(j);
+                }
+            }
+        }
+        Ok(Dump {
+            params: self.params,
+            layers: self
+                .layers
+                .iter()
+                .zip(bits)
+                .map(|((name, kind), bits)| Layer {
+                    name: name.clone(),
+                    data: match kind {
+                        0 => Data::U32(bits),
+                        _ => Data::F32(bits.into_iter().map(f32::from_bits).collect()),
+                    },
+                })
+                .collect(),
+        })
+    }
+}
+
+pub fn read(path: &Path) -> Result<Dump, String> {
+    Reader::open(path)?.read_all()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::params::Coloring;
+    use crate::render;
+    use crate::stats;
+
+    #[test]
+    fn dumps_round_trip_tile_by_tile() {
+        let params = RenderParams::builder()
+            .size(150, 70)
+            .iterations(200)
+            .coloring(Coloring::Smooth)
+            .build()
+            .unwrap();
+        let frame = render::render_frame(params);
+        let dump = Dump::from_frame(params, &frame);
+        let path = std::env::temp_dir().join(format!("mandelbrot-dump-{}.mbd", std::process::id()));
+        write(&path, &dump).unwrap();
+        assert_eq!(read(&path).unwrap(), dump);
+        // Escape counts of neighbouring pixels differ little and pack into about a byte each,
+        // the chaotic last orbit values hardly at all.
+        let escape = encode_tile(150, &dump.layers[..1], (0, 0, 150, 70));
+        assert!(escape.len() < 150 * 70 * 3 / 2, "{} bytes", escape.len());
+        let raw = 150 * 70 * 4 * dump.layers.len() as u64;
+        assert!(std::fs::metadata(&path).unwrap().len() < raw);
+
+        let mut reader = Reader::open(&path).unwrap();
+        assert_eq!(
+            reader.layer_names().collect::<Vec<_>>(),
+            ["escape", "z re", "z im"]
+        );
+        assert_eq!(reader.tiles().count(), 3 * 2);
+        let (x, y, w, h) = reader.tiles().last().unwrap();
+        assert_eq!((x, y, w, h), (128, 64, 22, 6));
+        let Data::U32(corner) = &reader.tile(5).unwrap()[0] else {
+            panic!("escape layer of the wrong kind");
+        };
+        let Some(Data::U32(escape)) = dump.layer("escape") else {
+            unreachable!()
+        };
+        assert_eq!(corner[w as usize + 3], escape[(65 * 150 + 131) as usize]);
+
+        // The samples recolor as the frame they came from, up to the f32 last orbit values.
+        let mut restored = dump.frame().unwrap();
+        let recolored = params
+            .to_builder()
+            .coloring(Coloring::EscapeTime)
+            .build()
+            .unwrap();
+        assert!(render::recolor(recolored, &mut restored));
+        assert_eq!(restored.rgb, render::render_frame(recolored).rgb);
+
+        let stats = Dump::from_stats(params, &stats::compute(&params));
+        write(&path, &stats).unwrap();
+        assert_eq!(read(&path).unwrap(), stats);
+        assert!(read(&path).unwrap().frame().is_err());
+        std::fs::write(&path, b"MBD0").unwrap();
+        assert!(Reader::open(&path).is_err());
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,141 @@
+use crate::viewport::Viewport;
+use num::complex::Complex;
+
+const NEWTON_STEPS: usize = 64;
+
+// An embedded Julia set found around a minibrot on the way into a point: it shows its
+// largest at about the geometric mean of the scales of the minibrot and of the feature of
+// lower period it sits in.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct EmbeddedJulia {
+    pub period: u32,
+    // Nucleus of the minibrot in the middle of the embedded Julia set.
+    pub center: Complex<f64>,
+    // Suggested height of the view.
+    pub height: f64,
+}
+
+// Atom domain periods of the orbit of `c`: the iterations at which |z| reaches a new minimum.
+// Each one is the period of a component the point is close to, the later the smaller.
+pub fn atom_periods(c: Complex<f64>, iterations: u32) -> Vec<u32> {
+    let mut periods = Vec::new();
+    let (mut z, mut min) = (Complex::new(0.0, 0.0), f64::INFINITY);
+    for n in 1..=iterations {
+        z = z * z + c;
+        let norm = z.norm_sqr();
+        if norm > 4.0 {
+            break;
+        }
+        if norm < min {
+            min = norm;
+            periods.push(n);
+        }
+    }
+    periods
+}
+
+// Newton's method for the nucleus of a period `period` component near `guess`, i.e. a root
+// of z_period(c) = 0.
+pub fn nucleus(guess: Complex<f64>, period: u32) -> Option<Complex<f64>> {
+    let mut c = guess;
+    for _ in 0..NEWTON_STEPS {
+        let (mut z, mut dz) = (Complex::new(0.0, 0.0), Complex::new(0.0, 0.0));
+        for _ in 0..period {
+            dz = 2.0 * z * dz + 1.0;
+            z = z * z + c;
+        }
+        let step = z / dz;
+        if !step.is_finite() {
+            return None;
+        }
+        c -= step;
+        if step.norm() <= 1e-15 * c.norm().max(1e-300) {
+            return Some(c);
+        }
+    }
+    None
+}
+
+// Estimated size of the minibrot of the given period at `nucleus`, relative to the whole set.
+pub fn minibrot_size(nucleus: Complex<f64>, period: u32) -> f64 {
+    let (mut z, mut l, mut b) = (
+        Complex::new(0.0, 0.0),
+        Complex::new(1.0, 0.0),
+        Complex::new(1.0, 0.0),
+    );
+    for _ in 1..period {
+        z = z * z + nucleus;
+        l = 2.0 * z * l;
+        b += 1.0 / l;
+    }
+    (1.0 / (b * l * l)).norm()
+}
+
+// Embedded Julia sets around the minibrots along the zoom into the view center, from the
+// largest to the smallest.
+pub fn detect(viewport: &Viewport, iterations: u32) -> Vec<EmbeddedJulia> {
+    let mut found: Vec<EmbeddedJulia> = Vec::new();
+    let mut parent_size = None;
+    for period in atom_periods(viewport.center, iterations) {
+        let Some(center) = nucleus(viewport.center, period) else {
+            continue;
+        };
+        let size = minibrot_size(center, period);
+        if !size.is_normal() {
+            continue;
+        }
+        if let Some(parent) = parent_size.filter(|parent| size < *parent) {
+            // Newton may land on the same nucleus for several periods, e.g. multiples.
+            if found
+                .iter()
+                .all(|julia| (julia.center - center).norm() > size)
+            {
+                found.push(EmbeddedJulia {
+                    period,
+                    center,
+                    height: (size * parent).sqrt(),
+                });
+            }
+        }
+        parent_size = Some(size);
+    }
+    found.sort_by(|a, b| b.height.total_cmp(&a.height));
+    found
+}
+
+// The first embedded Julia set noticeably deeper than the view.
+pub fn next_deeper(found: &[EmbeddedJulia], viewport: &Viewport) -> Option<EmbeddedJulia> {
+    found
+        .iter()
+        .find(|julia| julia.height < 0.5 * viewport.height)
+        .copied()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_period_three_minibrot() {
+        let c = Complex::new(-1.754, 0.001);
+        assert!(atom_periods(c, 1000).contains(&3));
+        let nucleus = nucleus(c, 3).unwrap();
+        assert!((nucleus - Complex::new(-1.754877666246693, 0.0)).norm() < 1e-12);
+        let size = minibrot_size(nucleus, 3);
+        assert!(size > 0.01 && size < 0.03, "{size}");
+    }
+
+    #[test]
+    fn suggests_deeper_embedded_julia_sets() {
+        // Seahorse valley, near a Feigenbaum-like chain of minibrots.
+        let viewport = Viewport::new(Complex::new(-0.743643887037151, 0.13182590420533), 4.0, 3.0);
+        let found = detect(&viewport, 5000);
+        assert!(!found.is_empty());
+        assert!(found.windows(2).all(|w| w[0].height >= w[1].height));
+        let next = next_deeper(&found, &viewport).unwrap();
+        assert!(next.height < 1.5);
+        let deeper = Viewport::new(next.center, next.height * 4.0 / 3.0, next.height);
+        let after = next_deeper(&found, &deeper);
+        assert!(after.is_none_or(|after| after.height < next.height));
+    }
+}
@@ -0,0 +1,204 @@
+use num::bigint::{BigInt, BigUint, Sign};
+use num::traits::{Float, ToPrimitive};
+use std::ops::{Add, Mul, Sub};
+
+// Fraction bits the centers of views are added up in, below the smallest f64.
+pub const CENTER_BITS: u64 = 1100;
+
+// Fixed-point numbers of arbitrary precision, an integer count of units of 2^-bits, for the
+// centers and reference orbits of views deeper than double-double resolves. Every number of
+// one computation has the same `bits`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Fixed {
+    value: BigInt,
+    bits: u64,
+}
+
+// `x` times 2^exponent, in steps that stay within the exponent range of f64.
+fn scaled(mut x: f64, mut exponent: i64) -> f64 {
+    while exponent > 1000 {
+        x *= 2f64.powi(1000);
+        exponent -= 1000;
+    }
+    while exponent < -1000 {
+        x *= 2f64.powi(-1000);
+        exponent += 1000;
+    }
+    x * 2f64.powi(exponent as i32)
+}
+
+impl Fixed {
+    pub fn zero(bits: u64) -> Fixed {
+        Fixed {
+            value: BigInt::from(0),
+            bits,
+        }
+    }
+
+    // Exact down to 2^-bits, the bits below cut off.
+    pub fn from_f64(x: f64, bits: u64) -> Fixed {
+        let (mantissa, exponent, sign) = x.integer_decode();
+        let magnitude = BigInt::from(mantissa);
+        let shift = exponent as i64 + bits as i64;
+        let magnitude = if shift >= 0 {
+            magnitude << shift as u64
+        } else {
+            magnitude >> (-shift) as u64
+        };
+        Fixed {
+            value: if sign < 0 { -magnitude } else { magnitude },
+            bits,
+        }
+    }
+
+    // The sum of the terms of an expansion, as `terms` splits it.
+    pub fn from_terms(terms: &[f64], bits: u64) -> Fixed {
+        terms.iter().fold(Fixed::zero(bits), |sum, &term| {
+            &sum + &Fixed::from_f64(term, bits)
+        })
+    }
+
+    pub fn to_f64(&self) -> f64 {
+        let magnitude = self.value.magnitude();
+        let shift = magnitude.bits().saturating_sub(64);
+        let head = (magnitude >> shift).to_u64().unwrap_or(0) as f64;
+        let x = scaled(head, shift as i64 - self.bits as i64);
+        match self.value.sign() {
+            Sign::Minus => -x,
+            _ => x,
+        }
+    }
+
+    // The number as up to `count` f64 terms, each the rounding of what the terms before leave,
+    // so that every term holds the next 53 bits or so.
+    pub fn terms(&self, count: usize) -> Vec<f64> {
+        let mut terms = Vec::with_capacity(count);
+        let mut rest = self.clone();
+        while terms.len() < count {
+            let term = rest.to_f64();
+            if term == 0.0 {
+                break;
+            }
+            rest = &rest - &Fixed::from_f64(term, self.bits);
+            terms.push(term);
+        }
+        terms
+    }
+
+    // A decimal number with optional sign, point and exponent, from as many digits as it has.
+    pub fn parse(text: &str, bits: u64) -> Option<Fixed> {
+        let (mantissa, exponent) = match text.split_once(['e', 'E']) {
+            Some((mantissa, exponent)) => (mantissa, exponent.parse::<i64>().ok()?),
+            None => (text, 0),
+        };
+        let (negative, mantissa) = match mantissa.strip_prefix('-') {
+            Some(mantissa) => (true, mantissa),
+            None => (false, mantissa.strip_prefix('+').unwrap_or(mantissa)),
+        };
+        let (integer, fraction) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+        let digits = format!("{integer}{fraction}");
+        if digits.is_empty() || !digits.bytes().all(|digit| digit.is_ascii_digit()) {
+            return None;
+        }
+        let exponent = exponent - fraction.len() as i64;
+        // Beyond this many places everything is cut off anyway.
+        if -exponent > (bits / 3) as i64 + digits.len() as i64 + 1 {
+            return Some(Fixed::zero(bits));
+        }
+        let ten = BigInt::from(10);
+        let scaled = BigInt::parse_bytes(digits.as_bytes(), 10)? << bits;
+        let magnitude = if exponent >= 0 {
+            scaled * ten.pow(exponent as u32)
+        } else {
+            scaled / ten.pow((-exponent) as u32)
+        };
+        Some(Fixed {
+            value: if negative { -magnitude } else { magnitude },
+            bits,
+        })
+    }
+
+    // The number rounded to `places` decimal places, with its sign as `{:+.places$}` writes it.
+    pub fn decimal(&self, places: usize) -> String {
+        let half = BigUint::from(1u32) << self.bits.saturating_sub(1);
+        let rounded =
+            (self.value.magnitude() * BigUint::from(10u32).pow(places as u32) + half) >> self.bits;
+        let digits = format!("{rounded:0>width$}", width = places + 1);
+        let (integer, fraction) = digits.split_at(digits.len() - places);
+        let sign = if self.value.sign() == Sign::Minus {
+            '-'
+        } else {
+            '+'
+        };
+        match places {
+            0 => format!("{sign}{integer}"),
+            _ => format!("{sign}{integer}.{fraction}"),
+        }
+    }
+}
+
+impl Add for &Fixed {
+    type Output = Fixed;
+
+    fn add(self, other: &Fixed) -> Fixed {
+        Fixed {
+            value: &self.value + &other.value,
+            bits: self.bits,
+        }
+    }
+}
+
+impl Sub for &Fixed {
+    type Output = Fixed;
+
+    fn sub(self, other: &Fixed) -> Fixed {
+        Fixed {
+            value: &self.value - &other.value,
+            bits: self.bits,
+        }
+    }
+}
+
+impl Mul for &Fixed {
+    type Output = Fixed;
+
+    fn mul(self, other: &Fixed) -> Fixed {
+        Fixed {
+            value: (&self.value * &other.value) >> self.bits,
+            bits: self.bits,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeps_digits_beyond_double_double() {
+        let bits = CENTER_BITS;
+        for x in [0.0, -0.75, 1e-300, 3.0_f64.sqrt(), -123456.789] {
+            assert_eq!(Fixed::from_f64(x, bits).to_f64(), x);
+        }
+        let text = "-1.740062382579339905220844167065825638296641720436171866879862418461";
+        let value = Fixed::parse(text, bits).unwrap();
+        let terms = value.terms(8);
+        assert_eq!(terms[0], -1.74006238257934);
+        assert!(terms
+            .windows(2)
+            .all(|w| w[1].abs() <= w[0].abs() * 2f64.powi(-52)));
+        let back = Fixed::from_terms(&terms, bits);
+        assert_eq!(back.decimal(66), text);
+        assert!((&back - &value).to_f64().abs() < 1e-120);
+        assert_eq!(Fixed::parse("+2.5e-3", 64).unwrap().decimal(4), "+0.0025");
+        assert_eq!(Fixed::parse("-0.0001e2", 64).unwrap().decimal(0), "-0");
+        assert_eq!(Fixed::from_f64(-0.75, 64).decimal(1), "-0.8");
+        for invalid in ["", "-", "1.2.3", "0x10", "1e"] {
+            assert!(Fixed::parse(invalid, 64).is_none(), "{invalid}");
+        }
+        assert_eq!(Fixed::parse("1e-1000000000", 64), Some(Fixed::zero(64)));
+        let (a, b) = (Fixed::from_f64(1.5, 80), Fixed::from_f64(-0.25, 80));
+        assert_eq!((&a * &b).to_f64(), -0.375);
+        assert_eq!((&a + &b).to_f64(), 1.25);
+    }
+}
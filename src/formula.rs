@@ -0,0 +1,583 @@
+use crate::params::{Fractal, Plane};
+use num::complex::Complex;
+use std::sync::{Arc, RwLock};
+
+// Squared radius beyond which the orbit of a formula counts as escaped. Larger than that of the
+// quadratic sets, as the other terms of a formula can pull it back from further out; orbits
+// overflowing or dividing by zero escape too.
+pub const BAILOUT: f64 = 1e4;
+
+// Formulas parsed so far, referred to by their index from `Fractal::Formula`, which stays
+// `Copy`. Each distinct text is compiled once.
+static FORMULAS: RwLock<Vec<Arc<Formula>>> = RwLock::new(Vec::new());
+
+// Newton's method for the critical points starts from the origin and from this many points on
+// a circle of the radius around it, for at most the steps.
+const CRITICAL_STARTS: usize = 8;
+const CRITICAL_RADIUS: f64 = 1.5;
+const NEWTON_STEPS: usize = 60;
+// The constant at which the critical points of a new formula are counted for the warning about
+// several of them.
+const PROBE: Complex<f64> = Complex::new(-0.3, 0.2);
+
+type Eval = Box<dyn Fn(Complex<f64>, Complex<f64>, Complex<f64>) -> Complex<f64> + Send + Sync>;
+
+// A user formula of `z`, `c` and a second parameter `p` iterated instead of z^2 + c, compiled
+// into nested closures so that the parse tree is walked once rather than for every pixel. Its
+// first two derivatives by `z` locate the critical points the orbits of the parameter plane
+// start from.
+pub struct Formula {
+    pub text: String,
+    eval: Eval,
+    // Whether the formula has the parameter `p`, and so a plane of it.
+    pub has_p: bool,
+    // None for formulas without a derivative, those taking `conj` of z.
+    derivatives: Option<(Eval, Eval)>,
+    // The critical points of formulas whose derivative depends on neither parameter, found once.
+    critical: Option<Vec<Complex<f64>>>,
+}
+
+impl Formula {
+    pub fn parse(text: &str) -> Result<Formula, String> {
+        let tokens = tokenize(text)?;
+        let mut parser = Parser { tokens, at: 0 };
+        let expr = parser.sum()?;
+        if let Some(token) = parser.tokens.get(parser.at) {
+            return Err(format!("Unexpected {token:?} in {text}"));
+        }
+        let first = expr.derivative();
+        let second = first.as_ref().and_then(Expr::derivative);
+        let constant = first.as_ref().is_some_and(|first| !first.has_parameters());
+        let mut formula = Formula {
+            text: text.to_string(),
+            has_p: expr.has(&|expr| matches!(expr, Expr::P)),
+            eval: compile(expr),
+            derivatives: first.zip(second).map(|(a, b)| (compile(a), compile(b))),
+            critical: None,
+        };
+        if constant {
+            formula.critical = Some(formula.find_critical_points(PROBE, PROBE));
+        }
+        Ok(formula)
+    }
+
+    pub fn eval(&self, z: Complex<f64>, c: Complex<f64>, p: Complex<f64>) -> Complex<f64> {
+        (self.eval)(z, c, p)
+    }
+
+    // The zeros of the derivative by z at `c` and `p` the Newton iterations from the starting
+    // points converge to, ordered by their real and then their imaginary part.
+    pub fn critical_points(&self, c: Complex<f64>, p: Complex<f64>) -> Vec<Complex<f64>> {
+        match &self.critical {
+            Some(points) => points.clone(),
+            None => self.find_critical_points(c, p),
+        }
+    }
+
+    // The starting value of the orbit of `c` and `p`: the `n`th of their critical points,
+    // counted around if there are fewer, or 0 without any.
+    pub fn critical_point(&self, c: Complex<f64>, p: Complex<f64>, n: u32) -> Complex<f64> {
+        let chosen = |points: &[Complex<f64>]| match points.len() {
+            0 => Complex::new(0.0, 0.0),
+            len => points[n as usize % len],
+        };
+        match &self.critical {
+            Some(points) => chosen(points),
+            None => chosen(&self.find_critical_points(c, p)),
+        }
+    }
+
+    fn find_critical_points(&self, c: Complex<f64>, p: Complex<f64>) -> Vec<Complex<f64>> {
+        let Some((first, second)) = &self.derivatives else {
+            return Vec::new();
+        };
+        let ring = (0..CRITICAL_STARTS).map(|k| {
+            let angle = (k as f64 + 0.5) / CRITICAL_STARTS as f64 * std::f64::consts::TAU;
+            Complex::from_polar(CRITICAL_RADIUS, angle)
+        });
+        let mut points = Vec::<Complex<f64>>::new();
+        for start in std::iter::once(Complex::new(0.0, 0.0)).chain(ring) {
+            let mut z = start;
+            let converged = (0..NEWTON_STEPS).any(|_| {
+                let step = first(z, c, p) / second(z, c, p);
+                z -= step;
+                step.norm() <= 1e-13 * (1.0 + z.norm())
+            });
+            let seen = |point: &Complex<f64>| (point - z).norm() < 1e-7 * (1.0 + z.norm());
+            if converged && z.is_finite() && !points.iter().any(seen) {
+                points.push(z);
+            }
+        }
+        // Rounded so that the order does not flip on the last bits of equal parts.
+        let key = |z: &Complex<f64>| ((z.re * 1e9).round() as i64, (z.im * 1e9).round() as i64);
+        points.sort_by_key(key);
+        points
+    }
+}
+
+// The fractal iterating the formula from its first critical point, with `c` the point of the
+// image and `p` 0. A warning tells when there are several critical points to choose from.
+pub fn fractal(text: &str) -> Result<Fractal, String> {
+    let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    let mut formulas = FORMULAS.write().unwrap();
+    let index = match formulas.iter().position(|formula| formula.text == text) {
+        Some(index) => index,
+        None => {
+            let formula = Formula::parse(&text)?;
+            let count = formula.critical_points(PROBE, PROBE).len();
+            if count > 1 {
+                println!(
+                    "{text} has {count} critical points, the orbits start from the first; \
+                     `set critical N` picks another"
+                );
+            }
+            formulas.push(Arc::new(formula));
+            formulas.len() - 1
+        }
+    };
+    Ok(Fractal::Formula {
+        index: index as u32,
+        critical: 0,
+        plane: Plane::C,
+        fixed: Complex::new(0.0, 0.0),
+    })
+}
+
+pub fn get(index: u32) -> Arc<Formula> {
+    FORMULAS.read().unwrap()[index as usize].clone()
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Number(f64),
+    // A number followed by `i`.
+    Imaginary(f64),
+    Name(String),
+    Symbol(char),
+}
+
+fn tokenize(text: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = Vec::new();
+    let mut chars = text.char_indices().peekable();
+    while let Some(&(start, ch)) = chars.peek() {
+        if ch.is_whitespace() {
+            chars.next();
+        } else if ch.is_ascii_digit() || ch == '.' {
+            let mut end = start;
+            while let Some(&(at, ch)) = chars.peek() {
+                // Exponents of scientific notation, with their sign.
+                let signed = matches!(ch, '+' | '-') && text[..at].ends_with(['e', 'E']);
+                if !(ch.is_ascii_digit() || matches!(ch, '.' | 'e' | 'E') || signed) {
+                    break;
+                }
+                end = at + ch.len_utf8();
+                chars.next();
+            }
+            let number = text[start..end]
+                .parse::<f64>()
+                .map_err(|_| format!("Invalid number {}", &text[start..end]))?;
+            if chars.peek().is_some_and(|&(_, ch)| ch == 'i') {
+                chars.next();
+                tokens.push(Token::Imaginary(number));
+            } else {
+                tokens.push(Token::Number(number));
+            }
+        } else if ch.is_ascii_alphabetic() {
+            let mut name = String::new();
+            while let Some(&(_, ch)) = chars.peek().filter(|(_, ch)| ch.is_ascii_alphanumeric()) {
+                name.push(ch);
+                chars.next();
+            }
+            tokens.push(Token::Name(name));
+        } else if "+-*/^()".contains(ch) {
+            tokens.push(Token::Symbol(ch));
+            chars.next();
+        } else {
+            return Err(format!("Unexpected {ch} in {text}"));
+        }
+    }
+    Ok(tokens)
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Function {
+    Exp,
+    Log,
+    Sqrt,
+    Sin,
+    Cos,
+    Conj,
+}
+
+#[derive(Clone, Debug)]
+enum Expr {
+    Z,
+    C,
+    P,
+    Constant(Complex<f64>),
+    Negate(Box<Expr>),
+    Binary(char, Box<Expr>, Box<Expr>),
+    Call(Function, Box<Expr>),
+}
+
+impl Expr {
+    // Whether any part of the expression is one `matching`.
+    fn has(&self, matching: &impl Fn(&Expr) -> bool) -> bool {
+        matching(self)
+            || match self {
+                Expr::Negate(expr) | Expr::Call(_, expr) => expr.has(matching),
+                Expr::Binary(_, a, b) => a.has(matching) || b.has(matching),
+                _ => false,
+            }
+    }
+
+    fn has_parameters(&self) -> bool {
+        self.has(&|expr| matches!(expr, Expr::C | Expr::P))
+    }
+
+    // The derivative by z, `None` through `conj`, which has none.
+    fn derivative(&self) -> Option<Expr> {
+        let number = |x: f64| Box::new(Expr::Constant(Complex::new(x, 0.0)));
+        let of = |op: char, a: Expr, b: Expr| Expr::Binary(op, Box::new(a), Box::new(b));
+        if self.constant().is_some() {
+            return Some(*number(0.0));
+        }
+        Some(match self {
+            Expr::Z => *number(1.0),
+            Expr::C | Expr::P | Expr::Constant(_) => *number(0.0),
+            Expr::Negate(expr) => Expr::Negate(Box::new(expr.derivative()?)),
+            Expr::Binary(op @ ('+' | '-'), a, b) => of(*op, a.derivative()?, b.derivative()?),
+            Expr::Binary('*', a, b) => of(
+                '+',
+                of('*', a.derivative()?, *b.clone()),
+                of('*', *a.clone(), b.derivative()?),
+            ),
+            Expr::Binary('/', a, b) => of(
+                '/',
+                of(
+                    '-',
+                    of('*', a.derivative()?, *b.clone()),
+                    of('*', *a.clone(), b.derivative()?),
+                ),
+                of('*', *b.clone(), *b.clone()),
+            ),
+            // n a^(n - 1) a' for constant exponents, a^b (b' log(a) + b a' / a) otherwise.
+            Expr::Binary(_, a, b) => match b.constant() {
+                Some(n) => of(
+                    '*',
+                    of(
+                        '*',
+                        Expr::Constant(n),
+                        of('^', *a.clone(), Expr::Constant(n - 1.0)),
+                    ),
+                    a.derivative()?,
+                ),
+                None => of(
+                    '*',
+                    self.clone(),
+                    of(
+                        '+',
+                        of('*', b.derivative()?, Expr::Call(Function::Log, a.clone())),
+                        of('/', of('*', *b.clone(), a.derivative()?), *a.clone()),
+                    ),
+                ),
+            },
+            Expr::Call(function, a) => {
+                let outer = match function {
+                    Function::Exp => self.clone(),
+                    Function::Log => of('/', *number(1.0), *a.clone()),
+                    Function::Sqrt => of('/', *number(0.5), self.clone()),
+                    Function::Sin => Expr::Call(Function::Cos, a.clone()),
+                    Function::Cos => Expr::Negate(Box::new(Expr::Call(Function::Sin, a.clone()))),
+                    Function::Conj => return None,
+                };
+                of('*', outer, a.derivative()?)
+            }
+        })
+    }
+
+    // The value of the parts without `z` and `c`, folded at compile time.
+    fn constant(&self) -> Option<Complex<f64>> {
+        let value = |expr: &Expr| Expr::constant(expr);
+        Some(match self {
+            Expr::Z | Expr::C | Expr::P => return None,
+            Expr::Constant(value) => *value,
+            Expr::Negate(expr) => -value(expr)?,
+            Expr::Binary(op, a, b) => binary(*op, value(a)?, value(b)?),
+            Expr::Call(function, expr) => call(*function, value(expr)?),
+        })
+    }
+}
+
+fn binary(op: char, a: Complex<f64>, b: Complex<f64>) -> Complex<f64> {
+    match op {
+        '+' => a + b,
+        '-' => a - b,
+        '*' => a * b,
+        '/' => a / b,
+        _ => match whole(b) {
+            Some(n) => a.powi(n),
+            None => a.powc(b),
+        },
+    }
+}
+
+// Exponents taken by repeated squaring.
+fn whole(exponent: Complex<f64>) -> Option<i32> {
+    (exponent.im == 0.0 && exponent.re.fract() == 0.0 && exponent.re.abs() <= 64.0)
+        .then_some(exponent.re as i32)
+}
+
+fn call(function: Function, z: Complex<f64>) -> Complex<f64> {
+    match function {
+        Function::Exp => z.exp(),
+        Function::Log => z.ln(),
+        Function::Sqrt => z.sqrt(),
+        Function::Sin => z.sin(),
+        Function::Cos => z.cos(),
+        Function::Conj => z.conj(),
+    }
+}
+
+// Recursive descent over
+//
+//     sum = product (("+" | "-") product)*
+//     product = unary (("*" | "/") unary)*
+//     unary = "-" unary | power
+//     power = atom ("^" unary)?
+//     atom = NUMBER | NUMBER "i" | "z" | "c" | "p" | "i" | FUNCTION "(" sum ")" | "(" sum ")"
+struct Parser {
+    tokens: Vec<Token>,
+    at: usize,
+}
+
+impl Parser {
+    fn next_symbol(&mut self, symbols: &str) -> Option<char> {
+        match self.tokens.get(self.at) {
+            Some(&Token::Symbol(symbol)) if symbols.contains(symbol) => {
+                self.at += 1;
+                Some(symbol)
+            }
+            _ => None,
+        }
+    }
+
+    fn sum(&mut self) -> Result<Expr, String> {
+        let mut expr = self.product()?;
+        while let Some(op) = self.next_symbol("+-") {
+            expr = Expr::Binary(op, Box::new(expr), Box::new(self.product()?));
+        }
+        Ok(expr)
+    }
+
+    fn product(&mut self) -> Result<Expr, String> {
+        let mut expr = self.unary()?;
+        while let Some(op) = self.next_symbol("*/") {
+            expr = Expr::Binary(op, Box::new(expr), Box::new(self.unary()?));
+        }
+        Ok(expr)
+    }
+
+    fn unary(&mut self) -> Result<Expr, String> {
+        if self.next_symbol("-").is_some() {
+            return Ok(Expr::Negate(Box::new(self.unary()?)));
+        }
+        let base = self.atom()?;
+        match self.next_symbol("^") {
+            // Right-associative, z^2^3 is z^8.
+            Some(op) => Ok(Expr::Binary(op, Box::new(base), Box::new(self.unary()?))),
+            None => Ok(base),
+        }
+    }
+
+    fn atom(&mut self) -> Result<Expr, String> {
+        let token = self.tokens.get(self.at).cloned();
+        self.at += 1;
+        match token {
+            Some(Token::Number(x)) => Ok(Expr::Constant(Complex::new(x, 0.0))),
+            Some(Token::Imaginary(y)) => Ok(Expr::Constant(Complex::new(0.0, y))),
+            Some(Token::Symbol('(')) => {
+                let expr = self.sum()?;
+                self.next_symbol(")")
+                    .ok_or_else(|| "Missing )".to_string())?;
+                Ok(expr)
+            }
+            Some(Token::Name(name)) => {
+                let function = match name.as_str() {
+                    "z" => return Ok(Expr::Z),
+                    "c" => return Ok(Expr::C),
+                    "p" => return Ok(Expr::P),
+                    "i" => return Ok(Expr::Constant(Complex::new(0.0, 1.0))),
+                    "exp" => Function::Exp,
+                    "log" => Function::Log,
+                    "sqrt" => Function::Sqrt,
+                    "sin" => Function::Sin,
+                    "cos" => Function::Cos,
+                    "conj" => Function::Conj,
+                    _ => {
+                        return Err(format!(
+                            "Unknown name {name}, expected z, c, p, i or a function"
+                        ))
+                    }
+                };
+                if self.next_symbol("(").is_none() {
+                    return Err(format!("Expected ( after {name}"));
+                }
+                let argument = self.sum()?;
+                self.next_symbol(")")
+                    .ok_or_else(|| "Missing )".to_string())?;
+                Ok(Expr::Call(function, Box::new(argument)))
+            }
+            Some(token) => Err(format!("Unexpected {token:?}")),
+            None => Err("Unexpected end of the formula".to_string()),
+        }
+    }
+}
+
+fn compile(expr: Expr) -> Eval {
+    if let Some(value) = expr.constant() {
+        return Box::new(move |_, _, _| value);
+    }
+    match expr {
+        Expr::Z => Box::new(|z, _, _| z),
+        Expr::C => Box::new(|_, c, _| c),
+        Expr::P => Box::new(|_, _, p| p),
+        Expr::Constant(value) => Box::new(move |_, _, _| value),
+        Expr::Negate(expr) => {
+            let a = compile(*expr);
+            Box::new(move |z, c, p| -a(z, c, p))
+        }
+        // Squares as a single product.
+        Expr::Binary('^', base, exponent) => {
+            let a = compile(*base);
+            match exponent.constant().and_then(whole) {
+                Some(2) => Box::new(move |z, c, p| {
+                    let w = a(z, c, p);
+                    w * w
+                }),
+                Some(n) => Box::new(move |z, c, p| a(z, c, p).powi(n)),
+                None => {
+                    let b = compile(*exponent);
+                    Box::new(move |z, c, p| binary('^', a(z, c, p), b(z, c, p)))
+                }
+            }
+        }
+        Expr::Binary(op, a, b) => {
+            let (a, b) = (compile(*a), compile(*b));
+            match op {
+                '+' => Box::new(move |z, c, p| a(z, c, p) + b(z, c, p)),
+                '-' => Box::new(move |z, c, p| a(z, c, p) - b(z, c, p)),
+                '*' => Box::new(move |z, c, p| a(z, c, p) * b(z, c, p)),
+                _ => Box::new(move |z, c, p| a(z, c, p) / b(z, c, p)),
+            }
+        }
+        Expr::Call(function, expr) => {
+            let a = compile(*expr);
+            Box::new(move |z, c, p| call(function, a(z, c, p)))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::render;
+
+    #[test]
+    fn parses_and_iterates_formulas() {
+        let (z, c, p) = (
+            Complex::new(0.5, -0.25),
+            Complex::new(-0.1, 0.7),
+            Complex::new(0.0, 0.0),
+        );
+        let formula = Formula::parse("z^3 + c*z + c").unwrap();
+        assert_eq!(formula.eval(z, c, p), z * z * z + c * z + c);
+        let formula = Formula::parse("-(z - 2i)^2 / (1.5e0 + i) + exp(conj(c))").unwrap();
+        let expected =
+            -(z - Complex::new(0.0, 2.0)).powi(2) / Complex::new(1.5, 1.0) + c.conj().exp();
+        assert!((formula.eval(z, c, p) - expected).norm() < 1e-12);
+        assert_eq!(Formula::parse("2^3^2").unwrap().eval(z, c, p).re, 512.0);
+        for invalid in ["z^", "(z + c", "w + c", "z $ c", "sin z", "z c"] {
+            assert!(Formula::parse(invalid).is_err(), "{invalid}");
+        }
+
+        // The same fractal for the same text, and z^2 + c has the interior of the Mandelbrot set.
+        let quadratic = fractal("z^2 + c").unwrap();
+        assert_eq!(fractal("z^2  +  c"), Ok(quadratic));
+        for i in 0..100 {
+            let c = Complex::new(-2.0 + (i % 10) as f64 * 0.25, -1.1 + (i / 10) as f64 * 0.24);
+            let mut z = Complex::new(0.0, 0.0);
+            let escape = (0..200).find(|_| {
+                z = z * z + c;
+                z.norm_sqr() > BAILOUT
+            });
+            assert_eq!(render::escape_time(quadratic, c, 200).escape, escape, "{c}");
+        }
+        // z^3 + c*z + c starts from the critical points +-sqrt(-c/3), z^2 + c from 0.
+        let cubic = Formula::parse("z^3 + c*z + c").unwrap();
+        let critical = cubic.critical_points(c, p);
+        let root = (-c / 3.0).sqrt();
+        assert_eq!(critical.len(), 2);
+        assert!(critical
+            .iter()
+            .all(|z| (z - root).norm().min((z + root).norm()) < 1e-12));
+        assert!(critical[0].re < critical[1].re);
+        assert_eq!(cubic.critical_point(c, p, 1), critical[1]);
+        assert_eq!(cubic.critical_point(c, p, 2), critical[0]);
+        assert_eq!(
+            Formula::parse("z^2 + c").unwrap().critical_points(c, p),
+            vec![z * 0.0]
+        );
+        let trigonometric = Formula::parse("sin(z) * exp(z / 2) + c").unwrap();
+        for z in trigonometric.critical_points(c, p) {
+            let derivative = (z.cos() + 0.5 * z.sin()) * (z / 2.0).exp();
+            assert!(derivative.norm() < 1e-9, "{z}");
+        }
+        assert!(Formula::parse("conj(z)^2 + c")
+            .unwrap()
+            .critical_points(c, p)
+            .is_empty());
+        // Division by zero escapes instead of spreading NaN.
+        let pole = fractal("1 / z + c").unwrap();
+        assert_eq!(
+            render::escape_time(pole, Complex::new(0.0, 0.0), 10).escape,
+            Some(0)
+        );
+    }
+
+    #[test]
+    fn planes_of_the_second_parameter() {
+        let formula = Formula::parse("z^3 + p*z + c").unwrap();
+        let (z, c, p) = (
+            Complex::new(0.5, -0.25),
+            Complex::new(-0.1, 0.7),
+            Complex::new(0.3, 0.1),
+        );
+        assert_eq!(formula.eval(z, c, p), z * z * z + p * z + c);
+        assert!(formula.has_p && !Formula::parse("z^2 + c").unwrap().has_p);
+        // The critical points follow p here, not c.
+        let root = (-p / 3.0).sqrt();
+        assert!(formula
+            .critical_points(c, p)
+            .iter()
+            .all(|z| (z - root).norm().min((z + root).norm()) < 1e-12));
+
+        // A point of the plane of p with c fixed is the point of the plane of c with p fixed.
+        let Fractal::Formula { index, .. } = fractal("z^2 + p*z + c").unwrap() else {
+            unreachable!()
+        };
+        let plane = |plane, fixed| Fractal::Formula {
+            index,
+            critical: 0,
+            plane,
+            fixed,
+        };
+        for i in 0..50 {
+            let point = Complex::new(-1.5 + (i % 10) as f64 * 0.3, -0.9 + (i / 10) as f64 * 0.4);
+            let fixed = Complex::new(0.8, 0.3);
+            let of_c = render::escape_time(plane(Plane::C, fixed), point, 100);
+            let of_p = render::escape_time(plane(Plane::P, point), fixed, 100);
+            assert_eq!(of_c.escape, of_p.escape, "{point}");
+        }
+    }
+}
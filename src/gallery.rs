@@ -0,0 +1,175 @@
+use crate::bookmarks::{self, Bookmark};
+use crate::cache::{self, RenderCache};
+use crate::manifest::{self, Manifest};
+use crate::params::RenderParams;
+use crate::png;
+use std::path::PathBuf;
+use std::time::Instant;
+
+const STYLE: &str = "body{background:#181818;color:#e6e6e6;font-family:sans-serif;margin:2em}\
+figure{display:inline-block;margin:0 1em 2em 0;vertical-align:top}\
+img{display:block;max-width:100%}figcaption{margin-top:.5em}\
+code{display:block;color:#aaa;margin:.3em 0}button{cursor:pointer}";
+
+// The `gallery build` subcommand: every bookmark of a file rendered into a static web page.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Gallery {
+    pub bookmarks: PathBuf,
+    // Directory receiving the images, their manifests and `index.html`.
+    pub output: PathBuf,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Default for Gallery {
+    fn default() -> Self {
+        Gallery {
+            bookmarks: PathBuf::from("bookmarks.json"),
+            output: PathBuf::from("gallery"),
+            width: 640,
+            height: 480,
+        }
+    }
+}
+
+// Console command going to the bookmarked view, to paste into the explorer.
+pub fn share(params: &RenderParams) -> String {
+    let viewport = &params.viewport;
+    format!(
+        "goto {:e} {:e} {:e}",
+        viewport.center.re, viewport.center.im, viewport.height
+    )
+}
+
+// File name of the image of the bookmark: its position and the name lowercased to letters and
+// digits, so that the files sort in the order of the bookmarks.
+fn file_name(index: usize, name: &str) -> String {
+    let slug = name
+        .to_lowercase()
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .collect::<Vec<_>>()
+        .join("-");
+    format!("{:03}-{slug}.png", index + 1)
+}
+
+fn escape(text: &str) -> String {
+    text.chars()
+        .map(|c| match c {
+            '&' => "&amp;".to_string(),
+            '<' => "&lt;".to_string(),
+            '>' => "&gt;".to_string(),
+            '"' => "&quot;".to_string(),
+            '\'' => "&#39;".to_string(),
+            c => c.to_string(),
+        })
+        .collect()
+}
+
+fn page(title: &str, figures: &[(String, Bookmark)]) -> String {
+    let mut html = format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{}</title>\n\
+         <style>{STYLE}</style>\n</head>\n<body>\n<h1>{}</h1>\n",
+        escape(title),
+        escape(title)
+    );
+    for (file, Bookmark { name, params }) in figures {
+        let share = escape(&share(params));
+        html += &format!(
+            "<figure>\n<a href=\"{file}\"><img src=\"{file}\" width=\"{}\" height=\"{}\" \
+             alt=\"{name}\"></a>\n<figcaption>{name}\n<code>{share}</code>\n<button \
+             data-share=\"{share}\" onclick=\"navigator.clipboard.writeText(this.dataset.share)\
+             \">Copy</button>\n</figcaption>\n</figure>\n",
+            params.width,
+            params.height,
+            name = escape(name),
+        );
+    }
+    html + "</body>\n</html>\n"
+}
+
+pub fn run(job: &Gallery, cache: Option<&RenderCache>) -> Result<(), String> {
+    let stamp = Instant::now();
+    let bookmarks = bookmarks::load(&job.bookmarks)?;
+    if bookmarks.is_empty() {
+        return Err(format!("{}: no bookmarks", job.bookmarks.display()));
+    }
+    std::fs::create_dir_all(&job.output).map_err(|e| format!("{}: {e}", job.output.display()))?;
+    let mut figures = Vec::new();
+    for (i, bookmark) in bookmarks.iter().enumerate() {
+        println!("Image {}/{}: {}", i + 1, bookmarks.len(), bookmark.name);
+        let params = bookmarks::resized(bookmark.params, job.width, job.height)?;
+        let rendered = Instant::now();
+        let frame = cache::render(cache, params);
+        let path = job.output.join(file_name(i, &bookmark.name));
+        png::write(&path, frame.width, frame.height, &frame.rgb)?;
+        // Each image can be rendered again with `--params`.
+        Manifest::new(params, Instant::now() - rendered).write(&manifest::sidecar(&path))?;
+        let file = path.file_name().unwrap().to_string_lossy().into_owned();
+        let name = bookmark.name.clone();
+        figures.push((file, Bookmark { name, params }));
+    }
+    let title = job
+        .bookmarks
+        .file_stem()
+        .map_or("Gallery".to_string(), |stem| {
+            stem.to_string_lossy().into_owned()
+        });
+    let index = job.output.join("index.html");
+    std::fs::write(&index, page(&title, &figures))
+        .map_err(|e| format!("{}: {e}", index.display()))?;
+    println!(
+        "Wrote {} with {} images in {:?}",
+        index.display(),
+        figures.len(),
+        Instant::now() - stamp
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::viewport::Viewport;
+    use num::complex::Complex;
+
+    #[test]
+    fn builds_gallery() {
+        assert_eq!(
+            file_name(4, "Seahorse valley, #2"),
+            "005-seahorse-valley-2.png"
+        );
+        assert_eq!(escape("<a & \"b\">"), "&lt;a &amp; &quot;b&quot;&gt;");
+        let params = RenderParams::builder()
+            .size(800, 600)
+            .viewport(Viewport::new(Complex::new(-0.75, 0.1), 0.2, 0.1))
+            .iterations(50)
+            .build()
+            .unwrap();
+        assert_eq!(share(&params), "goto -7.5e-1 1e-1 1e-1");
+        let dir = std::env::temp_dir().join(format!("mandelbrot-gallery-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let job = Gallery {
+            bookmarks: dir.join("favourites.json"),
+            output: dir.join("site"),
+            width: 32,
+            height: 24,
+        };
+        let bookmark = Bookmark {
+            name: "seahorses <3".to_string(),
+            params,
+        };
+        bookmarks::save(&job.bookmarks, &[bookmark]).unwrap();
+        run(&job, None).unwrap();
+        let image = job.output.join("001-seahorses-3.png");
+        assert!(image.exists());
+        let manifest = Manifest::load(&manifest::sidecar(&image)).unwrap();
+        assert_eq!((manifest.params.width, manifest.params.height), (32, 24));
+        let html = std::fs::read_to_string(job.output.join("index.html")).unwrap();
+        assert!(html.contains("<title>favourites</title>"));
+        assert!(html.contains("<img src=\"001-seahorses-3.png\" width=\"32\" height=\"24\""));
+        assert!(html.contains("seahorses &lt;3"));
+        assert!(html.contains("data-share=\"goto -7.5e-1 1e-1 1e-1\""));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
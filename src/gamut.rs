@@ -0,0 +1,128 @@
+use rayon::prelude::*;
+
+// Linear sRGB to linear Display P3, both with the D65 white point.
+const SRGB_TO_P3: [[f32; 3]; 3] = [
+    [0.8225, 0.1774, 0.0000],
+    [0.0332, 0.9669, 0.0000],
+    [0.0171, 0.0724, 0.9108],
+];
+// Resolution of the table encoding linear values back to 8 bits.
+const ENCODE_STEPS: usize = 4096;
+
+// Color space of the monitor the window is shown on. Frames are computed in sRGB; wide-gamut
+// monitors show them oversaturated unless the window converts them.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Gamut {
+    Srgb,
+    DisplayP3,
+    // Conversion from linear sRGB given as a row-major 3x3 matrix, keeping the sRGB transfer
+    // curve.
+    Matrix([[f32; 3]; 3]),
+}
+
+impl Gamut {
+    // `srgb`, `display-p3` or nine comma separated matrix coefficients.
+    pub fn parse(text: &str) -> Result<Gamut, String> {
+        match text {
+            "srgb" => Ok(Gamut::Srgb),
+            "display-p3" => Ok(Gamut::DisplayP3),
+            _ => {
+                let values = text
+                    .split(',')
+                    .map(|value| value.trim().parse::<f32>())
+                    .collect::<Result<Vec<_>, _>>()
+                    .map_err(|e| format!("Invalid gamut {text}: {e}"))?;
+                match values[..] {
+                    [a, b, c, d, e, f, g, h, i] => {
+                        Ok(Gamut::Matrix([[a, b, c], [d, e, f], [g, h, i]]))
+                    }
+                    _ => Err(format!(
+                        "Invalid gamut {text}, expected srgb, display-p3 or 9 matrix coefficients"
+                    )),
+                }
+            }
+        }
+    }
+
+    // The conversion for the window, `None` when frames are shown as they are.
+    pub fn transform(&self) -> Option<GamutTransform> {
+        match self {
+            Gamut::Srgb => None,
+            Gamut::DisplayP3 => Some(GamutTransform::new(SRGB_TO_P3)),
+            Gamut::Matrix(matrix) => Some(GamutTransform::new(*matrix)),
+        }
+    }
+}
+
+fn decode(value: f32) -> f32 {
+    if value <= 0.04045 {
+        value / 12.92
+    } else {
+        ((value + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+fn encode(linear: f32) -> f32 {
+    if linear <= 0.0031308 {
+        12.92 * linear
+    } else {
+        1.055 * linear.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+pub struct GamutTransform {
+    matrix: [[f32; 3]; 3],
+    decode: [f32; 256],
+    encode: Vec<u8>,
+}
+
+impl GamutTransform {
+    fn new(matrix: [[f32; 3]; 3]) -> Self {
+        GamutTransform {
+            matrix,
+            decode: std::array::from_fn(|i| decode(i as f32 / 255.0)),
+            encode: (0..ENCODE_STEPS)
+                .map(|i| {
+                    let linear = i as f32 / (ENCODE_STEPS - 1) as f32;
+                    (encode(linear) * 255.0).round() as u8
+                })
+                .collect(),
+        }
+    }
+
+    // Converts 8-bit sRGB pixels in place, clipping colors outside the target gamut.
+    pub fn apply(&self, rgb: &mut [u8]) {
+        rgb.par_chunks_mut(3).for_each(|pixel| {
+            let linear = [0, 1, 2].map(|i| self.decode[pixel[i] as usize]);
+            for (channel, row) in pixel.iter_mut().zip(self.matrix) {
+                let value = row[0] * linear[0] + row[1] * linear[1] + row[2] * linear[2];
+                let index = (value.clamp(0.0, 1.0) * (ENCODE_STEPS - 1) as f32).round();
+                *channel = self.encode[index as usize];
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn display_p3_desaturates_primaries() {
+        assert_eq!(Gamut::parse("srgb"), Ok(Gamut::Srgb));
+        assert!(Gamut::parse("1,0,0,0,1,0,0,0").is_err());
+        let identity = Gamut::parse("1,0,0, 0,1,0, 0,0,1")
+            .unwrap()
+            .transform()
+            .unwrap();
+        let mut rgb = [0, 0, 0, 255, 255, 255, 12, 128, 200];
+        identity.apply(&mut rgb);
+        assert_eq!(rgb, [0, 0, 0, 255, 255, 255, 12, 128, 200]);
+        let p3 = Gamut::DisplayP3.transform().unwrap();
+        let mut red = [255, 0, 0, 255, 255, 255];
+        p3.apply(&mut red);
+        // The sRGB primary is well inside the P3 gamut, white stays white.
+        assert!(red[0] > 225 && red[1] > 40 && red[1] < 70 && red[2] > 25 && red[2] < 50);
+        assert_eq!(red[3..], [255, 255, 255]);
+    }
+}
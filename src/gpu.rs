@@ -0,0 +1,716 @@
+use crate::buddhabrot::Accumulation;
+use crate::kernel;
+use crate::params::{Backend, Fractal, RenderParams};
+use crate::render::{self, Frame, Sample};
+use crate::scheduler::{Precision, Worker};
+use num::complex::Complex;
+use std::ffi::{c_char, c_void, CStr, CString};
+use std::ops::Range;
+use std::sync::mpsc::{self, Sender};
+use std::sync::OnceLock;
+
+// The escape-time backend of the `gpu` feature: OpenGL 4.3 compute shaders in a context of its
+// own, headless through EGL, which is loaded at runtime so that machines without it fall back to
+// the CPU. The context lives on a thread of its own, which the renders are handed to. Besides
+// escape times it accumulates the orbits of the Buddhabrot.
+
+const ESCAPE_TIME: &str = include_str!("shaders/escape_time.comp");
+const BUDDHABROT: &str = include_str!("shaders/buddhabrot.comp");
+// Invocations of a compute shader's work group, its `local_size_x`.
+const WORK_GROUP: u32 = 64;
+// Points computed by one dispatch, well within the work group counts every GPU supports.
+const BATCH: u32 = 1 << 20;
+
+const RTLD_NOW: i32 = 2;
+const EGL_NONE: i32 = 0x3038;
+const EGL_RENDERABLE_TYPE: i32 = 0x3040;
+const EGL_OPENGL_BIT: i32 = 0x0008;
+const EGL_OPENGL_API: u32 = 0x30a2;
+const EGL_CONTEXT_MAJOR_VERSION: i32 = 0x3098;
+const EGL_CONTEXT_MINOR_VERSION: i32 = 0x30fb;
+const EGL_CONTEXT_OPENGL_PROFILE_MASK: i32 = 0x30fd;
+const EGL_CONTEXT_OPENGL_CORE_PROFILE_BIT: i32 = 0x0001;
+const EGL_PLATFORM_SURFACELESS_MESA: u32 = 0x31dd;
+const GL_RENDERER: u32 = 0x1f01;
+const GL_COMPUTE_SHADER: u32 = 0x91b9;
+const GL_COMPILE_STATUS: u32 = 0x8b81;
+const GL_LINK_STATUS: u32 = 0x8b82;
+const GL_INFO_LOG_LENGTH: u32 = 0x8b84;
+const GL_SHADER_STORAGE_BUFFER: u32 = 0x90d2;
+const GL_DYNAMIC_READ: u32 = 0x88e9;
+const GL_SHADER_STORAGE_BARRIER_BIT: u32 = 0x2000;
+const GL_BUFFER_UPDATE_BARRIER_BIT: u32 = 0x0200;
+
+#[cfg(unix)]
+extern "C" {
+    fn dlopen(filename: *const c_char, flags: i32) -> *mut c_void;
+    fn dlsym(handle: *mut c_void, symbol: *const c_char) -> *mut c_void;
+}
+
+// The function at `address`, which has to be of type `F`.
+unsafe fn function<F: Copy>(address: *const c_void, name: &str) -> Result<F, String> {
+    match address.is_null() {
+        true => Err(format!("{name} is missing")),
+        false => Ok(std::mem::transmute_copy(&address)),
+    }
+}
+
+fn c_string(text: &str) -> CString {
+    CString::new(text).expect("no NUL in names")
+}
+
+type EglGetProcAddress = unsafe extern "C" fn(*const c_char) -> *const c_void;
+
+struct Egl {
+    get_proc_address: EglGetProcAddress,
+    get_display: unsafe extern "C" fn(*mut c_void) -> *mut c_void,
+    initialize: unsafe extern "C" fn(*mut c_void, *mut i32, *mut i32) -> u32,
+    bind_api: unsafe extern "C" fn(u32) -> u32,
+    choose_config:
+        unsafe extern "C" fn(*mut c_void, *const i32, *mut *mut c_void, i32, *mut i32) -> u32,
+    create_context:
+        unsafe extern "C" fn(*mut c_void, *mut c_void, *mut c_void, *const i32) -> *mut c_void,
+    make_current: unsafe extern "C" fn(*mut c_void, *mut c_void, *mut c_void, *mut c_void) -> u32,
+}
+
+impl Egl {
+    #[cfg(unix)]
+    fn load() -> Result<Egl, String> {
+        // SAFETY: the symbols are those of EGL 1.4, with these signatures.
+        unsafe {
+            let library = dlopen(c_string("libEGL.so.1").as_ptr(), RTLD_NOW);
+            if library.is_null() {
+                return Err("libEGL.so.1 not found".to_string());
+            }
+            let symbol = |name: &str| dlsym(library, c_string(name).as_ptr()) as *const c_void;
+            Ok(Egl {
+                get_proc_address: function(symbol("eglGetProcAddress"), "eglGetProcAddress")?,
+                get_display: function(symbol("eglGetDisplay"), "eglGetDisplay")?,
+                initialize: function(symbol("eglInitialize"), "eglInitialize")?,
+                bind_api: function(symbol("eglBindAPI"), "eglBindAPI")?,
+                choose_config: function(symbol("eglChooseConfig"), "eglChooseConfig")?,
+                create_context: function(symbol("eglCreateContext"), "eglCreateContext")?,
+                make_current: function(symbol("eglMakeCurrent"), "eglMakeCurrent")?,
+            })
+        }
+    }
+
+    #[cfg(not(unix))]
+    fn load() -> Result<Egl, String> {
+        Err("The GPU backend needs EGL, which this system does not have".to_string())
+    }
+
+    // Headless where the driver can do it, else on the default display.
+    fn display(&self) -> Result<*mut c_void, String> {
+        type GetPlatformDisplay = unsafe extern "C" fn(u32, *mut c_void, *const i32) -> *mut c_void;
+        // SAFETY: the extension function has this signature.
+        unsafe {
+            let name = "eglGetPlatformDisplayEXT";
+            let address = (self.get_proc_address)(c_string(name).as_ptr());
+            if let Ok(platform_display) = function::<GetPlatformDisplay>(address, name) {
+                let surfaceless = EGL_PLATFORM_SURFACELESS_MESA;
+                let display = platform_display(surfaceless, std::ptr::null_mut(), std::ptr::null());
+                if !display.is_null() && (self.initialize)(display, &mut 0, &mut 0) != 0 {
+                    return Ok(display);
+                }
+            }
+            let display = (self.get_display)(std::ptr::null_mut());
+            if display.is_null() || (self.initialize)(display, &mut 0, &mut 0) == 0 {
+                return Err("No EGL display".to_string());
+            }
+            Ok(display)
+        }
+    }
+
+    // An OpenGL 4.3 core context, current on this thread without a surface.
+    fn context(&self) -> Result<(), String> {
+        let display = self.display()?;
+        // SAFETY: the attribute lists end with EGL_NONE, the config is null if none matched,
+        // which drivers with EGL_KHR_no_config_context accept.
+        unsafe {
+            if (self.bind_api)(EGL_OPENGL_API) == 0 {
+                return Err("EGL has no OpenGL".to_string());
+            }
+            let attributes = [EGL_RENDERABLE_TYPE, EGL_OPENGL_BIT, EGL_NONE];
+            let (mut config, mut configs) = (std::ptr::null_mut(), 0);
+            (self.choose_config)(display, attributes.as_ptr(), &mut config, 1, &mut configs);
+            if configs == 0 {
+                config = std::ptr::null_mut();
+            }
+            let attributes = [
+                EGL_CONTEXT_MAJOR_VERSION,
+                4,
+                EGL_CONTEXT_MINOR_VERSION,
+                3,
+                EGL_CONTEXT_OPENGL_PROFILE_MASK,
+                EGL_CONTEXT_OPENGL_CORE_PROFILE_BIT,
+                EGL_NONE,
+            ];
+            let no_context = std::ptr::null_mut();
+            let context = (self.create_context)(display, config, no_context, attributes.as_ptr());
+            if context.is_null() {
+                return Err("No OpenGL 4.3 context".to_string());
+            }
+            let no_surface = std::ptr::null_mut();
+            if (self.make_current)(display, no_surface, no_surface, context) == 0 {
+                return Err("The OpenGL context cannot be made current".to_string());
+            }
+        }
+        Ok(())
+    }
+}
+
+// The OpenGL functions used.
+struct Gl {
+    get_string: unsafe extern "C" fn(u32) -> *const c_char,
+    get_error: unsafe extern "C" fn() -> u32,
+    create_shader: unsafe extern "C" fn(u32) -> u32,
+    shader_source: unsafe extern "C" fn(u32, i32, *const *const c_char, *const i32),
+    compile_shader: unsafe extern "C" fn(u32),
+    get_shaderiv: unsafe extern "C" fn(u32, u32, *mut i32),
+    get_shader_info_log: unsafe extern "C" fn(u32, i32, *mut i32, *mut c_char),
+    create_program: unsafe extern "C" fn() -> u32,
+    attach_shader: unsafe extern "C" fn(u32, u32),
+    link_program: unsafe extern "C" fn(u32),
+    get_programiv: unsafe extern "C" fn(u32, u32, *mut i32),
+    get_program_info_log: unsafe extern "C" fn(u32, i32, *mut i32, *mut c_char),
+    use_program: unsafe extern "C" fn(u32),
+    get_uniform_location: unsafe extern "C" fn(u32, *const c_char) -> i32,
+    uniform1ui: unsafe extern "C" fn(i32, u32),
+    uniform2ui: unsafe extern "C" fn(i32, u32, u32),
+    uniform3ui: unsafe extern "C" fn(i32, u32, u32, u32),
+    uniform2f: unsafe extern "C" fn(i32, f32, f32),
+    gen_buffers: unsafe extern "C" fn(i32, *mut u32),
+    bind_buffer: unsafe extern "C" fn(u32, u32),
+    buffer_data: unsafe extern "C" fn(u32, isize, *const c_void, u32),
+    bind_buffer_base: unsafe extern "C" fn(u32, u32, u32),
+    get_buffer_sub_data: unsafe extern "C" fn(u32, isize, isize, *mut c_void),
+    dispatch_compute: unsafe extern "C" fn(u32, u32, u32),
+    memory_barrier: unsafe extern "C" fn(u32),
+}
+
+impl Gl {
+    fn load(get_proc_address: EglGetProcAddress) -> Result<Gl, String> {
+        // SAFETY: the functions are those of OpenGL 4.3, with these signatures.
+        unsafe {
+            let get = |name: &str| get_proc_address(c_string(name).as_ptr());
+            Ok(Gl {
+                get_string: function(get("glGetString"), "glGetString")?,
+                get_error: function(get("glGetError"), "glGetError")?,
+                create_shader: function(get("glCreateShader"), "glCreateShader")?,
+                shader_source: function(get("glShaderSource"), "glShaderSource")?,
+                compile_shader: function(get("glCompileShader"), "glCompileShader")?,
+                get_shaderiv: function(get("glGetShaderiv"), "glGetShaderiv")?,
+                get_shader_info_log: function(get("glGetShaderInfoLog"), "glGetShaderInfoLog")?,
+                create_program: function(get("glCreateProgram"), "glCreateProgram")?,
+                attach_shader: function(get("glAttachShader"), "glAttachShader")?,
+                link_program: function(get("glLinkProgram"), "glLinkProgram")?,
+                get_programiv: function(get("glGetProgramiv"), "glGetProgramiv")?,
+                get_program_info_log: function(get("glGetProgramInfoLog"), "glGetProgramInfoLog")?,
+                use_program: function(get("glUseProgram"), "glUseProgram")?,
+                get_uniform_location: function(
+                    get("glGetUniformLocation"),
+                    "glGetUniformLocation",
+                )?,
+                uniform1ui: function(get("glUniform1ui"), "glUniform1ui")?,
+                uniform2ui: function(get("glUniform2ui"), "glUniform2ui")?,
+                uniform3ui: function(get("glUniform3ui"), "glUniform3ui")?,
+                uniform2f: function(get("glUniform2f"), "glUniform2f")?,
+                gen_buffers: function(get("glGenBuffers"), "glGenBuffers")?,
+                bind_buffer: function(get("glBindBuffer"), "glBindBuffer")?,
+                buffer_data: function(get("glBufferData"), "glBufferData")?,
+                bind_buffer_base: function(get("glBindBufferBase"), "glBindBufferBase")?,
+                get_buffer_sub_data: function(get("glGetBufferSubData"), "glGetBufferSubData")?,
+                dispatch_compute: function(get("glDispatchCompute"), "glDispatchCompute")?,
+                memory_barrier: function(get("glMemoryBarrier"), "glMemoryBarrier")?,
+            })
+        }
+    }
+}
+
+// What the GPU thread works with, the OpenGL context being current on it.
+pub struct Context {
+    gl: Gl,
+    escape_time: u32,
+    double_float: u32,
+    buddhabrot: u32,
+    // The storage buffers of bindings 0, 1 and 2.
+    buffers: [u32; 3],
+}
+
+impl Context {
+    fn new() -> Result<Context, String> {
+        let egl = Egl::load()?;
+        egl.context()?;
+        let gl = Gl::load(egl.get_proc_address)?;
+        let mut context = Context {
+            gl,
+            escape_time: 0,
+            double_float: 0,
+            buddhabrot: 0,
+            buffers: [0; 3],
+        };
+        context.escape_time = context.program(ESCAPE_TIME)?;
+        let double_float = ESCAPE_TIME.replacen("\n", "\n#define DOUBLE_FLOAT\n", 1);
+        context.double_float = context.program(&double_float)?;
+        context.buddhabrot = context.program(BUDDHABROT)?;
+        // SAFETY: the context is current on this thread, the buffer names fit.
+        unsafe { (context.gl.gen_buffers)(3, context.buffers.as_mut_ptr()) };
+        Ok(context)
+    }
+
+    fn renderer(&self) -> String {
+        // SAFETY: the context is current, glGetString returns a NUL-terminated string or null.
+        unsafe {
+            let name = (self.gl.get_string)(GL_RENDERER);
+            match name.is_null() {
+                true => "unknown".to_string(),
+                false => CStr::from_ptr(name).to_string_lossy().into_owned(),
+            }
+        }
+    }
+
+    // A compute shader compiled and linked into a program.
+    fn program(&self, source: &str) -> Result<u32, String> {
+        let gl = &self.gl;
+        let source = c_string(source);
+        // SAFETY: the context is current on this thread, the logs are as long as told.
+        unsafe {
+            let shader = (gl.create_shader)(GL_COMPUTE_SHADER);
+            (gl.shader_source)(shader, 1, &source.as_ptr(), std::ptr::null());
+            (gl.compile_shader)(shader);
+            let mut status = 0;
+            (gl.get_shaderiv)(shader, GL_COMPILE_STATUS, &mut status);
+            if status == 0 {
+                let mut length = 0;
+                (gl.get_shaderiv)(shader, GL_INFO_LOG_LENGTH, &mut length);
+                let mut log = vec![0u8; length.max(1) as usize];
+                let buffer = log.as_mut_ptr() as *mut c_char;
+                (gl.get_shader_info_log)(shader, length, std::ptr::null_mut(), buffer);
+                let log = String::from_utf8_lossy(&log);
+                return Err(format!(
+                    "Compiling a shader failed: {}",
+                    log.trim_end_matches('\0')
+                ));
+            }
+            let program = (gl.create_program)();
+            (gl.attach_shader)(program, shader);
+            (gl.link_program)(program);
+            (gl.get_programiv)(program, GL_LINK_STATUS, &mut status);
+            if status == 0 {
+                let mut length = 0;
+                (gl.get_programiv)(program, GL_INFO_LOG_LENGTH, &mut length);
+                let mut log = vec![0u8; length.max(1) as usize];
+                let buffer = log.as_mut_ptr() as *mut c_char;
+                (gl.get_program_info_log)(program, length, std::ptr::null_mut(), buffer);
+                let log = String::from_utf8_lossy(&log);
+                return Err(format!(
+                    "Linking a shader failed: {}",
+                    log.trim_end_matches('\0')
+                ));
+            }
+            Ok(program)
+        }
+    }
+
+    fn uniform(&self, program: u32, name: &str) -> i32 {
+        // SAFETY: the context is current on this thread.
+        unsafe { (self.gl.get_uniform_location)(program, c_string(name).as_ptr()) }
+    }
+
+    fn set_uint(&self, program: u32, name: &str, value: u32) {
+        // SAFETY: the context is current and the program in use.
+        unsafe { (self.gl.uniform1ui)(self.uniform(program, name), value) }
+    }
+
+    fn set_vec2(&self, program: u32, name: &str, value: Complex<f64>) {
+        // SAFETY: the context is current and the program in use.
+        unsafe {
+            (self.gl.uniform2f)(
+                self.uniform(program, name),
+                value.re as f32,
+                value.im as f32,
+            )
+        }
+    }
+
+    // `value` as the f32 nearest to it in `name` and what that leaves in `name_low`, the high
+    // and low parts of a double-float.
+    fn set_double_float(&self, program: u32, name: &str, value: Complex<f64>, low: Complex<f64>) {
+        let high = Complex::new(value.re as f32 as f64, value.im as f32 as f64);
+        self.set_vec2(program, name, high);
+        self.set_vec2(program, &format!("{name}_low"), value - high + low);
+    }
+
+    fn set_uvec2(&self, program: u32, name: &str, value: [u32; 2]) {
+        // SAFETY: the context is current and the program in use.
+        unsafe { (self.gl.uniform2ui)(self.uniform(program, name), value[0], value[1]) }
+    }
+
+    fn set_uvec3(&self, program: u32, name: &str, value: [u32; 3]) {
+        let location = self.uniform(program, name);
+        // SAFETY: the context is current and the program in use.
+        unsafe { (self.gl.uniform3ui)(location, value[0], value[1], value[2]) }
+    }
+
+    // Fills the storage buffer of `binding` with `data`.
+    fn upload<T: Copy>(&self, binding: u32, data: &[T]) {
+        let gl = &self.gl;
+        let buffer = self.buffers[binding as usize];
+        let bytes = std::mem::size_of_val(data) as isize;
+        // SAFETY: the context is current on this thread, `data` is `bytes` long.
+        unsafe {
+            (gl.bind_buffer)(GL_SHADER_STORAGE_BUFFER, buffer);
+            let data = data.as_ptr() as *const c_void;
+            (gl.buffer_data)(GL_SHADER_STORAGE_BUFFER, bytes, data, GL_DYNAMIC_READ);
+            (gl.bind_buffer_base)(GL_SHADER_STORAGE_BUFFER, binding, buffer);
+        }
+    }
+
+    // The first `len` values of the storage buffer of `binding`, once the dispatches so far
+    // are done writing it.
+    fn download<T: Copy + Default>(&self, binding: u32, len: usize) -> Vec<T> {
+        let gl = &self.gl;
+        let mut data = vec![T::default(); len];
+        let bytes = std::mem::size_of_val(data.as_slice()) as isize;
+        // SAFETY: the context is current on this thread, `data` is `bytes` long.
+        unsafe {
+            (gl.memory_barrier)(GL_SHADER_STORAGE_BARRIER_BIT | GL_BUFFER_UPDATE_BARRIER_BIT);
+            (gl.bind_buffer)(GL_SHADER_STORAGE_BUFFER, self.buffers[binding as usize]);
+            let target = data.as_mut_ptr() as *mut c_void;
+            (gl.get_buffer_sub_data)(GL_SHADER_STORAGE_BUFFER, 0, bytes, target);
+        }
+        data
+    }
+
+    fn dispatch(&self, invocations: u32) {
+        // SAFETY: the context is current on this thread.
+        unsafe { (self.gl.dispatch_compute)(invocations.div_ceil(WORK_GROUP), 1, 1) }
+    }
+
+    // OpenGL errors raised since the last check.
+    fn check(&self, what: &str) -> Result<(), String> {
+        // SAFETY: the context is current on this thread.
+        match unsafe { (self.gl.get_error)() } {
+            0 => Ok(()),
+            error => Err(format!("{what} failed with OpenGL error {error:#x}")),
+        }
+    }
+
+    // The samples of every point of the supersampling grids, as `render::computed_frame` takes
+    // them, in double-float where f32 does not resolve the view.
+    fn escape_times(&self, params: &RenderParams) -> Result<Vec<Sample>, String> {
+        let program = match Precision::Single.resolves(params) {
+            true => self.escape_time,
+            false => self.double_float,
+        };
+        let zero = Complex::new(0.0, 0.0);
+        let view = &params.viewport;
+        let size = (params.width, params.height);
+        let count = params.width * params.height * params.antialias * params.antialias;
+        let (julia, constant) = match params.fractal {
+            Fractal::Mandelbrot { z0 } => (false, z0),
+            Fractal::Julia { c } => (true, c),
+            _ => (false, zero),
+        };
+        let origin = view.offset(0.0, 0.0, size);
+        // SAFETY: the context is current on this thread.
+        unsafe { (self.gl.use_program)(program) };
+        self.set_uint(program, "count", count);
+        self.set_uint(program, "width", params.width);
+        self.set_uint(program, "aa", params.antialias);
+        self.set_uint(program, "iterations", params.iterations);
+        self.set_double_float(program, "center", view.center, view.center_low);
+        self.set_vec2(program, "origin", origin);
+        self.set_vec2(program, "step_x", view.offset(1.0, 0.0, size) - origin);
+        self.set_vec2(program, "step_y", view.offset(0.0, 1.0, size) - origin);
+        self.set_double_float(program, "constant", constant, zero);
+        self.set_uint(program, "julia", julia as u32);
+        self.set_uint(
+            program,
+            "fold",
+            (params.fractal == Fractal::BurningShip) as u32,
+        );
+        self.set_uint(
+            program,
+            "conjugate",
+            (params.fractal == Fractal::Tricorn) as u32,
+        );
+        self.set_uint(program, "periodicity", kernel::periodicity() as u32);
+        self.upload(0, &vec![[0f32; 4]; count as usize]);
+        for first in (0..count).step_by(BATCH as usize) {
+            self.set_uint(program, "first", first);
+            self.dispatch((count - first).min(BATCH));
+        }
+        let samples = self.download::<[f32; 4]>(0, count as usize);
+        self.check("Computing the escape times")?;
+        Ok(samples
+            .into_iter()
+            .map(|[escape, re, im, _]| Sample {
+                escape: u32::try_from(escape.to_bits() as i32).ok(),
+                z: Complex::new(re as f64, im as f64),
+            })
+            .collect())
+    }
+
+    // The hit counts of the Buddhabrot orbits from the starting points of the range of sample
+    // indices, and the orbits started and hits scored per cell of the importance grid of
+    // `side` cells a side, as `buddhabrot::sample_chunks` returns them. With `cells`, the
+    // cumulative probability and the probability of each cell, the starting points are drawn
+    // from the cells.
+    fn accumulate(
+        &self,
+        params: &RenderParams,
+        samples: Range<u64>,
+        cells: &[[f32; 2]],
+        side: usize,
+    ) -> Result<Accumulation, String> {
+        let program = self.buddhabrot;
+        let view = &params.viewport;
+        let channels = params.orbits.channels();
+        let pixels = (params.width * params.height) as usize * channels.len();
+        let (mut min_lengths, mut max_lengths) = ([0; 3], [0; 3]);
+        for (k, lengths) in channels.iter().enumerate() {
+            (min_lengths[k], max_lengths[k]) = (lengths.min, lengths.max);
+        }
+        // SAFETY: the context is current on this thread.
+        unsafe { (self.gl.use_program)(program) };
+        self.set_uint(program, "iterations", params.iterations);
+        self.set_uint(program, "width", params.width);
+        self.set_uint(program, "height", params.height);
+        self.set_vec2(program, "center", view.center + view.center_low);
+        self.set_vec2(
+            program,
+            "unrotation",
+            Complex::from_polar(1.0, -view.rotation),
+        );
+        let scale = Complex::new(
+            params.width as f64 / view.width,
+            params.height as f64 / view.height,
+        );
+        self.set_vec2(program, "scale", scale);
+        self.set_uint(program, "side", side as u32);
+        self.set_uint(program, "sampled", cells.len() as u32);
+        self.set_uint(program, "channels", channels.len() as u32);
+        self.set_uvec3(program, "min_lengths", min_lengths);
+        self.set_uvec3(program, "max_lengths", max_lengths);
+        self.upload(0, &vec![0f32; pixels]);
+        self.upload(1, &vec![0u32; 2 * side * side]);
+        // Bound even when empty.
+        self.upload(2, if cells.is_empty() { &[[0.0; 2]] } else { cells });
+        for first in (samples.start..samples.end).step_by(BATCH as usize) {
+            self.set_uvec2(program, "seed", [first as u32, (first >> 32) as u32]);
+            let count = (samples.end - first).min(BATCH as u64) as u32;
+            self.set_uint(program, "count", count);
+            self.dispatch(count);
+        }
+        let hits = self.download::<f32>(0, pixels);
+        let scores = self.download::<[u32; 2]>(1, side * side);
+        self.check("Accumulating the Buddhabrot")?;
+        Ok((
+            hits,
+            scores
+                .into_iter()
+                .map(|[orbits, scored]| (orbits, scored))
+                .collect(),
+        ))
+    }
+}
+
+type Task = Box<dyn FnOnce(&mut Context) + Send>;
+
+// The GPU thread, and the name of the GPU.
+pub struct Device {
+    tasks: Sender<Task>,
+    pub renderer: String,
+}
+
+static DEVICE: OnceLock<Result<Device, String>> = OnceLock::new();
+
+// The GPU, opened on first use, which tells why if it cannot be.
+pub fn device() -> Result<&'static Device, String> {
+    let device = DEVICE.get_or_init(|| {
+        let device = Device::open();
+        if let Err(e) = &device {
+            println!("No GPU backend: {e}");
+        }
+        device
+    });
+    device.as_ref().map_err(Clone::clone)
+}
+
+impl Device {
+    fn open() -> Result<Device, String> {
+        let (tasks, queue) = mpsc::channel::<Task>();
+        let (opened, status) = mpsc::channel();
+        std::thread::Builder::new()
+            .name("gpu".to_string())
+            .spawn(move || match Context::new() {
+                Ok(mut context) => {
+                    opened.send(Ok(context.renderer())).unwrap_or(());
+                    for task in queue {
+                        task(&mut context);
+                    }
+                }
+                Err(e) => opened.send(Err(e)).unwrap_or(()),
+            })
+            .map_err(|e| format!("Starting the GPU thread failed: {e}"))?;
+        let renderer = status
+            .recv()
+            .map_err(|_| "The GPU thread panicked".to_string())??;
+        Ok(Device { tasks, renderer })
+    }
+
+    // Runs `task` on the GPU thread and waits for its result.
+    fn run<T: Send + 'static>(
+        &self,
+        task: impl FnOnce(&mut Context) -> Result<T, String> + Send + 'static,
+    ) -> Result<T, String> {
+        let (reply, result) = mpsc::channel();
+        let task: Task = Box::new(move |context| reply.send(task(context)).unwrap_or(()));
+        self.tasks
+            .send(task)
+            .map_err(|_| "The GPU thread is gone".to_string())?;
+        result
+            .recv()
+            .map_err(|_| "The GPU thread panicked".to_string())?
+    }
+
+    pub fn escape_times(&self, params: RenderParams) -> Result<Vec<Sample>, String> {
+        self.run(move |context| context.escape_times(&params))
+    }
+
+    pub fn accumulate(
+        &self,
+        params: RenderParams,
+        samples: Range<u64>,
+        cells: Vec<[f32; 2]>,
+        side: usize,
+    ) -> Result<Accumulation, String> {
+        self.run(move |context| context.accumulate(&params, samples, &cells, side))
+    }
+}
+
+// Renders the escape times of the fractals the shader iterates, those of the vector kernels, in
+// views double-float resolves, leaving the coloring and every other job to the CPU.
+pub struct GpuWorker {
+    device: &'static Device,
+}
+
+impl GpuWorker {
+    pub fn detect() -> Result<GpuWorker, String> {
+        Ok(GpuWorker { device: device()? })
+    }
+
+    pub fn supports(&self, params: &RenderParams) -> bool {
+        let iterated = matches!(
+            params.fractal,
+            Fractal::Mandelbrot { .. }
+                | Fractal::Julia { .. }
+                | Fractal::BurningShip
+                | Fractal::Tricorn
+        );
+        iterated && self.precision().resolves(params)
+    }
+}
+
+impl Worker for GpuWorker {
+    fn backend(&self) -> Backend {
+        Backend::Gpu
+    }
+
+    fn precision(&self) -> Precision {
+        Precision::DoubleSingle
+    }
+
+    fn render(&self, jobs: &[RenderParams]) -> Vec<Frame> {
+        jobs.iter()
+            .map(|&params| {
+                let samples = match self.supports(&params) {
+                    true => self.device.escape_times(params).map(Some),
+                    false => Ok(None),
+                };
+                match samples {
+                    Ok(Some(samples)) => render::computed_frame(params, samples),
+                    Ok(None) => render::escape_time_frame(params),
+                    Err(e) => {
+                        println!("{e}, rendering on the CPU instead");
+                        render::escape_time_frame(params)
+                    }
+                }
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::viewport::Viewport;
+
+    #[test]
+    fn gpu_escape_times_match_the_cpu() {
+        // Without a GPU there is nothing to compare, the CPU renders alone.
+        let Ok(worker) = GpuWorker::detect() else {
+            return;
+        };
+        let params = RenderParams::builder()
+            .viewport(Viewport::new(Complex::new(-0.75, 0.1), 1.8, 1.2))
+            .size(120, 80)
+            .antialias(2)
+            .iterations(200)
+            .build()
+            .unwrap();
+        for fractal in [
+            Fractal::MANDELBROT,
+            Fractal::Julia {
+                c: Complex::new(-0.8, 0.156),
+            },
+            Fractal::BurningShip,
+        ] {
+            let params = params.to_builder().fractal(fractal).build().unwrap();
+            assert!(worker.supports(&params));
+            let gpu = worker.render(&[params]).remove(0);
+            let cpu = render::escape_time_frame(params);
+            assert_eq!(gpu.supersamples.len(), cpu.supersamples.len());
+            // f32 orbits part from f64 ones near the boundary only.
+            let differing = gpu
+                .supersamples
+                .iter()
+                .zip(&cpu.supersamples)
+                .filter(|(a, b)| a.escape.is_some() != b.escape.is_some())
+                .count();
+            assert!(
+                differing * 100 < cpu.supersamples.len(),
+                "{differing} differ"
+            );
+        }
+        // Beyond f32, double-float still agrees with f64, but not beyond double-float.
+        let center = Complex::new(-0.743643887037151, 0.131825904205330);
+        let deep = params
+            .to_builder()
+            .viewport(Viewport::new(center, 1.5e-7, 1e-7))
+            .iterations(1000)
+            .build()
+            .unwrap();
+        assert!(!Precision::Single.resolves(&deep) && worker.supports(&deep));
+        let gpu = worker.render(&[deep]).remove(0);
+        let cpu = render::escape_time_frame(deep);
+        let differing = gpu
+            .supersamples
+            .iter()
+            .zip(&cpu.supersamples)
+            .filter(|(a, b)| a.escape.is_some() != b.escape.is_some())
+            .count();
+        assert!(
+            differing * 100 < cpu.supersamples.len(),
+            "{differing} differ"
+        );
+        let deeper = deep
+            .to_builder()
+            .viewport(Viewport::new(center, 1.5e-13, 1e-13))
+            .build()
+            .unwrap();
+        assert!(!worker.supports(&deeper));
+    }
+}
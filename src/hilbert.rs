@@ -0,0 +1,47 @@
+// Hilbert curve traversal of a grid: unlike the Z curve, every step goes to an adjacent cell,
+// so whatever has been visited so far is a compact, connected region.
+
+// Position along the curve filling a `side x side` square, `side` a power of two, of the cell
+// at (x, y).
+pub fn index(side: u32, mut x: u32, mut y: u32) -> u64 {
+    let mut index = 0;
+    let mut s = side / 2;
+    while s > 0 {
+        let (rx, ry) = ((x & s != 0) as u32, (y & s != 0) as u32);
+        index += s as u64 * s as u64 * ((3 * rx) ^ ry) as u64;
+        // Turns the quadrant so that the curve within it starts and ends next to its
+        // neighbours.
+        if ry == 0 {
+            if rx == 1 {
+                x = side - 1 - x;
+                y = side - 1 - y;
+            }
+            (x, y) = (y, x);
+        }
+        s /= 2;
+    }
+    index
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn curve_steps_to_neighbours() {
+        let order = |side: u32| {
+            let mut cells = (0..side)
+                .flat_map(|y| (0..side).map(move |x| (x, y)))
+                .collect::<Vec<_>>();
+            cells.sort_by_key(|&(x, y)| index(side, x, y));
+            cells
+        };
+        assert_eq!(order(2), [(0, 0), (0, 1), (1, 1), (1, 0)]);
+        let cells = order(16);
+        assert_eq!(cells.first(), Some(&(0, 0)));
+        assert_eq!(index(16, 15, 0), 255);
+        assert!(cells
+            .windows(2)
+            .all(|step| step[0].0.abs_diff(step[1].0) + step[0].1.abs_diff(step[1].1) == 1));
+    }
+}
@@ -0,0 +1,64 @@
+use crate::viewport::Viewport;
+
+// Most views kept to go back to, the oldest dropped first.
+const CAPACITY: usize = 256;
+
+// The views navigated away from, to go back and forth between like the pages of a browser.
+#[derive(Debug, Default)]
+pub struct History {
+    back: Vec<Viewport>,
+    forward: Vec<Viewport>,
+}
+
+impl History {
+    // Records the view left for a new one, which drops the views gone back from.
+    pub fn visit(&mut self, left: Viewport) {
+        if self.back.last() != Some(&left) {
+            if self.back.len() == CAPACITY {
+                self.back.remove(0);
+            }
+            self.back.push(left);
+        }
+        self.forward.clear();
+    }
+
+    // The view before `current`, which can then be gone forward to.
+    pub fn back(&mut self, current: Viewport) -> Option<Viewport> {
+        let view = self.back.pop()?;
+        self.forward.push(current);
+        Some(view)
+    }
+
+    pub fn forward(&mut self, current: Viewport) -> Option<Viewport> {
+        let view = self.forward.pop()?;
+        self.back.push(current);
+        Some(view)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use num::complex::Complex;
+
+    #[test]
+    fn goes_back_and_forth_between_visited_views() {
+        let view = |re| Viewport::new(Complex::new(re, 0.0), 4.0, 3.0);
+        let mut history = History::default();
+        assert_eq!(history.back(view(0.0)), None);
+        history.visit(view(0.0));
+        history.visit(view(1.0));
+        assert_eq!(history.back(view(2.0)), Some(view(1.0)));
+        assert_eq!(history.back(view(1.0)), Some(view(0.0)));
+        assert_eq!(history.forward(view(0.0)), Some(view(1.0)));
+        // A new view drops the one still ahead.
+        history.visit(view(1.0));
+        assert_eq!(history.forward(view(3.0)), None);
+        assert_eq!(history.back(view(3.0)), Some(view(1.0)));
+        for i in 0..CAPACITY + 10 {
+            history.visit(view(i as f64));
+        }
+        assert_eq!(history.back.len(), CAPACITY);
+        assert_eq!(history.back[0], view(10.0));
+    }
+}
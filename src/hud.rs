@@ -0,0 +1,151 @@
+use crate::fixed::{Fixed, CENTER_BITS};
+use crate::kernel;
+use crate::locale::{self, Separator};
+use crate::render::Frame;
+use crate::schedule::IterationSchedule;
+use crate::style::Style;
+use crate::text;
+use crate::viewport::Viewport;
+
+// Significant digits that round-trip an f64 exactly, and those every further term of a
+// center adds.
+const F64_DIGITS: i32 = 17;
+const TERM_DIGITS: i32 = 16;
+const GROUP: usize = 5;
+
+// Decimal places that tell neighbouring pixels of the view apart, plus one.
+pub fn decimals(viewport: &Viewport, window_height: u32) -> usize {
+    let pixel = viewport.height / window_height as f64;
+    ((-pixel.log10()).ceil() as i64 + 1).max(3) as usize
+}
+
+// The digits of a coordinate given by the terms of `Viewport::center_terms`, up to `decimals`
+// places, cut to those the terms actually hold. The flag tells whether the zoom needs more of
+// them than there are.
+fn digits(terms: &[f64], decimals: usize) -> (String, bool) {
+    let magnitude = if terms[0] == 0.0 {
+        0
+    } else {
+        terms[0].abs().log10().floor() as i32
+    };
+    let held = F64_DIGITS + TERM_DIGITS * (terms.len() as i32 - 1);
+    let available = (held - 1 - magnitude).max(0) as usize;
+    let shown = decimals.min(available);
+    let value = Fixed::from_terms(terms, CENTER_BITS);
+    (value.decimal(shown), shown < decimals)
+}
+
+// A coordinate for reading: decimals in groups of five, `...` where the precision runs out.
+pub fn format(terms: &[f64], decimals: usize) -> String {
+    let (digits, truncated) = digits(terms, decimals);
+    let (integer, fraction) = digits.split_once('.').unwrap_or((&digits, ""));
+    let groups = fraction
+        .as_bytes()
+        .chunks(GROUP)
+        .map(|group| std::str::from_utf8(group).unwrap())
+        .collect::<Vec<_>>()
+        .join(" ");
+    let mut text = if groups.is_empty() {
+        integer.to_string()
+    } else {
+        format!("{integer}.{groups}")
+    };
+    if truncated {
+        text.push_str("...");
+    }
+    text
+}
+
+// The view as arguments of the console `goto` command, for the clipboard, which the console
+// reads back with either separator.
+pub fn goto_arguments(viewport: &Viewport, window_height: u32, separator: Separator) -> String {
+    let decimals = decimals(viewport, window_height);
+    let (re, im) = viewport.center_terms();
+    let arguments = format!(
+        "{} {} {:e}",
+        digits(&re, decimals).0,
+        digits(&im, decimals).0,
+        viewport.height
+    );
+    separator.display(&arguments).into_owned()
+}
+
+// Center and zoom of the view, and the escape-time kernel, over the top-left corner.
+pub fn draw(frame: &mut Frame, viewport: &Viewport, style: &Style) {
+    let decimals = decimals(viewport, frame.height);
+    let separator = locale::current();
+    let number = |text: String| separator.display(&text).into_owned();
+    let (re, im) = viewport.center_terms();
+    let lines = [
+        format!("re {}", number(format(&re, decimals))),
+        format!("im {}", number(format(&im, decimals))),
+        format!(
+            "zoom {}",
+            number(format!("{:.3e}", IterationSchedule::zoom(viewport)))
+        ),
+        format!("kernel {}", kernel::current().name()),
+    ];
+    let scale = if frame.width >= 640 { 2 } else { 1 };
+    let line_height = (text::LINE_HEIGHT * scale) as i32;
+    let longest = lines.iter().map(String::len).max().unwrap_or(0) as u32;
+    let size = (
+        longest * text::ADVANCE * scale + 16,
+        lines.len() as u32 * line_height as u32 + 12,
+    );
+    style.panel(frame, (0, 0), size);
+    for (i, line) in lines.iter().enumerate() {
+        text::draw(
+            frame,
+            8,
+            8 + i as i32 * line_height,
+            scale,
+            style.text,
+            line,
+        );
+    }
+}
+
+// A line of status over the top-right corner, clear of the coordinates.
+pub fn status(frame: &mut Frame, line: &str, style: &Style) {
+    let scale = if frame.width >= 640 { 2 } else { 1 };
+    let width = line.len() as u32 * text::ADVANCE * scale + 16;
+    let height = text::LINE_HEIGHT * scale + 12;
+    let x = frame.width.saturating_sub(width) as i32;
+    style.panel(frame, (x, 0), (width, height));
+    text::draw(frame, x + 8, 8, scale, style.text, line);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use num::complex::Complex;
+
+    #[test]
+    fn digits_follow_the_zoom() {
+        assert_eq!(decimals(&Viewport::default(), 600), 4);
+        assert_eq!(format(&[-0.75], 4), "-0.7500");
+        assert_eq!(format(&[0.0], 12), "+0.00000 00000 00");
+        let deep = Viewport::new(
+            Complex::new(-0.743643887037151, 0.13182590420533),
+            4e-14,
+            3e-14,
+        );
+        assert_eq!(decimals(&deep, 600), 18);
+        assert_eq!(format(&[deep.center.re], 16), "-0.74364 38870 37151 0");
+        // Deeper than f64 reaches the digits are cut and marked, unless the center has more
+        // terms.
+        assert_eq!(format(&[deep.center.re], 18), "-0.74364 38870 37151 01...");
+        assert_eq!(
+            format(&[0.5, 2f64.powi(-60)], 20),
+            "+0.50000 00000 00000 00087"
+        );
+        assert_eq!(
+            goto_arguments(&deep, 600, Separator::Point),
+            "-0.74364388703715101 +0.13182590420533000 3e-14"
+        );
+        assert_eq!(
+            goto_arguments(&Viewport::default(), 600, Separator::Comma),
+            "+0,0000 +0,0000 3e0"
+        );
+    }
+}
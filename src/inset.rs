@@ -0,0 +1,78 @@
+use crate::palette::Palette;
+use crate::params::{Coloring, Fractal, RenderParams};
+use crate::render::{self, Frame};
+use crate::roi::{self, Rect};
+use crate::style::Style;
+use crate::viewport::Viewport;
+use num::complex::Complex;
+
+// Size of the inset and its iteration count, low enough to follow the cursor in real time.
+pub const WIDTH: u32 = 160;
+pub const HEIGHT: u32 = 120;
+pub const ITERATIONS: u32 = 150;
+// Gap between the inset and the edges of the window.
+const MARGIN: u32 = 8;
+
+// The whole Julia set of `c`, in the palette of the view.
+pub fn params(c: Complex<f64>, palette: Option<Palette>) -> RenderParams {
+    RenderParams::builder()
+        .fractal(Fractal::Julia { c })
+        .viewport(Viewport::new(Complex::new(0.0, 0.0), 4.0, 3.0))
+        .size(WIDTH, HEIGHT)
+        .iterations(ITERATIONS)
+        .coloring(Coloring::Smooth)
+        .palette(palette)
+        .build()
+        .expect("valid inset params")
+}
+
+pub fn render(c: Complex<f64>, palette: Option<Palette>) -> Frame {
+    render::render_frame(params(c, palette))
+}
+
+// Where the inset goes: the bottom-right corner, clear of the inspector and the coordinates.
+// `None` if the window is too small to hold it.
+pub fn placement(width: u32, height: u32) -> Option<(u32, u32)> {
+    let x = width.checked_sub(WIDTH + MARGIN)?;
+    let y = height.checked_sub(HEIGHT + MARGIN)?;
+    (x >= MARGIN && y >= MARGIN).then_some((x, y))
+}
+
+// The inset over the bottom-right corner of the frame, outlined.
+pub fn draw(frame: &mut Frame, inset: &Frame, style: &Style) {
+    let Some((x, y)) = placement(frame.width, frame.height) else {
+        return;
+    };
+    frame.paste(x, y, inset);
+    let rect = Rect {
+        x: x - 1,
+        y: y - 1,
+        width: inset.width + 2,
+        height: inset.height + 2,
+    };
+    roi::outline(frame, rect, style.outline);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn inset_fits_the_bottom_right_corner() {
+        let inset = render(Complex::new(-1.0, 0.0), None);
+        assert_eq!((inset.width, inset.height), (WIDTH, HEIGHT));
+        // The origin is on the 2-cycle of the basilica, inside the set.
+        let center = ((HEIGHT / 2 * WIDTH + WIDTH / 2) * 3) as usize;
+        let corner = &inset.rgb[..3];
+        assert_ne!(&inset.rgb[center..center + 3], corner);
+
+        let mut frame = Frame::from_rgb(400, 300, vec![0; 400 * 300 * 3]);
+        draw(&mut frame, &inset, &Style::default());
+        let (x, y) = placement(400, 300).unwrap();
+        assert_eq!((x, y), (400 - WIDTH - MARGIN, 300 - HEIGHT - MARGIN));
+        let pixel = (((y + 1) * 400 + x + 1) * 3) as usize;
+        let piece = ((WIDTH + 1) * 3) as usize;
+        assert_eq!(&frame.rgb[pixel..pixel + 3], &inset.rgb[piece..piece + 3]);
+        assert_eq!(placement(WIDTH, HEIGHT), None);
+    }
+}
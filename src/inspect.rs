@@ -0,0 +1,107 @@
+use crate::params::Fractal;
+use crate::render::{self, Frame};
+use crate::stats;
+use crate::style::Style;
+use crate::text;
+use num::complex::Complex;
+use std::f64::consts::TAU;
+
+// Everything the inspector `K` shows about the orbit of a single point.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Inspection {
+    pub point: Complex<f64>,
+    // Escape iteration at the bailout radius of the image, `None` inside the set.
+    pub escape: Option<u32>,
+    pub smooth: f64,
+    // Modulus and argument in turns of the last orbit value.
+    pub modulus: f64,
+    pub angle: f64,
+    // Closest approach of the orbit to the origin.
+    pub trap: f64,
+    // Period of the cycle an interior orbit settled on, if it did within the iterations.
+    pub period: Option<u32>,
+    pub distance: f64,
+}
+
+// Evaluates a single point the way the renderer and the statistics export see it.
+pub fn evaluate(fractal: Fractal, point: Complex<f64>, iterations: u32) -> Inspection {
+    let sample = render::escape_time(fractal, point, iterations);
+    let stats = stats::orbit_stats(fractal, point, iterations);
+    let (_, c) = fractal.start(point);
+    Inspection {
+        point,
+        escape: sample.escape,
+        smooth: stats.smooth,
+        modulus: sample.z.norm(),
+        angle: (sample.z.arg() / TAU).rem_euclid(1.0),
+        trap: stats.trap,
+        period: match sample.escape {
+            Some(_) => None,
+            None => render::cycle_period(fractal, sample.z, c),
+        },
+        distance: stats.distance,
+    }
+}
+
+impl Inspection {
+    pub fn lines(&self) -> Vec<String> {
+        vec![
+            format!("point {:.6e} {:.6e}", self.point.re, self.point.im),
+            match self.escape {
+                Some(escape) => format!("escape {escape}  smooth {:.3}", self.smooth),
+                None => "inside".to_string(),
+            },
+            format!("|z| {:.4e}  arg {:.4} turns", self.modulus, self.angle),
+            format!("min |z| {:.4e}", self.trap),
+            match self.period {
+                Some(period) => format!("period {period}"),
+                None => "period -".to_string(),
+            },
+            format!("distance {:.4e}", self.distance),
+        ]
+    }
+}
+
+// The statistics over the bottom-left corner.
+pub fn draw(frame: &mut Frame, inspection: &Inspection, style: &Style) {
+    let lines = inspection.lines();
+    let scale = if frame.width >= 640 { 2 } else { 1 };
+    let line_height = (text::LINE_HEIGHT * scale) as i32;
+    let longest = lines.iter().map(String::len).max().unwrap_or(0) as u32;
+    let size = (
+        longest * text::ADVANCE * scale + 16,
+        lines.len() as u32 * line_height as u32 + 12,
+    );
+    let top = frame.height as i32 - size.1 as i32;
+    style.panel(frame, (0, top), size);
+    for (i, line) in lines.iter().enumerate() {
+        text::draw(
+            frame,
+            8,
+            top + 6 + i as i32 * line_height,
+            scale,
+            style.text,
+            line,
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn inspects_interior_and_exterior_points() {
+        // c = -1 settles on the 2-cycle 0, -1.
+        let inside = evaluate(Fractal::MANDELBROT, Complex::new(-1.0, 0.0), 500);
+        assert_eq!((inside.escape, inside.period), (None, Some(2)));
+        assert_eq!(inside.trap, 0.0);
+        let cardioid = evaluate(Fractal::MANDELBROT, Complex::new(-0.1, 0.1), 2000);
+        assert_eq!(cardioid.period, Some(1));
+        let outside = evaluate(Fractal::MANDELBROT, Complex::new(0.5, 0.0), 500);
+        assert_eq!(outside.escape, Some(4));
+        assert_eq!(outside.period, None);
+        assert!(outside.modulus > 2.0 && outside.distance > 0.0 && outside.smooth > 0.0);
+        assert_eq!(outside.lines().len(), 6);
+    }
+}
@@ -0,0 +1,89 @@
+use num::complex::Complex;
+
+pub struct JuliaPreset {
+    pub name: &'static str,
+    pub title: &'static str,
+    pub c: Complex<f64>,
+    pub classification: &'static str,
+}
+
+pub const PRESETS: &[JuliaPreset] = &[
+    JuliaPreset {
+        name: "douady-rabbit",
+        title: "Douady rabbit",
+        c: Complex::new(-0.122561166876654, 0.744861766619744),
+        classification: "superattracting period-3 cycle, connected",
+    },
+    JuliaPreset {
+        name: "basilica",
+        title: "Basilica",
+        c: Complex::new(-1.0, 0.0),
+        classification: "superattracting period-2 cycle, connected",
+    },
+    JuliaPreset {
+        name: "san-marco",
+        title: "San Marco",
+        c: Complex::new(-0.75, 0.0),
+        classification: "parabolic, root of the period-2 bulb",
+    },
+    JuliaPreset {
+        name: "cauliflower",
+        title: "Cauliflower",
+        c: Complex::new(0.25, 0.0),
+        classification: "parabolic, cusp of the main cardioid",
+    },
+    JuliaPreset {
+        name: "airplane",
+        title: "Airplane",
+        c: Complex::new(-1.754877666246693, 0.0),
+        classification: "superattracting period-3 cycle, connected",
+    },
+    JuliaPreset {
+        name: "dendrite",
+        title: "Dendrite",
+        c: Complex::new(0.0, 1.0),
+        classification: "Misiurewicz point, dendrite without interior",
+    },
+    JuliaPreset {
+        name: "siegel-disk",
+        title: "Siegel disk",
+        c: Complex::new(-0.390540870218399, -0.586787907346969),
+        classification: "irrationally indifferent, golden mean rotation number",
+    },
+    JuliaPreset {
+        name: "cantor-dust",
+        title: "Cantor dust",
+        c: Complex::new(0.26, 0.0),
+        classification: "escaping critical orbit, totally disconnected",
+    },
+];
+
+pub fn describe(preset: &JuliaPreset) -> String {
+    format!(
+        "{} (c = {} {:+}i): {}",
+        preset.title, preset.c.re, preset.c.im, preset.classification
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::params::Fractal;
+    use crate::render;
+
+    #[test]
+    fn classification_matches_mandelbrot_membership() {
+        for preset in PRESETS {
+            let escape = render::escape_time(Fractal::MANDELBROT, preset.c, 10_000).escape;
+            if preset.classification.contains("superattracting") {
+                assert_eq!(
+                    escape, None,
+                    "{} should be in the Mandelbrot set",
+                    preset.name
+                );
+            } else if preset.classification.contains("escaping") {
+                assert!(escape.is_some(), "{} should escape", preset.name);
+            }
+        }
+    }
+}
@@ -0,0 +1,233 @@
+use crate::params::Fractal;
+use crate::render::{self, Sample};
+use num::complex::Complex;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::OnceLock;
+
+// Points iterated together, as lanes the compiler keeps in vector registers.
+const LANES: usize = 8;
+
+// Squared distance at which an orbit counts as back where it was, only one settled on its
+// attracting cycle comes this close.
+pub const PERIODICITY_TOLERANCE: f64 = 1e-30;
+
+static SELECTED: OnceLock<Kernel> = OnceLock::new();
+static PERIODICITY: AtomicBool = AtomicBool::new(true);
+
+// Escape-time loops compiled for the instruction set extensions, picked at startup for the
+// CPU. All of them compute exactly the samples of the scalar one.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Kernel {
+    Scalar,
+    Neon,
+    Avx2,
+    Avx512,
+}
+
+impl Kernel {
+    // From the narrowest to the widest.
+    pub const ALL: [Kernel; 4] = [Kernel::Scalar, Kernel::Neon, Kernel::Avx2, Kernel::Avx512];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Kernel::Scalar => "scalar",
+            Kernel::Neon => "neon",
+            Kernel::Avx2 => "avx2",
+            Kernel::Avx512 => "avx512",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Kernel> {
+        Kernel::ALL.into_iter().find(|kernel| kernel.name() == name)
+    }
+
+    pub fn supported(&self) -> bool {
+        match self {
+            Kernel::Scalar => true,
+            #[cfg(target_arch = "x86_64")]
+            Kernel::Avx2 => is_x86_feature_detected!("avx2"),
+            #[cfg(target_arch = "x86_64")]
+            Kernel::Avx512 => is_x86_feature_detected!("avx512f"),
+            #[cfg(target_arch = "aarch64")]
+            Kernel::Neon => std::arch::is_aarch64_feature_detected!("neon"),
+            #[allow(unreachable_patterns)]
+            _ => false,
+        }
+    }
+
+    // The widest kernel the CPU supports.
+    pub fn detect() -> Kernel {
+        Kernel::ALL
+            .into_iter()
+            .rev()
+            .find(Kernel::supported)
+            .unwrap_or(Kernel::Scalar)
+    }
+
+    // Samples of the points, falling back to the scalar loop on CPUs without the extension and
+    // for the powers of the Multibrot sets and the formulas.
+    pub fn escape_time(
+        &self,
+        fractal: Fractal,
+        points: &[Complex<f64>],
+        iterations: u32,
+    ) -> Vec<Sample> {
+        let squared = !matches!(fractal, Fractal::Multibrot { .. } | Fractal::Formula { .. });
+        let vectorized = self.supported() && squared;
+        match self {
+            // SAFETY: the CPU supports the features the functions are compiled for.
+            #[cfg(target_arch = "x86_64")]
+            Kernel::Avx2 if vectorized => unsafe { avx2(fractal, points, iterations) },
+            #[cfg(target_arch = "x86_64")]
+            Kernel::Avx512 if vectorized => unsafe { avx512(fractal, points, iterations) },
+            #[cfg(target_arch = "aarch64")]
+            Kernel::Neon if vectorized => unsafe { neon(fractal, points, iterations) },
+            _ => points
+                .iter()
+                .map(|&point| render::escape_time(fractal, point, iterations))
+                .collect(),
+        }
+    }
+}
+
+// Chooses the kernel of all escape-time renders, the detected one unless overridden.
+pub fn select(kernel: Option<Kernel>) -> Result<Kernel, String> {
+    let kernel = match kernel {
+        Some(kernel) if !kernel.supported() => {
+            return Err(format!(
+                "The CPU does not support the {} kernel",
+                kernel.name()
+            ))
+        }
+        Some(kernel) => kernel,
+        None => Kernel::detect(),
+    };
+    Ok(*SELECTED.get_or_init(|| kernel))
+}
+
+pub fn current() -> Kernel {
+    *SELECTED.get_or_init(Kernel::detect)
+}
+
+// Turns the periodicity checking of the escape-time loops on or off, it is on by default.
+pub fn check_periodicity(enabled: bool) {
+    PERIODICITY.store(enabled, Ordering::Relaxed);
+}
+
+pub fn periodicity() -> bool {
+    PERIODICITY.load(Ordering::Relaxed)
+}
+
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "avx2")]
+unsafe fn avx2(fractal: Fractal, points: &[Complex<f64>], iterations: u32) -> Vec<Sample> {
+    lanes(fractal, points, iterations)
+}
+
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "avx512f")]
+unsafe fn avx512(fractal: Fractal, points: &[Complex<f64>], iterations: u32) -> Vec<Sample> {
+    lanes(fractal, points, iterations)
+}
+
+#[cfg(target_arch = "aarch64")]
+#[target_feature(enable = "neon")]
+unsafe fn neon(fractal: Fractal, points: &[Complex<f64>], iterations: u32) -> Vec<Sample> {
+    lanes(fractal, points, iterations)
+}
+
+// The scalar loop over `LANES` points at a time, without branches in the lanes: escaped lanes
+// keep their first value outside the bailout radius until all of them are done. Inlined into
+// the functions above, it is vectorized for their instruction sets.
+#[inline(always)]
+fn lanes(fractal: Fractal, points: &[Complex<f64>], iterations: u32) -> Vec<Sample> {
+    let mut samples = Vec::with_capacity(points.len());
+    for chunk in points.chunks(LANES) {
+        let (mut zr, mut zi, mut cr, mut ci) =
+            ([0.0; LANES], [0.0; LANES], [0.0; LANES], [0.0; LANES]);
+        let mut escape = [u32::MAX; LANES];
+        // The lanes past the end of the last chunk are done from the start, and so are those
+        // of the main cardioid and the period-2 bulb.
+        let mut done = std::array::from_fn::<bool, LANES, _>(|l| l >= chunk.len());
+        for (l, &point) in chunk.iter().enumerate() {
+            let (mut z, c) = fractal.start(point);
+            if fractal == Fractal::MANDELBROT {
+                if let Some(cycle) = render::main_component_cycle(c) {
+                    (z, done[l]) = (cycle, true);
+                }
+            }
+            (zr[l], zi[l], cr[l], ci[l]) = (z.re, z.im, c.re, c.im);
+        }
+        let (mut saved_r, mut saved_i) = (zr, zi);
+        let periodicity = periodicity();
+        let (fold, conjugate) = (fractal == Fractal::BurningShip, fractal == Fractal::Tricorn);
+        for i in 0..iterations {
+            for l in 0..LANES {
+                let (x, y) = match (fold, conjugate) {
+                    (true, _) => (zr[l].abs(), zi[l].abs()),
+                    (_, true) => (zr[l], -zi[l]),
+                    _ => (zr[l], zi[l]),
+                };
+                // The operations of `z * z + c` in the order of `render::julia`.
+                let re = x * x - y * y + cr[l];
+                let im = x * y + y * x + ci[l];
+                let escaped = re * re + im * im > 4.0;
+                let live = !done[l];
+                zr[l] = if live { re } else { zr[l] };
+                zi[l] = if live { im } else { zi[l] };
+                escape[l] = if live && escaped { i } else { escape[l] };
+                let (dr, di) = (re - saved_r[l], im - saved_i[l]);
+                let repeated = periodicity && dr * dr + di * di < PERIODICITY_TOLERANCE;
+                done[l] |= escaped || repeated;
+            }
+            if periodicity && (i + 1).is_power_of_two() {
+                (saved_r, saved_i) = (zr, zi);
+            }
+            if done.iter().all(|&done| done) {
+                break;
+            }
+        }
+        samples.extend((0..chunk.len()).map(|l| Sample {
+            escape: (escape[l] != u32::MAX).then_some(escape[l]),
+            z: Complex::new(zr[l], zi[l]),
+        }));
+    }
+    samples
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn kernels_match_scalar() {
+        assert_eq!(Kernel::from_name("avx2"), Some(Kernel::Avx2));
+        assert_eq!(Kernel::from_name("sse9"), None);
+        assert!(Kernel::detect().supported());
+        // Not a multiple of the lanes, and a mix of escaping and bounded points.
+        let points = (0..203)
+            .map(|i| Complex::new(-2.0 + i as f64 * 0.0123, 0.6 - i as f64 * 0.004))
+            .collect::<Vec<_>>();
+        let julia = Fractal::Julia {
+            c: Complex::new(-0.8, 0.156),
+        };
+        let generalized = Fractal::Mandelbrot {
+            z0: Complex::new(0.3, -0.2),
+        };
+        for fractal in [
+            Fractal::MANDELBROT,
+            generalized,
+            julia,
+            Fractal::BurningShip,
+            Fractal::Tricorn,
+        ] {
+            let scalar = Kernel::Scalar.escape_time(fractal, &points, 300);
+            assert!(scalar.iter().any(|s| s.escape.is_none()));
+            assert!(scalar.iter().any(|s| s.escape.is_some()));
+            assert_eq!(lanes(fractal, &points, 300), scalar);
+            for kernel in Kernel::ALL.into_iter().filter(Kernel::supported) {
+                assert_eq!(kernel.escape_time(fractal, &points, 300), scalar);
+            }
+        }
+    }
+}
@@ -0,0 +1,95 @@
+use sdl2::keyboard::{Keycode, Mod};
+
+// The keys the view is panned with, chosen in the setup.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum KeyScheme {
+    // The arrow keys.
+    Arrows,
+    // `H`, `J`, `K` and `L` as well, as in vi. Their own actions move to `Alt` with the letter.
+    Vi,
+}
+
+impl KeyScheme {
+    pub const ALL: [KeyScheme; 2] = [KeyScheme::Arrows, KeyScheme::Vi];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            KeyScheme::Arrows => "arrows",
+            KeyScheme::Vi => "vi",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<KeyScheme> {
+        KeyScheme::ALL
+            .into_iter()
+            .find(|scheme| scheme.name() == name)
+    }
+
+    pub fn description(self) -> &'static str {
+        match self {
+            KeyScheme::Arrows => "arrow keys pan",
+            KeyScheme::Vi => "hjkl pan as well, Alt+H/J/K/L for their own actions",
+        }
+    }
+
+    // The key and modifiers a key press acts as.
+    pub fn translate(self, keycode: Keycode, keymod: Mod) -> (Keycode, Mod) {
+        if self == KeyScheme::Arrows {
+            return (keycode, keymod);
+        }
+        let alt = Mod::LALTMOD | Mod::RALTMOD;
+        let pan = match keycode {
+            Keycode::H => Keycode::Left,
+            Keycode::J => Keycode::Down,
+            Keycode::K => Keycode::Up,
+            Keycode::L => Keycode::Right,
+            _ => return (keycode, keymod),
+        };
+        let shift_or_ctrl = Mod::LSHIFTMOD | Mod::RSHIFTMOD | Mod::LCTRLMOD | Mod::RCTRLMOD;
+        if keymod.intersects(alt) {
+            (keycode, keymod - alt)
+        } else if keymod.intersects(shift_or_ctrl) {
+            (keycode, keymod)
+        } else {
+            (pan, keymod)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn vi_keys_pan() {
+        for scheme in KeyScheme::ALL {
+            assert_eq!(KeyScheme::from_name(scheme.name()), Some(scheme));
+        }
+        let plain = Mod::NOMOD;
+        assert_eq!(
+            KeyScheme::Arrows.translate(Keycode::H, plain),
+            (Keycode::H, plain)
+        );
+        assert_eq!(
+            KeyScheme::Vi.translate(Keycode::H, plain),
+            (Keycode::Left, plain)
+        );
+        assert_eq!(
+            KeyScheme::Vi.translate(Keycode::K, Mod::NUMMOD),
+            (Keycode::Up, Mod::NUMMOD)
+        );
+        // `Shift+J` and `Ctrl+J` keep their actions, `Alt+L` is the `L` of the arrows.
+        assert_eq!(
+            KeyScheme::Vi.translate(Keycode::J, Mod::LSHIFTMOD),
+            (Keycode::J, Mod::LSHIFTMOD)
+        );
+        assert_eq!(
+            KeyScheme::Vi.translate(Keycode::L, Mod::RALTMOD),
+            (Keycode::L, plain)
+        );
+        assert_eq!(
+            KeyScheme::Vi.translate(Keycode::Q, plain),
+            (Keycode::Q, plain)
+        );
+    }
+}
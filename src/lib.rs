@@ -0,0 +1,74 @@
+// The rendering engine of the explorer, usable without its SDL2 front-end: parameters are
+// built with `RenderParams::builder()` and rendered into an RGB `Frame` with `render_frame`.
+pub mod annotate;
+pub mod bookmarks;
+pub mod buddhabrot;
+pub mod cache;
+pub mod cap;
+pub mod certify;
+pub mod coalesce;
+pub mod config;
+pub mod contact;
+pub mod contrast;
+pub mod dd;
+pub mod dialog;
+pub mod dive;
+pub mod doctor;
+pub mod dropped;
+pub mod dual;
+pub mod dump;
+pub mod embedded;
+pub mod fixed;
+pub mod formula;
+pub mod gallery;
+pub mod gamut;
+#[cfg(feature = "gpu")]
+pub mod gpu;
+pub mod hilbert;
+pub mod history;
+pub mod hud;
+pub mod inset;
+pub mod inspect;
+pub mod json;
+pub mod julia;
+pub mod kernel;
+pub mod keys;
+pub mod locale;
+pub mod location;
+pub mod manifest;
+pub mod measure;
+pub mod morton;
+pub mod noise;
+pub mod nudge;
+pub mod orbit;
+pub mod pack;
+pub mod palette;
+pub mod params;
+pub mod perturbation;
+pub mod png;
+pub mod poster;
+pub mod postprocess;
+pub mod profile;
+pub mod quality;
+pub mod render;
+pub mod roi;
+pub mod schedule;
+pub mod scheduler;
+pub mod setup;
+pub mod splash;
+pub mod stats;
+pub mod style;
+pub mod subdivide;
+pub mod superres;
+pub mod text;
+pub mod tiff;
+pub mod tiles;
+pub mod tonemap;
+pub mod tour;
+pub mod video;
+pub mod viewport;
+pub mod wallpaper;
+
+pub use params::{Fractal, RenderParams};
+pub use render::{render_frame, Frame};
+pub use viewport::Viewport;
@@ -0,0 +1,96 @@
+use std::borrow::Cow;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+// Languages writing a decimal comma, by their ISO 639-1 code.
+const COMMA_LANGUAGES: &[&str] = &[
+    "af", "az", "be", "bg", "ca", "cs", "da", "de", "el", "es", "et", "eu", "fi", "fr", "gl", "hr",
+    "hu", "id", "is", "it", "kk", "lt", "lv", "mk", "nb", "nl", "nn", "no", "pl", "pt", "ro", "ru",
+    "sk", "sl", "sq", "sr", "sv", "tr", "uk", "vi",
+];
+
+// Decimal separator of the coordinates shown in the HUD and copied to the clipboard. Files and
+// exports always write the point, and the console reads numbers with either.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Separator {
+    Point,
+    Comma,
+}
+
+static COMMA: AtomicBool = AtomicBool::new(false);
+
+impl Separator {
+    pub fn from_name(name: &str) -> Option<Separator> {
+        match name {
+            "point" => Some(Separator::Point),
+            "comma" => Some(Separator::Comma),
+            _ => None,
+        }
+    }
+
+    // The separator of a POSIX locale name such as `de_DE.UTF-8`.
+    pub fn of_locale(locale: &str) -> Separator {
+        let language = locale.split(['_', '.', '@', '-']).next().unwrap_or("");
+        match COMMA_LANGUAGES.contains(&language) {
+            true => Separator::Comma,
+            false => Separator::Point,
+        }
+    }
+
+    // The separator of the numeric locale of the environment, in the order of precedence of
+    // the C library.
+    pub fn from_env() -> Separator {
+        ["LC_ALL", "LC_NUMERIC", "LANG"]
+            .into_iter()
+            .filter_map(|name| std::env::var(name).ok())
+            .find(|value| !value.is_empty())
+            .map_or(Separator::Point, |locale| Separator::of_locale(&locale))
+    }
+
+    // Numbers formatted with the point, as shown with this separator.
+    pub fn display(self, text: &str) -> Cow<'_, str> {
+        match self {
+            Separator::Comma => Cow::Owned(text.replace('.', ",")),
+            Separator::Point => Cow::Borrowed(text),
+        }
+    }
+}
+
+pub fn set(separator: Separator) {
+    COMMA.store(separator == Separator::Comma, Ordering::Relaxed);
+}
+
+pub fn current() -> Separator {
+    match COMMA.load(Ordering::Relaxed) {
+        true => Separator::Comma,
+        false => Separator::Point,
+    }
+}
+
+// A number written with either separator as Rust parses it: a single comma without a point is
+// the decimal separator of the other locales.
+pub fn normalize(text: &str) -> Cow<'_, str> {
+    match (text.matches(',').count(), text.contains('.')) {
+        (1, false) => Cow::Owned(text.replace(',', ".")),
+        _ => Cow::Borrowed(text),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn separators_follow_the_locale() {
+        assert_eq!(Separator::of_locale("de_DE.UTF-8"), Separator::Comma);
+        assert_eq!(Separator::of_locale("cs_CZ"), Separator::Comma);
+        assert_eq!(Separator::of_locale("en_US.UTF-8"), Separator::Point);
+        assert_eq!(Separator::of_locale("C"), Separator::Point);
+        assert_eq!(Separator::from_name("comma"), Some(Separator::Comma));
+        assert_eq!(Separator::Comma.display("+0.12345 6"), "+0,12345 6");
+        assert_eq!(Separator::Point.display("+0.12345 6"), "+0.12345 6");
+        assert_eq!(normalize("-0,75"), "-0.75");
+        assert_eq!(normalize("-0.75"), "-0.75");
+        assert_eq!(normalize("1,000,000"), "1,000,000");
+        assert_eq!(normalize("1e-8"), "1e-8");
+    }
+}
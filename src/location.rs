@@ -0,0 +1,245 @@
+use crate::fixed::{Fixed, CENTER_BITS};
+use crate::json::{FromJson, Json};
+use crate::locale;
+use crate::viewport::{Viewport, TAIL_TERMS};
+use num::complex::Complex;
+
+const FORMATS: &str = "RE IM [HEIGHT], \"RE, IM\", \"RE + IM i\", the Re:, Im: and Zoom: lines of \
+                       Kalles Fraktaler or a viewport JSON";
+
+// A place in the plane as shared in text: the center, with the rest of its digits beyond f64
+// split as in `Viewport`, and the height of the view if the text gives one.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Location {
+    pub center: Complex<f64>,
+    pub center_low: Complex<f64>,
+    pub center_tail: [Complex<f64>; TAIL_TERMS],
+    pub height: Option<f64>,
+}
+
+impl Location {
+    // The view of `viewport` moved to the location, its height left to the caller.
+    pub fn place(&self, viewport: Viewport) -> Viewport {
+        Viewport {
+            center: self.center,
+            center_low: self.center_low,
+            center_tail: self.center_tail,
+            ..viewport
+        }
+    }
+}
+
+// Reads a location in any of `FORMATS`, with either decimal separator.
+pub fn parse(text: &str) -> Result<Location, String> {
+    let text = text.trim();
+    if text.starts_with('{') {
+        let viewport = Viewport::from_json(&Json::parse(text)?)?;
+        return Ok(Location {
+            center: viewport.center,
+            center_low: viewport.center_low,
+            center_tail: viewport.center_tail,
+            height: Some(viewport.height),
+        });
+    }
+    let (re, im, height) = match labelled(text)? {
+        Some(fields) => fields,
+        None => match algebraic(text) {
+            Some((re, im)) => (re, im, None),
+            None => listed(text)?,
+        },
+    };
+    let (re, im) = (coordinate(&re)?, coordinate(&im)?);
+    if let Some(height) = height {
+        if height <= 0.0 {
+            return Err(format!("Invalid view height {height}"));
+        }
+    }
+    let mut viewport = Viewport::default();
+    viewport.set_center(&re, &im);
+    Ok(Location {
+        center: viewport.center,
+        center_low: viewport.center_low,
+        center_tail: viewport.center_tail,
+        height,
+    })
+}
+
+// The `Key: value` lines of Kalles Fraktaler, other lines ignored, or `None` without any.
+// Its zoom 1 shows a height of 4.
+fn labelled(text: &str) -> Result<Option<(String, String, Option<f64>)>, String> {
+    let (mut re, mut im, mut height) = (None, None, None);
+    for line in text.lines() {
+        let Some((key, value)) = line.split_once([':', '=']) else {
+            continue;
+        };
+        let value = value.trim();
+        match key.trim().to_ascii_lowercase().as_str() {
+            "re" | "real" => re = Some(value),
+            "im" | "imag" => im = Some(value),
+            "zoom" => height = Some(4.0 / number(value)?),
+            "height" => height = Some(number(value)?),
+            _ => {}
+        }
+    }
+    match (re, im) {
+        (None, None) => Ok(None),
+        (Some(re), Some(im)) => Ok(Some((re.to_string(), im.to_string(), height))),
+        (None, _) => Err("Missing the Re: line".to_string()),
+        (_, None) => Err("Missing the Im: line".to_string()),
+    }
+}
+
+// The parts of `RE + IM i`, written with or without spaces, `*` and parentheses.
+fn algebraic(text: &str) -> Option<(String, String)> {
+    let compact = text.split_whitespace().collect::<String>();
+    let body = compact.trim_matches(['(', ')']).strip_suffix('i')?;
+    let body = body.strip_suffix('*').unwrap_or(body);
+    let bytes = body.as_bytes();
+    // The sign between the parts, not that of an exponent.
+    let split = (1..bytes.len())
+        .rev()
+        .find(|&i| matches!(bytes[i], b'+' | b'-') && !matches!(bytes[i - 1], b'e' | b'E'));
+    let (re, im) = match split {
+        Some(i) => (&body[..i], &body[i..]),
+        None => ("0", body),
+    };
+    // A lone sign stands for 1.
+    let im = match im {
+        "" | "+" => "1",
+        "-" => "-1",
+        _ => im,
+    };
+    Some((re.to_string(), im.to_string())).filter(|(re, _)| !re.is_empty())
+}
+
+// Two or three numbers apart by spaces, `;` or commas: a comma followed by a space, or the only
+// separator of two numbers written without spaces. Commas inside numbers are decimal commas.
+fn listed(text: &str) -> Result<(String, String, Option<f64>), String> {
+    let text = text.trim_matches(['(', ')', '[', ']', ' ']);
+    let numbers = if text.contains(';') {
+        text.split(';').map(str::trim).collect::<Vec<_>>()
+    } else {
+        let words = text
+            .split_whitespace()
+            .map(|word| word.trim_end_matches(','))
+            .filter(|word| !word.is_empty() && *word != ",")
+            .collect::<Vec<_>>();
+        match words[..] {
+            [word] if word.contains(',') => word.split(',').collect(),
+            _ => words,
+        }
+    };
+    match numbers[..] {
+        [re, im] => Ok((re.to_string(), im.to_string(), None)),
+        [re, im, height] => Ok((re.to_string(), im.to_string(), Some(number(height)?))),
+        [] => Err(format!("Expected {FORMATS}")),
+        _ => Err(format!(
+            "Expected {FORMATS}, got {} numbers (numbers with a decimal comma go apart by \
+             spaces or ;)",
+            numbers.len()
+        )),
+    }
+}
+
+fn number(text: &str) -> Result<f64, String> {
+    match locale::normalize(text).parse::<f64>() {
+        Ok(value) if value.is_finite() => Ok(value),
+        _ => Err(format!("Invalid number {text}")),
+    }
+}
+
+// A finite coordinate in fixed point, from as many digits as it has.
+fn coordinate(text: &str) -> Result<Fixed, String> {
+    number(text)?;
+    Fixed::parse(&locale::normalize(text), CENTER_BITS)
+        .ok_or_else(|| format!("Invalid number {text}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hud;
+    use crate::json::ToJson;
+    use crate::locale::Separator;
+    use crate::viewport::NO_TAIL;
+
+    fn center(text: &str) -> (f64, f64, Option<f64>) {
+        let location = parse(text).unwrap();
+        (location.center.re, location.center.im, location.height)
+    }
+
+    #[test]
+    fn reads_coordinates_in_many_shapes() {
+        let expected = (-0.75, 0.1, None);
+        for text in [
+            "-0.75 0.1",
+            "-0.75, 0.1",
+            "(-0.75,0.1)",
+            "-0.75;0.1",
+            "-0,75 0,1",
+            "-0,75; 0,1",
+            "-0.75 + 0.1i",
+            "-0.75+0.1*i",
+            "(-7.5e-1 + 1e-1 i)",
+            "-0,75 + 0,1i",
+        ] {
+            assert_eq!(center(text), expected, "{text}");
+        }
+        assert_eq!(center("-0.75 -1E-1i"), (-0.75, -0.1, None));
+        assert_eq!(center("0.5i"), (0.0, 0.5, None));
+        assert_eq!(center("-1 - i"), (-1.0, -1.0, None));
+        assert_eq!(center("-0.75 0.1 1e-8"), (-0.75, 0.1, Some(1e-8)));
+        let kf = "Re: -0.75\r\nIm: 0.1\r\nZoom: 4E8\r\nIterations: 1000\r\n";
+        assert_eq!(center(kf), (-0.75, 0.1, Some(1e-8)));
+        for invalid in [
+            "",
+            "-0.75",
+            "-0,75,0,1",
+            "-0.75 0.1 2 3",
+            "-0.75 abc",
+            "-0.75 inf",
+            "Re: -0.75",
+            "-0.75 0.1 -1",
+            "{\"center_re\": 1}",
+        ] {
+            assert!(parse(invalid).is_err(), "{invalid}");
+        }
+        assert!(parse("-0.75 0.1 2 3")
+            .unwrap_err()
+            .contains("got 4 numbers"));
+    }
+
+    #[test]
+    fn shared_views_round_trip() {
+        let deep = Viewport::new(
+            Complex::new(-0.743643887037151, 0.13182590420533),
+            4e-14,
+            3e-14,
+        );
+        for separator in [Separator::Point, Separator::Comma] {
+            let location = parse(&hud::goto_arguments(&deep, 600, separator)).unwrap();
+            let error = location.center + location.center_low - deep.center;
+            assert!(error.norm() < deep.height / 6000.0, "{separator:?}");
+            assert_eq!(location.height, Some(deep.height));
+        }
+        let mut viewport = deep;
+        viewport.pan(Complex::new(1e-25, -3e-26));
+        let location = parse(&viewport.to_json().to_string()).unwrap();
+        assert_eq!(
+            (location.center, location.center_low),
+            (viewport.center, viewport.center_low)
+        );
+        // The digits beyond f64 are kept.
+        let location = parse("-1.7400623825793399052 + 0.0281753397792110489i").unwrap();
+        assert_eq!(location.center.re, -1.74006238257934);
+        assert!((location.center_low.re - 7.827937176731648e-17).abs() < 1e-30);
+        // And those beyond double-double, through the coordinates the console copies too.
+        let text = format!("-0.75{} 0.1 3e-80", "1234567890".repeat(9));
+        let location = parse(&text).unwrap();
+        assert_ne!(location.center_tail, NO_TAIL);
+        let viewport = location.place(Viewport::new(location.center, 4e-80, 3e-80));
+        let copied = parse(&hud::goto_arguments(&viewport, 600, Separator::Point)).unwrap();
+        let moved = copied.place(viewport).displacement(&viewport);
+        assert!(moved.norm() < viewport.height / 6000.0, "{moved}");
+    }
+}
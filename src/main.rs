@@ -1,77 +1,106 @@
 extern crate sdl2;
-use itertools::Itertools;
+mod cli;
+mod console;
+mod preview;
+
+use mandelbrot_explorer::{
+    annotate, bookmarks, buddhabrot, cache, cap, coalesce, config, contact, contrast, dialog, dive,
+    doctor, dropped, dual, dump, embedded, formula, gallery, gamut, history, hud, inset, inspect,
+    json, julia, kernel, locale, location, manifest, measure, noise, nudge, orbit, pack, palette,
+    params, png, postprocess, profile, quality, render, roi, schedule, setup, splash, stats, style,
+    subdivide, superres, tiles, tonemap, tour, video, viewport, wallpaper,
+};
+
+use annotate::{Annotation, Tool};
+use bookmarks::Bookmark;
+use buddhabrot::{Importance, Progressive, SplitMix64};
+use cache::RenderCache;
+use cli::Options;
+use coalesce::Coalescer;
+use config::Config;
+use console::{Action, Console};
+use contrast::AutoContrast;
+use dialog::{Dialogs, Purpose};
+use dive::{Dive, Glide, ScreenshotSeries};
+use dropped::Dropped;
+use gamut::GamutTransform;
+use history::History;
+use inspect::Inspection;
+use json::{FromJson, Json, ToJson};
+use locale::Separator;
+use location::Location;
+use manifest::Manifest;
+use measure::Measurement;
 use num::complex::Complex;
-use rayon::prelude::*;
-use sdl2::keyboard::Keycode;
-use sdl2::mouse::MouseState;
+use palette::Palette;
+use params::{Coloring, Fractal, Plane, RenderParams};
+use postprocess::{Bloom, Effect};
+use preview::Preview;
+use profile::IterationPolicy;
+use quality::QualityController;
+use render::Frame;
+use roi::Rect;
+use schedule::IterationSchedule;
+use sdl2::event::{Event, WindowEvent};
+use sdl2::keyboard::{Keycode, Mod};
+use sdl2::mouse::{MouseButton, MouseState};
 use sdl2::pixels::PixelFormatEnum;
-use sdl2::render::Canvas;
+use sdl2::render::TextureCreator;
+use sdl2::render::{Canvas, RendererInfo};
 use sdl2::surface::Surface;
+use sdl2::sys::SDL_RendererFlags;
 use sdl2::video::{Window, WindowContext};
-use sdl2::{event::Event, render::TextureCreator};
+use sdl2::{EventPump, EventSubsystem};
+use setup::{Input, Setup};
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::time::{Duration, Instant};
+use style::Style;
+use superres::SuperResolution;
+use tiles::{level_factor, TileCache, TileGrid};
+use tonemap::ToneMap;
+use viewport::{Viewport, NO_TAIL};
 
-fn mandelbrot(c: Complex<f64>, iterations: u32) -> Option<u32> {
-    let mut z = Complex::new(0.0, 0.0);
-    for i in 0..iterations {
-        z = z * z + c;
-        if z.re * z.re + z.im * z.im > 4.0 {
-            return Some(i);
-        }
-    }
-
-    None
+// Whether SDL picked a hardware renderer.
+fn accelerated(renderer: &RendererInfo) -> bool {
+    renderer.flags & SDL_RendererFlags::SDL_RENDERER_ACCELERATED as u32 != 0
 }
 
-fn x_y_to_complex(
-    x: i32,
-    y: i32,
-    window_size: &(u32, u32),
-    view_port: &(Complex<f64>, Complex<f64>),
-) -> Complex<f64> {
-    let rel_x = x as f64 / window_size.0 as f64;
-    let rel_y = y as f64 / window_size.1 as f64;
-    let d = view_port.1 - view_port.0;
-    let re = view_port.0.re + rel_x * d.re;
-    let im = view_port.0.im + rel_y * d.im;
-    Complex::new(re, im)
+// Name, kind and limits of an SDL renderer.
+fn describe_renderer(renderer: &RendererInfo) -> String {
+    format!(
+        "{}: {}, textures up to {}x{}",
+        renderer.name,
+        if accelerated(renderer) {
+            "accelerated"
+        } else {
+            "software"
+        },
+        renderer.max_texture_width,
+        renderer.max_texture_height
+    )
 }
 
-pub fn draw_fractal(
+// Shows the frame, converted to the gamut of the monitor if there is a transform. The frame
+// itself keeps its sRGB colors for exports and recoloring.
+pub fn draw_frame(
     canvas: &mut Canvas<Window>,
     texture_creator: &TextureCreator<WindowContext>,
-    y_x_coords: &[(i32, i32)],
-    view_port: &(Complex<f64>, Complex<f64>),
-    iterations: u32,
+    gamut: Option<&GamutTransform>,
+    frame: &mut Frame,
 ) -> Result<(), String> {
-    let window_size = canvas.window().size();
-    let (width, height) = window_size;
-
     let stamp = Instant::now();
-    let data = y_x_coords
-        .par_iter()
-        .map(|(y, x)| {
-            let c = x_y_to_complex(*x, *y, &window_size, view_port);
-            mandelbrot(c, iterations)
-        })
-        .collect::<Vec<_>>();
-    let elapsed = Instant::now() - stamp;
-    println!("Computation time {elapsed:?}");
-
-    let stamp = Instant::now();
-    let mut data = data
-        .into_iter()
-        .flat_map(|i| {
-            if let Some(iter) = i {
-                let c = (255 * iter / iterations) as u8;
-                [c / 2, c, c]
-            } else {
-                [0, 0, 0]
-            }
-        })
-        .collect::<Vec<_>>();
-
-    let surface = Surface::from_data(&mut data, width, height, width * 3, PixelFormatEnum::RGB24)
+    let (width, height) = (frame.width, frame.height);
+    let mut converted;
+    let rgb = match gamut {
+        Some(gamut) => {
+            converted = frame.rgb.clone();
+            gamut.apply(&mut converted);
+            &mut converted
+        }
+        None => &mut frame.rgb,
+    };
+    let surface = Surface::from_data(rgb, width, height, width * 3, PixelFormatEnum::RGB24)
         .map_err(|e| e.to_string())?;
     let texture = texture_creator
         .create_texture_from_surface(surface)
@@ -87,115 +116,2448 @@ pub fn draw_fractal(
     Ok(())
 }
 
+// Everything drawn over the rendered frames, from the bottom up.
+struct Overlays<'a> {
+    annotations: &'a [Annotation],
+    measurement: Option<&'a Measurement>,
+    orbit: &'a [Complex<f64>],
+    // Startup hints.
+    hints: bool,
+    // Coordinates of the view.
+    hud: bool,
+    // Orbit statistics of the point under the cursor.
+    inspection: Option<&'a Inspection>,
+    // Progress of the Buddhabrot accumulating.
+    accumulating: Option<&'a Progressive>,
+    // Julia set of the parameter under the cursor.
+    julia: Option<&'a Frame>,
+    console: &'a Console,
+    style: &'a Style,
+}
+
+// Draws a frame of the viewport with the overlays, leaving the frame itself untouched.
+fn draw_with_overlays(
+    canvas: &mut Canvas<Window>,
+    texture_creator: &TextureCreator<WindowContext>,
+    gamut: Option<&GamutTransform>,
+    (viewport, frame): (&Viewport, &mut Frame),
+    overlays: &Overlays,
+) -> Result<(), String> {
+    let Overlays {
+        annotations,
+        measurement,
+        orbit,
+        hints,
+        hud,
+        inspection,
+        accumulating,
+        julia,
+        console,
+        style,
+    } = *overlays;
+    let empty = annotations.is_empty() && measurement.is_none() && orbit.is_empty();
+    let panels = hints || hud || inspection.is_some() || accumulating.is_some() || julia.is_some();
+    if empty && !panels && !console.open {
+        return draw_frame(canvas, texture_creator, gamut, frame);
+    }
+    let mut shown = Frame::from_rgb(frame.width, frame.height, frame.rgb.clone());
+    annotate::draw(&mut shown, viewport, annotations, style);
+    if let Some(measurement) = measurement {
+        measurement.draw(&mut shown, viewport, style);
+    }
+    orbit::draw(&mut shown, viewport, orbit, style);
+    if hud {
+        hud::draw(&mut shown, viewport, style);
+    }
+    if let Some(inspection) = inspection {
+        inspect::draw(&mut shown, inspection, style);
+    }
+    if let Some(accumulating) = accumulating {
+        hud::status(&mut shown, &accumulating.status(), style);
+    }
+    if let Some(julia) = julia {
+        inset::draw(&mut shown, julia, style);
+    }
+    if hints {
+        splash::hints(&mut shown, style);
+    }
+    if console.open {
+        console.overlay(&mut shown, style);
+    }
+    draw_frame(canvas, texture_creator, gamut, &mut shown)
+}
+
+// Whether input waits in the queue that makes the view being rendered obsolete, or will be
+// handled before it could be shown anyway.
+fn input_pending(event_pump: &mut EventPump, events: &EventSubsystem) -> bool {
+    event_pump.pump_events();
+    events.peek_events::<Vec<Event>>(64).iter().any(|event| {
+        matches!(
+            event,
+            Event::MouseWheel { .. } | Event::KeyDown { .. } | Event::Quit { .. }
+        )
+    })
+}
+
+fn export_frame(path: &Path, frame: &Frame) -> Result<(), String> {
+    match path.extension().and_then(|extension| extension.to_str()) {
+        Some("png") => png::write(path, frame.width, frame.height, &frame.rgb),
+        Some("ppm") => video::write_ppm(path, frame.width, frame.height, &frame.rgb),
+        _ => Err(format!("{}: export as .png or .ppm", path.display())),
+    }
+}
+
+// Renders the frame afresh from `params`, leaving out whatever the interactive frame gained
+// from outside of them (tiles, auto-contrast, enhanced regions), and writes the manifest to
+// render it again.
+fn export_reproducible(path: &Path, params: RenderParams) -> Result<(), String> {
+    let stamp = Instant::now();
+    let frame = render::render_frame(params);
+    let manifest = Manifest::new(params, Instant::now() - stamp);
+    export_frame(path, &frame)?;
+    manifest.write(&manifest::sidecar(path))
+}
+
+// The console lines reporting a suggested iteration cap.
+fn cap_report(suggestion: &cap::Suggestion, iterations: u32) -> Vec<String> {
+    let quantiles = cap::QUANTILES
+        .iter()
+        .filter_map(|&percent| Some(format!("{percent}%: {}", suggestion.quantile(percent)?)))
+        .collect::<Vec<_>>();
+    let mut lines = vec![format!(
+        "{} of {} points escape within {}",
+        suggestion.escaped(),
+        suggestion.sampled,
+        suggestion.probe
+    )];
+    if !quantiles.is_empty() {
+        lines.push(quantiles.join("  "));
+    }
+    lines.push(match suggestion.cap {
+        Some(cap) => format!(
+            "Suggested cap {cap} (now {iterations}, {} points cut short)",
+            suggestion.missed
+        ),
+        None => "No point escapes, no cap to suggest".to_string(),
+    });
+    lines
+}
+
+// The named palettes followed by those of --palettes.
+fn palettes(options: &Options) -> Vec<(String, Palette)> {
+    palette::NAMED
+        .iter()
+        .map(|&(name, palette)| (name.to_string(), palette))
+        .chain(options.palettes.iter().cloned())
+        .collect()
+}
+
+// The setup of the first run in a window of its own, over the initial view in the palette being
+// chosen. `None` when the window is closed.
+fn run_setup(options: &Options) -> Result<Option<Config>, String> {
+    const SIZE: (u32, u32) = (800, 600);
+    println!("Benchmarking the escape-time kernels");
+    let kernels = config::benchmark();
+    for (kernel, elapsed) in &kernels {
+        println!("{:<8} {elapsed:?}", kernel.name());
+    }
+    let mut setup = Setup::new(kernels, palettes(options), (options.width, options.height));
+    let sdl_context = sdl2::init()?;
+    let window = sdl_context
+        .video()?
+        .window("Mandelbrot explorer setup", SIZE.0, SIZE.1)
+        .build()
+        .map_err(|e| e.to_string())?;
+    let mut canvas = window.into_canvas().build().map_err(|e| e.to_string())?;
+    let texture_creator = canvas.texture_creator();
+    let gamut = options.gamut.transform();
+    let style = options.theme.style();
+    let mut event_pump = sdl_context.event_pump()?;
+    let mut background: Option<(Option<Palette>, Frame)> = None;
+    loop {
+        if background.as_ref().map(|(shown, _)| *shown) != Some(setup.palette()) {
+            let params = RenderParams::builder()
+                .viewport(Viewport::default().fit(SIZE))
+                .size(SIZE.0, SIZE.1)
+                .palette(setup.palette())
+                .build()?;
+            background = Some((setup.palette(), render::render_frame(params)));
+        }
+        let rgb = background.as_ref().unwrap().1.rgb.clone();
+        let mut frame = Frame::from_rgb(SIZE.0, SIZE.1, rgb);
+        setup.draw(&mut frame, &style);
+        draw_frame(&mut canvas, &texture_creator, gamut.as_ref(), &mut frame)?;
+        let input = match event_pump.wait_event() {
+            Event::Quit { .. } => return Ok(None),
+            Event::KeyDown {
+                keycode: Some(keycode),
+                ..
+            } => match keycode {
+                Keycode::Up | Keycode::Left => Input::Previous,
+                Keycode::Down | Keycode::Right => Input::Next,
+                Keycode::Return | Keycode::KpEnter => Input::Confirm,
+                Keycode::Escape => Input::Skip,
+                _ => continue,
+            },
+            _ => continue,
+        };
+        if setup.input(input) {
+            return Ok(Some(setup.config()));
+        }
+    }
+}
+
+// Saves the bookmark into the bookmarks file, replacing one of the same name.
+fn save_bookmark(
+    path: &Path,
+    bookmarks: &mut Vec<Bookmark>,
+    bookmark: Bookmark,
+) -> Result<String, String> {
+    let index = bookmarks::put(bookmarks, bookmark);
+    bookmarks::save(path, bookmarks)?;
+    Ok(format!(
+        "Saved bookmark {}: {}",
+        index + 1,
+        bookmarks[index].name
+    ))
+}
+
+// The view of the window centered at the location, as high as `current` unless it says.
+fn goto(current: &Viewport, location: Location, (width, height): (u32, u32)) -> Viewport {
+    let view_height = location.height.unwrap_or(current.height);
+    let viewport = Viewport::new(
+        location.center,
+        view_height * width as f64 / height as f64,
+        view_height,
+    );
+    location.place(viewport)
+}
+
 pub fn main() -> Result<(), String> {
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+    let mut options = Options::parse(args.clone())?;
+    let config = match config::load(&options.config)? {
+        None if options.setup && options.explorer() => match run_setup(&options)? {
+            Some(config) => {
+                config::save(&options.config, &config)?;
+                println!("Wrote {}", options.config.display());
+                Some(config)
+            }
+            None => return Ok(()),
+        },
+        config => config,
+    };
+    if let Some(config) = &config {
+        options = Options::parse_over(Options::from_config(config), args)?;
+    }
+    if options.help {
+        println!("{}", cli::USAGE);
+        return Ok(());
+    }
+    kernel::check_periodicity(options.periodicity);
+    subdivide::enable(options.subdivision);
+    noise::animate(options.animated_noise);
+    let profiles = profile::load(
+        options.profiles.as_deref(),
+        &options.config,
+        Path::new(profile::FALLBACK),
+    )?;
+    let startup_profile = match &options.profile {
+        Some(name) => Some(profile::find(&profiles, name)?),
+        None => None,
+    };
+    let chosen_kernel = options
+        .kernel
+        .or(startup_profile.and_then(|index| profiles[index].kernel));
+    locale::set(options.decimal.unwrap_or_else(Separator::from_env));
+    let supported = kernel::Kernel::ALL
+        .into_iter()
+        .filter(kernel::Kernel::supported)
+        .map(|kernel| kernel.name())
+        .collect::<Vec<_>>();
+    println!(
+        "Escape-time kernel {} (supported: {})",
+        kernel::select(chosen_kernel)?.name(),
+        supported.join(", ")
+    );
+    if options.doctor {
+        for line in doctor::report() {
+            println!("{line}");
+        }
+        println!("SDL {}", sdl2::version::version());
+        println!(
+            "Video driver {}",
+            sdl2::init()?.video()?.current_video_driver()
+        );
+        for renderer in sdl2::render::drivers() {
+            println!("Renderer {}", describe_renderer(&renderer));
+        }
+        return Ok(());
+    }
+    // The renders of `--headless`, `poster`, `gallery` and `contactsheet` go through the cache
+    // as well as those of the explorer.
+    let cache = if options.cache {
+        let dir = options
+            .cache_dir
+            .clone()
+            .unwrap_or_else(RenderCache::default_dir);
+        Some(RenderCache::new(dir, options.cache_size_mb * 1024 * 1024)?)
+    } else {
+        None
+    };
+    if let Some(path) = &options.params {
+        let manifest = Manifest::load(path)?;
+        // Rendered again with the switches the image was rendered with.
+        subdivide::enable(manifest.subdivision);
+        kernel::check_periodicity(manifest.periodicity);
+        if manifest.version != manifest::VERSION {
+            println!(
+                "Manifest written by version {}, this is {}",
+                manifest.version,
+                manifest::VERSION
+            );
+        }
+        let output = options
+            .output
+            .as_ref()
+            .ok_or_else(|| "--params needs --output FILE".to_string())?;
+        let stamp = Instant::now();
+        export_frame(output, &cache::render(cache.as_ref(), manifest.params))?;
+        println!(
+            "Rendered {} in {:?} (originally {:?})",
+            output.display(),
+            Instant::now() - stamp,
+            manifest.elapsed
+        );
+        return Ok(());
+    }
+    if let Some(job) = &options.poster {
+        let stamp = Instant::now();
+        match (&job.dump, job.output.extension().and_then(|e| e.to_str())) {
+            (Some(path), _) => {
+                let params = job.params()?;
+                let frame = render::render_frame(params);
+                export_frame(&job.output, &frame)?;
+                dump::write(path, &dump::Dump::from_frame(params, &frame))?;
+            }
+            (None, Some("png")) => job.stream()?,
+            (None, _) => export_frame(&job.output, &cache::render(cache.as_ref(), job.params()?))?,
+        }
+        println!(
+            "Rendered {} in {:?}",
+            job.output.display(),
+            Instant::now() - stamp
+        );
+        return Ok(());
+    }
+    if let Some(job) = &options.zoom_video {
+        let mut preview = if job.preview {
+            Some(Preview::open("Zoom video preview", job.width, job.height)?)
+        } else {
+            None
+        };
+        return video::run(job, |_, rgb| {
+            let shown = preview
+                .as_mut()
+                .map(|window| window.show(job.width, job.height, rgb));
+            match shown {
+                Some(Ok(false)) => preview = None,
+                Some(Err(e)) => {
+                    println!("Preview failed: {e}");
+                    preview = None;
+                }
+                _ => {}
+            }
+        });
+    }
+    if let Some(job) = &options.tour {
+        return tour::run(job);
+    }
+    if let Some(job) = &options.contact_sheet {
+        return contact::run(job, cache.as_ref());
+    }
+    if let Some(job) = &options.orbit_batch {
+        return orbit::run(job);
+    }
+    if let Some(job) = &options.exchange {
+        return pack::run(job);
+    }
+    if let Some(job) = &options.gallery {
+        return gallery::run(job, cache.as_ref());
+    }
+    if let Some(job) = &options.wallpaper {
+        let mut job = job.clone();
+        if job.size.is_none() {
+            let mode = sdl2::init()?.video()?.desktop_display_mode(0)?;
+            job.size = Some((mode.w as u32, mode.h as u32));
+        }
+        return wallpaper::run(&job);
+    }
+    if options.list_julia {
+        for preset in julia::PRESETS {
+            println!("{:<14} {}", preset.name, julia::describe(preset));
+        }
+        return Ok(());
+    }
+    let mut julia_preset = match &options.julia {
+        Some(name) => Some(
+            julia::PRESETS
+                .iter()
+                .position(|preset| preset.name == name)
+                .ok_or_else(|| format!("Unknown Julia constant {name}, see --list-julia"))?,
+        ),
+        None => None,
+    };
+    // The Mandelbrot set `M` returns to, from the starting value of --z0 or `set z0`.
+    let mut mandelbrot = Fractal::Mandelbrot { z0: options.z0 };
+    let mut fractal = match julia_preset {
+        Some(index) => Fractal::Julia {
+            c: julia::PRESETS[index].c,
+        },
+        None => match options.fractal {
+            Some(Fractal::Mandelbrot { .. }) | None => mandelbrot,
+            Some(other) => other,
+        },
+    };
+
     let sdl_context = sdl2::init()?;
     let video_subsystem = sdl_context.video()?;
-    const WIDTH: u32 = 800;
-    const HEIGHT: u32 = 600;
+    const ZOOM_OUT_LEVELS: i32 = 2;
+    // How often input is checked while a frame is computed.
+    const RENDER_POLL: Duration = Duration::from_millis(10);
+    // Highest exponent the Multibrot key cycles to, before starting over from 3.
+    const MAX_EXPONENT: u32 = 8;
+    const IDLE: Duration = Duration::from_secs(2);
+    // A left button held still longer than this keeps zooming in, one moved further than
+    // `DRAG` pixels drags the rectangle to zoom into instead.
+    const HOLD: Duration = Duration::from_millis(250);
+    const DRAG: i32 = 8;
 
     let window = video_subsystem
-        .window("Mandelbrot explorer", WIDTH, HEIGHT)
+        .window("Mandelbrot explorer", options.width, options.height)
+        .resizable()
         .build()
         .map_err(|e| e.to_string())?;
 
     let mut canvas = window.into_canvas().build().map_err(|e| e.to_string())?;
+    let renderer = canvas.info();
+    println!("Renderer {}", describe_renderer(&renderer));
+    let mut warnings = doctor::warnings();
+    if !accelerated(&renderer) {
+        warnings.push("The renderer is not accelerated, drawing frames is slow".to_string());
+    }
+    for warning in warnings {
+        println!("Warning: {warning} (see `mandelbrot-explorer doctor`)");
+    }
     let texture_creator = canvas.texture_creator();
-    let mut view_port = (Complex::new(-2.0, -1.5), Complex::new(2.0, 1.5));
+    let mut view_port = match &options.viewport_file {
+        Some(path) => {
+            let text = std::fs::read_to_string(path).map_err(|e| format!("{path}: {e}"))?;
+            Viewport::from_json(&Json::parse(&text)?)?
+        }
+        None => Viewport::default(),
+    }
+    .fit((options.width, options.height));
+    // Interactive navigation moves in whole pixels and zoom levels of this grid so that tiles
+    // rendered for one view can be reused by the next.
+    let mut tiles = TileCache::new(
+        TileGrid {
+            base_scale: view_port.width / options.width as f64,
+        },
+        512,
+    );
+    tiles.scan = options.scan_order;
+    view_port = tiles.grid.snap(view_port, (options.width, options.height));
+    println!("Render backends: {}", tiles.scheduler.backends().join(", "));
     let mut iterations = 200;
-    let y_x_coords = (0..HEIGHT as i32)
-        .cartesian_product(0..WIDTH as i32)
-        .collect::<Vec<_>>();
-
-    draw_fractal(
-        &mut canvas,
-        &texture_creator,
-        &y_x_coords,
-        &view_port,
-        iterations,
-    )?;
+    // Manual adjustment on top of the iteration schedule, if there is one.
+    let mut iteration_offset = 0i64;
+    // Followed by the iteration count while on, shifted by the offset.
+    let mut schedule = options.iterations_schedule.clone();
+    let mut antialias = 1;
+    let mut coloring = Coloring::EscapeTime;
+    let mut interior = None;
+    // Random palettes from `G`, the ones they replaced kept for undoing with `Ctrl+Backspace`.
+    let mut palette_history: Vec<Option<Palette>> = Vec::new();
+    // Gradients cycled through with `F`, the named ones followed by those of --palettes.
+    let mut named_palettes = palettes(&options);
+    let mut palette = match &options.palette {
+        Some(name) => Some(
+            named_palettes
+                .iter()
+                .find(|(named, _)| named == name)
+                .ok_or_else(|| format!("Unknown palette {name}"))?
+                .1,
+        ),
+        None => None,
+    };
+    let mut rng = SplitMix64::new(
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map_or(0, |elapsed| elapsed.as_nanos() as u64),
+    );
+    let mut effect: Option<Effect> = None;
+    let mut bloom: Option<Bloom> = None;
+    let mut tonemap = ToneMap::default();
+    let mut orbits = options.orbits;
+    let mut auto_contrast: Option<AutoContrast> = None;
+    let mut frame: Option<(RenderParams, Frame)> = None;
+    // The Buddhabrot of the frame while it accumulates, `Space` pausing it.
+    let mut accumulating: Option<Progressive> = None;
+    // Computation time of the frame, for the manifests of its screenshots.
+    let mut frame_time = Duration::ZERO;
 
     let mut event_pump = sdl_context.event_pump()?;
+    let events = sdl_context.event()?;
+    let mut coalescer = Coalescer::default();
+    let mut redraw = true;
+    // Region-of-interest selection: armed by `R`, then dragged with the left button.
+    let mut roi_mode = false;
+    let mut selection: Option<((i32, i32), (i32, i32))> = None;
+    // Annotation mode: the tool chosen with `N` and the start of the shape being dragged.
+    let mut annotations: Vec<Annotation> = Vec::new();
+    let mut tool: Option<Tool> = None;
+    let mut drag_start: Option<Complex<f64>> = None;
+    // Measure mode, toggled with `D`: a click starts a segment, the next one ends it.
+    let mut measure_mode = false;
+    let mut measurement: Option<Measurement> = None;
+    let mut measuring = false;
+    // Orbit mode, toggled with `O`: the orbit of the point under the cursor (Mandelbrot set) or
+    // of the last point clicked (Julia sets).
+    let mut orbit_mode = false;
+    let mut orbit: Vec<Complex<f64>> = Vec::new();
+    let mut console = Console::default();
+    let mut style = options.theme.style();
+    let gamut = options.gamut.transform();
+    // Animated zoom towards a minibrot started with `Z`, stopped by any input.
+    let mut dive: Option<Dive> = None;
+    // Animated zoom of a click or a wheel notch, finished at once by any other input.
+    let mut glide: Option<Glide> = None;
+    let mut screenshots = options
+        .dive_screenshots
+        .map(|every| ScreenshotSeries::new(every, "dive-screenshots".into()));
+    let mut quality = if options.deterministic {
+        QualityController::fixed()
+    } else {
+        QualityController::default()
+    };
+    // The quality profile last chosen with --profile or `5`, and the one to switch to once the
+    // events are handled. It decides the factor idle views are rendered again at.
+    let mut profile: Option<usize> = None;
+    let mut switch_profile = startup_profile;
+    let mut resolution_scale = 2;
+    video_subsystem.text_input().stop();
+    // Hints are shown over the frames until the first input.
+    let mut splash = true;
+    let mut hud = false;
+    // The view shown side by side as Mandelbrot set and Burning Ship.
+    let mut dual = false;
+    // The inspector `K` follows the cursor when on.
+    let mut inspecting = false;
+    let mut inspection: Option<Inspection> = None;
+    // `Ctrl+J` shows the Julia set of the parameter under the cursor in an inset when on.
+    let mut julia_inset = false;
+    let mut julia_preview: Option<Frame> = None;
+    // Window position of the cursor, for the actions on the point under it.
+    let mut cursor = (0, 0);
+    // View of the Mandelbrot set that `J` left for the Julia set under the cursor.
+    let mut julia_origin: Option<Viewport> = None;
+    // The importance map warm-starting Buddhabrot renders when loaded, and the one learned by
+    // the last of them.
+    let mut importance: Option<Importance> = None;
+    let mut learned: Option<Importance> = None;
+    // Bookmarks of --bookmarks, saved with `Insert` or `bookmark save` and cycled through with
+    // `Page Up` and `Page Down`: the one last recalled, and the one to recall once the events
+    // are handled.
+    let mut bookmarks = bookmarks::load_or_empty(&options.bookmarks)?;
+    let mut bookmark: Option<usize> = None;
+    let mut recall: Option<usize> = None;
+    // Files chosen in the dialogs of `S`, `Ctrl` + `O` and `Ctrl` + `F` or dropped onto the
+    // window: the parameters opened, applied like a recalled bookmark, and the palettes added.
+    let mut dialogs = Dialogs::default();
+    let mut opened: Option<RenderParams> = None;
+    let mut added: Vec<(String, Palette)> = Vec::new();
+    // Drag-to-pan: where the drag started and the view at the time.
+    let mut panning: Option<((i32, i32), Viewport)> = None;
+    // The view rendered again at a higher resolution once the input has paused for `IDLE`,
+    // and the scaled frame once that completed, for screenshots.
+    let mut super_resolution: Option<SuperResolution> = None;
+    let mut scaled: Option<(RenderParams, Frame)> = None;
+    let mut last_input = Instant::now();
+    // The views left, gone back to with `Backspace` and forward again with `Shift+Backspace`.
+    // A zoom held down or a dive is one step, from the view it started at.
+    let mut history = History::default();
+    let mut rendered_view = view_port;
+    let mut gesture: Option<Viewport> = None;
+    let mut navigated = false;
+    // Where and when the left button went down to zoom, and the other corner of the rectangle
+    // once it is dragged.
+    let mut zoom_press: Option<((i32, i32), Instant)> = None;
+    let mut zoom_drag: Option<(i32, i32)> = None;
     'running: loop {
+        let mut overlays_changed = false;
         for event in event_pump.poll_iter() {
+            if splash && matches!(event, Event::KeyDown { .. } | Event::MouseButtonDown { .. }) {
+                splash = false;
+                overlays_changed = true;
+            }
+            if matches!(event, Event::KeyDown { .. } | Event::MouseButtonDown { .. }) {
+                dive = None;
+            }
+            if matches!(
+                event,
+                Event::KeyDown { .. } | Event::MouseButtonDown { .. } | Event::MouseWheel { .. }
+            ) {
+                if let Some(glide) = glide.take() {
+                    view_port = glide.target();
+                    redraw = true;
+                }
+            }
+            if matches!(
+                event,
+                Event::KeyDown { .. } | Event::MouseButtonDown { .. } | Event::MouseWheel { .. }
+            ) {
+                last_input = Instant::now();
+            }
+            if matches!(
+                event,
+                Event::MouseWheel { .. } | Event::KeyDown { repeat: true, .. }
+            ) {
+                coalescer.input(Instant::now());
+            }
+            // A resized window shows the same height of the plane at its new aspect ratio, rendered
+            // once the resizing pauses.
+            if let Event::Window {
+                win_event: WindowEvent::SizeChanged(width, height),
+                ..
+            } = event
+            {
+                view_port = view_port.fit((width as u32, height as u32));
+                coalescer.input(Instant::now());
+                redraw = true;
+            }
+            if let Event::MouseMotion { x, y, .. } = event {
+                cursor = (x, y);
+                if inspecting {
+                    let point =
+                        view_port.pixel_to_complex(x as f64, y as f64, canvas.window().size());
+                    inspection = Some(inspect::evaluate(fractal, point, iterations));
+                    overlays_changed = true;
+                }
+                if julia_inset && !matches!(fractal, Fractal::Julia { .. }) {
+                    let c = view_port.pixel_to_complex(x as f64, y as f64, canvas.window().size());
+                    julia_preview = Some(inset::render(c, palette));
+                    overlays_changed = true;
+                } else if julia_preview.take().is_some() {
+                    overlays_changed = true;
+                }
+            }
+            // The open console takes all keyboard input.
+            if console.open {
+                match event {
+                    Event::Quit { .. } => break 'running,
+                    Event::KeyDown {
+                        keycode: Some(Keycode::Backquote | Keycode::Escape),
+                        ..
+                    } => {
+                        console.open = false;
+                        video_subsystem.text_input().stop();
+                    }
+                    Event::TextInput { text, .. } => console.type_text(&text),
+                    Event::KeyDown {
+                        keycode: Some(keycode),
+                        ..
+                    } => match keycode {
+                        Keycode::Backspace => console.backspace(),
+                        Keycode::Tab => console.complete(),
+                        Keycode::Up => console.browse(-1),
+                        Keycode::Down => console.browse(1),
+                        Keycode::Return | Keycode::KpEnter => {
+                            let line = console.submit();
+                            let actions = console::execute(&line).unwrap_or_else(|e| {
+                                console.print(e);
+                                Vec::new()
+                            });
+                            for action in actions {
+                                match action {
+                                    Action::SetIterations(count) => {
+                                        iteration_offset += count as i64 - iterations as i64;
+                                        iterations = count;
+                                    }
+                                    Action::SetAntialias(factor) => antialias = factor,
+                                    Action::SetExposure(exposure) => tonemap.exposure = exposure,
+                                    Action::SetTheme(theme) => style = theme.style(),
+                                    Action::SetColoring(chosen) => coloring = chosen,
+                                    Action::SetInterior(chosen) => interior = chosen,
+                                    Action::SetOrbits(chosen) => orbits = chosen,
+                                    Action::SetZ0(z0) => {
+                                        if fractal == mandelbrot {
+                                            fractal = Fractal::Mandelbrot { z0 };
+                                        }
+                                        mandelbrot = Fractal::Mandelbrot { z0 };
+                                    }
+                                    Action::SetCritical(n) => match &mut fractal {
+                                        Fractal::Formula {
+                                            index,
+                                            critical,
+                                            plane,
+                                            fixed,
+                                        } => {
+                                            let (c, p) = plane.parameters(view_port.center, *fixed);
+                                            let count =
+                                                formula::get(*index).critical_points(c, p).len();
+                                            console.print(format!(
+                                                "Starting from critical point {n} of the {count} \
+                                                 at the view center"
+                                            ));
+                                            *critical = n;
+                                        }
+                                        _ => console.print(
+                                            "Only formulas have critical points to choose from",
+                                        ),
+                                    },
+                                    Action::SetP(value) => match &mut fractal {
+                                        Fractal::Formula {
+                                            plane: Plane::C,
+                                            fixed,
+                                            ..
+                                        } => *fixed = value,
+                                        Fractal::Formula { .. } => console
+                                            .print("The view is the plane of p, J swaps it for c"),
+                                        _ => console.print("Only formulas have a parameter p"),
+                                    },
+                                    Action::SetFractal(formula) => {
+                                        fractal = formula;
+                                        julia_preset = None;
+                                    }
+                                    Action::Goto(location) => {
+                                        let size = canvas.window().size();
+                                        view_port = goto(&view_port, *location, size);
+                                    }
+                                    Action::Export {
+                                        path,
+                                        annotated,
+                                        manifest,
+                                    } => {
+                                        let exported = match &frame {
+                                            Some((last, _)) if manifest => {
+                                                export_reproducible(&path, *last)
+                                            }
+                                            // At the higher resolution once rendered so.
+                                            Some(shown) => {
+                                                let (shot, shot_frame) =
+                                                    scaled.as_ref().unwrap_or(shown);
+                                                if annotated {
+                                                    let mut marked = Frame::from_rgb(
+                                                        shot_frame.width,
+                                                        shot_frame.height,
+                                                        shot_frame.rgb.clone(),
+                                                    );
+                                                    annotate::draw(
+                                                        &mut marked,
+                                                        &shot.viewport,
+                                                        &annotations,
+                                                        &style,
+                                                    );
+                                                    export_frame(&path, &marked)
+                                                } else {
+                                                    export_frame(&path, shot_frame)
+                                                }
+                                            }
+                                            None => Err("Nothing rendered yet".to_string()),
+                                        };
+                                        console.print(match exported {
+                                            Ok(()) => format!("Exported {}", path.display()),
+                                            Err(e) => e,
+                                        });
+                                        continue;
+                                    }
+                                    Action::Annotations(edit) => {
+                                        let result = annotate::edit(&mut annotations, edit);
+                                        console.print(result.unwrap_or_else(|e| e));
+                                        continue;
+                                    }
+                                    Action::Nudge(nudge) => {
+                                        let compared = match &frame {
+                                            Some((last, _)) => nudge::compare(*last, nudge),
+                                            None => Err("Nothing rendered yet".to_string()),
+                                        };
+                                        match compared {
+                                            Ok((difference, counts)) => {
+                                                console.print(format!(
+                                                    "{} pixels changed, {} across the set, \
+                                                     max {:.3}",
+                                                    counts.changed, counts.flipped, counts.max
+                                                ));
+                                                frame.as_mut().unwrap().1 = difference;
+                                                (super_resolution, scaled) = (None, None);
+                                            }
+                                            Err(e) => console.print(e),
+                                        }
+                                        continue;
+                                    }
+                                    Action::SaveImportance(path) => {
+                                        let saved = match &learned {
+                                            Some(map) => map.save(&path),
+                                            None => Err("No Buddhabrot rendered yet".to_string()),
+                                        };
+                                        console.print(match saved {
+                                            Ok(()) => format!("Saved {}", path.display()),
+                                            Err(e) => e,
+                                        });
+                                        continue;
+                                    }
+                                    Action::LoadImportance(path) => {
+                                        match Importance::load(&path) {
+                                            Ok(map) => {
+                                                importance = Some(map);
+                                                console.print(format!(
+                                                    "Warm-starting Buddhabrot renders from {}",
+                                                    path.display()
+                                                ));
+                                            }
+                                            Err(e) => console.print(e),
+                                        }
+                                        continue;
+                                    }
+                                    Action::SaveBookmark(name) => {
+                                        let saved = match &frame {
+                                            Some((last, _)) => save_bookmark(
+                                                &options.bookmarks,
+                                                &mut bookmarks,
+                                                Bookmark {
+                                                    name,
+                                                    params: RenderParams {
+                                                        viewport: view_port,
+                                                        iterations,
+                                                        ..*last
+                                                    },
+                                                },
+                                            ),
+                                            None => Err("Nothing rendered yet".to_string()),
+                                        };
+                                        console.print(saved.unwrap_or_else(|e| e));
+                                        continue;
+                                    }
+                                    Action::GotoBookmark(key) => {
+                                        recall = bookmarks::find(&bookmarks, &key);
+                                        if recall.is_none() {
+                                            console.print(format!("No bookmark {key}"));
+                                        }
+                                        continue;
+                                    }
+                                    Action::DeleteBookmark(key) => {
+                                        let deleted = match bookmarks::find(&bookmarks, &key) {
+                                            Some(index) => {
+                                                let removed = bookmarks.remove(index);
+                                                bookmark = None;
+                                                bookmarks::save(&options.bookmarks, &bookmarks)
+                                                    .map(|()| format!("Deleted {}", removed.name))
+                                            }
+                                            None => Err(format!("No bookmark {key}")),
+                                        };
+                                        console.print(deleted.unwrap_or_else(|e| e));
+                                        continue;
+                                    }
+                                    Action::ListBookmarks => {
+                                        if bookmarks.is_empty() {
+                                            console.print("No bookmarks yet");
+                                        }
+                                        for (i, saved) in bookmarks.iter().enumerate() {
+                                            console.print(format!("{} {}", i + 1, saved.name));
+                                        }
+                                        continue;
+                                    }
+                                    Action::SuggestCap { apply } => {
+                                        let suggested = match &frame {
+                                            Some((last, _)) => cap::suggest(&RenderParams {
+                                                viewport: view_port,
+                                                iterations,
+                                                ..*last
+                                            }),
+                                            None => Err("Nothing rendered yet".to_string()),
+                                        };
+                                        let suggestion = match suggested {
+                                            Ok(suggestion) => suggestion,
+                                            Err(e) => {
+                                                console.print(e);
+                                                continue;
+                                            }
+                                        };
+                                        for line in cap_report(&suggestion, iterations) {
+                                            console.print(line);
+                                        }
+                                        match suggestion.cap {
+                                            Some(count) if apply => {
+                                                iteration_offset +=
+                                                    count as i64 - iterations as i64;
+                                                iterations = count;
+                                            }
+                                            _ => continue,
+                                        }
+                                    }
+                                    Action::ClearImportance => {
+                                        importance = None;
+                                        console.print("Sampling Buddhabrot renders uniformly");
+                                        continue;
+                                    }
+                                    Action::Print(line) => {
+                                        console.print(line);
+                                        continue;
+                                    }
+                                    Action::SetPalette(name) => {
+                                        let chosen = match name.as_str() {
+                                            "built-in" => Some(None),
+                                            _ => named_palettes
+                                                .iter()
+                                                .find(|(named, _)| *named == name)
+                                                .map(|&(_, named)| Some(named)),
+                                        };
+                                        match chosen {
+                                            Some(chosen) => {
+                                                palette_history.push(palette);
+                                                palette = chosen;
+                                            }
+                                            None => {
+                                                console.print(format!("Unknown palette {name}"));
+                                                continue;
+                                            }
+                                        }
+                                    }
+                                    // Switched to the first once the events are handled.
+                                    Action::LoadPalettes(path) => match palette::load(&path) {
+                                        Ok(loaded) => added = loaded,
+                                        Err(e) => {
+                                            console.print(e);
+                                            continue;
+                                        }
+                                    },
+                                }
+                                redraw = true;
+                            }
+                        }
+                        _ => {}
+                    },
+                    _ => {}
+                }
+                overlays_changed = true;
+                continue;
+            }
+            // Keys of the chosen scheme in the place of those they act as.
+            let event = match event {
+                Event::KeyDown {
+                    timestamp,
+                    window_id,
+                    keycode: Some(keycode),
+                    scancode,
+                    keymod,
+                    repeat,
+                } => {
+                    let (keycode, keymod) = options.keys.translate(keycode, keymod);
+                    Event::KeyDown {
+                        timestamp,
+                        window_id,
+                        keycode: Some(keycode),
+                        scancode,
+                        keymod,
+                        repeat,
+                    }
+                }
+                event => event,
+            };
             match event {
                 Event::Quit { .. }
                 | Event::KeyDown {
                     keycode: Some(Keycode::Escape),
                     ..
                 } => break 'running,
+                Event::DropFile { filename, .. } => match dropped::open(Path::new(&filename)) {
+                    Ok(Dropped::Params(params)) => {
+                        println!("Opened {filename}");
+                        opened = Some(params);
+                    }
+                    Ok(Dropped::Viewport(viewport)) => {
+                        println!("Opened {filename}");
+                        view_port = viewport.fit(canvas.window().size());
+                        redraw = true;
+                    }
+                    Ok(Dropped::Palettes(loaded)) => added = loaded,
+                    Ok(Dropped::Fractal(formula)) => {
+                        println!("Formula of {filename}");
+                        fractal = formula;
+                        julia_preset = None;
+                        redraw = true;
+                    }
+                    Err(e) => println!("Cannot open {e}"),
+                },
+                Event::MouseButtonDown {
+                    mouse_btn, x, y, ..
+                } if mouse_btn == MouseButton::Middle
+                    || mouse_btn == MouseButton::Left
+                        && (sdl_context.keyboard().mod_state())
+                            .intersects(Mod::LCTRLMOD | Mod::RCTRLMOD) =>
+                {
+                    panning = Some(((x, y), view_port));
+                }
+                Event::MouseMotion { x, y, .. } if panning.is_some() => {
+                    let ((x0, y0), start) = panning.unwrap();
+                    let size = canvas.window().size();
+                    view_port = start;
+                    view_port.pan(
+                        start.offset(x0 as f64, y0 as f64, size)
+                            - start.offset(x as f64, y as f64, size),
+                    );
+                    // The last frame moved along until the drag ends.
+                    if let Some((last, last_frame)) = &frame {
+                        let corner = last.viewport.offset(0.0, 0.0, size)
+                            - view_port.displacement(&last.viewport);
+                        let (dx, dy) = view_port.offset_to_pixel(corner, size);
+                        let mut shifted = last_frame.shifted(dx.round() as i32, dy.round() as i32);
+                        draw_frame(&mut canvas, &texture_creator, gamut.as_ref(), &mut shifted)?;
+                    }
+                }
+                Event::MouseButtonUp {
+                    mouse_btn: MouseButton::Middle | MouseButton::Left,
+                    ..
+                } if panning.is_some() => {
+                    panning = None;
+                    redraw = true;
+                }
                 Event::KeyDown {
                     keycode: Some(Keycode::KpPlus),
                     ..
                 } => {
                     iterations += 100;
+                    iteration_offset += 100;
                     println!("Increasing iterations count to {iterations}");
-                    draw_fractal(
-                        &mut canvas,
-                        &texture_creator,
-                        &y_x_coords,
-                        &view_port,
-                        iterations,
-                    )?;
+                    redraw = true;
+                }
+                Event::KeyDown {
+                    keycode: Some(Keycode::Q),
+                    ..
+                } => {
+                    schedule = match schedule {
+                        Some(_) => None,
+                        None => Some(
+                            (options.iterations_schedule.clone())
+                                .unwrap_or(IterationSchedule::AUTO),
+                        ),
+                    };
+                    match schedule {
+                        Some(_) => println!("Automatic iterations count"),
+                        None => println!("Fixed iterations count {iterations}"),
+                    }
+                    iteration_offset = 0;
+                    redraw = true;
                 }
                 Event::KeyDown {
                     keycode: Some(Keycode::KpMinus),
                     ..
+                } if iterations > 100 => {
+                    iterations -= 100;
+                    iteration_offset -= 100;
+                    println!("Decreasing iterations count to {iterations}");
+                    redraw = true;
+                }
+                Event::KeyDown {
+                    keycode:
+                        Some(
+                            keycode
+                            @ (Keycode::Left | Keycode::Right | Keycode::Up | Keycode::Down),
+                        ),
+                    ..
+                } => {
+                    let delta = match keycode {
+                        Keycode::Left => Complex::new(-0.1, 0.0),
+                        Keycode::Right => Complex::new(0.1, 0.0),
+                        Keycode::Up => Complex::new(0.0, -0.1),
+                        _ => Complex::new(0.0, 0.1),
+                    };
+                    let delta =
+                        Complex::new(delta.re * view_port.width, delta.im * view_port.height)
+                            * Complex::from_polar(1.0, view_port.rotation);
+                    view_port.pan(delta);
+                    redraw = true;
+                }
+                Event::KeyDown {
+                    keycode: Some(keycode @ (Keycode::LeftBracket | Keycode::RightBracket)),
+                    ..
+                } => {
+                    let angle = if keycode == Keycode::LeftBracket {
+                        -0.1
+                    } else {
+                        0.1
+                    };
+                    view_port.rotate(angle);
+                    redraw = true;
+                }
+                Event::KeyDown {
+                    keycode: Some(Keycode::A),
+                    ..
+                } => {
+                    antialias = antialias % 4 + 1;
+                    println!("Anti-aliasing {antialias}x{antialias}");
+                    redraw = true;
+                }
+                Event::KeyDown {
+                    keycode: Some(Keycode::E),
+                    ..
+                } => {
+                    effect = match effect {
+                        None => Some(Effect::ALL[0]),
+                        Some(current) => Effect::ALL
+                            .into_iter()
+                            .skip_while(|effect| *effect != current)
+                            .nth(1),
+                    };
+                    println!(
+                        "Post-processing effect: {}",
+                        effect.map_or("none", |e| e.name())
+                    );
+                    redraw = true;
+                }
+                Event::KeyDown {
+                    keycode: Some(Keycode::G),
+                    ..
+                } => {
+                    palette_history.push(palette);
+                    let random = Palette::random(&mut rng);
+                    println!("Random palette {}", random.describe());
+                    palette = Some(random);
+                    redraw = true;
+                }
+                Event::KeyDown {
+                    keycode: Some(Keycode::F),
+                    keymod,
+                    ..
+                } if keymod.intersects(Mod::LCTRLMOD | Mod::RCTRLMOD) => {
+                    let loaded = dialogs
+                        .open(Purpose::Palettes)
+                        .and_then(|path| path.map(|path| palette::load(&path)).transpose());
+                    match loaded {
+                        Ok(loaded) => added = loaded.unwrap_or_default(),
+                        Err(e) => println!("{e}"),
+                    }
+                }
+                Event::KeyDown {
+                    keycode: Some(Keycode::F),
+                    ..
+                } => {
+                    palette_history.push(palette);
+                    let current = named_palettes
+                        .iter()
+                        .position(|&(_, named)| palette == Some(named));
+                    // After the last one the built-in gradient comes again.
+                    let next = current.map_or(0, |i| i + 1);
+                    match named_palettes.get(next) {
+                        Some((name, named)) => {
+                            println!("Palette {name} {}", named.describe());
+                            palette = Some(*named);
+                        }
+                        None => {
+                            println!("Palette built-in");
+                            palette = None;
+                        }
+                    }
+                    redraw = true;
+                }
+                Event::KeyDown {
+                    keycode: Some(Keycode::Backspace),
+                    keymod,
+                    ..
+                } if !keymod.intersects(Mod::LCTRLMOD | Mod::RCTRLMOD) => {
+                    let shift = keymod.intersects(Mod::LSHIFTMOD | Mod::RSHIFTMOD);
+                    let next = match shift {
+                        true => history.forward(view_port),
+                        false => history.back(view_port),
+                    };
+                    match next {
+                        Some(view) => {
+                            view_port = view.fit(canvas.window().size());
+                            navigated = true;
+                            redraw = true;
+                        }
+                        None if shift => println!("No view to go forward to"),
+                        None => println!("No view to go back to"),
+                    }
+                }
+                Event::KeyDown {
+                    keycode: Some(Keycode::Home),
+                    ..
+                } => {
+                    view_port = Viewport::default().fit(canvas.window().size());
+                    redraw = true;
+                }
+                Event::KeyDown {
+                    keycode: Some(Keycode::Backspace),
+                    ..
+                } => match palette_history.pop() {
+                    Some(previous) => {
+                        palette = previous;
+                        println!(
+                            "Palette {}",
+                            palette.map_or("built-in".to_string(), |p| p.describe())
+                        );
+                        redraw = true;
+                    }
+                    None => println!("No palette to undo"),
+                },
+                Event::KeyDown {
+                    keycode: Some(Keycode::V),
+                    keymod,
+                    ..
+                } if keymod.intersects(Mod::LCTRLMOD | Mod::RCTRLMOD) => {
+                    let pasted = video_subsystem.clipboard().clipboard_text();
+                    match pasted.and_then(|text| location::parse(&text)) {
+                        Ok(location) => {
+                            view_port = goto(&view_port, location, canvas.window().size());
+                            println!("Pasted {}", view_port.to_json());
+                            redraw = true;
+                        }
+                        Err(e) => println!("Cannot go to the clipboard: {e}"),
+                    }
+                }
+                Event::KeyDown {
+                    keycode: Some(Keycode::O),
+                    keymod,
+                    ..
+                } if keymod.intersects(Mod::LCTRLMOD | Mod::RCTRLMOD) => {
+                    match dialogs.open(Purpose::Params) {
+                        Ok(Some(path)) => match Manifest::load(&path) {
+                            Ok(manifest) => {
+                                println!("Opened {}", path.display());
+                                opened = Some(manifest.params);
+                            }
+                            Err(e) => println!("Cannot open {}: {e}", path.display()),
+                        },
+                        Ok(None) => {}
+                        Err(e) => println!("{e}"),
+                    }
+                }
+                Event::KeyDown {
+                    keycode: Some(Keycode::V),
+                    ..
+                } => {
+                    coloring = match coloring {
+                        Coloring::Certified => Coloring::EscapeTime,
+                        _ => Coloring::Certified,
+                    };
+                    println!(
+                        "Certified rendering {}",
+                        if coloring == Coloring::Certified {
+                            "on"
+                        } else {
+                            "off"
+                        }
+                    );
+                    redraw = true;
+                }
+                Event::KeyDown {
+                    keycode: Some(Keycode::W),
+                    ..
+                } => {
+                    coloring = match coloring {
+                        Coloring::Smooth => Coloring::EscapeTime,
+                        _ => Coloring::Smooth,
+                    };
+                    println!(
+                        "Smooth coloring {}",
+                        if coloring == Coloring::Smooth {
+                            "on"
+                        } else {
+                            "off"
+                        }
+                    );
+                    redraw = true;
+                }
+                Event::KeyDown {
+                    keycode: Some(Keycode::Space),
+                    ..
+                } => {
+                    if let Some(progressive) = accumulating.as_mut().filter(|p| !p.done()) {
+                        progressive.paused = !progressive.paused;
+                        println!("Buddhabrot {}", progressive.status());
+                        overlays_changed = true;
+                    }
+                }
+                Event::KeyDown {
+                    keycode: Some(Keycode::B),
+                    ..
+                } => {
+                    bloom = match bloom {
+                        None => Some(Bloom::default()),
+                        Some(_) => None,
+                    };
+                    println!("Bloom {}", if bloom.is_some() { "on" } else { "off" });
+                    redraw = true;
+                }
+                Event::KeyDown {
+                    keycode: Some(Keycode::U),
+                    ..
+                } => {
+                    fractal = match fractal {
+                        Fractal::Buddhabrot { .. } => mandelbrot,
+                        _ => Fractal::Buddhabrot {
+                            samples_per_pixel: 64,
+                        },
+                    };
+                    println!("{fractal:?}");
+                    julia_preset = None;
+                    redraw = true;
+                }
+                Event::KeyDown {
+                    keycode: Some(Keycode::Y),
+                    ..
+                } => match dual::partner(fractal) {
+                    Some(other) => {
+                        dual = !dual;
+                        match dual {
+                            true => println!("Dual view with {other:?}"),
+                            false => println!("Dual view off"),
+                        }
+                        // The frame of the other view cannot be recolored into this one.
+                        frame = None;
+                        redraw = true;
+                    }
+                    None => println!("No dual view for {fractal:?}"),
+                },
+                Event::KeyDown {
+                    keycode: Some(Keycode::T),
+                    ..
+                } => {
+                    tonemap.operator = tonemap.operator.next();
+                    println!("Tone mapping: {}", tonemap.operator.name());
+                    redraw = true;
+                }
+                Event::KeyDown {
+                    keycode: Some(keycode @ (Keycode::Comma | Keycode::Period)),
+                    ..
+                } => {
+                    tonemap.exposure *= if keycode == Keycode::Period {
+                        1.25
+                    } else {
+                        0.8
+                    };
+                    println!("Exposure {:.3}", tonemap.exposure);
+                    redraw = true;
+                }
+                Event::KeyDown {
+                    keycode: Some(Keycode::C),
+                    ..
+                } => {
+                    let index = julia_preset.map_or(0, |index| (index + 1) % julia::PRESETS.len());
+                    let preset = &julia::PRESETS[index];
+                    println!(
+                        "Julia set {}/{}: {}",
+                        index + 1,
+                        julia::PRESETS.len(),
+                        julia::describe(preset)
+                    );
+                    julia_preset = Some(index);
+                    fractal = Fractal::Julia { c: preset.c };
+                    view_port = Viewport::default();
+                    redraw = true;
+                }
+                Event::KeyDown {
+                    keycode:
+                        Some(
+                            keycode @ (Keycode::Num1
+                            | Keycode::Num2
+                            | Keycode::Num3
+                            | Keycode::Num4),
+                        ),
+                    ..
+                } => {
+                    // The view stays, to compare the same region in the other fractal.
+                    fractal = match (keycode, fractal) {
+                        (Keycode::Num1, _) => mandelbrot,
+                        (Keycode::Num2, _) => Fractal::BurningShip,
+                        (Keycode::Num3, _) => Fractal::Tricorn,
+                        // Again for the next exponent.
+                        (_, Fractal::Multibrot { exponent }) => Fractal::Multibrot {
+                            exponent: if exponent >= MAX_EXPONENT {
+                                3
+                            } else {
+                                exponent + 1
+                            },
+                        },
+                        _ => Fractal::Multibrot {
+                            exponent: options.exponent,
+                        },
+                    };
+                    println!("{fractal:?}");
+                    julia_preset = None;
+                    redraw = true;
+                }
+                Event::KeyDown {
+                    keycode: Some(Keycode::M),
+                    ..
+                } if fractal != mandelbrot => {
+                    println!("Mandelbrot set");
+                    julia_preset = None;
+                    fractal = mandelbrot;
+                    view_port = Viewport::default();
+                    redraw = true;
+                }
+                Event::KeyDown {
+                    keycode: Some(Keycode::Z),
+                    ..
+                } => {
+                    if fractal != Fractal::MANDELBROT {
+                        println!("Minibrots are searched in the classic Mandelbrot set only");
+                        continue;
+                    }
+                    match dive::find(&view_port, iterations) {
+                        Some(target) => {
+                            println!(
+                                "Diving to the period {} minibrot at {} {}, height {:.3e}",
+                                target.period,
+                                target.nucleus.re,
+                                target.nucleus.im,
+                                target.viewport.height
+                            );
+                            dive = Some(Dive::new(view_port, target.viewport));
+                            if let Some(series) = &mut screenshots {
+                                series.start();
+                            }
+                        }
+                        None => println!("No minibrot nucleus found in the view"),
+                    }
+                }
+                Event::KeyDown {
+                    keycode: Some(Keycode::J),
+                    keymod,
+                    ..
+                } if keymod.intersects(Mod::LCTRLMOD | Mod::RCTRLMOD) => {
+                    julia_inset = !julia_inset;
+                    julia_preview = None;
+                    println!("Julia inset {}", if julia_inset { "on" } else { "off" });
+                    overlays_changed = true;
+                }
+                Event::KeyDown {
+                    keycode: Some(Keycode::J),
+                    keymod,
+                    ..
+                } if keymod.intersects(Mod::LSHIFTMOD | Mod::RSHIFTMOD) => {
+                    if fractal != Fractal::MANDELBROT {
+                        println!(
+                            "Embedded Julia sets are searched in the classic Mandelbrot set only"
+                        );
+                        continue;
+                    }
+                    let found = embedded::detect(&view_port, iterations);
+                    match embedded::next_deeper(&found, &view_port) {
+                        Some(julia) => {
+                            println!(
+                                "Embedded Julia set around the period {} minibrot at {} {}, \
+                                 height {:.3e}",
+                                julia.period, julia.center.re, julia.center.im, julia.height
+                            );
+                            let (width, height) = canvas.window().size();
+                            let aspect = width as f64 / height as f64;
+                            view_port =
+                                Viewport::new(julia.center, julia.height * aspect, julia.height);
+                            redraw = true;
+                        }
+                        None => println!("No embedded Julia set found deeper into the view center"),
+                    }
+                }
+                Event::KeyDown {
+                    keycode: Some(Keycode::J),
+                    ..
+                } => {
+                    match fractal {
+                        // The parameter under the cursor fixed, the view on the other one at
+                        // the value it was fixed at.
+                        Fractal::Formula {
+                            index,
+                            critical,
+                            plane,
+                            fixed,
+                        } if formula::get(index).has_p => {
+                            let point = view_port.pixel_to_complex(
+                                cursor.0 as f64,
+                                cursor.1 as f64,
+                                canvas.window().size(),
+                            );
+                            let other = plane.other();
+                            println!(
+                                "Plane of {} at {} = {} {}",
+                                other.name(),
+                                plane.name(),
+                                point.re,
+                                point.im
+                            );
+                            fractal = Fractal::Formula {
+                                index,
+                                critical,
+                                plane: other,
+                                fixed: point,
+                            };
+                            view_port = Viewport {
+                                center: fixed,
+                                center_low: Complex::new(0.0, 0.0),
+                                center_tail: NO_TAIL,
+                                ..view_port
+                            };
+                        }
+                        Fractal::Julia { .. } => {
+                            println!("Mandelbrot set");
+                            fractal = mandelbrot;
+                            view_port = julia_origin.take().unwrap_or_default();
+                        }
+                        _ => {
+                            let c = view_port.pixel_to_complex(
+                                cursor.0 as f64,
+                                cursor.1 as f64,
+                                canvas.window().size(),
+                            );
+                            println!("Julia set of c = {} {}", c.re, c.im);
+                            fractal = Fractal::Julia { c };
+                            julia_origin = Some(view_port);
+                            view_port = Viewport::default();
+                        }
+                    }
+                    julia_preset = None;
+                    redraw = true;
+                }
+                Event::KeyDown {
+                    keycode: Some(Keycode::P),
+                    ..
+                } => {
+                    println!("{}", view_port.to_json());
+                    let arguments = hud::goto_arguments(
+                        &view_port,
+                        canvas.window().size().1,
+                        locale::current(),
+                    );
+                    match video_subsystem.clipboard().set_clipboard_text(&arguments) {
+                        Ok(()) => println!("Copied to the clipboard: {arguments}"),
+                        Err(e) => println!("Clipboard unavailable: {e}"),
+                    }
+                }
+                Event::KeyDown {
+                    keycode: Some(Keycode::Num5),
+                    ..
+                } => switch_profile = Some(profile.map_or(0, |index| (index + 1) % profiles.len())),
+                Event::KeyDown {
+                    keycode: Some(Keycode::Insert),
+                    ..
+                } => match &frame {
+                    Some((last, _)) => {
+                        let name = bookmarks::unused_name(&bookmarks);
+                        let saved = save_bookmark(
+                            &options.bookmarks,
+                            &mut bookmarks,
+                            Bookmark {
+                                name,
+                                params: RenderParams {
+                                    viewport: view_port,
+                                    iterations,
+                                    ..*last
+                                },
+                            },
+                        );
+                        println!("{}", saved.unwrap_or_else(|e| e));
+                    }
+                    None => println!("Nothing rendered yet"),
+                },
+                Event::KeyDown {
+                    keycode: Some(keycode @ (Keycode::PageUp | Keycode::PageDown)),
+                    ..
+                } => {
+                    let count = bookmarks.len();
+                    recall = match (keycode, bookmark) {
+                        _ if count == 0 => {
+                            println!("No bookmarks in {}", options.bookmarks.display());
+                            None
+                        }
+                        (Keycode::PageDown, Some(index)) => Some((index + 1) % count),
+                        (Keycode::PageDown, None) => Some(0),
+                        (_, Some(index)) => Some((index + count - 1) % count),
+                        (_, None) => Some(count - 1),
+                    };
+                }
+                Event::KeyDown {
+                    keycode: Some(Keycode::I),
+                    ..
+                } => {
+                    hud = !hud;
+                    overlays_changed = true;
+                }
+                Event::KeyDown {
+                    keycode: Some(Keycode::K),
+                    ..
+                } => {
+                    inspecting = !inspecting;
+                    inspection = None;
+                    println!("Inspector {}", if inspecting { "on" } else { "off" });
+                    overlays_changed = true;
+                }
+                Event::KeyDown {
+                    keycode: Some(Keycode::Backquote),
+                    ..
+                } => {
+                    console.open = true;
+                    video_subsystem.text_input().start();
+                    overlays_changed = true;
+                }
+                Event::KeyDown {
+                    keycode: Some(Keycode::X),
+                    ..
+                } => {
+                    let (width, height) = canvas.window().size();
+                    let params = RenderParams::builder()
+                        .fractal(fractal)
+                        .viewport(view_port)
+                        .size(width, height)
+                        .iterations(iterations)
+                        .build()?;
+                    let path = format!("orbit-stats-{:016x}.tiff", params.content_hash());
+                    match stats::export(&params, std::path::Path::new(&path)) {
+                        Ok(()) => println!("Orbit statistics written to {path}"),
+                        Err(e) => println!("Export failed: {e}"),
+                    }
+                }
+                Event::KeyDown {
+                    keycode: Some(Keycode::L),
+                    ..
+                } => {
+                    let point = view_port.pixel_to_complex(
+                        cursor.0 as f64,
+                        cursor.1 as f64,
+                        canvas.window().size(),
+                    );
+                    let points = orbit::trace(fractal, point, iterations);
+                    let csv = manifest::timestamped("orbit", "csv", std::time::SystemTime::now());
+                    let json = csv.with_extension("json");
+                    match orbit::export(&csv, &points).and_then(|()| orbit::export(&json, &points))
+                    {
+                        Ok(()) => println!(
+                            "Orbit of {} {} ({} values) written to {} and {}",
+                            point.re,
+                            point.im,
+                            points.len(),
+                            csv.display(),
+                            json.display()
+                        ),
+                        Err(e) => println!("Orbit export failed: {e}"),
+                    }
+                }
+                Event::KeyDown {
+                    keycode: Some(Keycode::S),
+                    ..
+                } => {
+                    // The frame as displayed, with a manifest of the parameters it was rendered
+                    // with to render it again.
+                    let target = match &frame {
+                        Some(_) => {
+                            let path = manifest::timestamped(
+                                "screenshot",
+                                "png",
+                                std::time::SystemTime::now(),
+                            );
+                            let name = path.to_string_lossy();
+                            match dialog::ENABLED {
+                                true => match dialogs.save(Purpose::Screenshot, &name) {
+                                    Ok(chosen) => chosen,
+                                    Err(e) => {
+                                        println!("{e}, saving as {}", path.display());
+                                        Some(path)
+                                    }
+                                },
+                                false => Some(path),
+                            }
+                        }
+                        None => None,
+                    };
+                    let saved = match (&frame, target) {
+                        (None, _) => Err("Nothing rendered yet".to_string()),
+                        (Some(_), None) => continue,
+                        (Some((last, last_frame)), Some(path)) => {
+                            let manifest = Manifest::new(*last, frame_time);
+                            let (width, height) = (last_frame.width, last_frame.height);
+                            let written = match path.extension().is_some_and(|e| e == "png") {
+                                true => manifest.write_png(&path, width, height, &last_frame.rgb),
+                                false => export_frame(&path, last_frame),
+                            };
+                            written
+                                .and_then(|()| manifest.write(&manifest::sidecar(&path)))
+                                .map(|()| path)
+                        }
+                    };
+                    match saved {
+                        Ok(path) => println!("Screenshot {}", path.display()),
+                        Err(e) => println!("Screenshot failed: {e}"),
+                    }
+                }
+                Event::KeyDown {
+                    keycode: Some(Keycode::H),
+                    ..
+                } => {
+                    auto_contrast = match auto_contrast {
+                        None if options.deterministic => Some(AutoContrast::unsmoothed()),
+                        None => Some(AutoContrast::default()),
+                        Some(_) => None,
+                    };
+                    println!(
+                        "Histogram auto-contrast {}",
+                        if auto_contrast.is_some() { "on" } else { "off" }
+                    );
+                    redraw = true;
+                }
+                Event::KeyDown {
+                    keycode: Some(Keycode::R),
+                    ..
                 } => {
-                    if iterations > 100 {
-                        iterations -= 100;
-                        println!("Decreasing iterations count to {iterations}");
-                        draw_fractal(
+                    roi_mode = !roi_mode;
+                    selection = None;
+                    tool = None;
+                    measure_mode = false;
+                    (measurement, measuring) = (None, false);
+                    (orbit_mode, orbit) = (false, Vec::new());
+                    println!(
+                        "{}",
+                        if roi_mode {
+                            "Drag a rectangle to re-render at higher quality"
+                        } else {
+                            "Region selection cancelled"
+                        }
+                    );
+                }
+                Event::KeyDown {
+                    keycode: Some(Keycode::N),
+                    ..
+                } => {
+                    tool = Tool::next(tool);
+                    roi_mode = false;
+                    measure_mode = false;
+                    drag_start = None;
+                    (measurement, measuring) = (None, false);
+                    (orbit_mode, orbit) = (false, Vec::new());
+                    match tool {
+                        Some(tool) => println!("Annotation tool: {}", tool.name()),
+                        None => println!("Annotation mode off"),
+                    }
+                }
+                Event::KeyDown {
+                    keycode: Some(Keycode::D),
+                    ..
+                } => {
+                    measure_mode = !measure_mode;
+                    measurement = None;
+                    measuring = false;
+                    roi_mode = false;
+                    tool = None;
+                    (orbit_mode, orbit) = (false, Vec::new());
+                    println!("Measure mode {}", if measure_mode { "on" } else { "off" });
+                    overlays_changed = true;
+                }
+                Event::KeyDown {
+                    keycode: Some(Keycode::O),
+                    ..
+                } => {
+                    orbit_mode = !orbit_mode;
+                    orbit.clear();
+                    roi_mode = false;
+                    tool = None;
+                    (measure_mode, measurement, measuring) = (false, None, false);
+                    println!("Orbit mode {}", if orbit_mode { "on" } else { "off" });
+                    overlays_changed = true;
+                }
+                Event::MouseMotion { x, y, .. }
+                    if orbit_mode && matches!(fractal, Fractal::Mandelbrot { .. }) =>
+                {
+                    let c = view_port.pixel_to_complex(x as f64, y as f64, canvas.window().size());
+                    orbit = orbit::points(fractal, c, iterations);
+                    overlays_changed = true;
+                }
+                Event::MouseButtonDown {
+                    mouse_btn: MouseButton::Left,
+                    x,
+                    y,
+                    ..
+                } if orbit_mode => {
+                    let z = view_port.pixel_to_complex(x as f64, y as f64, canvas.window().size());
+                    orbit = orbit::points(fractal, z, iterations);
+                    println!(
+                        "Orbit of {} {}: {} points{}",
+                        z.re,
+                        z.im,
+                        orbit.len(),
+                        if orbit.last().is_some_and(|z| z.norm_sqr() > 4.0) {
+                            ", escapes"
+                        } else {
+                            ""
+                        }
+                    );
+                    overlays_changed = true;
+                }
+                Event::MouseButtonDown {
+                    mouse_btn: MouseButton::Left,
+                    x,
+                    y,
+                    ..
+                } if measure_mode => {
+                    let window_size = canvas.window().size();
+                    let point = view_port.pixel_to_complex(x as f64, y as f64, window_size);
+                    match &mut measurement {
+                        Some(segment) if measuring => {
+                            segment.to = point;
+                            println!("{}", segment.describe(&view_port, window_size).join(", "));
+                        }
+                        _ => {
+                            measurement = Some(Measurement {
+                                from: point,
+                                to: point,
+                            })
+                        }
+                    }
+                    measuring = !measuring;
+                    overlays_changed = true;
+                }
+                Event::MouseMotion { x, y, .. } if measuring => {
+                    if let Some(segment) = &mut measurement {
+                        segment.to =
+                            view_port.pixel_to_complex(x as f64, y as f64, canvas.window().size());
+                        overlays_changed = true;
+                    }
+                }
+                Event::MouseButtonDown {
+                    mouse_btn: MouseButton::Left,
+                    x,
+                    y,
+                    ..
+                } if roi_mode => selection = Some(((x, y), (x, y))),
+                Event::MouseButtonDown {
+                    mouse_btn: MouseButton::Left,
+                    x,
+                    y,
+                    ..
+                } if tool.is_some() => {
+                    let point =
+                        view_port.pixel_to_complex(x as f64, y as f64, canvas.window().size());
+                    if tool == Some(Tool::Label) {
+                        // The text of a label is entered in the console.
+                        console.open = true;
+                        video_subsystem.text_input().start();
+                        console.type_text(&format!("label {} {} ", point.re, point.im));
+                        overlays_changed = true;
+                    } else {
+                        drag_start = Some(point);
+                    }
+                }
+                Event::MouseMotion { x, y, .. } if drag_start.is_some() => {
+                    let end =
+                        view_port.pixel_to_complex(x as f64, y as f64, canvas.window().size());
+                    if let (Some((last, last_frame)), Some(tool)) = (&mut frame, tool) {
+                        let mut shown = annotations.clone();
+                        shown.extend(tool.shape(drag_start.unwrap(), end));
+                        draw_with_overlays(
                             &mut canvas,
                             &texture_creator,
-                            &y_x_coords,
-                            &view_port,
-                            iterations,
+                            gamut.as_ref(),
+                            (&last.viewport, last_frame),
+                            &Overlays {
+                                annotations: &shown,
+                                measurement: measurement.as_ref(),
+                                orbit: &orbit,
+                                hints: splash,
+                                hud,
+                                inspection: inspection.as_ref(),
+                                accumulating: accumulating.as_ref(),
+                                julia: julia_preview.as_ref(),
+                                console: &console,
+                                style: &style,
+                            },
+                        )?;
+                    }
+                }
+                Event::MouseButtonUp {
+                    mouse_btn: MouseButton::Left,
+                    x,
+                    y,
+                    ..
+                } if drag_start.is_some() => {
+                    let end =
+                        view_port.pixel_to_complex(x as f64, y as f64, canvas.window().size());
+                    let start = drag_start.take().unwrap();
+                    if let Some(shape) = tool.and_then(|tool| tool.shape(start, end)) {
+                        if start != end {
+                            annotations.push(shape);
+                        }
+                    }
+                    overlays_changed = true;
+                }
+                Event::MouseMotion { x, y, .. } if selection.is_some() => {
+                    let (start, _) = selection.unwrap();
+                    selection = Some((start, (x, y)));
+                    if let (Some((_, last_frame)), Some(rect)) = (
+                        &frame,
+                        Rect::spanning(start, (x, y), canvas.window().size()),
+                    ) {
+                        let mut outlined = Frame::from_rgb(
+                            last_frame.width,
+                            last_frame.height,
+                            last_frame.rgb.clone(),
+                        );
+                        roi::outline(&mut outlined, rect, style.outline);
+                        draw_frame(&mut canvas, &texture_creator, gamut.as_ref(), &mut outlined)?;
+                    }
+                }
+                Event::MouseButtonUp {
+                    mouse_btn: MouseButton::Left,
+                    x,
+                    y,
+                    ..
+                } if selection.is_some() => {
+                    let (start, _) = selection.take().unwrap();
+                    roi_mode = false;
+                    let rect = Rect::spanning(start, (x, y), canvas.window().size());
+                    if let (Some((last, last_frame)), Some(rect)) = (&mut frame, rect) {
+                        let stamp = Instant::now();
+                        (super_resolution, scaled) = (None, None);
+                        // Four times the iterations and twice the supersampling.
+                        roi::enhance(
+                            *last,
+                            last_frame,
+                            rect,
+                            last.iterations * 4,
+                            (last.antialias * 2).min(8),
+                            style.outline,
                         )?;
+                        println!(
+                            "Enhanced {}x{} region in {:?}",
+                            rect.width,
+                            rect.height,
+                            Instant::now() - stamp
+                        );
+                        draw_frame(&mut canvas, &texture_creator, gamut.as_ref(), last_frame)?;
+                    }
+                }
+                Event::MouseButtonDown {
+                    mouse_btn: MouseButton::Left,
+                    x,
+                    y,
+                    ..
+                } => zoom_press = Some(((x, y), Instant::now())),
+                Event::MouseMotion { x, y, .. } if zoom_press.is_some() && !dual => {
+                    let ((x0, y0), _) = zoom_press.unwrap();
+                    if zoom_drag.is_some() || (x - x0).abs().max((y - y0).abs()) > DRAG {
+                        zoom_drag = Some((x, y));
+                        let outline = roi::zoom_outline((x0, y0), (x, y), canvas.window().size());
+                        if let (Some((_, last_frame)), Some(rect)) = (&frame, outline) {
+                            let mut outlined = Frame::from_rgb(
+                                last_frame.width,
+                                last_frame.height,
+                                last_frame.rgb.clone(),
+                            );
+                            roi::outline(&mut outlined, rect, style.outline);
+                            draw_frame(
+                                &mut canvas,
+                                &texture_creator,
+                                gamut.as_ref(),
+                                &mut outlined,
+                            )?;
+                        }
+                    }
+                }
+                Event::MouseButtonUp {
+                    mouse_btn: MouseButton::Left,
+                    x,
+                    y,
+                    ..
+                } if zoom_press.is_some() => {
+                    let (start, at) = zoom_press.take().unwrap();
+                    let size = canvas.window().size();
+                    if zoom_drag.take().is_some() {
+                        view_port = roi::zoom_viewport(&view_port, size, start, (x, y));
+                        redraw = true;
+                    } else if at.elapsed() < HOLD {
+                        // A click zooms in a level, a hold has been zooming in all along.
+                        let x = match dual {
+                            true => dual::unsplit(start.0 as f64, size.0),
+                            false => start.0 as f64,
+                        };
+                        let offset = view_port.offset(x, start.1 as f64, size);
+                        glide = Some(Glide::new(view_port, offset, level_factor(-1)));
                     }
                 }
+                Event::MouseWheel { y, .. } if y != 0 => {
+                    // A level in or out per notch, about the point under the cursor.
+                    let size = canvas.window().size();
+                    let x = match dual {
+                        true => dual::unsplit(cursor.0 as f64, size.0),
+                        false => cursor.0 as f64,
+                    };
+                    let offset = view_port.offset(x, cursor.1 as f64, size);
+                    glide = Some(Glide::new(view_port, offset, level_factor(-y)));
+                }
                 _ => {}
             }
         }
 
+        if let Some(index) = switch_profile.take() {
+            let chosen = &profiles[index];
+            println!("Profile {}", chosen.name);
+            antialias = chosen.antialias;
+            match &chosen.iterations {
+                IterationPolicy::Fixed(count) => (schedule, iterations) = (None, *count),
+                // A schedule of --iterations-schedule is the one followed, as with `Q`.
+                IterationPolicy::Scheduled(own) => {
+                    schedule = Some(options.iterations_schedule.clone().unwrap_or(own.clone()))
+                }
+            }
+            iteration_offset = 0;
+            quality = match chosen.adaptive && !options.deterministic {
+                true => QualityController::default(),
+                false => QualityController::fixed(),
+            };
+            resolution_scale = chosen.resolution_scale;
+            if chosen
+                .kernel
+                .is_some_and(|chosen| chosen != kernel::current())
+            {
+                println!("The kernel of a profile is only chosen at startup, with --profile");
+            }
+            profile = Some(index);
+            redraw = true;
+        }
+
+        if let Some((name, first)) = added.first().cloned() {
+            println!("Palette {name} {}, {} added", first.describe(), added.len());
+            named_palettes.append(&mut added);
+            palette_history.push(palette);
+            palette = Some(first);
+            redraw = true;
+        }
+
+        let recalled = match (recall.take(), opened.take()) {
+            (Some(index), _) => {
+                println!("Bookmark {}: {}", index + 1, bookmarks[index].name);
+                bookmark = Some(index);
+                Some(bookmarks[index].params)
+            }
+            (None, params) => params,
+        };
+        if let Some(params) = recalled {
+            let (width, height) = canvas.window().size();
+            let params = bookmarks::resized(params, width, height)?;
+            fractal = params.fractal;
+            if let Fractal::Mandelbrot { .. } = fractal {
+                mandelbrot = fractal;
+            }
+            julia_preset = None;
+            view_port = params.viewport;
+            (palette, coloring, interior) = (params.palette, params.coloring, params.interior);
+            iterations = params.iterations;
+            // The schedule carries on from the bookmarked count.
+            if let Some(schedule) = &schedule {
+                let zoom = IterationSchedule::zoom(&view_port);
+                iteration_offset = iterations as i64 - schedule.iterations(zoom) as i64;
+            }
+            redraw = true;
+        }
+
+        if overlays_changed && !redraw {
+            if let Some((last, last_frame)) = &mut frame {
+                draw_with_overlays(
+                    &mut canvas,
+                    &texture_creator,
+                    gamut.as_ref(),
+                    (&last.viewport, last_frame),
+                    &Overlays {
+                        annotations: &annotations,
+                        measurement: measurement.as_ref(),
+                        orbit: &orbit,
+                        hints: splash,
+                        hud,
+                        inspection: inspection.as_ref(),
+                        accumulating: accumulating.as_ref(),
+                        julia: julia_preview.as_ref(),
+                        console: &console,
+                        style: &style,
+                    },
+                )?;
+            }
+        }
+
         let mouse_state = MouseState::new(&event_pump);
-        if mouse_state.left() {
-            let d = view_port.1 - view_port.0;
-            let click_point = x_y_to_complex(
-                mouse_state.x(),
-                mouse_state.y(),
-                &canvas.window().size(),
-                &view_port,
-            );
-            let rel_click = Complex::new(
-                (click_point.re - view_port.0.re) / d.re,
-                (click_point.im - view_port.0.im) / d.im,
-            );
-            view_port.0 = Complex::new(
-                view_port.0.re + d.re * 0.1 * (rel_click.re),
-                view_port.0.im + d.im * 0.1 * (rel_click.im),
-            );
-            view_port.1 = Complex::new(
-                view_port.1.re - d.re * 0.1 * (1.0 - rel_click.re),
-                view_port.1.im - d.im * 0.1 * (1.0 - rel_click.im),
-            );
-            draw_fractal(
-                &mut canvas,
-                &texture_creator,
-                &y_x_coords,
-                &view_port,
-                iterations,
-            )?;
+        // While selecting a region, annotating, measuring, picking orbits or panning the left
+        // button does not zoom.
+        let left_zooms = !roi_mode
+            && tool.is_none()
+            && !measure_mode
+            && !orbit_mode
+            && !console.open
+            && panning.is_none();
+        let held = zoom_press.is_some_and(|(_, at)| at.elapsed() >= HOLD) && zoom_drag.is_none();
+        if mouse_state.left() && left_zooms && held {
+            let size = canvas.window().size();
+            let x = match dual {
+                true => dual::unsplit(mouse_state.x() as f64, size.0),
+                false => mouse_state.x() as f64,
+            };
+            let click_offset = view_port.offset(x, mouse_state.y() as f64, size);
+            view_port.zoom_at(click_offset, level_factor(-1));
+            quality.moved(Instant::now());
+            gesture.get_or_insert(rendered_view);
+            redraw = true;
         } else if mouse_state.right() {
-            let d = view_port.1 - view_port.0;
-            view_port.0 -= d * 0.1;
-            view_port.1 += d * 0.1;
-            draw_fractal(
+            view_port.zoom_about(view_port.center, level_factor(ZOOM_OUT_LEVELS));
+            quality.moved(Instant::now());
+            gesture.get_or_insert(rendered_view);
+            redraw = true;
+        } else if let Some(step) = dive.as_mut().and_then(Dive::next) {
+            view_port = step;
+            quality.moved(Instant::now());
+            gesture.get_or_insert(rendered_view);
+            redraw = true;
+        } else if let Some(step) = glide.as_mut().and_then(Glide::next) {
+            view_port = step;
+            if glide.as_ref().is_some_and(Glide::finished) {
+                glide = None;
+            }
+            quality.moved(Instant::now());
+            gesture.get_or_insert(rendered_view);
+            redraw = true;
+        } else {
+            if let Some(start) = gesture.take() {
+                history.visit(start);
+            }
+            if quality.settle(Instant::now()) {
+                println!("Full quality pass");
+                redraw = true;
+            }
+        }
+
+        // Bursts of input are rendered once they pause, for the view they end at.
+        let deferred = redraw && !coalescer.ready(Instant::now());
+        if redraw && !deferred {
+            let coalesced = coalescer.rendered();
+            if coalesced > 1 {
+                println!("Coalesced {coalesced} input events into one render");
+            }
+            let (width, height) = canvas.window().size();
+            // The frames of a glide zoom by fractions of the levels of the grid.
+            if glide.is_none() {
+                view_port = tiles.grid.snap(view_port, (width, height));
+            }
+            if view_port != rendered_view {
+                if !navigated && gesture.is_none() {
+                    history.visit(rendered_view);
+                }
+                rendered_view = view_port;
+            }
+            navigated = false;
+            if let Some(schedule) = &schedule {
+                // Auto-deepening: the count follows the zoom.
+                let zoom = IterationSchedule::zoom(&view_port);
+                let scheduled = (schedule.iterations(zoom) as i64 + iteration_offset).max(1) as u32;
+                if scheduled != iterations {
+                    iterations = scheduled;
+                    println!("Iterations count {iterations} at zoom {zoom:.3e}");
+                }
+            }
+            let params = RenderParams::builder()
+                .fractal(fractal)
+                .viewport(view_port)
+                .size(width, height)
+                .iterations(quality.iterations(iterations))
+                .coloring(coloring)
+                .interior(interior)
+                .palette(palette)
+                .antialias(antialias)
+                .effect(effect)
+                .bloom(bloom)
+                .tonemap(tonemap)
+                .orbits(orbits)
+                .build();
+            // Settings the builder rejects leave the last frame on screen.
+            let params = match params {
+                Ok(params) => params,
+                Err(e) => {
+                    println!("Not rendered: {e}");
+                    redraw = false;
+                    continue;
+                }
+            };
+            let stamp = Instant::now();
+            // Palette, coloring, tone mapping and post-processing changes are re-derived from
+            // the accumulation buffer or the samples of the previous frame when possible.
+            let recolored = match &mut frame {
+                // Auto-contrast settling only recolors the unchanged frame.
+                Some((last, _)) if *last == params && auto_contrast.is_some() => true,
+                Some((last, last_frame))
+                    if RenderParams {
+                        palette: params.palette,
+                        coloring: params.coloring,
+                        interior: params.interior,
+                        tonemap: params.tonemap,
+                        effect: params.effect,
+                        bloom: params.bloom,
+                        ..*last
+                    } == params =>
+                {
+                    render::recolor(params, last_frame)
+                }
+                _ => false,
+            };
+            let first = frame.is_none();
+            // Buddhabrot frames accumulate a pass at a time between the events instead, shown as
+            // they improve, and learn their importance map along.
+            let progressive =
+                Progressive::new(params, importance.as_ref()).filter(|_| !recolored && !dual);
+            if !recolored {
+                accumulating = None;
+            }
+            let mut next = match (frame.take(), progressive) {
+                (Some((_, last_frame)), _) if recolored => last_frame,
+                (_, Some(progressive)) => {
+                    let progressive = accumulating.insert(progressive);
+                    progressive.pass();
+                    learned = Some(progressive.learned());
+                    render::accumulation_frame(params, progressive.accumulation())
+                }
+                (last, None) => {
+                    // Tiles are rendered from viewports of their own, which differ from the
+                    // view's in the last bits, so deterministic frames are always rendered whole.
+                    let tiled = !options.deterministic && !dual;
+                    // Show what can be reconstructed from other zoom levels while the missing
+                    // tiles are computed. Otherwise the first frame is revealed coarse to fine
+                    // instead of after a blank wait, and so are the others when the last one
+                    // took long to render.
+                    let preview = tiles.preview(params).filter(|_| tiled);
+                    let passes = match (first, &preview) {
+                        _ if dual => &[],
+                        (true, _) => &splash::PASSES[..],
+                        (false, None) => quality::passes(frame_time),
+                        (false, Some(_)) => &[],
+                    };
+                    // The image on screen while computing, which the tiles are revealed over.
+                    let mut shown = None;
+                    for &step in passes {
+                        let mut pass = splash::coarse(params, step);
+                        draw_with_overlays(
+                            &mut canvas,
+                            &texture_creator,
+                            gamut.as_ref(),
+                            (&params.viewport, &mut pass),
+                            &Overlays {
+                                annotations: &annotations,
+                                measurement: measurement.as_ref(),
+                                orbit: &orbit,
+                                hints: splash,
+                                hud,
+                                inspection: inspection.as_ref(),
+                                accumulating: accumulating.as_ref(),
+                                julia: julia_preview.as_ref(),
+                                console: &console,
+                                style: &style,
+                            },
+                        )?;
+                        shown = Some(pass);
+                    }
+                    if let Some(mut preview) = preview {
+                        println!("Preview from cached tiles");
+                        draw_frame(&mut canvas, &texture_creator, gamut.as_ref(), &mut preview)?;
+                        shown = Some(preview);
+                    }
+                    // The frame is computed on a worker thread while this one keeps pumping the
+                    // events, so the window stays responsive. Input arriving meanwhile cancels
+                    // the tiles still missing, those computed so far stay cached for the next
+                    // view. Over a preview or coarse pass, the tiles are shown as they come.
+                    let superseded = AtomicBool::new(false);
+                    let (reveal, revealed) = std::sync::mpsc::channel();
+                    let rendered = std::thread::scope(|scope| {
+                        let tiles = &mut tiles;
+                        let (importance, learned) = (&importance, &mut learned);
+                        let (superseded, cache) = (&superseded, &cache);
+                        let worker = scope.spawn(move || {
+                            let cancelled = || superseded.load(Ordering::Relaxed);
+                            if let Some(frame) = dual.then(|| dual::render(params)).flatten() {
+                                return Some(frame);
+                            }
+                            let reveal = |x, y, piece| reveal.send((x, y, piece)).unwrap_or(());
+                            let tiled = tiled
+                                .then(|| tiles.render_progressively(params, cancelled, reveal));
+                            match tiled.flatten() {
+                                Some(tiled) => Some(tiled),
+                                None if cancelled() => None,
+                                None => Some(match (params.fractal, cache) {
+                                    // Sampled afresh rather than taken from the cache, to learn
+                                    // its importance map.
+                                    (Fractal::Buddhabrot { samples_per_pixel }, _) => {
+                                        let (frame, map) = render::render_buddhabrot(
+                                            params,
+                                            samples_per_pixel,
+                                            importance.as_ref(),
+                                        );
+                                        *learned = Some(map);
+                                        frame
+                                    }
+                                    (_, Some(cache)) => cache.render(params),
+                                    (_, None) => render::render_frame(params),
+                                }),
+                            }
+                        });
+                        while !worker.is_finished() {
+                            if input_pending(&mut event_pump, &events) {
+                                superseded.store(true, Ordering::Relaxed);
+                            }
+                            if let Some(shown) = &mut shown {
+                                let pieces = revealed
+                                    .try_iter()
+                                    .map(|(x, y, piece)| shown.paste(x, y, &piece))
+                                    .count();
+                                if pieces > 0 {
+                                    draw_frame(
+                                        &mut canvas,
+                                        &texture_creator,
+                                        gamut.as_ref(),
+                                        shown,
+                                    )?;
+                                }
+                            }
+                            std::thread::sleep(RENDER_POLL);
+                        }
+                        worker
+                            .join()
+                            .map_err(|_| "Render thread panicked".to_string())
+                    })?;
+                    match rendered {
+                        Some(rendered) => rendered,
+                        None => {
+                            println!("Render superseded by new input");
+                            frame = last;
+                            continue;
+                        }
+                    }
+                }
+            };
+            // Keep drawing until the smoothed contrast settles.
+            let settled = auto_contrast
+                .as_mut()
+                .is_none_or(|contrast| contrast.apply(params, &mut next));
+            let elapsed = Instant::now() - stamp;
+            println!("Computation time {elapsed:?}");
+            frame_time = elapsed;
+            draw_with_overlays(
                 &mut canvas,
                 &texture_creator,
-                &y_x_coords,
-                &view_port,
-                iterations,
+                gamut.as_ref(),
+                (&params.viewport, &mut next),
+                &Overlays {
+                    annotations: &annotations,
+                    measurement: measurement.as_ref(),
+                    orbit: &orbit,
+                    hints: splash,
+                    hud,
+                    inspection: inspection.as_ref(),
+                    accumulating: accumulating.as_ref(),
+                    julia: julia_preview.as_ref(),
+                    console: &console,
+                    style: &style,
+                },
             )?;
+            frame = Some((params, next));
+            // The frames of motion and those adjusted afterwards are not worth rendering again.
+            let settled_view = !quality.in_motion() && !options.deterministic && !dual;
+            super_resolution = (settled_view && auto_contrast.is_none())
+                .then(|| {
+                    let budget = options.super_resolution_mb << 20;
+                    SuperResolution::new(params, resolution_scale, budget)
+                })
+                .flatten();
+            scaled = None;
+            let due = match (&dive, &mut screenshots) {
+                (Some(dive), Some(series)) => series.due(dive.start(), &params.viewport),
+                _ => None,
+            };
+            if let Some(path) = due {
+                // Frames of the dive are rendered at motion quality, screenshots at full.
+                let full = params.to_builder().iterations(iterations).build()?;
+                let saved = std::fs::create_dir_all(path.parent().unwrap())
+                    .map_err(|e| e.to_string())
+                    .and_then(|()| export_reproducible(&path, full));
+                match saved {
+                    Ok(()) => println!("Dive screenshot {}", path.display()),
+                    Err(e) => println!("Dive screenshot failed: {e}"),
+                }
+            }
+            // Prefetching waits for the full-quality pass, whose tiles are the ones to keep.
+            if !quality.in_motion() && !options.deterministic {
+                tiles.plan_prefetch(params, ZOOM_OUT_LEVELS);
+            }
+            redraw = !settled;
+        } else if deferred {
+            std::thread::sleep(coalescer.wait(Instant::now()));
+            continue;
+        } else if accumulating.as_ref().is_some_and(Progressive::running) {
+            if let (Some(progressive), Some((params, shown))) = (&mut accumulating, &mut frame) {
+                progressive.pass();
+                shown.accumulation = Some(progressive.accumulation());
+                shown.seed = noise::seed(progressive.passes().0);
+                render::retonemap(*params, shown);
+                learned = Some(progressive.learned());
+                if progressive.done() {
+                    println!("Buddhabrot {}", progressive.status());
+                }
+                draw_with_overlays(
+                    &mut canvas,
+                    &texture_creator,
+                    gamut.as_ref(),
+                    (&params.viewport, shown),
+                    &Overlays {
+                        annotations: &annotations,
+                        measurement: measurement.as_ref(),
+                        orbit: &orbit,
+                        hints: splash,
+                        hud,
+                        inspection: inspection.as_ref(),
+                        accumulating: Some(progressive),
+                        julia: julia_preview.as_ref(),
+                        console: &console,
+                        style: &style,
+                    },
+                )?;
+            }
+            continue;
+        } else if tiles.has_prefetch_work() {
+            // Prefetching runs in short slices so that input is still handled promptly.
+            tiles.prefetch(Duration::from_millis(20));
+            continue;
+        } else if super_resolution.is_some() && last_input.elapsed() >= IDLE {
+            // The same for the scaled render, then swapped in for the frame it improves on.
+            let job = super_resolution.as_mut().unwrap();
+            if job.step(Duration::from_millis(20)) {
+                let scale = job.scale();
+                let (shown, sharp) = super_resolution.take().unwrap().finish();
+                if let Some((last, last_frame)) = &mut frame {
+                    last_frame.rgb = shown.rgb;
+                    draw_with_overlays(
+                        &mut canvas,
+                        &texture_creator,
+                        gamut.as_ref(),
+                        (&last.viewport, last_frame),
+                        &Overlays {
+                            annotations: &annotations,
+                            measurement: measurement.as_ref(),
+                            orbit: &orbit,
+                            hints: splash,
+                            hud,
+                            inspection: inspection.as_ref(),
+                            accumulating: accumulating.as_ref(),
+                            julia: julia_preview.as_ref(),
+                            console: &console,
+                            style: &style,
+                        },
+                    )?;
+                    println!("Swapped in the view rendered at {scale}x the resolution");
+                    scaled = Some(sharp);
+                }
+            }
+            continue;
         }
         std::thread::sleep(Duration::new(0, 1_000_000_000u32 / 30));
     }
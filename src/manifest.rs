@@ -0,0 +1,220 @@
+use crate::json::{FromJson, Json, ToJson};
+use crate::kernel;
+use crate::params::RenderParams;
+use crate::png;
+use crate::subdivide;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+pub const VERSION: &str = env!("CARGO_PKG_VERSION");
+// Keyword of the text chunk holding the manifest in screenshots.
+const PNG_KEYWORD: &str = "mandelbrot-explorer manifest";
+
+// Everything needed to render an exported image again, written next to it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Manifest {
+    pub params: RenderParams,
+    // Version of the explorer that rendered the image.
+    pub version: String,
+    pub elapsed: Duration,
+    // The switches of --no-subdivision and --no-periodicity the image was rendered with.
+    pub subdivision: bool,
+    pub periodicity: bool,
+}
+
+impl Manifest {
+    pub fn new(params: RenderParams, elapsed: Duration) -> Self {
+        Manifest {
+            params,
+            version: VERSION.to_string(),
+            elapsed,
+            subdivision: subdivide::enabled(),
+            periodicity: kernel::periodicity(),
+        }
+    }
+
+    pub fn write(&self, path: &Path) -> Result<(), String> {
+        std::fs::write(path, self.to_json().to_string())
+            .map_err(|e| format!("{}: {e}", path.display()))
+    }
+
+    // Writes the image as a PNG file with the manifest embedded.
+    pub fn write_png(
+        &self,
+        path: &Path,
+        width: u32,
+        height: u32,
+        rgb: &[u8],
+    ) -> Result<(), String> {
+        let text = self.to_json().to_string();
+        let png = png::encode_with_text(width, height, rgb, PNG_KEYWORD, &text);
+        std::fs::write(path, png).map_err(|e| format!("{}: {e}", path.display()))
+    }
+
+    // The manifest embedded in a PNG file, or else the one next to it.
+    pub fn from_png(path: &Path) -> Result<Manifest, String> {
+        let png = std::fs::read(path).map_err(|e| format!("{}: {e}", path.display()))?;
+        match png::text(&png, PNG_KEYWORD) {
+            Some(text) => Manifest::from_json(&Json::parse(&text)?),
+            None if sidecar(path).exists() => Manifest::load(&sidecar(path)),
+            None => Err(format!(
+                "{}: no embedded parameters and no {}",
+                path.display(),
+                sidecar(path).display()
+            )),
+        }
+    }
+
+    // Reads a manifest, or bare render parameters as printed by the explorer.
+    pub fn load(path: &Path) -> Result<Manifest, String> {
+        let text = std::fs::read_to_string(path).map_err(|e| format!("{}: {e}", path.display()))?;
+        let json = Json::parse(&text)?;
+        match json.get("params") {
+            Some(_) => Manifest::from_json(&json),
+            None => Ok(Manifest::new(
+                RenderParams::from_json(&json)?,
+                Duration::ZERO,
+            )),
+        }
+    }
+}
+
+// `image.png` is described by `image.json`.
+pub fn sidecar(image: &Path) -> PathBuf {
+    image.with_extension("json")
+}
+
+// Name of a file written at `time`, e.g. `screenshot-20261014-153012.png` in UTC.
+pub fn timestamped(prefix: &str, extension: &str, time: SystemTime) -> PathBuf {
+    let seconds = time
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs());
+    let (days, second) = (seconds / 86400, seconds % 86400);
+    // Days since the epoch to the proleptic Gregorian calendar, in eras of 400 years starting
+    // on March 1st so that leap days end the year.
+    let days = days as i64 + 719468;
+    let era = days.div_euclid(146097);
+    let day_of_era = days.rem_euclid(146097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 {
+        shifted_month + 3
+    } else {
+        shifted_month - 9
+    };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    PathBuf::from(format!(
+        "{prefix}-{year:04}{month:02}{day:02}-{:02}{:02}{:02}.{extension}",
+        second / 3600,
+        second / 60 % 60,
+        second % 60
+    ))
+}
+
+impl ToJson for Manifest {
+    fn to_json(&self) -> Json {
+        Json::object([
+            ("params", self.params.to_json()),
+            ("version", Json::String(self.version.clone())),
+            (
+                "backend",
+                Json::String(self.params.backend.name().to_string()),
+            ),
+            (
+                "elapsed_ms",
+                Json::Number(self.elapsed.as_secs_f64() * 1000.0),
+            ),
+            ("subdivision", Json::Bool(self.subdivision)),
+            ("periodicity", Json::Bool(self.periodicity)),
+        ])
+    }
+}
+
+impl FromJson for Manifest {
+    fn from_json(json: &Json) -> Result<Self, String> {
+        // On in the manifests written before they were recorded.
+        let switch = |key: &str| match json.get(key) {
+            None => Ok(true),
+            Some(Json::Bool(value)) => Ok(*value),
+            Some(other) => Err(format!("Expected true or false for {key}, got {other}")),
+        };
+        Ok(Manifest {
+            params: RenderParams::from_json(json.field("params")?)?,
+            version: match json.field("version")? {
+                Json::String(version) => version.clone(),
+                other => return Err(format!("Invalid version {other}")),
+            },
+            elapsed: Duration::from_secs_f64(json.field("elapsed_ms")?.as_f64()?.max(0.0) / 1000.0),
+            subdivision: switch("subdivision")?,
+            periodicity: switch("periodicity")?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::render;
+
+    #[test]
+    fn manifest_reproduces_the_image() {
+        let params = RenderParams::builder()
+            .size(48, 32)
+            .iterations(300)
+            .antialias(2)
+            .build()
+            .unwrap();
+        let image = std::env::temp_dir().join(format!("mandelbrot-{}.png", std::process::id()));
+        let path = sidecar(&image);
+        assert_eq!(path.extension().unwrap(), "json");
+        let manifest = Manifest::new(params, Duration::from_millis(12));
+        manifest.write(&path).unwrap();
+        let loaded = Manifest::load(&path).unwrap();
+        assert_eq!(loaded, manifest);
+        let unsubdivided = Manifest {
+            subdivision: false,
+            ..manifest.clone()
+        };
+        let text = unsubdivided.to_json().to_string();
+        assert_eq!(
+            Manifest::from_json(&Json::parse(&text).unwrap()),
+            Ok(unsubdivided)
+        );
+        assert_eq!(
+            render::render_frame(loaded.params).rgb,
+            render::render_frame(params).rgb
+        );
+        // Bare parameters are accepted as well.
+        std::fs::write(&path, params.to_json().to_string()).unwrap();
+        assert_eq!(Manifest::load(&path).unwrap().params, params);
+        // Images carry their manifest, those of other writers have it next to them.
+        let rgb = render::render_frame(params).rgb;
+        manifest.write_png(&image, 48, 32, &rgb).unwrap();
+        assert_eq!(Manifest::from_png(&image).unwrap(), manifest);
+        png::write(&image, 48, 32, &rgb).unwrap();
+        assert_eq!(Manifest::from_png(&image).unwrap().params, params);
+        std::fs::remove_file(&path).unwrap();
+        assert!(Manifest::from_png(&image).is_err());
+        std::fs::remove_file(&image).unwrap();
+    }
+
+    #[test]
+    fn files_are_named_by_time() {
+        let at = |seconds| SystemTime::UNIX_EPOCH + Duration::from_secs(seconds);
+        assert_eq!(
+            timestamped("screenshot", "png", at(0)),
+            PathBuf::from("screenshot-19700101-000000.png")
+        );
+        assert_eq!(
+            timestamped("orbit", "csv", at(951_825_599)),
+            PathBuf::from("orbit-20000229-115959.csv")
+        );
+        assert_eq!(
+            timestamped("screenshot", "png", at(1_791_991_812)),
+            PathBuf::from("screenshot-20261014-153012.png")
+        );
+    }
+}
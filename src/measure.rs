@@ -0,0 +1,73 @@
+use crate::annotate;
+use crate::render::Frame;
+use crate::style::Style;
+use crate::text;
+use crate::viewport::Viewport;
+use num::complex::Complex;
+
+// A segment between two points of the complex plane, measured with `D`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Measurement {
+    pub from: Complex<f64>,
+    pub to: Complex<f64>,
+}
+
+impl Measurement {
+    // Complex difference, Euclidean distance and the distance in window pixels of the view.
+    pub fn describe(&self, viewport: &Viewport, window_size: (u32, u32)) -> [String; 3] {
+        let delta = self.to - self.from;
+        let (a, b) = (
+            viewport.complex_to_pixel(self.from, window_size),
+            viewport.complex_to_pixel(self.to, window_size),
+        );
+        [
+            format!("d = {:+.6e} {:+.6e}i", delta.re, delta.im),
+            format!("|d| = {:.6e}", delta.norm()),
+            format!("{:.1} px", (b.0 - a.0).hypot(b.1 - a.1)),
+        ]
+    }
+
+    // The segment with its end points marked and the measures next to its end.
+    pub fn draw(&self, frame: &mut Frame, viewport: &Viewport, style: &Style) {
+        let color = style.measurement;
+        let window_size = (frame.width, frame.height);
+        let (a, b) = (
+            viewport.complex_to_pixel(self.from, window_size),
+            viewport.complex_to_pixel(self.to, window_size),
+        );
+        annotate::line(frame, a, b, color);
+        for (x, y) in [a, b] {
+            annotate::line(frame, (x - 5.0, y), (x + 5.0, y), color);
+            annotate::line(frame, (x, y - 5.0), (x, y + 5.0), color);
+        }
+        let lines = self.describe(viewport, window_size);
+        let line_height = text::LINE_HEIGHT as i32 * 2;
+        let width = lines.iter().map(|line| line.len()).max().unwrap_or(0) as u32;
+        let size = (width * text::ADVANCE * 2 + 8, line_height as u32 * 3 + 4);
+        // Next to the end point, but kept inside the window.
+        let x = (b.0 as i32 + 12).clamp(0, (frame.width as i32 - size.0 as i32).max(0));
+        let y = (b.1 as i32 + 12).clamp(0, (frame.height as i32 - size.1 as i32).max(0));
+        style.panel(frame, (x, y), size);
+        for (i, line) in lines.iter().enumerate() {
+            text::draw(frame, x + 4, y + 4 + i as i32 * line_height, 2, color, line);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn describes_complex_and_pixel_distances() {
+        let measurement = Measurement {
+            from: Complex::new(-1.0, 0.0),
+            to: Complex::new(-0.4, 0.8),
+        };
+        let [delta, distance, pixels] = measurement.describe(&Viewport::default(), (800, 600));
+        assert_eq!(delta, "d = +6.000000e-1 +8.000000e-1i");
+        assert_eq!(distance, "|d| = 1.000000e0");
+        // 200 pixels per unit of the default view.
+        assert_eq!(pixels, "200.0 px");
+    }
+}
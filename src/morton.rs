@@ -0,0 +1,94 @@
+// Z-order (Morton) traversal of an image: square tiles visited along the Z curve, and the
+// pixels within a tile along it too, so neighbouring work stays close in the plane and in
+// the output buffers.
+
+// Side of a tile, a power of two.
+pub const TILE: u32 = 16;
+
+// Interleaves the bits of the coordinates, x in the even bits and y in the odd ones.
+pub const fn encode(x: u32, y: u32) -> u64 {
+    spread(x) | spread(y) << 1
+}
+
+pub const fn decode(code: u64) -> (u32, u32) {
+    (compact(code), compact(code >> 1))
+}
+
+// Moves bit i of the value to bit 2i.
+const fn spread(value: u32) -> u64 {
+    let mut v = value as u64;
+    v = (v | v << 16) & 0x0000_ffff_0000_ffff;
+    v = (v | v << 8) & 0x00ff_00ff_00ff_00ff;
+    v = (v | v << 4) & 0x0f0f_0f0f_0f0f_0f0f;
+    v = (v | v << 2) & 0x3333_3333_3333_3333;
+    (v | v << 1) & 0x5555_5555_5555_5555
+}
+
+// Moves bit 2i of the value to bit i, dropping the odd bits.
+const fn compact(value: u64) -> u32 {
+    let mut v = value & 0x5555_5555_5555_5555;
+    v = (v | v >> 1) & 0x3333_3333_3333_3333;
+    v = (v | v >> 2) & 0x0f0f_0f0f_0f0f_0f0f;
+    v = (v | v >> 4) & 0x00ff_00ff_00ff_00ff;
+    v = (v | v >> 8) & 0x0000_ffff_0000_ffff;
+    (v | v >> 16) as u32
+}
+
+// The origins of the tiles covering the image, in Z order. The tiles at the right and bottom
+// edges stick out of images not a multiple of the tile size.
+pub fn tiles(width: u32, height: u32) -> Vec<(u32, u32)> {
+    let mut tiles = (0..height.div_ceil(TILE))
+        .flat_map(|y| (0..width.div_ceil(TILE)).map(move |x| (x, y)))
+        .collect::<Vec<_>>();
+    tiles.sort_unstable_by_key(|&(x, y)| encode(x, y));
+    tiles
+        .into_iter()
+        .map(|(x, y)| (x * TILE, y * TILE))
+        .collect()
+}
+
+// Offsets of the pixels of a tile in Z order.
+const ORDER: [(u32, u32); (TILE * TILE) as usize] = {
+    let mut order = [(0, 0); (TILE * TILE) as usize];
+    let mut code = 0;
+    while code < order.len() {
+        order[code] = decode(code as u64);
+        code += 1;
+    }
+    order
+};
+
+// The pixels of the tile at `origin` inside the image, in Z order.
+pub fn pixels(origin: (u32, u32), width: u32, height: u32) -> impl Iterator<Item = (u32, u32)> {
+    ORDER
+        .iter()
+        .map(move |&(x, y)| (origin.0 + x, origin.1 + y))
+        .filter(move |&(x, y)| x < width && y < height)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn z_order_covers_image_once() {
+        assert_eq!(encode(0b11, 0b01), 0b0111);
+        assert_eq!(decode(encode(12345, u32::MAX)), (12345, u32::MAX));
+        assert_eq!(
+            pixels((0, 0), 2, 2).collect::<Vec<_>>(),
+            [(0, 0), (1, 0), (0, 1), (1, 1)]
+        );
+        assert_eq!(
+            tiles(40, 20),
+            [(0, 0), (16, 0), (0, 16), (16, 16), (32, 0), (32, 16)]
+        );
+        let (width, height) = (53, 37);
+        let mut covered = vec![0; (width * height) as usize];
+        for tile in tiles(width, height) {
+            for (x, y) in pixels(tile, width, height) {
+                covered[(y * width + x) as usize] += 1;
+            }
+        }
+        assert!(covered.iter().all(|&count| count == 1));
+    }
+}
@@ -0,0 +1,53 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+
+static ANIMATED: AtomicBool = AtomicBool::new(false);
+
+// Turns the animated noise on or off, it is off by default: every frame has the noise of seed
+// 0 and an idle image does not shimmer. When on, frames accumulating over time (the passes of
+// the Buddhabrot) draw new noise every pass, which the eye averages like the accumulation.
+pub fn animate(enabled: bool) {
+    ANIMATED.store(enabled, Ordering::Relaxed);
+}
+
+pub fn animated() -> bool {
+    ANIMATED.load(Ordering::Relaxed)
+}
+
+// Seed of the noise of the nth frame of an accumulation.
+pub fn seed(frame: u64) -> u64 {
+    if animated() {
+        frame
+    } else {
+        0
+    }
+}
+
+// Uniform in [0, 1), hashed from the pixel position and the seed, so the same pixel draws the
+// same value in every frame of the same seed. Shared by all the stochastic per-pixel effects.
+pub fn value(x: i64, y: i64, seed: u64) -> f64 {
+    let mut h = (x as u64).wrapping_mul(0x9e3779b97f4a7c15)
+        ^ (y as u64).wrapping_mul(0xc2b2ae3d27d4eb4f)
+        ^ seed.wrapping_mul(0x165667b19e3779f9);
+    h ^= h >> 29;
+    h = h.wrapping_mul(0xbf58476d1ce4e5b9);
+    h ^= h >> 32;
+    (h >> 11) as f64 / (1u64 << 53) as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn noise_is_stable_per_seed() {
+        assert_eq!(value(3, 4, 0), value(3, 4, 0));
+        assert_ne!(value(3, 4, 0), value(4, 3, 0));
+        assert_ne!(value(3, 4, 0), value(3, 4, 1));
+        let mean = (0..100)
+            .flat_map(|y| (0..100).map(move |x| value(x, y, 7)))
+            .sum::<f64>()
+            / 10000.0;
+        assert!((mean - 0.5).abs() < 0.01, "{mean}");
+        assert_eq!(seed(5), 0);
+    }
+}
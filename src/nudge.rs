@@ -0,0 +1,113 @@
+use crate::contrast;
+use crate::params::{Fractal, RenderParams};
+use crate::render::{self, Frame};
+use num::complex::Complex;
+
+// Pixels inside the set in one render and outside in the other.
+const FLIPPED: [u8; 3] = [255, 64, 64];
+
+// A small change of one parameter, to see how sensitive the view is to it.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Nudge {
+    Z0(Complex<f64>),
+    C(Complex<f64>),
+    Iterations(i64),
+}
+
+impl Nudge {
+    pub fn apply(&self, params: RenderParams) -> Result<RenderParams, String> {
+        let builder = params.to_builder();
+        match (*self, params.fractal) {
+            (Nudge::Z0(delta), Fractal::Mandelbrot { z0 }) => {
+                builder.fractal(Fractal::Mandelbrot { z0: z0 + delta })
+            }
+            (Nudge::C(delta), Fractal::Julia { c }) => {
+                builder.fractal(Fractal::Julia { c: c + delta })
+            }
+            (Nudge::Iterations(delta), _) => {
+                builder.iterations((params.iterations as i64 + delta).max(1) as u32)
+            }
+            (Nudge::Z0(_), _) => return Err("z0 is a parameter of the Mandelbrot set".to_string()),
+            (Nudge::C(_), _) => return Err("c is a parameter of Julia sets".to_string()),
+        }
+        .build()
+    }
+}
+
+// How much the two renders of a view differ.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Difference {
+    pub flipped: usize,
+    pub changed: usize,
+    pub max: f64,
+}
+
+// Renders the view as it is and nudged, and pictures the per-pixel difference of the smooth
+// iteration counts: black where they agree, brighter the more they differ, red where a point
+// changed sides of the set.
+pub fn compare(params: RenderParams, nudge: Nudge) -> Result<(Frame, Difference), String> {
+    if matches!(params.fractal, Fractal::Buddhabrot { .. }) {
+        return Err("Nudges compare escape-time renders".to_string());
+    }
+    let (a, b) = (
+        render::escape_time_frame(params),
+        render::escape_time_frame(nudge.apply(params)?),
+    );
+    let deltas = a
+        .samples
+        .iter()
+        .zip(&b.samples)
+        .map(
+            |(a, b)| match (contrast::smooth_value(a), contrast::smooth_value(b)) {
+                (Some(a), Some(b)) => Some((a - b).abs()),
+                (None, None) => Some(0.0),
+                _ => None,
+            },
+        )
+        .collect::<Vec<_>>();
+    let mut difference = Difference {
+        flipped: deltas.iter().filter(|delta| delta.is_none()).count(),
+        changed: 0,
+        max: 0.0,
+    };
+    for &delta in deltas.iter().flatten() {
+        difference.changed += (delta > 0.0) as usize;
+        difference.max = difference.max.max(delta);
+    }
+    difference.changed += difference.flipped;
+    let rgb = deltas
+        .iter()
+        .flat_map(|delta| match delta {
+            None => FLIPPED,
+            Some(delta) if difference.max > 0.0 => {
+                [((delta / difference.max).sqrt() * 255.0) as u8; 3]
+            }
+            Some(_) => [0; 3],
+        })
+        .collect();
+    Ok((Frame::from_rgb(a.width, a.height, rgb), difference))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn nudged_renders_differ() {
+        let params = RenderParams::builder()
+            .size(64, 48)
+            .iterations(200)
+            .build()
+            .unwrap();
+        let (frame, same) = compare(params, Nudge::Z0(Complex::new(0.0, 0.0))).unwrap();
+        assert_eq!((same.changed, same.flipped), (0, 0));
+        assert!(frame.rgb.iter().all(|&channel| channel == 0));
+        let (frame, nudged) = compare(params, Nudge::Z0(Complex::new(0.01, 0.0))).unwrap();
+        assert!(nudged.flipped > 0 && nudged.changed > nudged.flipped && nudged.max > 0.0);
+        assert!(frame.rgb.chunks(3).any(|pixel| pixel == FLIPPED));
+        // More iterations only move the boundary of the interior.
+        let (_, deeper) = compare(params, Nudge::Iterations(100)).unwrap();
+        assert_eq!(deeper.changed, deeper.flipped);
+        assert!(compare(params, Nudge::C(Complex::new(0.01, 0.0))).is_err());
+    }
+}
@@ -0,0 +1,228 @@
+use crate::annotate;
+use crate::json::Json;
+use crate::params::Fractal;
+use crate::render::Frame;
+use crate::style::Style;
+use crate::viewport::Viewport;
+use num::complex::Complex;
+use rayon::prelude::*;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+// Points drawn at most, the later ones of long orbits pile up on their cycle anyway.
+const MAX_POINTS: u32 = 256;
+const MARK: f64 = 3.0;
+
+// Orbit shown by the orbit mode `O`. In the Mandelbrot set the point picked is the parameter c
+// and the orbit that of the critical point 0, in the dynamical plane that shares the
+// coordinates; in a Julia set the point picked is the starting point itself.
+pub fn points(fractal: Fractal, point: Complex<f64>, iterations: u32) -> Vec<Complex<f64>> {
+    trace(fractal, point, iterations.min(MAX_POINTS))
+}
+
+// The orbit of the point, from its starting value until the first value outside the bailout
+// radius or the given number of iterations.
+pub fn trace(fractal: Fractal, point: Complex<f64>, iterations: u32) -> Vec<Complex<f64>> {
+    let (mut z, c) = fractal.start(point);
+    let mut orbit = vec![z];
+    for _ in 0..iterations {
+        z = fractal.step(z, c);
+        orbit.push(z);
+        if z.norm_sqr() > 4.0 {
+            break;
+        }
+    }
+    orbit
+}
+
+// The orbits of many points at once, traced in parallel.
+pub fn trace_all(
+    fractal: Fractal,
+    points: &[Complex<f64>],
+    iterations: u32,
+) -> Vec<Vec<Complex<f64>>> {
+    points
+        .par_iter()
+        .map(|&point| trace(fractal, point, iterations))
+        .collect()
+}
+
+// The `orbits` subcommand: the orbits of the points of a JSON file, for tools built on top of
+// them. Points and orbit values are [re, im] pairs, the output is an array with the orbit of
+// every point.
+#[derive(Clone, Debug, PartialEq)]
+pub struct OrbitBatch {
+    pub points: PathBuf,
+    pub output: PathBuf,
+    pub iterations: u32,
+    // The orbits of the Julia set of this constant instead of the critical orbits.
+    pub julia: Option<Complex<f64>>,
+}
+
+impl Default for OrbitBatch {
+    fn default() -> Self {
+        OrbitBatch {
+            points: PathBuf::from("points.json"),
+            output: PathBuf::from("orbits.json"),
+            iterations: 1000,
+            julia: None,
+        }
+    }
+}
+
+fn pair(z: Complex<f64>) -> Json {
+    Json::Array(vec![Json::Number(z.re), Json::Number(z.im)])
+}
+
+fn from_pair(json: &Json) -> Result<Complex<f64>, String> {
+    match json.as_array()? {
+        [re, im] => Ok(Complex::new(re.as_f64()?, im.as_f64()?)),
+        _ => Err(format!("Invalid point {json}, expected [re, im]")),
+    }
+}
+
+// Writes the orbit with the modulus of every value as .csv or .json, for plotting elsewhere.
+pub fn export(path: &Path, orbit: &[Complex<f64>]) -> Result<(), String> {
+    let text = match path.extension().and_then(|extension| extension.to_str()) {
+        Some("csv") => {
+            let mut csv = "iteration,re,im,abs\n".to_string();
+            for (i, z) in orbit.iter().enumerate() {
+                csv += &format!("{i},{:e},{:e},{:e}\n", z.re, z.im, z.norm());
+            }
+            csv
+        }
+        Some("json") => Json::Array(
+            orbit
+                .iter()
+                .enumerate()
+                .map(|(i, z)| {
+                    Json::object([
+                        ("iteration", Json::Number(i as f64)),
+                        ("re", Json::Number(z.re)),
+                        ("im", Json::Number(z.im)),
+                        ("abs", Json::Number(z.norm())),
+                    ])
+                })
+                .collect(),
+        )
+        .to_string(),
+        _ => return Err(format!("{}: export as .csv or .json", path.display())),
+    };
+    std::fs::write(path, text).map_err(|e| format!("{}: {e}", path.display()))
+}
+
+pub fn run(job: &OrbitBatch) -> Result<(), String> {
+    let stamp = Instant::now();
+    let text = std::fs::read_to_string(&job.points)
+        .map_err(|e| format!("{}: {e}", job.points.display()))?;
+    let points = Json::parse(&text)?
+        .as_array()?
+        .iter()
+        .map(from_pair)
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("{}: {e}", job.points.display()))?;
+    let fractal = match job.julia {
+        Some(c) => Fractal::Julia { c },
+        None => Fractal::MANDELBROT,
+    };
+    let orbits = trace_all(fractal, &points, job.iterations);
+    let json = Json::Array(
+        orbits
+            .iter()
+            .map(|orbit| Json::Array(orbit.iter().copied().map(pair).collect()))
+            .collect(),
+    );
+    std::fs::write(&job.output, json.to_string())
+        .map_err(|e| format!("{}: {e}", job.output.display()))?;
+    println!(
+        "Wrote {} orbits to {} in {:?}",
+        orbits.len(),
+        job.output.display(),
+        Instant::now() - stamp
+    );
+    Ok(())
+}
+
+// The orbit as a path through its points, each marked with a small square.
+pub fn draw(frame: &mut Frame, viewport: &Viewport, orbit: &[Complex<f64>], style: &Style) {
+    let window_size = (frame.width, frame.height);
+    let pixels = orbit
+        .iter()
+        .map(|&z| viewport.complex_to_pixel(z, window_size))
+        .collect::<Vec<_>>();
+    for pair in pixels.windows(2) {
+        annotate::line(frame, pair[0], pair[1], style.orbit);
+    }
+    for &(x, y) in &pixels {
+        let corners = [
+            (x - MARK, y - MARK),
+            (x + MARK, y - MARK),
+            (x + MARK, y + MARK),
+            (x - MARK, y + MARK),
+        ];
+        for i in 0..4 {
+            annotate::line(frame, corners[i], corners[(i + 1) % 4], style.orbit);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn critical_and_julia_orbits() {
+        // The critical orbit of c = -1 is the 2-cycle 0, -1, 0, ...
+        let cycle = points(Fractal::MANDELBROT, Complex::new(-1.0, 0.0), 4);
+        let re = cycle.iter().map(|z| z.re).collect::<Vec<_>>();
+        assert_eq!(re, [0.0, -1.0, 0.0, -1.0, 0.0]);
+        // Escaping orbits end with their first point outside the bailout radius.
+        let julia = Fractal::Julia {
+            c: Complex::new(0.0, 0.0),
+        };
+        let escaping = points(julia, Complex::new(1.5, 0.0), 100);
+        assert_eq!(escaping, [Complex::new(1.5, 0.0), Complex::new(2.25, 0.0)]);
+    }
+
+    #[test]
+    fn batch_of_orbits() {
+        let dir = std::env::temp_dir().join(format!("mandelbrot-orbits-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let job = OrbitBatch {
+            points: dir.join("points.json"),
+            output: dir.join("orbits.json"),
+            iterations: 1000,
+            julia: None,
+        };
+        std::fs::write(&job.points, "[[-1, 0], [0.5, 0], [-0.1, 0.1]]").unwrap();
+        run(&job).unwrap();
+        let orbits = Json::parse(&std::fs::read_to_string(&job.output).unwrap()).unwrap();
+        let lengths = orbits
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|orbit| orbit.as_array().unwrap().len())
+            .collect::<Vec<_>>();
+        // Bounded orbits run the full iterations, unlike the capped ones of the orbit mode.
+        assert_eq!(lengths, [1001, 6, 1001]);
+        let starts = [Complex::new(-1.0, 0.0), Complex::new(0.5, 0.0)];
+        let batch = trace_all(Fractal::MANDELBROT, &starts, 1000);
+        assert_eq!(batch[1], points(Fractal::MANDELBROT, starts[1], 1000));
+        assert_eq!(from_pair(&pair(starts[1])).unwrap(), starts[1]);
+        let orbit = trace(Fractal::MANDELBROT, Complex::new(0.5, 0.0), 100);
+        export(&dir.join("orbit.csv"), &orbit).unwrap();
+        let csv = std::fs::read_to_string(dir.join("orbit.csv")).unwrap();
+        assert_eq!(csv.lines().count(), orbit.len() + 1);
+        assert_eq!(csv.lines().nth(2), Some("1,5e-1,0e0,5e-1"));
+        export(&dir.join("orbit.json"), &orbit).unwrap();
+        let json = Json::parse(&std::fs::read_to_string(dir.join("orbit.json")).unwrap()).unwrap();
+        assert_eq!(
+            json.as_array().unwrap()[5].field("iteration"),
+            Ok(&Json::Number(5.0))
+        );
+        assert!(export(&dir.join("orbit.txt"), &orbit).is_err());
+        std::fs::write(&job.points, "[[1, 2, 3]]").unwrap();
+        assert!(run(&job).is_err());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,294 @@
+use crate::bookmarks::{self, Bookmark};
+use crate::json::{FromJson, Json, ToJson};
+use crate::manifest;
+use crate::png;
+use crate::render;
+use std::path::{Path, PathBuf};
+use std::time::{Instant, SystemTime};
+
+const FORMAT: &str = "mandelbrot-explorer bookmark pack";
+const BASE64: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+// A bookmark as shared in a pack, with a PNG thumbnail of the place.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Entry {
+    pub bookmark: Bookmark,
+    pub thumbnail: Vec<u8>,
+}
+
+// A collection of bookmarks in one file to pass around: the bookmarks with their thumbnails
+// embedded as base64, the version of the explorer that made it and when.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Pack {
+    pub version: String,
+    // Seconds since the Unix epoch.
+    pub created: u64,
+    pub entries: Vec<Entry>,
+}
+
+impl ToJson for Pack {
+    fn to_json(&self) -> Json {
+        Json::object([
+            ("format", Json::String(FORMAT.to_string())),
+            ("version", Json::String(self.version.clone())),
+            ("created", Json::Number(self.created as f64)),
+            (
+                "bookmarks",
+                Json::Array(
+                    self.entries
+                        .iter()
+                        .map(|entry| {
+                            Json::object([
+                                ("name", Json::String(entry.bookmark.name.clone())),
+                                ("params", entry.bookmark.params.to_json()),
+                                ("thumbnail", Json::String(encode(&entry.thumbnail))),
+                            ])
+                        })
+                        .collect(),
+                ),
+            ),
+        ])
+    }
+}
+
+impl FromJson for Pack {
+    fn from_json(json: &Json) -> Result<Self, String> {
+        if json.field("format")? != &Json::String(FORMAT.to_string()) {
+            return Err("Not a bookmark pack".to_string());
+        }
+        let entries = json
+            .field("bookmarks")?
+            .as_array()?
+            .iter()
+            .map(|entry| {
+                Ok(Entry {
+                    bookmark: Bookmark::from_json(entry)?,
+                    thumbnail: match entry.field("thumbnail")? {
+                        Json::String(text) => decode(text)?,
+                        other => return Err(format!("Invalid thumbnail {other}")),
+                    },
+                })
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+        Ok(Pack {
+            version: match json.field("version")? {
+                Json::String(version) => version.clone(),
+                other => return Err(format!("Invalid version {other}")),
+            },
+            created: json.field("created")?.as_f64()? as u64,
+            entries,
+        })
+    }
+}
+
+pub fn load(path: &Path) -> Result<Pack, String> {
+    let text = std::fs::read_to_string(path).map_err(|e| format!("{}: {e}", path.display()))?;
+    Json::parse(&text)
+        .and_then(|json| Pack::from_json(&json))
+        .map_err(|e| format!("{}: {e}", path.display()))
+}
+
+// Standard base64 with padding.
+fn encode(bytes: &[u8]) -> String {
+    let mut text = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let group = chunk.iter().enumerate().fold(0u32, |group, (i, &byte)| {
+            group | (byte as u32) << (16 - 8 * i)
+        });
+        for i in 0..4 {
+            text.push(if i <= chunk.len() {
+                BASE64[(group >> (18 - 6 * i) & 63) as usize] as char
+            } else {
+                '='
+            });
+        }
+    }
+    text
+}
+
+fn decode(text: &str) -> Result<Vec<u8>, String> {
+    let text = text.as_bytes();
+    if !text.len().is_multiple_of(4) {
+        return Err("Invalid base64 length".to_string());
+    }
+    let mut bytes = Vec::with_capacity(text.len() / 4 * 3);
+    for chunk in text.chunks(4) {
+        let padding = chunk.iter().rev().take_while(|&&c| c == b'=').count();
+        if padding > 2 {
+            return Err("Invalid base64 padding".to_string());
+        }
+        let mut group = 0;
+        for (i, &c) in chunk[..4 - padding].iter().enumerate() {
+            let value = BASE64
+                .iter()
+                .position(|&digit| digit == c)
+                .ok_or_else(|| format!("Invalid base64 character {:?}", c as char))?;
+            group |= (value as u32) << (18 - 6 * i);
+        }
+        bytes.extend((0..3 - padding).map(|i| (group >> (16 - 8 * i)) as u8));
+    }
+    Ok(bytes)
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Direction {
+    Export,
+    Import,
+}
+
+// The `bookmarks` subcommand: a bookmarks file exported to a pack, or a pack merged into one.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Exchange {
+    pub direction: Direction,
+    pub pack: PathBuf,
+    pub bookmarks: PathBuf,
+    pub thumbnail_width: u32,
+    pub thumbnail_height: u32,
+}
+
+impl Default for Exchange {
+    fn default() -> Self {
+        Exchange {
+            direction: Direction::Export,
+            pack: PathBuf::from("pack.json"),
+            bookmarks: PathBuf::from("bookmarks.json"),
+            thumbnail_width: 128,
+            thumbnail_height: 96,
+        }
+    }
+}
+
+pub fn run(job: &Exchange) -> Result<(), String> {
+    let stamp = Instant::now();
+    match job.direction {
+        Direction::Export => {
+            let bookmarks = bookmarks::load(&job.bookmarks)?;
+            let mut entries = Vec::new();
+            for (i, bookmark) in bookmarks.into_iter().enumerate() {
+                println!("Thumbnail {}: {}", i + 1, bookmark.name);
+                let params =
+                    bookmarks::resized(bookmark.params, job.thumbnail_width, job.thumbnail_height)?;
+                let frame = render::render_frame(params);
+                entries.push(Entry {
+                    bookmark,
+                    thumbnail: png::encode(frame.width, frame.height, &frame.rgb),
+                });
+            }
+            let pack = Pack {
+                version: manifest::VERSION.to_string(),
+                created: SystemTime::now()
+                    .duration_since(SystemTime::UNIX_EPOCH)
+                    .map_or(0, |elapsed| elapsed.as_secs()),
+                entries,
+            };
+            std::fs::write(&job.pack, pack.to_json().to_string())
+                .map_err(|e| format!("{}: {e}", job.pack.display()))?;
+            println!(
+                "Wrote {} bookmarks to {} in {:?}",
+                pack.entries.len(),
+                job.pack.display(),
+                Instant::now() - stamp
+            );
+        }
+        Direction::Import => {
+            let pack = load(&job.pack)?;
+            // Importing into a new collection starts it.
+            let mut bookmarks = if job.bookmarks.exists() {
+                bookmarks::load(&job.bookmarks)?
+            } else {
+                Vec::new()
+            };
+            let offered = pack.entries.len();
+            let added = bookmarks::merge(
+                &mut bookmarks,
+                pack.entries.into_iter().map(|entry| entry.bookmark),
+            );
+            bookmarks::save(&job.bookmarks, &bookmarks)?;
+            println!(
+                "Added {added} of {offered} bookmarks to {}, {} already there",
+                job.bookmarks.display(),
+                offered - added
+            );
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::params::RenderParams;
+    use crate::viewport::Viewport;
+    use num::complex::Complex;
+
+    #[test]
+    fn base64_round_trip() {
+        assert_eq!(encode(b"Man"), "TWFu");
+        assert_eq!(encode(b"Ma"), "TWE=");
+        assert_eq!(encode(b"M"), "TQ==");
+        for length in 0..8 {
+            let bytes = (0..length).map(|i| (i * 97) as u8).collect::<Vec<_>>();
+            assert_eq!(decode(&encode(&bytes)).unwrap(), bytes);
+        }
+        assert!(decode("TQ=").is_err());
+        assert!(decode("T*==").is_err());
+        assert!(decode("T===").is_err());
+    }
+
+    #[test]
+    fn packs_exchange_bookmarks() {
+        let params = RenderParams::builder()
+            .size(800, 600)
+            .iterations(50)
+            .build()
+            .unwrap();
+        let seahorses = Bookmark {
+            name: "seahorses".to_string(),
+            params: params
+                .to_builder()
+                .viewport(Viewport::new(Complex::new(-0.75, 0.1), 0.1, 0.1))
+                .build()
+                .unwrap(),
+        };
+        let home = Bookmark {
+            name: "home".to_string(),
+            params,
+        };
+        let dir = std::env::temp_dir().join(format!("mandelbrot-pack-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let mut job = Exchange {
+            pack: dir.join("pack.json"),
+            bookmarks: dir.join("mine.json"),
+            thumbnail_width: 16,
+            thumbnail_height: 12,
+            ..Exchange::default()
+        };
+        bookmarks::save(&job.bookmarks, &[home.clone(), seahorses.clone()]).unwrap();
+        run(&job).unwrap();
+        let pack = load(&job.pack).unwrap();
+        assert_eq!(pack.entries.len(), 2);
+        assert_eq!(pack.entries[1].bookmark, seahorses);
+        assert_eq!(pack.entries[1].thumbnail, {
+            let frame = render::render_frame(bookmarks::resized(seahorses.params, 16, 12).unwrap());
+            png::encode(16, 12, &frame.rgb)
+        });
+
+        // Someone else already has home under another name.
+        job.direction = Direction::Import;
+        job.bookmarks = dir.join("theirs.json");
+        let renamed = Bookmark {
+            name: "start".to_string(),
+            ..home
+        };
+        bookmarks::save(&job.bookmarks, std::slice::from_ref(&renamed)).unwrap();
+        run(&job).unwrap();
+        assert_eq!(
+            bookmarks::load(&job.bookmarks).unwrap(),
+            [renamed, seahorses]
+        );
+        job.bookmarks = dir.join("new.json");
+        run(&job).unwrap();
+        assert_eq!(bookmarks::load(&job.bookmarks).unwrap().len(), 2);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,258 @@
+use crate::buddhabrot::SplitMix64;
+use crate::json::{FromJson, Json, ToJson};
+use std::path::Path;
+
+pub const STOPS: usize = 5;
+// Lightness climbs by a bounded step from one stop to the next, so a random palette neither
+// flattens into one tone nor jumps between black and white.
+const FIRST_LIGHTNESS: (f64, f64) = (5.0, 25.0);
+const LIGHTNESS_STEP: (f64, f64) = (8.0, 22.0);
+const CHROMA: (f64, f64) = (20.0, 70.0);
+const HUE_STEP: f64 = 90.0;
+
+// Gradient of the exterior from the slowest to the fastest escaping points.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Palette {
+    pub stops: [[u8; 3]; STOPS],
+}
+
+// Gradients cycled through with `F` after the built-in coloring.
+pub const NAMED: [(&str, Palette); 4] = [
+    (
+        "fire",
+        Palette {
+            stops: [
+                [0x00, 0x00, 0x00],
+                [0x7a, 0x08, 0x00],
+                [0xe0, 0x3c, 0x00],
+                [0xff, 0xb0, 0x00],
+                [0xff, 0xff, 0xe0],
+            ],
+        },
+    ),
+    (
+        "ocean",
+        Palette {
+            stops: [
+                [0x00, 0x08, 0x14],
+                [0x00, 0x2a, 0x5c],
+                [0x00, 0x77, 0xb6],
+                [0x48, 0xca, 0xe4],
+                [0xe0, 0xfb, 0xfc],
+            ],
+        },
+    ),
+    (
+        "grayscale",
+        Palette {
+            stops: [
+                [0x00, 0x00, 0x00],
+                [0x40, 0x40, 0x40],
+                [0x80, 0x80, 0x80],
+                [0xbf, 0xbf, 0xbf],
+                [0xff, 0xff, 0xff],
+            ],
+        },
+    ),
+    (
+        "rainbow",
+        Palette {
+            stops: [
+                [0xff, 0x00, 0x00],
+                [0xff, 0xcc, 0x00],
+                [0x33, 0xff, 0x00],
+                [0x00, 0x99, 0xff],
+                [0x99, 0x00, 0xff],
+            ],
+        },
+    ),
+];
+
+fn between(rng: &mut SplitMix64, (low, high): (f64, f64)) -> f64 {
+    low + rng.next_f64() * (high - low)
+}
+
+// CIE LCh (D65) to 8-bit sRGB, clipping colors outside the gamut.
+pub fn lch_to_rgb(lightness: f64, chroma: f64, hue: f64) -> [u8; 3] {
+    let (a, b) = (
+        chroma * hue.to_radians().cos(),
+        chroma * hue.to_radians().sin(),
+    );
+    let fy = (lightness + 16.0) / 116.0;
+    let inverse = |t: f64| {
+        if t > 6.0 / 29.0 {
+            t * t * t
+        } else {
+            3.0 * (6.0f64 / 29.0).powi(2) * (t - 4.0 / 29.0)
+        }
+    };
+    let (x, y, z) = (
+        0.95047 * inverse(fy + a / 500.0),
+        inverse(fy),
+        1.08883 * inverse(fy - b / 200.0),
+    );
+    [
+        3.2406 * x - 1.5372 * y - 0.4986 * z,
+        -0.9689 * x + 1.8758 * y + 0.0415 * z,
+        0.0557 * x - 0.2040 * y + 1.0570 * z,
+    ]
+    .map(|linear| {
+        let v = if linear <= 0.0031308 {
+            12.92 * linear
+        } else {
+            1.055 * linear.powf(1.0 / 2.4) - 0.055
+        };
+        (v.clamp(0.0, 1.0) * 255.0).round() as u8
+    })
+}
+
+impl Palette {
+    pub fn random(rng: &mut SplitMix64) -> Palette {
+        let mut lightness = between(rng, FIRST_LIGHTNESS);
+        let mut hue = between(rng, (0.0, 360.0));
+        let mut stops = [[0; 3]; STOPS];
+        for stop in stops.iter_mut() {
+            *stop = lch_to_rgb(lightness.min(98.0), between(rng, CHROMA), hue);
+            lightness += between(rng, LIGHTNESS_STEP);
+            hue += between(rng, (-HUE_STEP, HUE_STEP));
+        }
+        Palette { stops }
+    }
+
+    // Linear interpolation between the stops, `t` from 0 to 1.
+    pub fn color(&self, t: f64) -> [u8; 3] {
+        let position = t.clamp(0.0, 1.0) * (STOPS - 1) as f64;
+        let index = (position as usize).min(STOPS - 2);
+        let frac = position - index as f64;
+        let (a, b) = (self.stops[index], self.stops[index + 1]);
+        [0, 1, 2].map(|i| (a[i] as f64 + (b[i] as f64 - a[i] as f64) * frac).round() as u8)
+    }
+
+    // Comma separated `#rrggbb` stops.
+    pub fn parse(text: &str) -> Result<Palette, String> {
+        let stops = text
+            .split(',')
+            .map(|stop| parse_hex(stop.trim()))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Palette {
+            stops: stops.try_into().map_err(|stops: Vec<_>| {
+                format!("Expected {STOPS} palette stops, got {}", stops.len())
+            })?,
+        })
+    }
+
+    pub fn describe(&self) -> String {
+        self.stops.map(hex).join(" ")
+    }
+}
+
+impl ToJson for Palette {
+    fn to_json(&self) -> Json {
+        Json::Array(self.stops.map(|stop| Json::String(hex(stop))).to_vec())
+    }
+}
+
+impl FromJson for Palette {
+    fn from_json(json: &Json) -> Result<Self, String> {
+        let stops = json.as_array()?;
+        if stops.len() != STOPS {
+            return Err(format!(
+                "Expected {STOPS} palette stops, got {}",
+                stops.len()
+            ));
+        }
+        let mut palette = Palette {
+            stops: [[0; 3]; STOPS],
+        };
+        for (stop, json) in palette.stops.iter_mut().zip(stops) {
+            *stop = match json {
+                Json::String(text) => parse_hex(text)?,
+                other => return Err(format!("Invalid palette stop {other}")),
+            };
+        }
+        Ok(palette)
+    }
+}
+
+// A palettes file is a JSON object of named gradients, each an array of `#rrggbb` stops, e.g.
+// `{"sunset": ["#1a0533", "#5c1a5c", "#c2375a", "#f28b50", "#fde2a6"]}`.
+pub fn load(path: &Path) -> Result<Vec<(String, Palette)>, String> {
+    let text = std::fs::read_to_string(path).map_err(|e| format!("{}: {e}", path.display()))?;
+    match Json::parse(&text).map_err(|e| format!("{}: {e}", path.display()))? {
+        Json::Object(fields) => fields
+            .iter()
+            .map(|(name, stops)| {
+                Palette::from_json(stops)
+                    .map(|palette| (name.clone(), palette))
+                    .map_err(|e| format!("{}: palette {name}: {e}", path.display()))
+            })
+            .collect(),
+        other => Err(format!(
+            "{}: expected an object of named palettes, got {other}",
+            path.display()
+        )),
+    }
+}
+
+// Gradient of the escape-time coloring, the built-in one without a palette.
+pub fn shade(palette: Option<Palette>, t: f64) -> [u8; 3] {
+    match palette {
+        Some(palette) => palette.color(t),
+        None => {
+            let c = (t.clamp(0.0, 1.0) * 255.0) as u8;
+            [c / 2, c, c]
+        }
+    }
+}
+
+fn hex([r, g, b]: [u8; 3]) -> String {
+    format!("#{r:02x}{g:02x}{b:02x}")
+}
+
+fn parse_hex(text: &str) -> Result<[u8; 3], String> {
+    let digits = text
+        .strip_prefix('#')
+        .filter(|digits| digits.len() == 6 && digits.is_ascii())
+        .ok_or_else(|| format!("Invalid color {text}"))?;
+    let channel = |i: usize| {
+        u8::from_str_radix(&digits[i..i + 2], 16).map_err(|_| format!("Invalid color {text}"))
+    };
+    Ok([channel(0)?, channel(2)?, channel(4)?])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn random_palettes_get_lighter() {
+        assert_eq!(lch_to_rgb(0.0, 0.0, 0.0), [0, 0, 0]);
+        assert_eq!(lch_to_rgb(100.0, 0.0, 0.0), [255, 255, 255]);
+        let mut rng = SplitMix64::new(7);
+        for _ in 0..20 {
+            let palette = Palette::random(&mut rng);
+            let luma =
+                |[r, g, b]: [u8; 3]| 0.2126 * r as f64 + 0.7152 * g as f64 + 0.0722 * b as f64;
+            assert!(luma(palette.stops[0]) < luma(palette.stops[STOPS - 1]));
+            assert_eq!(palette.color(0.0), palette.stops[0]);
+            assert_eq!(palette.color(1.0), palette.stops[STOPS - 1]);
+            assert_eq!(Palette::from_json(&palette.to_json()), Ok(palette));
+            let listed = palette.describe().replace(' ', ",");
+            assert_eq!(Palette::parse(&listed), Ok(palette));
+        }
+        assert!(Palette::parse("#000000,#ffffff").is_err());
+    }
+
+    #[test]
+    fn loads_named_palettes() {
+        let path = std::env::temp_dir().join(format!("mandelbrot-palettes-{}", std::process::id()));
+        let fire = NAMED[0].1.describe().replace(' ', "\", \"");
+        std::fs::write(&path, format!("{{\"mine\": [\"{fire}\"]}}")).unwrap();
+        assert_eq!(load(&path), Ok(vec![("mine".to_string(), NAMED[0].1)]));
+        std::fs::write(&path, "{\"short\": [\"#000000\"]}").unwrap();
+        assert!(load(&path).unwrap_err().contains("palette short"));
+        std::fs::write(&path, "[]").unwrap();
+        assert!(load(&path).is_err());
+        std::fs::remove_file(&path).unwrap();
+    }
+}
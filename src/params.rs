@@ -0,0 +1,882 @@
+use crate::buddhabrot::Orbits;
+use crate::formula;
+use crate::json::{FromJson, Json, ToJson};
+use crate::palette::Palette;
+use crate::postprocess::{Bloom, Effect};
+use crate::tonemap::{ToneMap, ToneOperator};
+use crate::viewport::Viewport;
+use num::complex::Complex;
+use std::hash::{Hash, Hasher};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Fractal {
+    // Orbits from `z0`, the critical point 0 in the classic set. Other starting values give
+    // generalized sets, no longer connected nor symmetric.
+    Mandelbrot {
+        z0: Complex<f64>,
+    },
+    Julia {
+        c: Complex<f64>,
+    },
+    // Density of escaping Mandelbrot orbits rather than an escape-time image.
+    Buddhabrot {
+        samples_per_pixel: u32,
+    },
+    // Mandelbrot orbits folded into the first quadrant before every squaring.
+    BurningShip,
+    // Mandelbrot orbits conjugated before every squaring, the Mandelbar set.
+    Tricorn,
+    // Orbits of z^exponent + c from 0, with exponent - 1 fold symmetry.
+    Multibrot {
+        exponent: u32,
+    },
+    // Orbits of a formula of z, c and p typed by the user, the index of `formula::get`, from the
+    // `critical`th of its critical points. The points of the image are the parameter of
+    // `plane`, the other one is `fixed`.
+    Formula {
+        index: u32,
+        critical: u32,
+        plane: Plane,
+        fixed: Complex<f64>,
+    },
+}
+
+// The parameter of a formula the image is a plane of.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Plane {
+    C,
+    P,
+}
+
+impl Plane {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Plane::C => "c",
+            Plane::P => "p",
+        }
+    }
+
+    pub fn other(&self) -> Plane {
+        match self {
+            Plane::C => Plane::P,
+            Plane::P => Plane::C,
+        }
+    }
+
+    // `c` and `p` of a point of the plane, the other parameter fixed.
+    pub fn parameters(
+        &self,
+        point: Complex<f64>,
+        fixed: Complex<f64>,
+    ) -> (Complex<f64>, Complex<f64>) {
+        match self {
+            Plane::C => (point, fixed),
+            Plane::P => (fixed, point),
+        }
+    }
+}
+
+impl Fractal {
+    pub const MANDELBROT: Fractal = Fractal::Mandelbrot {
+        z0: Complex::new(0.0, 0.0),
+    };
+
+    // The starting value and the constant of the orbit of a point of the image.
+    pub fn start(&self, point: Complex<f64>) -> (Complex<f64>, Complex<f64>) {
+        match *self {
+            Fractal::Mandelbrot { z0 } => (z0, point),
+            Fractal::Julia { c } => (point, c),
+            Fractal::Buddhabrot { .. }
+            | Fractal::BurningShip
+            | Fractal::Tricorn
+            | Fractal::Multibrot { .. } => (Complex::new(0.0, 0.0), point),
+            Fractal::Formula {
+                index,
+                critical,
+                plane,
+                fixed,
+            } => {
+                let (c, p) = plane.parameters(point, fixed);
+                (formula::get(index).critical_point(c, p, critical), point)
+            }
+        }
+    }
+
+    // Iterated as z^2 + c without any fold, as the vector kernels and perturbation assume.
+    pub fn quadratic(&self) -> bool {
+        matches!(
+            self,
+            Fractal::Mandelbrot { .. } | Fractal::Julia { .. } | Fractal::Buddhabrot { .. }
+        )
+    }
+
+    // The power of z in the iteration.
+    pub fn degree(&self) -> u32 {
+        match self {
+            Fractal::Multibrot { exponent } => *exponent,
+            _ => 2,
+        }
+    }
+
+    // The escape-time fractals of the plane of the Mandelbrot set by the kind of their JSON,
+    // Multibrot sets with the given exponent.
+    pub fn from_name(name: &str, exponent: u32) -> Option<Fractal> {
+        match name {
+            "mandelbrot" => Some(Fractal::MANDELBROT),
+            "burning-ship" => Some(Fractal::BurningShip),
+            "tricorn" => Some(Fractal::Tricorn),
+            "multibrot" => Some(Fractal::Multibrot { exponent }),
+            _ => None,
+        }
+    }
+
+    // The next value of an orbit, `c` the point of the image as `start` returns it.
+    pub fn step(&self, z: Complex<f64>, c: Complex<f64>) -> Complex<f64> {
+        match self {
+            Fractal::BurningShip => {
+                let z = Complex::new(z.re.abs(), z.im.abs());
+                z * z + c
+            }
+            Fractal::Tricorn => {
+                let z = z.conj();
+                z * z + c
+            }
+            Fractal::Multibrot { exponent } => z.powu(*exponent) + c,
+            Fractal::Formula {
+                index,
+                plane,
+                fixed,
+                ..
+            } => {
+                let (c, p) = plane.parameters(c, *fixed);
+                formula::get(*index).eval(z, c, p)
+            }
+            _ => z * z + c,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Coloring {
+    EscapeTime,
+    // Escape time continued between the iterations by the normalized iteration count, without
+    // the bands at every iteration boundary.
+    Smooth,
+    // Escape time of the points certified by ball arithmetic, the rest flagged.
+    Certified,
+    // Escape time darkened in bands of the smooth iteration count: every `period` iterations
+    // a band starts, lit for the `duty` fraction of it.
+    Stripes { period: u32, duty: f64 },
+    // Escape time darkened where the last orbit value lies in the lower half-plane.
+    BinaryDecomposition,
+    // Escape time crossed by the boundaries of the iteration bands and by rays of the argument
+    // of the last orbit value, which join up into the field lines of the exterior.
+    FieldLines,
+    // Interior points colored by the period of the cycle their orbit settles on, which is
+    // that of the hyperbolic component they belong to.
+    Period,
+}
+
+impl Coloring {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Coloring::EscapeTime => "escape-time",
+            Coloring::Smooth => "smooth",
+            Coloring::Certified => "certified",
+            Coloring::Stripes { .. } => "stripes",
+            Coloring::BinaryDecomposition => "binary-decomposition",
+            Coloring::FieldLines => "field-lines",
+            Coloring::Period => "period",
+        }
+    }
+
+    // A name followed by its parameters, e.g. `stripes 4 0.5`.
+    pub fn parse(args: &[&str]) -> Result<Coloring, String> {
+        let number = |text: &str| {
+            text.parse::<f64>()
+                .map_err(|e| format!("Invalid coloring parameter {text}: {e}"))
+        };
+        match args {
+            ["escape-time"] => Ok(Coloring::EscapeTime),
+            ["smooth"] => Ok(Coloring::Smooth),
+            ["certified"] => Ok(Coloring::Certified),
+            ["binary-decomposition"] => Ok(Coloring::BinaryDecomposition),
+            ["field-lines"] => Ok(Coloring::FieldLines),
+            ["period"] => Ok(Coloring::Period),
+            ["stripes", period, duty] => {
+                let period = match number(period)? {
+                    n if n >= 1.0 && n <= u32::MAX as f64 && n.fract() == 0.0 => n as u32,
+                    n => return Err(format!("Stripe period {n} is not a positive integer")),
+                };
+                let duty = match number(duty)? {
+                    d if d > 0.0 && d < 1.0 => d,
+                    d => return Err(format!("Stripe duty {d} outside 0 to 1")),
+                };
+                Ok(Coloring::Stripes { period, duty })
+            }
+            _ => Err(format!(
+                "Unknown coloring {}, expected escape-time, smooth, certified, \
+                 binary-decomposition, field-lines, period or stripes PERIOD DUTY",
+                args.join(" ")
+            )),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Backend {
+    Cpu,
+    Gpu,
+}
+
+impl Backend {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Backend::Cpu => "cpu",
+            Backend::Gpu => "gpu",
+        }
+    }
+}
+
+// Everything needed to reproduce a single frame. Values are immutable once built; derive a
+// modified scene through `to_builder()`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RenderParams {
+    pub fractal: Fractal,
+    pub viewport: Viewport,
+    pub width: u32,
+    pub height: u32,
+    pub iterations: u32,
+    pub coloring: Coloring,
+    // Coloring of the points inside the set, composited with `coloring` of the exterior. With
+    // `None` the interior is left to `coloring` too.
+    pub interior: Option<Coloring>,
+    // Gradient of the exterior, the built-in one if `None`.
+    pub palette: Option<Palette>,
+    // Supersampling grid per pixel axis, 1 disables anti-aliasing.
+    pub antialias: u32,
+    pub effect: Option<Effect>,
+    pub bloom: Option<Bloom>,
+    pub tonemap: ToneMap,
+    // The orbits a Buddhabrot accumulates, by their lengths.
+    pub orbits: Orbits,
+    pub backend: Backend,
+}
+
+pub struct RenderParamsBuilder {
+    params: RenderParams,
+}
+
+impl Default for RenderParams {
+    fn default() -> Self {
+        RenderParams {
+            fractal: Fractal::MANDELBROT,
+            viewport: Viewport::default(),
+            width: 800,
+            height: 600,
+            iterations: 200,
+            coloring: Coloring::EscapeTime,
+            interior: None,
+            palette: None,
+            antialias: 1,
+            effect: None,
+            bloom: None,
+            tonemap: ToneMap::default(),
+            orbits: Orbits::ALL,
+            backend: Backend::Cpu,
+        }
+    }
+}
+
+impl RenderParams {
+    pub fn builder() -> RenderParamsBuilder {
+        RenderParams::default().to_builder()
+    }
+
+    pub fn to_builder(self) -> RenderParamsBuilder {
+        RenderParamsBuilder { params: self }
+    }
+
+    // Stable across runs and platforms (FNV-1a over the canonical JSON form), so it can be
+    // used to key persistent caches.
+    pub fn content_hash(&self) -> u64 {
+        self.to_json().content_hash()
+    }
+}
+
+impl Eq for RenderParams {}
+
+impl Hash for RenderParams {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        state.write_u64(self.content_hash());
+    }
+}
+
+impl RenderParamsBuilder {
+    pub fn fractal(mut self, fractal: Fractal) -> Self {
+        self.params.fractal = fractal;
+        self
+    }
+
+    pub fn viewport(mut self, viewport: Viewport) -> Self {
+        self.params.viewport = viewport;
+        self
+    }
+
+    pub fn size(mut self, width: u32, height: u32) -> Self {
+        self.params.width = width;
+        self.params.height = height;
+        self
+    }
+
+    pub fn iterations(mut self, iterations: u32) -> Self {
+        self.params.iterations = iterations;
+        self
+    }
+
+    pub fn coloring(mut self, coloring: Coloring) -> Self {
+        self.params.coloring = coloring;
+        self
+    }
+
+    pub fn interior(mut self, interior: Option<Coloring>) -> Self {
+        self.params.interior = interior;
+        self
+    }
+
+    pub fn palette(mut self, palette: Option<Palette>) -> Self {
+        self.params.palette = palette;
+        self
+    }
+
+    pub fn antialias(mut self, antialias: u32) -> Self {
+        self.params.antialias = antialias;
+        self
+    }
+
+    pub fn effect(mut self, effect: Option<Effect>) -> Self {
+        self.params.effect = effect;
+        self
+    }
+
+    pub fn bloom(mut self, bloom: Option<Bloom>) -> Self {
+        self.params.bloom = bloom;
+        self
+    }
+
+    pub fn tonemap(mut self, tonemap: ToneMap) -> Self {
+        self.params.tonemap = tonemap;
+        self
+    }
+
+    pub fn orbits(mut self, orbits: Orbits) -> Self {
+        self.params.orbits = orbits;
+        self
+    }
+
+    pub fn backend(mut self, backend: Backend) -> Self {
+        self.params.backend = backend;
+        self
+    }
+
+    pub fn build(self) -> Result<RenderParams, String> {
+        let params = self.params;
+        if params.width == 0 || params.height == 0 {
+            return Err(format!(
+                "Invalid render size {}x{}",
+                params.width, params.height
+            ));
+        }
+        if params.iterations == 0 {
+            return Err("Iteration count must be positive".to_string());
+        }
+        if let Some(bloom) = params.bloom {
+            if !(0.0..1.0).contains(&bloom.threshold) || bloom.strength < 0.0 {
+                return Err(format!("Invalid bloom settings {bloom:?}"));
+            }
+        }
+        if params.tonemap.exposure.is_nan() || params.tonemap.exposure <= 0.0 {
+            return Err("Exposure must be positive".to_string());
+        }
+        if let Fractal::Buddhabrot {
+            samples_per_pixel: 0,
+        } = params.fractal
+        {
+            return Err("Buddhabrot needs at least one sample per pixel".to_string());
+        }
+        if let Some(empty) = params
+            .orbits
+            .channels()
+            .iter()
+            .find(|lengths| lengths.min > lengths.max)
+        {
+            return Err(format!("Empty orbit lengths {}:{}", empty.min, empty.max));
+        }
+        if let Fractal::Multibrot { exponent: 0 | 1 } = params.fractal {
+            return Err("Multibrot sets need an exponent of at least 2".to_string());
+        }
+        for coloring in [Some(params.coloring), params.interior]
+            .into_iter()
+            .flatten()
+        {
+            let Coloring::Stripes { period, duty } = coloring else {
+                continue;
+            };
+            if period == 0 || !(duty > 0.0 && duty < 1.0) {
+                return Err(format!(
+                    "Stripes need a positive period and a duty between 0 and 1, got {period} {duty}"
+                ));
+            }
+        }
+        if !(1..=8).contains(&params.antialias) {
+            return Err(format!(
+                "Anti-aliasing factor {} is outside 1..=8",
+                params.antialias
+            ));
+        }
+        Ok(params)
+    }
+}
+
+impl ToJson for Fractal {
+    fn to_json(&self) -> Json {
+        match self {
+            // The classic set is written without z0, as before it could be changed.
+            Fractal::Mandelbrot { .. } if *self == Fractal::MANDELBROT => {
+                Json::object([("kind", Json::String("mandelbrot".to_string()))])
+            }
+            Fractal::Mandelbrot { z0 } => Json::object([
+                ("kind", Json::String("mandelbrot".to_string())),
+                ("z0_re", Json::Number(z0.re)),
+                ("z0_im", Json::Number(z0.im)),
+            ]),
+            Fractal::Julia { c } => Json::object([
+                ("kind", Json::String("julia".to_string())),
+                ("c_re", Json::Number(c.re)),
+                ("c_im", Json::Number(c.im)),
+            ]),
+            Fractal::Buddhabrot { samples_per_pixel } => Json::object([
+                ("kind", Json::String("buddhabrot".to_string())),
+                ("samples_per_pixel", Json::Number(*samples_per_pixel as f64)),
+            ]),
+            Fractal::BurningShip => {
+                Json::object([("kind", Json::String("burning-ship".to_string()))])
+            }
+            Fractal::Tricorn => Json::object([("kind", Json::String("tricorn".to_string()))]),
+            Fractal::Multibrot { exponent } => Json::object([
+                ("kind", Json::String("multibrot".to_string())),
+                ("exponent", Json::Number(*exponent as f64)),
+            ]),
+            Fractal::Formula {
+                index,
+                critical,
+                plane,
+                fixed,
+            } => {
+                let mut fields = vec![
+                    ("kind", Json::String("formula".to_string())),
+                    ("formula", Json::String(formula::get(*index).text.clone())),
+                ];
+                if *critical > 0 {
+                    fields.push(("critical", Json::Number(*critical as f64)));
+                }
+                // The value of the parameter that is not the plane, p of the c-plane by default.
+                if *plane == Plane::P {
+                    fields.push(("plane", Json::String(plane.name().to_string())));
+                }
+                if *plane == Plane::P || *fixed != Complex::new(0.0, 0.0) {
+                    let (re, im) = match plane {
+                        Plane::C => ("p_re", "p_im"),
+                        Plane::P => ("c_re", "c_im"),
+                    };
+                    fields.push((re, Json::Number(fixed.re)));
+                    fields.push((im, Json::Number(fixed.im)));
+                }
+                Json::object(fields)
+            }
+        }
+    }
+}
+
+impl FromJson for Fractal {
+    fn from_json(json: &Json) -> Result<Self, String> {
+        match json.field("kind")? {
+            Json::String(kind) if kind == "mandelbrot" => Ok(Fractal::Mandelbrot {
+                z0: match (json.get("z0_re"), json.get("z0_im")) {
+                    (Some(re), Some(im)) => Complex::new(re.as_f64()?, im.as_f64()?),
+                    _ => Complex::new(0.0, 0.0),
+                },
+            }),
+            Json::String(kind) if kind == "julia" => Ok(Fractal::Julia {
+                c: Complex::new(json.field("c_re")?.as_f64()?, json.field("c_im")?.as_f64()?),
+            }),
+            Json::String(kind) if kind == "buddhabrot" => Ok(Fractal::Buddhabrot {
+                samples_per_pixel: json.field("samples_per_pixel")?.as_f64()? as u32,
+            }),
+            Json::String(kind) if kind == "burning-ship" => Ok(Fractal::BurningShip),
+            Json::String(kind) if kind == "tricorn" => Ok(Fractal::Tricorn),
+            Json::String(kind) if kind == "multibrot" => Ok(Fractal::Multibrot {
+                exponent: json.field("exponent")?.as_f64()? as u32,
+            }),
+            Json::String(kind) if kind == "formula" => match json.field("formula")? {
+                Json::String(text) => {
+                    let Fractal::Formula { index, .. } = formula::fractal(text)? else {
+                        unreachable!("formula::fractal returns formulas")
+                    };
+                    let plane = match json.get("plane") {
+                        Some(Json::String(name)) if name == "p" => Plane::P,
+                        Some(Json::String(name)) if name == "c" => Plane::C,
+                        Some(other) => return Err(format!("Unknown plane {other}")),
+                        None => Plane::C,
+                    };
+                    let (re, im) = match plane {
+                        Plane::C => ("p_re", "p_im"),
+                        Plane::P => ("c_re", "c_im"),
+                    };
+                    Ok(Fractal::Formula {
+                        index,
+                        critical: match json.get("critical") {
+                            Some(critical) => critical.as_f64()? as u32,
+                            None => 0,
+                        },
+                        plane,
+                        fixed: match (json.get(re), json.get(im)) {
+                            (Some(re), Some(im)) => Complex::new(re.as_f64()?, im.as_f64()?),
+                            _ => Complex::new(0.0, 0.0),
+                        },
+                    })
+                }
+                other => Err(format!("Expected the text of a formula, got {other}")),
+            },
+            other => Err(format!("Unknown fractal {other}")),
+        }
+    }
+}
+
+// Colorings without parameters are stored by name, the others as objects of their parameters.
+impl ToJson for Coloring {
+    fn to_json(&self) -> Json {
+        match self {
+            Coloring::Stripes { period, duty } => Json::object([
+                ("kind", Json::String(self.name().to_string())),
+                ("period", Json::Number(*period as f64)),
+                ("duty", Json::Number(*duty)),
+            ]),
+            _ => Json::String(self.name().to_string()),
+        }
+    }
+}
+
+impl FromJson for Coloring {
+    fn from_json(json: &Json) -> Result<Self, String> {
+        match json {
+            Json::String(name) => Coloring::parse(&[name]),
+            Json::Object(_) => match json.field("kind")? {
+                Json::String(kind) if kind == "stripes" => Ok(Coloring::Stripes {
+                    period: json.field("period")?.as_f64()? as u32,
+                    duty: json.field("duty")?.as_f64()?,
+                }),
+                other => Err(format!("Unknown coloring {other}")),
+            },
+            other => Err(format!("Unknown coloring {other}")),
+        }
+    }
+}
+
+impl ToJson for RenderParams {
+    fn to_json(&self) -> Json {
+        let mut fields = vec![
+            ("fractal", self.fractal.to_json()),
+            ("viewport", self.viewport.to_json()),
+            ("width", Json::Number(self.width as f64)),
+            ("height", Json::Number(self.height as f64)),
+            ("iterations", Json::Number(self.iterations as f64)),
+            ("coloring", self.coloring.to_json()),
+            (
+                "interior",
+                match self.interior {
+                    Some(interior) => interior.to_json(),
+                    None => Json::Null,
+                },
+            ),
+            (
+                "palette",
+                match self.palette {
+                    Some(palette) => palette.to_json(),
+                    None => Json::Null,
+                },
+            ),
+            ("antialias", Json::Number(self.antialias as f64)),
+            (
+                "effect",
+                match self.effect {
+                    Some(effect) => Json::String(effect.name().to_string()),
+                    None => Json::Null,
+                },
+            ),
+            (
+                "bloom",
+                match self.bloom {
+                    Some(bloom) => Json::object([
+                        ("threshold", Json::Number(bloom.threshold as f64)),
+                        ("radius", Json::Number(bloom.radius as f64)),
+                        ("strength", Json::Number(bloom.strength as f64)),
+                    ]),
+                    None => Json::Null,
+                },
+            ),
+            (
+                "tonemap",
+                Json::object([
+                    (
+                        "operator",
+                        Json::String(self.tonemap.operator.name().to_string()),
+                    ),
+                    ("exposure", Json::Number(self.tonemap.exposure as f64)),
+                ]),
+            ),
+            ("backend", Json::String(self.backend.name().to_string())),
+        ];
+        // Without a filter of the orbits, unchanged from before they could be filtered.
+        if self.orbits != Orbits::ALL {
+            fields.push(("orbits", self.orbits.to_json()));
+        }
+        Json::object(fields)
+    }
+}
+
+impl FromJson for RenderParams {
+    fn from_json(json: &Json) -> Result<Self, String> {
+        let coloring = Coloring::from_json(json.field("coloring")?)?;
+        let backend = match json.field("backend")? {
+            Json::String(name) if name == "cpu" => Backend::Cpu,
+            Json::String(name) if name == "gpu" => Backend::Gpu,
+            other => return Err(format!("Unknown backend {other}")),
+        };
+        let effect = match json.get("effect") {
+            None | Some(Json::Null) => None,
+            Some(Json::String(name)) => {
+                Some(Effect::from_name(name).ok_or_else(|| format!("Unknown effect {name}"))?)
+            }
+            Some(other) => return Err(format!("Invalid effect {other}")),
+        };
+        let interior = match json.get("interior") {
+            None | Some(Json::Null) => None,
+            Some(interior) => Some(Coloring::from_json(interior)?),
+        };
+        let palette = match json.get("palette") {
+            None | Some(Json::Null) => None,
+            Some(palette) => Some(Palette::from_json(palette)?),
+        };
+        let bloom = match json.get("bloom") {
+            None | Some(Json::Null) => None,
+            Some(bloom) => Some(Bloom {
+                threshold: bloom.field("threshold")?.as_f64()? as f32,
+                radius: bloom.field("radius")?.as_f64()? as u32,
+                strength: bloom.field("strength")?.as_f64()? as f32,
+            }),
+        };
+        let tonemap = match json.get("tonemap") {
+            None => ToneMap::default(),
+            Some(tonemap) => ToneMap {
+                operator: match tonemap.field("operator")? {
+                    Json::String(name) => ToneOperator::from_name(name)
+                        .ok_or_else(|| format!("Unknown tone operator {name}"))?,
+                    other => return Err(format!("Invalid tone operator {other}")),
+                },
+                exposure: tonemap.field("exposure")?.as_f64()? as f32,
+            },
+        };
+        let orbits = match json.get("orbits") {
+            None => Orbits::ALL,
+            Some(orbits) => Orbits::from_json(orbits)?,
+        };
+        RenderParams::builder()
+            .fractal(Fractal::from_json(json.field("fractal")?)?)
+            .viewport(Viewport::from_json(json.field("viewport")?)?)
+            .size(
+                json.field("width")?.as_f64()? as u32,
+                json.field("height")?.as_f64()? as u32,
+            )
+            .iterations(json.field("iterations")?.as_f64()? as u32)
+            .coloring(coloring)
+            .interior(interior)
+            .palette(palette)
+            .antialias(json.field("antialias")?.as_f64()? as u32)
+            .effect(effect)
+            .bloom(bloom)
+            .tonemap(tonemap)
+            .orbits(orbits)
+            .backend(backend)
+            .build()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builder_validates() {
+        assert!(RenderParams::builder().size(0, 10).build().is_err());
+        assert!(RenderParams::builder().iterations(0).build().is_err());
+        assert!(RenderParams::builder().antialias(9).build().is_err());
+        let stripes = Coloring::Stripes {
+            period: 4,
+            duty: 1.5,
+        };
+        assert!(RenderParams::builder().coloring(stripes).build().is_err());
+        for period in ["0", "-4", "2.5"] {
+            assert!(
+                Coloring::parse(&["stripes", period, "0.5"]).is_err(),
+                "{period}"
+            );
+        }
+        for duty in ["0", "1", "1.5", "NaN"] {
+            assert!(Coloring::parse(&["stripes", "4", duty]).is_err(), "{duty}");
+        }
+        assert_eq!(
+            Coloring::parse(&["stripes", "4", "0.25"]),
+            Ok(Coloring::Stripes {
+                period: 4,
+                duty: 0.25
+            })
+        );
+        assert!(Coloring::parse(&["plaid"]).is_err());
+        for coloring in [
+            Coloring::Smooth,
+            Coloring::BinaryDecomposition,
+            Coloring::FieldLines,
+            Coloring::Period,
+        ] {
+            assert_eq!(Coloring::parse(&[coloring.name()]), Ok(coloring));
+        }
+        assert_eq!(RenderParams::builder().build(), Ok(RenderParams::default()));
+    }
+
+    #[test]
+    fn json_round_trip() {
+        let params = RenderParams::builder()
+            .fractal(Fractal::Julia {
+                c: Complex::new(-0.8, 0.156),
+            })
+            .viewport(Viewport::new(Complex::new(-0.75, 0.1), 0.01, 0.0075))
+            .size(320, 240)
+            .iterations(1234)
+            .coloring(Coloring::Certified)
+            .interior(Some(Coloring::Period))
+            .palette(Some(Palette {
+                stops: [
+                    [0, 0, 0],
+                    [40, 10, 80],
+                    [200, 40, 60],
+                    [250, 180, 30],
+                    [255, 255, 240],
+                ],
+            }))
+            .antialias(3)
+            .effect(Some(Effect::FlowStreaks))
+            .bloom(Some(Bloom::default()))
+            .tonemap(ToneMap {
+                operator: ToneOperator::Reinhard,
+                exposure: 2.5,
+            })
+            .orbits(Orbits::parse("20:,:500,:50").unwrap())
+            .build()
+            .unwrap();
+        let text = params.to_json().to_string();
+        assert_eq!(
+            RenderParams::from_json(&Json::parse(&text).unwrap()),
+            Ok(params)
+        );
+        let stripes = Coloring::Stripes {
+            period: 3,
+            duty: 0.6,
+        };
+        assert_eq!(Coloring::from_json(&stripes.to_json()), Ok(stripes));
+        let generalized = Fractal::Mandelbrot {
+            z0: Complex::new(0.25, -0.5),
+        };
+        assert_eq!(Fractal::from_json(&generalized.to_json()), Ok(generalized));
+        for fractal in [
+            Fractal::BurningShip,
+            Fractal::Tricorn,
+            Fractal::Multibrot { exponent: 5 },
+            crate::formula::fractal("z^3 + c*z + c").unwrap(),
+            match crate::formula::fractal("z^3 + p*z + c").unwrap() {
+                Fractal::Formula { index, .. } => Fractal::Formula {
+                    index,
+                    critical: 1,
+                    plane: Plane::P,
+                    fixed: Complex::new(-0.5, 0.25),
+                },
+                other => other,
+            },
+        ] {
+            assert_eq!(Fractal::from_json(&fractal.to_json()), Ok(fractal));
+        }
+        assert!(RenderParams::builder()
+            .fractal(Fractal::Multibrot { exponent: 1 })
+            .build()
+            .is_err());
+        // Manifests of the classic set are unchanged by the starting value.
+        assert_eq!(
+            Fractal::MANDELBROT.to_json().to_string(),
+            r#"{"kind":"mandelbrot"}"#
+        );
+    }
+
+    #[test]
+    fn content_hash_tracks_every_field() {
+        let base = RenderParams::default();
+        let same = RenderParams::builder().build().unwrap();
+        assert_eq!(base.content_hash(), same.content_hash());
+        let variants = [
+            base.to_builder().iterations(201).build().unwrap(),
+            base.to_builder().size(800, 601).build().unwrap(),
+            base.to_builder().antialias(2).build().unwrap(),
+            base.to_builder()
+                .coloring(Coloring::Certified)
+                .build()
+                .unwrap(),
+            base.to_builder()
+                .coloring(Coloring::Stripes {
+                    period: 2,
+                    duty: 0.5,
+                })
+                .build()
+                .unwrap(),
+            base.to_builder()
+                .interior(Some(Coloring::Period))
+                .build()
+                .unwrap(),
+            base.to_builder()
+                .fractal(Fractal::Mandelbrot {
+                    z0: Complex::new(0.0, 0.1),
+                })
+                .build()
+                .unwrap(),
+            base.to_builder()
+                .palette(Some(Palette {
+                    stops: [[0, 0, 0]; crate::palette::STOPS],
+                }))
+                .build()
+                .unwrap(),
+            base.to_builder()
+                .effect(Some(Effect::DomainShading))
+                .build()
+                .unwrap(),
+            base.to_builder()
+                .viewport(Viewport::new(Complex::new(1e-300, 0.0), 4.0, 3.0))
+                .build()
+                .unwrap(),
+            base.to_builder()
+                .orbits(Orbits::parse("10:").unwrap())
+                .build()
+                .unwrap(),
+        ];
+        for variant in variants {
+            assert_ne!(variant.content_hash(), base.content_hash());
+        }
+    }
+}
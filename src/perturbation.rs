@@ -0,0 +1,478 @@
+use crate::dd;
+use crate::fixed::{Fixed, CENTER_BITS};
+use crate::params::Fractal;
+use crate::render::Sample;
+use crate::scheduler::Precision;
+use crate::viewport::Viewport;
+use num::complex::Complex;
+use std::sync::{Arc, Mutex};
+
+// A pixel whose orbit comes this close to zero relative to the reference orbit (squared) has
+// lost the precision of its delta, the criterion of Pauldelbrot.
+const GLITCH: f64 = 1e-6;
+// Series approximation stays in use while it matches the probe orbits to this relative error.
+const SERIES_TOLERANCE: f64 = 1e-9;
+// Further references tried for the glitched pixels of a batch before iterating the rest on
+// their own.
+const MAX_REFERENCES: usize = 8;
+// Fraction bits of fixed-point orbits beyond those of the height of the view, for the rounding
+// errors the orbit amplifies.
+const GUARD_BITS: u64 = 64;
+// How much deeper than the view it was computed for a cached reference is still used for. Its
+// series was checked out to the corners of that view, deeper views could skip further.
+const MAX_REUSE_ZOOM: f64 = 16.0;
+
+// The reference of the latest deep view, kept for the views panned and zoomed around it.
+static CACHE: Mutex<Cache> = Mutex::new(Cache(None));
+
+// Perturbation rendering: the orbit of one reference point is computed in double-double, or in
+// fixed point for views beyond that, and the orbits of the pixels around it as their deltas to
+// it in f64,
+//
+//     d(n+1) = 2 Z(n) d(n) + d(n)^2 + dc,
+//
+// with dc the offset of the pixel from the reference for the Mandelbrot set, and the starting
+// delta the offset for Julia sets. The first iterations of the delta are skipped by its series
+// in the offset, d(n) = A(n) dc + B(n) dc^2 + C(n) dc^3, for as long as that matches the
+// deltas of probe points at the edge of the view.
+pub struct Reference {
+    fractal: Fractal,
+    precision: Precision,
+    // The view it was computed for and the offset of the reference point from its center.
+    view: Viewport,
+    offset: Complex<f64>,
+    iterations: u32,
+    // Of the probes the series was checked at from the reference point.
+    radius: f64,
+    // Its orbit Z(0), Z(1), ..., up to the escape or the iteration count.
+    orbit: Vec<Complex<f64>>,
+    julia: bool,
+    // Iterations skipped by the series and its coefficients there.
+    skip: usize,
+    series: [Complex<f64>; 3],
+}
+
+// The orbit of the point at `offset` from the center of `view` in double-double.
+fn dd_orbit(
+    fractal: Fractal,
+    view: &Viewport,
+    offset: Complex<f64>,
+    iterations: u32,
+) -> Vec<Complex<f64>> {
+    let (mut z, c) = dd::start(fractal, (view.center, view.center_low), offset);
+    let mut orbit = vec![dd::rounded(z)];
+    for _ in 0..iterations {
+        z = dd::step(z, c);
+        orbit.push(dd::rounded(z));
+        if orbit.last().unwrap().norm_sqr() > 4.0 {
+            break;
+        }
+    }
+    orbit
+}
+
+// The same in fixed point, with the bits of the height of the view and `GUARD_BITS` more.
+fn fixed_orbit(
+    fractal: Fractal,
+    view: &Viewport,
+    offset: Complex<f64>,
+    iterations: u32,
+) -> Vec<Complex<f64>> {
+    let bits = ((-view.height.log2()).max(0.0) as u64 + GUARD_BITS).min(CENTER_BITS);
+    let (re, im) = view.center_fixed(bits);
+    let point = (
+        &re + &Fixed::from_f64(offset.re, bits),
+        &im + &Fixed::from_f64(offset.im, bits),
+    );
+    let lift = |z: Complex<f64>| (Fixed::from_f64(z.re, bits), Fixed::from_f64(z.im, bits));
+    let (mut z, c) = match fractal {
+        Fractal::Mandelbrot { z0 } => (lift(z0), point),
+        Fractal::Julia { c } => (point, lift(c)),
+        _ => (lift(Complex::new(0.0, 0.0)), point),
+    };
+    let rounded = |(re, im): &(Fixed, Fixed)| Complex::new(re.to_f64(), im.to_f64());
+    let mut orbit = vec![rounded(&z)];
+    for _ in 0..iterations {
+        let (re, im) = &z;
+        let product = re * im;
+        z = (
+            &(&(re * re) - &(im * im)) + &c.0,
+            &(&product + &product) + &c.1,
+        );
+        orbit.push(rounded(&z));
+        if orbit.last().unwrap().norm_sqr() > 4.0 {
+            break;
+        }
+    }
+    orbit
+}
+
+impl Reference {
+    // The reference at `offset` from the center of `view`, its orbit computed in `precision`,
+    // double-double or fixed point.
+    pub fn new(
+        fractal: Fractal,
+        view: &Viewport,
+        precision: Precision,
+        offset: Complex<f64>,
+        iterations: u32,
+        probes: &[Complex<f64>],
+    ) -> Reference {
+        let orbit = match precision {
+            Precision::FixedPoint => fixed_orbit(fractal, view, offset, iterations),
+            _ => dd_orbit(fractal, view, offset, iterations),
+        };
+        let julia = matches!(fractal, Fractal::Julia { .. });
+        let radius = probes
+            .iter()
+            .map(|probe| (probe - offset).norm())
+            .fold(0.0, f64::max);
+        let mut reference = Reference {
+            fractal,
+            precision,
+            view: *view,
+            offset,
+            iterations,
+            radius,
+            orbit,
+            julia,
+            skip: 0,
+            series: [
+                Complex::new(julia as u8 as f64, 0.0),
+                Complex::new(0.0, 0.0),
+                Complex::new(0.0, 0.0),
+            ],
+        };
+        reference.approximate(probes);
+        reference
+    }
+
+    // Whether the orbit and the series are still valid for `view` with its corners at
+    // `corners`, of the same fractal and iterations and no more precise: the corners within the
+    // radius the series was checked at and the view not too much smaller than the one of the
+    // reference.
+    fn covers(
+        &self,
+        fractal: Fractal,
+        view: &Viewport,
+        precision: Precision,
+        iterations: u32,
+        corners: &[Complex<f64>],
+    ) -> bool {
+        let shift = view.displacement(&self.view);
+        let extent = corners.iter().map(|c| c.norm()).fold(0.0, f64::max);
+        self.fractal == fractal
+            && self.precision >= precision
+            && self.iterations == iterations
+            && extent * MAX_REUSE_ZOOM >= self.radius
+            && corners
+                .iter()
+                .all(|corner| (corner + shift - self.offset).norm() <= self.radius)
+    }
+
+    // The starting delta and the delta of the constant of the point at `offset`.
+    fn deltas(&self, offset: Complex<f64>) -> (Complex<f64>, Complex<f64>) {
+        let d = offset - self.offset;
+        match self.julia {
+            true => (d, Complex::new(0.0, 0.0)),
+            false => (Complex::new(0.0, 0.0), d),
+        }
+    }
+
+    fn series_at(series: &[Complex<f64>; 3], d: Complex<f64>) -> Complex<f64> {
+        ((series[2] * d + series[1]) * d + series[0]) * d
+    }
+
+    // Advances the series for as long as it predicts the deltas of the probes.
+    fn approximate(&mut self, probes: &[Complex<f64>]) {
+        if probes.is_empty() {
+            return;
+        }
+        let mut deltas = probes
+            .iter()
+            .map(|&probe| (probe - self.offset, self.deltas(probe)))
+            .map(|(d, (delta, dc))| (d, delta, dc))
+            .collect::<Vec<_>>();
+        let one = Complex::new(!self.julia as u8 as f64, 0.0);
+        for n in 0..self.orbit.len().saturating_sub(2) {
+            let z = self.orbit[n];
+            let [a, b, c] = self.series;
+            let next = [
+                2.0 * z * a + one,
+                2.0 * z * b + a * a,
+                2.0 * z * c + 2.0 * a * b,
+            ];
+            let mut matches = true;
+            for (d, delta, dc) in deltas.iter_mut() {
+                *delta = 2.0 * z * *delta + *delta * *delta + *dc;
+                let error = (Reference::series_at(&next, *d) - *delta).norm();
+                matches &= error <= SERIES_TOLERANCE * delta.norm()
+                    && (self.orbit[n + 1] + *delta).norm_sqr() <= 4.0;
+            }
+            if !matches {
+                break;
+            }
+            self.series = next;
+            self.skip = n + 1;
+        }
+    }
+
+    // The sample of the point at `offset`, or `None` when it glitched or outlived the
+    // reference orbit.
+    pub fn sample(&self, offset: Complex<f64>, iterations: u32) -> Option<Sample> {
+        let (start, dc) = self.deltas(offset);
+        let mut delta = match self.skip {
+            0 => start,
+            _ => Reference::series_at(&self.series, offset - self.offset),
+        };
+        let mut z = self.orbit[self.skip] + delta;
+        for n in self.skip..iterations as usize {
+            let next = *self.orbit.get(n + 1)?;
+            delta = 2.0 * self.orbit[n] * delta + delta * delta + dc;
+            z = next + delta;
+            let norm = z.norm_sqr();
+            if norm > 4.0 {
+                return Some(Sample {
+                    escape: Some(n as u32),
+                    z,
+                });
+            }
+            if norm < GLITCH * next.norm_sqr() {
+                return None;
+            }
+        }
+        Some(Sample { escape: None, z })
+    }
+
+    // The sample of the reference point itself, straight from its orbit.
+    fn own_sample(&self) -> Sample {
+        let z = *self.orbit.last().unwrap();
+        Sample {
+            escape: (z.norm_sqr() > 4.0).then(|| self.orbit.len() as u32 - 2),
+            z,
+        }
+    }
+}
+
+// Samples of the points at `offsets` from the center of `view`. Glitched points get a
+// reference of their own, and those still glitched after `MAX_REFERENCES` are iterated on
+// their own, in the precision of the references.
+pub fn escape_times(
+    fractal: Fractal,
+    view: &Viewport,
+    primary: &Reference,
+    offsets: &[Complex<f64>],
+    iterations: u32,
+) -> Vec<Sample> {
+    let precision = primary.precision;
+    let shift = view.displacement(&primary.view);
+    let mut samples = offsets
+        .iter()
+        .map(|&offset| primary.sample(offset + shift, iterations))
+        .collect::<Vec<_>>();
+    for _ in 0..MAX_REFERENCES {
+        let Some(glitched) = samples.iter().position(Option::is_none) else {
+            break;
+        };
+        let reference =
+            Reference::new(fractal, view, precision, offsets[glitched], iterations, &[]);
+        for (sample, &offset) in samples.iter_mut().zip(offsets) {
+            if sample.is_none() {
+                *sample = reference.sample(offset, iterations);
+            }
+        }
+    }
+    samples
+        .into_iter()
+        .zip(offsets)
+        .map(|(sample, &offset)| {
+            sample.unwrap_or_else(|| match precision {
+                Precision::FixedPoint => {
+                    Reference::new(fractal, view, precision, offset, iterations, &[]).own_sample()
+                }
+                _ => dd::escape_time(fractal, (view.center, view.center_low), offset, iterations),
+            })
+        })
+        .collect()
+}
+
+// Keeps the latest reference until a view it does not cover.
+pub struct Cache(Option<Arc<Reference>>);
+
+impl Cache {
+    // The reference for `view` in `precision`, the series checked at its `corners`.
+    pub fn reference(
+        &mut self,
+        fractal: Fractal,
+        view: &Viewport,
+        precision: Precision,
+        iterations: u32,
+        corners: &[Complex<f64>],
+    ) -> Arc<Reference> {
+        match &self.0 {
+            Some(reference) if reference.covers(fractal, view, precision, iterations, corners) => {
+                reference.clone()
+            }
+            _ => {
+                let offset = Complex::new(0.0, 0.0);
+                let reference =
+                    Reference::new(fractal, view, precision, offset, iterations, corners);
+                self.0.insert(Arc::new(reference)).clone()
+            }
+        }
+    }
+}
+
+// The reference of a deep view out of the cache shared by all frames.
+pub fn reference(
+    fractal: Fractal,
+    view: &Viewport,
+    precision: Precision,
+    iterations: u32,
+    corners: &[Complex<f64>],
+) -> Arc<Reference> {
+    CACHE
+        .lock()
+        .unwrap()
+        .reference(fractal, view, precision, iterations, corners)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CENTER: Complex<f64> = Complex::new(-0.743643887037151, 0.13182590420533);
+
+    #[test]
+    fn perturbed_orbits_match_double_double() {
+        let view = Viewport::new(CENTER, 2e-11, 1e-11);
+        let offsets = (0..200)
+            .map(|i| Complex::new((i % 20) as f64 - 9.5, (i / 20) as f64 - 4.5) * 1e-12)
+            .collect::<Vec<_>>();
+        let corners = [Complex::new(-10.0, -5.0), Complex::new(10.0, 5.0)].map(|c| c * 1e-12);
+        for fractal in [
+            Fractal::MANDELBROT,
+            Fractal::Julia {
+                c: Complex::new(-0.8, 0.156),
+            },
+        ] {
+            let origin = Complex::new(0.0, 0.0);
+            let precision = Precision::DoubleDouble;
+            let reference = Reference::new(fractal, &view, precision, origin, 3000, &corners);
+            if fractal == Fractal::MANDELBROT {
+                assert!(reference.skip > 10, "skipped {} iterations", reference.skip);
+            }
+            // The orbit in fixed point is the same, only rounded differently.
+            let fixed = fixed_orbit(fractal, &view, origin, 3000);
+            let prefix = reference.orbit.len().min(fixed.len()).min(200);
+            assert!((0..prefix).all(|n| (reference.orbit[n] - fixed[n]).norm() < 1e-12));
+            let perturbed = escape_times(fractal, &view, &reference, &offsets, 3000);
+            let center = (view.center, view.center_low);
+            let direct = offsets
+                .iter()
+                .map(|&offset| dd::escape_time(fractal, center, offset, 3000).escape)
+                .collect::<Vec<_>>();
+            let differing = perturbed
+                .iter()
+                .zip(&direct)
+                .filter(|(sample, escape)| sample.escape != **escape)
+                .count();
+            // Orbits this long amplify any rounding, a few near the boundary end differently.
+            assert!(differing <= 6, "{differing} of 200 differ");
+        }
+    }
+
+    #[test]
+    fn fixed_point_references_go_beyond_double_double() {
+        // A view ninety orders deep next to the Misiurewicz point i, off it by digits far beyond
+        // double-double.
+        let zeros = "0".repeat(89);
+        let part = |integer: u8, digits: &str| {
+            let text = format!("{integer}.{zeros}{digits}");
+            Fixed::parse(&text, CENTER_BITS).unwrap()
+        };
+        let re = part(
+            0,
+            "17320508075688772935274463415058723669428052538103806280558069794519",
+        );
+        let im = part(
+            1,
+            "26457513110645905905016157536392604257102591830824501803683344592010",
+        );
+        let mut view = Viewport::new(Complex::new(0.0, 0.0), 4e-90, 3e-90);
+        view.set_center(&re, &im);
+        let offsets = (0..64)
+            .map(|i| Complex::new((i % 8) as f64 - 3.5, (i / 8) as f64 - 3.5) * 4e-91)
+            .collect::<Vec<_>>();
+        let corners = [Complex::new(-2e-90, -1.5e-90), Complex::new(2e-90, 1.5e-90)];
+        let (fractal, precision) = (Fractal::MANDELBROT, Precision::FixedPoint);
+        let origin = Complex::new(0.0, 0.0);
+        let reference = Reference::new(fractal, &view, precision, origin, 2000, &corners);
+        let perturbed = escape_times(fractal, &view, &reference, &offsets, 2000);
+        let direct = offsets.iter().map(|&offset| {
+            Reference::new(fractal, &view, precision, offset, 2000, &[]).own_sample()
+        });
+        for (sample, direct) in perturbed.iter().zip(direct) {
+            assert_eq!(sample.escape, direct.escape);
+        }
+        // In double-double the pixels would all be the point i, here they tell apart.
+        let escapes = perturbed
+            .iter()
+            .map(|sample| sample.escape)
+            .collect::<Vec<_>>();
+        assert!(
+            escapes.contains(&Some(239)) && escapes.contains(&Some(240)),
+            "{escapes:?}"
+        );
+    }
+
+    #[test]
+    fn small_pans_reuse_the_reference() {
+        let corners = |half: f64| {
+            [(-1.0, -0.75), (1.0, -0.75), (-1.0, 0.75), (1.0, 0.75)]
+                .map(|(re, im)| Complex::new(re, im) * half)
+        };
+        let at = |re: f64| {
+            let mut view = Viewport::new(CENTER, 2e-12, 1.5e-12);
+            view.pan(Complex::new(re, 0.0));
+            view
+        };
+        let fractal = Fractal::MANDELBROT;
+        let precision = Precision::DoubleDouble;
+        let mut cache = Cache(None);
+        let first = cache.reference(fractal, &at(0.0), precision, 1000, &corners(1e-12));
+        // A tenth of the view to the side: the corners are still where the series was checked.
+        let panned = cache.reference(fractal, &at(2e-13), precision, 1000, &corners(0.8e-12));
+        assert!(Arc::ptr_eq(&first, &panned));
+        // Panned by its samples, the reused reference renders the view as a new one would.
+        let offsets = corners(0.5e-12);
+        let reused = escape_times(fractal, &at(2e-13), &panned, &offsets, 1000);
+        let origin = Complex::new(0.0, 0.0);
+        let fresh = Reference::new(fractal, &at(2e-13), precision, origin, 1000, &[]);
+        let direct = escape_times(fractal, &at(2e-13), &fresh, &offsets, 1000);
+        for (a, b) in reused.iter().zip(&direct) {
+            assert_eq!(a.escape, b.escape);
+        }
+        // Too far, too wide, too deep, other iterations or more precise: a new reference.
+        let rebuilt = [
+            cache.reference(fractal, &at(5e-12), precision, 1000, &corners(1e-12)),
+            cache.reference(fractal, &at(0.0), precision, 1000, &corners(4e-12)),
+            cache.reference(fractal, &at(0.0), precision, 1000, &corners(1e-14)),
+            cache.reference(fractal, &at(0.0), precision, 2000, &corners(1e-14)),
+            cache.reference(
+                fractal,
+                &at(0.0),
+                Precision::FixedPoint,
+                2000,
+                &corners(1e-14),
+            ),
+        ];
+        assert!(!rebuilt
+            .iter()
+            .any(|reference| Arc::ptr_eq(reference, &first)));
+        assert!(rebuilt.windows(2).all(|w| !Arc::ptr_eq(&w[0], &w[1])));
+        // A fixed-point reference serves the views double-double would do for as well.
+        let reused = cache.reference(fractal, &at(0.0), precision, 2000, &corners(1e-14));
+        assert!(Arc::ptr_eq(&reused, &rebuilt[4]));
+    }
+}
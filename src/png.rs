@@ -0,0 +1,260 @@
+use std::io::Write;
+use std::path::Path;
+
+// Largest payload of an uncompressed deflate block.
+const STORED_BLOCK: usize = 65535;
+// The signature and the IHDR chunk.
+const HEADER_END: usize = 33;
+
+fn crc32(bytes: &[u8]) -> u32 {
+    !crc32_update(!0, bytes)
+}
+
+fn crc32_update(crc: u32, bytes: &[u8]) -> u32 {
+    bytes.iter().fold(crc, |crc, &byte| {
+        (0..8).fold(crc ^ byte as u32, |crc, _| {
+            if crc & 1 == 1 {
+                (crc >> 1) ^ 0xedb88320
+            } else {
+                crc >> 1
+            }
+        })
+    })
+}
+
+fn adler32_update(sums: (u32, u32), bytes: &[u8]) -> (u32, u32) {
+    bytes.iter().fold(sums, |(a, b), &byte| {
+        let a = (a + byte as u32) % 65521;
+        (a, (b + a) % 65521)
+    })
+}
+
+fn chunk(png: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+    png.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    let start = png.len();
+    png.extend_from_slice(kind);
+    png.extend_from_slice(data);
+    let crc = crc32(&png[start..]);
+    png.extend_from_slice(&crc.to_be_bytes());
+}
+
+// Writes an 8-bit RGB image to `out` as its rows come, each batch of them in an IDAT chunk of
+// its own, so that images larger than the memory can be encoded band by band. The image data is
+// stored without compression, which keeps the encoder trivial; exported frames are few and
+// external tools can recompress them.
+pub struct Streamer<W: Write> {
+    out: W,
+    width: u32,
+    height: u32,
+    rows: u32,
+    adler: (u32, u32),
+}
+
+impl<W: Write> Streamer<W> {
+    pub fn new(mut out: W, width: u32, height: u32) -> Result<Self, String> {
+        let mut header = Vec::new();
+        header.extend_from_slice(&width.to_be_bytes());
+        header.extend_from_slice(&height.to_be_bytes());
+        // 8 bits per channel, truecolor, deflate, adaptive filtering, no interlace.
+        header.extend_from_slice(&[8, 2, 0, 0, 0]);
+        let mut png = b"\x89PNG\r\n\x1a\n".to_vec();
+        chunk(&mut png, b"IHDR", &header);
+        out.write_all(&png).map_err(|e| e.to_string())?;
+        Ok(Streamer {
+            out,
+            width,
+            height,
+            rows: 0,
+            adler: (1, 0),
+        })
+    }
+
+    // Appends whole rows, top to bottom.
+    pub fn write_rows(&mut self, rgb: &[u8]) -> Result<(), String> {
+        let line = self.width as usize * 3;
+        let rows = rgb.len().checked_div(line).unwrap_or(0) as u32;
+        if rgb.len() != rows as usize * line || self.rows + rows > self.height {
+            return Err(format!(
+                "{} bytes are not rows of the {} still missing",
+                rgb.len(),
+                self.height - self.rows
+            ));
+        }
+        // Every scanline starts with filter type 0 (none).
+        let raw = rgb
+            .chunks(line.max(1))
+            .flat_map(|line| std::iter::once(0).chain(line.iter().copied()))
+            .collect::<Vec<_>>();
+        self.adler = adler32_update(self.adler, &raw);
+        let mut zlib = match self.rows {
+            0 => vec![0x78, 0x01],
+            _ => Vec::new(),
+        };
+        self.rows += rows;
+        let last = self.rows == self.height;
+        let blocks = raw.chunks(STORED_BLOCK).collect::<Vec<_>>();
+        for (i, block) in blocks.iter().enumerate() {
+            zlib.push((last && i + 1 == blocks.len()) as u8);
+            let len = block.len() as u16;
+            zlib.extend_from_slice(&len.to_le_bytes());
+            zlib.extend_from_slice(&(!len).to_le_bytes());
+            zlib.extend_from_slice(block);
+        }
+        if last {
+            if blocks.is_empty() {
+                zlib.extend_from_slice(&[1, 0, 0, 0xff, 0xff]);
+            }
+            let (a, b) = self.adler;
+            zlib.extend_from_slice(&((b << 16) | a).to_be_bytes());
+        }
+        self.idat(&zlib)
+    }
+
+    fn idat(&mut self, zlib: &[u8]) -> Result<(), String> {
+        let crc = !crc32_update(crc32_update(!0, b"IDAT"), zlib);
+        let write = |out: &mut W| {
+            out.write_all(&(zlib.len() as u32).to_be_bytes())?;
+            out.write_all(b"IDAT")?;
+            out.write_all(zlib)?;
+            out.write_all(&crc.to_be_bytes())
+        };
+        write(&mut self.out).map_err(|e| e.to_string())
+    }
+
+    pub fn finish(mut self) -> Result<W, String> {
+        if self.rows == 0 && self.height == 0 {
+            self.write_rows(&[])?;
+        }
+        if self.rows != self.height {
+            return Err(format!(
+                "Only {} of {} rows written",
+                self.rows, self.height
+            ));
+        }
+        let mut end = Vec::new();
+        chunk(&mut end, b"IEND", &[]);
+        self.out.write_all(&end).map_err(|e| e.to_string())?;
+        self.out.flush().map_err(|e| e.to_string())?;
+        Ok(self.out)
+    }
+}
+
+pub fn encode(width: u32, height: u32, rgb: &[u8]) -> Vec<u8> {
+    let mut streamer = Streamer::new(Vec::new(), width, height).unwrap();
+    streamer.write_rows(rgb).unwrap();
+    streamer.finish().unwrap()
+}
+
+pub fn write(path: &Path, width: u32, height: u32, rgb: &[u8]) -> Result<(), String> {
+    std::fs::write(path, encode(width, height, rgb)).map_err(|e| format!("{}: {e}", path.display()))
+}
+
+// The image with a UTF-8 text chunk (iTXt) of the keyword after the header, where readers look
+// for metadata.
+pub fn encode_with_text(width: u32, height: u32, rgb: &[u8], keyword: &str, text: &str) -> Vec<u8> {
+    let png = encode(width, height, rgb);
+    // Keyword, no compression, and empty language tag and translated keyword.
+    let mut data = keyword.as_bytes().to_vec();
+    data.extend_from_slice(&[0, 0, 0, 0, 0]);
+    data.extend_from_slice(text.as_bytes());
+    let mut with_text = png[..HEADER_END].to_vec();
+    chunk(&mut with_text, b"iTXt", &data);
+    with_text.extend_from_slice(&png[HEADER_END..]);
+    with_text
+}
+
+// The uncompressed text of the keyword in a tEXt or iTXt chunk of the PNG file.
+pub fn text(png: &[u8], keyword: &str) -> Option<String> {
+    let mut at = 8;
+    while at + 12 <= png.len() {
+        let len = u32::from_be_bytes(png[at..at + 4].try_into().ok()?) as usize;
+        let data = png.get(at + 8..at + 8 + len)?;
+        let (key, rest) = data.split_at(data.iter().position(|&byte| byte == 0)?);
+        if key == keyword.as_bytes() {
+            match &png[at + 4..at + 8] {
+                b"tEXt" => return Some(rest[1..].iter().map(|&byte| byte as char).collect()),
+                // Only uncompressed text, past the language tag and the translated keyword.
+                b"iTXt" if rest.get(1) == Some(&0) => {
+                    let mut fields = rest.get(3..)?.splitn(3, |&byte| byte == 0);
+                    let text = fields.nth(2)?;
+                    return String::from_utf8(text.to_vec()).ok();
+                }
+                _ => {}
+            }
+        }
+        at += len + 12;
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn checksums_match_reference_values() {
+        assert_eq!(crc32(b"IEND"), 0xae426082);
+        assert_eq!(adler32_update((1, 0), b"Wikipedia"), (0x0398, 0x11e6));
+    }
+
+    #[test]
+    fn stores_scanlines_uncompressed() {
+        let rgb = (0..2 * 3 * 3).map(|i| i as u8).collect::<Vec<_>>();
+        let png = encode(3, 2, &rgb);
+        assert!(png.starts_with(b"\x89PNG\r\n\x1a\n"));
+        assert!(png.ends_with(&[0, 0, 0, 0, b'I', b'E', b'N', b'D', 0xae, 0x42, 0x60, 0x82]));
+        // Two scanlines of a filter byte and 9 channel values after the zlib and block headers.
+        let idat = png.windows(4).position(|w| w == b"IDAT").unwrap() + 4;
+        assert_eq!(&png[idat..idat + 7], &[0x78, 0x01, 1, 20, 0, !20, 0xff]);
+        assert_eq!(&png[idat + 7..idat + 11], &[0, 0, 1, 2]);
+    }
+
+    #[test]
+    fn carries_text_chunks() {
+        let rgb = [255, 0, 0];
+        let png = encode_with_text(1, 1, &rgb, "Comment", "{\"name\": \"Mandelbröt\"}");
+        assert_eq!(&png[HEADER_END + 4..HEADER_END + 8], b"iTXt");
+        assert_eq!(
+            text(&png, "Comment").as_deref(),
+            Some("{\"name\": \"Mandelbröt\"}")
+        );
+        assert_eq!(text(&png, "Title"), None);
+        assert_eq!(text(&encode(1, 1, &rgb), "Comment"), None);
+        let mut latin1 = encode(1, 1, &rgb)[..HEADER_END].to_vec();
+        chunk(&mut latin1, b"tEXt", b"Comment\0caf\xe9");
+        assert_eq!(text(&latin1, "Comment").as_deref(), Some("café"));
+    }
+
+    #[test]
+    fn streams_rows_in_chunks() {
+        let rgb = (0..5 * 4 * 3).map(|i| i as u8).collect::<Vec<_>>();
+        let mut streamer = Streamer::new(Vec::new(), 4, 5).unwrap();
+        for band in rgb.chunks(2 * 4 * 3) {
+            streamer.write_rows(band).unwrap();
+        }
+        assert!(streamer.write_rows(&rgb[..12]).is_err());
+        let png = streamer.finish().unwrap();
+        assert_eq!(png.windows(4).filter(|w| w == b"IDAT").count(), 3);
+        // The same stored blocks and checksum as in one chunk, only split up.
+        let data = |png: &[u8]| {
+            let mut data = Vec::new();
+            let mut at = 33;
+            while &png[at + 4..at + 8] == b"IDAT" {
+                let len = u32::from_be_bytes(png[at..at + 4].try_into().unwrap()) as usize;
+                data.extend_from_slice(&png[at + 8..at + 8 + len]);
+                at += len + 12;
+            }
+            data
+        };
+        let whole = encode(4, 5, &rgb);
+        assert_eq!(data(&png)[..2], data(&whole)[..2]);
+        assert_eq!(
+            data(&png)[data(&png).len() - 4..],
+            data(&whole)[data(&whole).len() - 4..]
+        );
+        let mut short = Streamer::new(Vec::new(), 4, 5).unwrap();
+        short.write_rows(&rgb[..12]).unwrap();
+        assert!(short.finish().is_err());
+        assert_eq!(encode(0, 0, &[]).len(), encode(3, 0, &[]).len());
+    }
+}
@@ -0,0 +1,152 @@
+use crate::palette::Palette;
+use crate::params::RenderParams;
+use crate::png;
+use crate::render;
+use crate::viewport::Viewport;
+use num::complex::Complex;
+use std::io::BufWriter;
+use std::path::PathBuf;
+
+// Pixels rendered at a time by `stream`, bounding its memory to a few hundred megabytes.
+const BAND_PIXELS: u64 = 1 << 22;
+
+// The `--headless` mode: a single image rendered from the command line, without a window.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Poster {
+    pub width: u32,
+    pub height: u32,
+    pub center: Complex<f64>,
+    // Magnification over the initial view of the explorer.
+    pub zoom: f64,
+    pub iterations: u32,
+    pub palette: Option<Palette>,
+    pub output: PathBuf,
+    // Iteration dump of the escape results written too, rendering the image whole.
+    pub dump: Option<PathBuf>,
+}
+
+impl Default for Poster {
+    fn default() -> Self {
+        Poster {
+            width: 1920,
+            height: 1080,
+            center: Viewport::default().center,
+            zoom: 1.0,
+            iterations: 1000,
+            palette: None,
+            output: PathBuf::from("mandelbrot.png"),
+            dump: None,
+        }
+    }
+}
+
+impl Poster {
+    // The view of the given zoom at the aspect ratio of the image.
+    pub fn params(&self) -> Result<RenderParams, String> {
+        if !(self.zoom > 0.0 && self.zoom.is_finite()) {
+            return Err(format!("Invalid zoom {}", self.zoom));
+        }
+        let height = Viewport::default().height / self.zoom;
+        RenderParams::builder()
+            .size(self.width, self.height)
+            .viewport(Viewport::new(
+                self.center,
+                height * self.width as f64 / self.height as f64,
+                height,
+            ))
+            .iterations(self.iterations)
+            .palette(self.palette)
+            .build()
+    }
+
+    // The rows `top..top + rows` of the image as a view of their own.
+    fn band(&self, params: RenderParams, top: u32, rows: u32) -> Result<RenderParams, String> {
+        let pixel = params.viewport.height / params.height as f64;
+        let center = params.viewport.center
+            + Complex::new(
+                0.0,
+                (top as f64 + 0.5 * rows as f64 - 0.5 * params.height as f64) * pixel,
+            );
+        params
+            .to_builder()
+            .size(params.width, rows)
+            .viewport(Viewport::new(
+                center,
+                params.viewport.width,
+                rows as f64 * pixel,
+            ))
+            .build()
+    }
+
+    // Renders a band of rows after the other into a PNG file written as they complete, so that
+    // images of billions of pixels need memory for a band only.
+    pub fn stream(&self) -> Result<(), String> {
+        let params = self.params()?;
+        let file = std::fs::File::create(&self.output)
+            .map_err(|e| format!("{}: {e}", self.output.display()))?;
+        let mut png = png::Streamer::new(BufWriter::new(file), params.width, params.height)?;
+        let rows = (BAND_PIXELS / params.width as u64).clamp(1, params.height as u64) as u32;
+        for top in (0..params.height).step_by(rows as usize) {
+            let rows = rows.min(params.height - top);
+            if params.height > rows {
+                println!("Rows {top} to {} of {}", top + rows, params.height);
+            }
+            png.write_rows(&render::render_frame(self.band(params, top, rows)?).rgb)?;
+        }
+        png.finish().map(|_| ())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn poster_view() {
+        let poster = Poster {
+            width: 400,
+            height: 200,
+            center: Complex::new(-0.75, 0.1),
+            zoom: 100.0,
+            ..Poster::default()
+        };
+        let params = poster.params().unwrap();
+        assert_eq!(params.viewport.center, poster.center);
+        assert!((params.viewport.height - 0.03).abs() < 1e-12);
+        assert!((params.viewport.width - 0.06).abs() < 1e-12);
+        let zero = Poster {
+            zoom: 0.0,
+            ..poster
+        };
+        assert!(zero.params().is_err());
+    }
+
+    #[test]
+    fn bands_tile_the_view() {
+        let poster = Poster {
+            width: 40,
+            height: 30,
+            center: Complex::new(-0.75, 0.1),
+            zoom: 4.0,
+            ..Poster::default()
+        };
+        let params = poster.params().unwrap();
+        let band = poster.band(params, 10, 5).unwrap();
+        assert_eq!((band.width, band.height), (40, 5));
+        let size = (params.width, params.height);
+        for (x, y) in [(0.0, 0.0), (39.5, 4.5), (20.0, 2.0)] {
+            let whole = params.viewport.pixel_to_complex(x, y + 10.0, size);
+            let part = band.viewport.pixel_to_complex(x, y, (40, 5));
+            assert!((whole - part).norm() < 1e-12);
+        }
+        let streamed = Poster {
+            output: std::env::temp_dir().join(format!("poster-{}.png", std::process::id())),
+            ..poster
+        };
+        streamed.stream().unwrap();
+        let png = std::fs::read(&streamed.output).unwrap();
+        // A single band in this small image, so the same bytes as the whole render encoded.
+        assert_eq!(png, png::encode(40, 30, &render::render_frame(params).rgb));
+        std::fs::remove_file(&streamed.output).unwrap();
+    }
+}
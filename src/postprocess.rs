@@ -0,0 +1,274 @@
+use crate::noise;
+use crate::render::Frame;
+use rayon::prelude::*;
+use std::f64::consts::TAU;
+
+// Effects driven by the final orbit value of every pixel, applied after coloring. Interior
+// pixels are left untouched.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Effect {
+    // Tints the exterior by the argument of the final z and adds contour rings of its modulus.
+    DomainShading,
+    // Line integral convolution of noise along the direction field given by arg(z), which
+    // produces streaks flowing around the set.
+    FlowStreaks,
+}
+
+impl Effect {
+    pub const ALL: [Effect; 2] = [Effect::DomainShading, Effect::FlowStreaks];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Effect::DomainShading => "domain-shading",
+            Effect::FlowStreaks => "flow-streaks",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Effect> {
+        Effect::ALL.into_iter().find(|effect| effect.name() == name)
+    }
+}
+
+// Glow around bright structures: bright-pass, separable gaussian blur, additive composite. Runs
+// on the final 8-bit image so it picks up whatever coloring produced.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Bloom {
+    // Luminance (0..1) above which pixels contribute to the glow.
+    pub threshold: f32,
+    // Blur radius in pixels.
+    pub radius: u32,
+    pub strength: f32,
+}
+
+impl Default for Bloom {
+    fn default() -> Self {
+        Bloom {
+            threshold: 0.35,
+            radius: 8,
+            strength: 1.5,
+        }
+    }
+}
+
+pub fn apply(effect: Effect, frame: &mut Frame) {
+    match effect {
+        Effect::DomainShading => domain_shading(frame),
+        Effect::FlowStreaks => flow_streaks(frame),
+    }
+}
+
+pub fn bloom(bloom: Bloom, frame: &mut Frame) {
+    let (width, height) = (frame.width as usize, frame.height as usize);
+    let bright = frame
+        .rgb
+        .par_chunks(3)
+        .flat_map_iter(|pixel| {
+            let rgb = [pixel[0], pixel[1], pixel[2]].map(|c| c as f32 / 255.0);
+            let luminance = 0.2126 * rgb[0] + 0.7152 * rgb[1] + 0.0722 * rgb[2];
+            let excess = (luminance - bloom.threshold).max(0.0) / (1.0 - bloom.threshold).max(1e-3);
+            let scale = if luminance > 0.0 {
+                excess / luminance
+            } else {
+                0.0
+            };
+            rgb.map(|c| c * scale)
+        })
+        .collect::<Vec<_>>();
+
+    let radius = bloom.radius as isize;
+    let sigma = (bloom.radius as f32 / 2.0).max(0.5);
+    let kernel = (-radius..=radius)
+        .map(|i| (-(i * i) as f32 / (2.0 * sigma * sigma)).exp())
+        .collect::<Vec<_>>();
+    let norm = kernel.iter().sum::<f32>();
+    let kernel = kernel.iter().map(|w| w / norm).collect::<Vec<_>>();
+
+    // Both passes clamp reads at the image border.
+    let blur = |src: &[f32], dx: isize, dy: isize| {
+        let mut dst = vec![0.0; src.len()];
+        dst.par_chunks_mut(width * 3)
+            .enumerate()
+            .for_each(|(y, row)| {
+                for x in 0..width {
+                    let mut acc = [0.0f32; 3];
+                    for (k, weight) in kernel.iter().enumerate() {
+                        let offset = k as isize - radius;
+                        let sx = (x as isize + offset * dx).clamp(0, width as isize - 1) as usize;
+                        let sy = (y as isize + offset * dy).clamp(0, height as isize - 1) as usize;
+                        let i = (sy * width + sx) * 3;
+                        for (channel, value) in acc.iter_mut().zip(&src[i..i + 3]) {
+                            *channel += weight * value;
+                        }
+                    }
+                    row[x * 3..x * 3 + 3].copy_from_slice(&acc);
+                }
+            });
+        dst
+    };
+    let glow = blur(&blur(&bright, 1, 0), 0, 1);
+
+    frame
+        .rgb
+        .par_iter_mut()
+        .zip(glow.par_iter())
+        .for_each(|(channel, glow)| {
+            let value = *channel as f32 + glow * bloom.strength * 255.0;
+            *channel = value.clamp(0.0, 255.0) as u8;
+        });
+}
+
+// Rotates the hue of every pixel by `degrees` around the gray axis, keeping luminance roughly
+// constant.
+pub fn rotate_hue(rgb: &mut [u8], degrees: f64) {
+    let (sin, cos) = degrees.to_radians().sin_cos();
+    let (a, b) = ((1.0 - cos) / 3.0, (1.0f64 / 3.0).sqrt() * sin);
+    let matrix = [
+        [cos + a, a - b, a + b],
+        [a + b, cos + a, a - b],
+        [a - b, a + b, cos + a],
+    ];
+    rgb.par_chunks_mut(3).for_each(|pixel| {
+        let input = [pixel[0] as f64, pixel[1] as f64, pixel[2] as f64];
+        for (channel, row) in pixel.iter_mut().zip(&matrix) {
+            let value = row.iter().zip(input).map(|(m, v)| m * v).sum::<f64>();
+            *channel = value.round().clamp(0.0, 255.0) as u8;
+        }
+    });
+}
+
+fn hue_to_rgb(hue: f64) -> [f64; 3] {
+    let h = hue.rem_euclid(1.0) * 6.0;
+    let x = 1.0 - (h % 2.0 - 1.0).abs();
+    match h as u32 {
+        0 => [1.0, x, 0.0],
+        1 => [x, 1.0, 0.0],
+        2 => [0.0, 1.0, x],
+        3 => [0.0, x, 1.0],
+        4 => [x, 0.0, 1.0],
+        _ => [1.0, 0.0, x],
+    }
+}
+
+fn domain_shading(frame: &mut Frame) {
+    frame
+        .rgb
+        .par_chunks_mut(3)
+        .zip(frame.samples.par_iter())
+        .filter(|(_, sample)| sample.escape.is_some())
+        .for_each(|(pixel, sample)| {
+            let tint = hue_to_rgb(sample.z.arg() / TAU);
+            let rings = 0.75 + 0.25 * (sample.z.norm().log2().fract() * TAU).cos();
+            for (channel, tint) in pixel.iter_mut().zip(tint) {
+                let base = *channel as f64 / 255.0;
+                let shaded = (0.5 * base + 0.5 * tint * (0.3 + 0.7 * base)) * rings;
+                *channel = (shaded.clamp(0.0, 1.0) * 255.0) as u8;
+            }
+        });
+}
+
+fn flow_streaks(frame: &mut Frame) {
+    const STEPS: i32 = 12;
+    let (width, height) = (frame.width as i64, frame.height as i64);
+    let (samples, seed) = (&frame.samples, frame.seed);
+    let noise = |x: i64, y: i64| noise::value(x, y, seed);
+    let direction = |x: f64, y: f64| {
+        let (px, py) = (x.round() as i64, y.round() as i64);
+        if px < 0 || py < 0 || px >= width || py >= height {
+            return None;
+        }
+        let sample = samples[(py * width + px) as usize];
+        sample.escape.map(|_| {
+            let angle = sample.z.arg();
+            (angle.cos(), angle.sin())
+        })
+    };
+
+    frame
+        .rgb
+        .par_chunks_mut(3)
+        .enumerate()
+        .filter(|(i, _)| samples[*i].escape.is_some())
+        .for_each(|(i, pixel)| {
+            let (x0, y0) = ((i as i64 % width) as f64, (i as i64 / width) as f64);
+            let mut total = noise(x0 as i64, y0 as i64);
+            let mut count = 1.0;
+            for sign in [1.0, -1.0] {
+                let (mut x, mut y) = (x0, y0);
+                for _ in 0..STEPS {
+                    let Some((dx, dy)) = direction(x, y) else {
+                        break;
+                    };
+                    x += sign * dx;
+                    y += sign * dy;
+                    total += noise(x.round() as i64, y.round() as i64);
+                    count += 1.0;
+                }
+            }
+            // Averaging flattens the noise towards 0.5, stretch it back to full contrast.
+            let streak = ((total / count - 0.5) * 3.0 + 0.5).clamp(0.0, 1.0);
+            for channel in pixel.iter_mut() {
+                let value = *channel as f64 * (0.6 + 0.8 * streak) + 48.0 * streak;
+                *channel = value.clamp(0.0, 255.0) as u8;
+            }
+        });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::params::RenderParams;
+    use crate::render::{render_frame, Sample};
+
+    #[test]
+    fn bloom_spreads_light_into_dark_neighbourhood() {
+        let samples = vec![
+            Sample {
+                escape: None,
+                z: Default::default(),
+            };
+            9 * 9
+        ];
+        let mut rgb = vec![0u8; 9 * 9 * 3];
+        rgb[(4 * 9 + 4) * 3..(4 * 9 + 4) * 3 + 3].copy_from_slice(&[255, 255, 255]);
+        let mut frame = Frame {
+            width: 9,
+            height: 9,
+            rgb,
+            samples,
+            supersamples: Vec::new(),
+            accumulation: None,
+            seed: 0,
+        };
+        bloom(
+            Bloom {
+                radius: 2,
+                ..Bloom::default()
+            },
+            &mut frame,
+        );
+        let at = |x: usize, y: usize| frame.rgb[(y * 9 + x) * 3];
+        assert_eq!(at(4, 4), 255);
+        assert!(at(5, 4) > 0 && at(4, 6) > 0);
+        assert!(at(5, 4) >= at(6, 4));
+        assert_eq!(at(0, 0), 0);
+    }
+
+    #[test]
+    fn effects_leave_interior_untouched() {
+        let params = RenderParams::builder().size(64, 48).build().unwrap();
+        let plain = render_frame(params);
+        for effect in Effect::ALL {
+            let shaded = render_frame(params.to_builder().effect(Some(effect)).build().unwrap());
+            let mut changed = false;
+            for (i, sample) in plain.samples.iter().enumerate() {
+                let (a, b) = (&plain.rgb[i * 3..i * 3 + 3], &shaded.rgb[i * 3..i * 3 + 3]);
+                if sample.escape.is_none() {
+                    assert_eq!(a, b);
+                } else {
+                    changed |= a != b;
+                }
+            }
+            assert!(changed, "{} did not modify the exterior", effect.name());
+        }
+    }
+}
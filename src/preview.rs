@@ -0,0 +1,77 @@
+use sdl2::event::Event;
+use sdl2::pixels::PixelFormatEnum;
+use sdl2::render::{Canvas, TextureCreator};
+use sdl2::surface::Surface;
+use sdl2::video::{Window, WindowContext};
+use sdl2::{EventPump, Sdl};
+
+// Longest side of the preview window.
+const MAX_SIZE: u32 = 640;
+
+pub fn preview_size(width: u32, height: u32) -> (u32, u32) {
+    let scale = (MAX_SIZE as f64 / width.max(height) as f64).min(1.0);
+    (
+        ((width as f64 * scale).round() as u32).max(1),
+        ((height as f64 * scale).round() as u32).max(1),
+    )
+}
+
+// Read-only window showing the frames of a batch job as they complete. Closing it only stops
+// the preview, the job goes on.
+pub struct Preview {
+    _sdl: Sdl,
+    canvas: Canvas<Window>,
+    texture_creator: TextureCreator<WindowContext>,
+    event_pump: EventPump,
+}
+
+impl Preview {
+    pub fn open(title: &str, width: u32, height: u32) -> Result<Preview, String> {
+        let sdl = sdl2::init()?;
+        let (width, height) = preview_size(width, height);
+        let window = sdl
+            .video()?
+            .window(title, width, height)
+            .build()
+            .map_err(|e| e.to_string())?;
+        let canvas = window.into_canvas().build().map_err(|e| e.to_string())?;
+        let texture_creator = canvas.texture_creator();
+        let event_pump = sdl.event_pump()?;
+        Ok(Preview {
+            _sdl: sdl,
+            canvas,
+            texture_creator,
+            event_pump,
+        })
+    }
+
+    // Shows a frame scaled to the window. Returns false once the window was closed.
+    pub fn show(&mut self, width: u32, height: u32, rgb: &mut [u8]) -> Result<bool, String> {
+        for event in self.event_pump.poll_iter() {
+            if let Event::Quit { .. } = event {
+                return Ok(false);
+            }
+        }
+        let surface = Surface::from_data(rgb, width, height, width * 3, PixelFormatEnum::RGB24)
+            .map_err(|e| e.to_string())?;
+        let texture = self
+            .texture_creator
+            .create_texture_from_surface(surface)
+            .map_err(|e| e.to_string())?;
+        self.canvas.copy(&texture, None, None)?;
+        self.canvas.present();
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn preview_fits_and_keeps_aspect() {
+        assert_eq!(preview_size(1920, 1080), (640, 360));
+        assert_eq!(preview_size(300, 200), (300, 200));
+        assert_eq!(preview_size(1000, 4000), (160, 640));
+    }
+}
@@ -0,0 +1,217 @@
+use crate::json::{FromJson, Json};
+use crate::kernel::Kernel;
+use crate::schedule::IterationSchedule;
+use std::path::Path;
+
+// How the iteration count is chosen.
+#[derive(Clone, Debug, PartialEq)]
+pub enum IterationPolicy {
+    Fixed(u32),
+    Scheduled(IterationSchedule),
+}
+
+// A named bundle of the quality settings, chosen with --profile and cycled through with `5`
+// instead of setting each on its own.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Profile {
+    pub name: String,
+    pub antialias: u32,
+    pub iterations: IterationPolicy,
+    // Detected with `None`. The kernel is chosen at startup, so only --profile sets it.
+    pub kernel: Option<Kernel>,
+    // Cheaper frames while zooming, followed by a full-quality one.
+    pub adaptive: bool,
+    // The factor the view is rendered again at once the input pauses, 1 for none.
+    pub resolution_scale: u32,
+}
+
+impl Profile {
+    fn builtin(name: &str, antialias: u32, base: f64, per_octave: f64, adaptive: bool) -> Self {
+        Profile {
+            name: name.to_string(),
+            antialias,
+            iterations: IterationPolicy::Scheduled(IterationSchedule::Formula {
+                base,
+                per_octave,
+                max: None,
+            }),
+            kernel: None,
+            adaptive,
+            resolution_scale: if antialias > 1 { 2 } else { 1 },
+        }
+    }
+
+    pub fn builtins() -> Vec<Profile> {
+        vec![
+            Profile::builtin("interactive", 1, 200.0, 50.0, true),
+            Profile::builtin("quality", 2, 300.0, 100.0, true),
+            Profile::builtin("poster", 4, 500.0, 150.0, false),
+        ]
+    }
+
+    // The fields of the JSON object over those of `base`.
+    fn from_json_over(name: &str, base: &Profile, json: &Json) -> Result<Profile, String> {
+        let mut profile = Profile {
+            name: name.to_string(),
+            ..base.clone()
+        };
+        if let Some(antialias) = json.get("antialias") {
+            profile.antialias = match antialias.as_f64()? {
+                n if (1.0..=8.0).contains(&n) && n.fract() == 0.0 => n as u32,
+                n => return Err(format!("Antialiasing factor {n} outside 1 to 8")),
+            };
+        }
+        match (json.get("iterations"), json.get("schedule")) {
+            (Some(_), Some(_)) => return Err("Use either iterations or schedule".to_string()),
+            (Some(iterations), None) => {
+                let iterations = iterations.as_f64()?.clamp(1.0, u32::MAX as f64) as u32;
+                profile.iterations = IterationPolicy::Fixed(iterations);
+            }
+            (None, Some(schedule)) => {
+                profile.iterations =
+                    IterationPolicy::Scheduled(IterationSchedule::from_json(schedule)?);
+            }
+            (None, None) => {}
+        }
+        if let Some(kernel) = json.get("kernel") {
+            profile.kernel = match kernel {
+                Json::Null => None,
+                Json::String(name) => {
+                    Some(Kernel::from_name(name).ok_or_else(|| format!("Unknown kernel {name}"))?)
+                }
+                _ => return Err(format!("Expected a kernel name, got {kernel}")),
+            };
+        }
+        match json.get("adaptive") {
+            Some(Json::Bool(value)) => profile.adaptive = *value,
+            Some(value) => return Err(format!("Expected true or false, got {value}")),
+            None => {}
+        }
+        if let Some(scale) = json.get("resolution_scale") {
+            profile.resolution_scale = match scale.as_f64()? {
+                n if (1.0..=MAX_RESOLUTION_SCALE).contains(&n) && n.fract() == 0.0 => n as u32,
+                n => return Err(format!("Resolution scale {n} outside 1 to 4")),
+            };
+        }
+        Ok(profile)
+    }
+}
+
+// The profiles file read when neither --profiles nor the configuration gives any.
+pub const FALLBACK: &str = "profiles.json";
+
+// Largest factor of the idle render, whose memory grows with its square.
+const MAX_RESOLUTION_SCALE: f64 = 4.0;
+
+fn read(path: &Path) -> Result<Json, String> {
+    let text = std::fs::read_to_string(path).map_err(|e| format!("{}: {e}", path.display()))?;
+    Json::parse(&text).map_err(|e| format!("{}: {e}", path.display()))
+}
+
+// The built-in profiles followed by those of an object of profiles by name:
+// `{"deep": {"antialias": 2, "schedule": {"base": 400, "per_octave": 150}}}`, from the file of
+// --profiles if given, else the `profiles` object of the configuration, else the fallback file if
+// it exists. A profile takes the settings it leaves out from the built-in one of its name, which
+// it replaces, or else from `interactive`.
+pub fn load(file: Option<&Path>, config: &Path, fallback: &Path) -> Result<Vec<Profile>, String> {
+    let configured = match config.exists() {
+        true => read(config)?.get("profiles").cloned(),
+        false => None,
+    };
+    let (json, path) = match (file, configured) {
+        (Some(file), _) => (read(file)?, file),
+        (None, Some(json)) => (json, config),
+        (None, None) if fallback.exists() => (read(fallback)?, fallback),
+        (None, None) => return Ok(Profile::builtins()),
+    };
+    profiles(&json).map_err(|e| format!("{}: {e}", path.display()))
+}
+
+fn profiles(json: &Json) -> Result<Vec<Profile>, String> {
+    let mut profiles = Profile::builtins();
+    let Json::Object(entries) = json else {
+        return Err("expected an object of profiles".to_string());
+    };
+    for (name, json) in entries {
+        let existing = profiles.iter().position(|profile| profile.name == *name);
+        let base = &profiles[existing.unwrap_or(0)];
+        let profile = Profile::from_json_over(name, base, json)
+            .map_err(|e| format!("profile {name}: {e}"))?;
+        match existing {
+            Some(index) => profiles[index] = profile,
+            None => profiles.push(profile),
+        }
+    }
+    Ok(profiles)
+}
+
+pub fn find(profiles: &[Profile], name: &str) -> Result<usize, String> {
+    profiles
+        .iter()
+        .position(|profile| profile.name == name)
+        .ok_or_else(|| {
+            let names = profiles.iter().map(|profile| profile.name.as_str());
+            format!(
+                "Unknown profile {name} (known: {})",
+                names.collect::<Vec<_>>().join(", ")
+            )
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn file_profiles_extend_the_builtins() {
+        let dir = std::env::temp_dir().join(format!("profiles-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let (path, config, fallback) = (
+            dir.join("mine.json"),
+            dir.join("config.json"),
+            dir.join("profiles.json"),
+        );
+        let loaded = |file: Option<&Path>| load(file, &config, &fallback);
+        assert_eq!(loaded(None).unwrap(), Profile::builtins());
+        std::fs::write(
+            &path,
+            r#"{"poster": {"antialias": 8}, "deep": {"iterations": 5000, "kernel": "scalar",
+                "resolution_scale": 3}}"#,
+        )
+        .unwrap();
+        let profiles = loaded(Some(&path)).unwrap();
+        let poster = &profiles[find(&profiles, "poster").unwrap()];
+        assert_eq!(poster.antialias, 8);
+        assert!(!poster.adaptive);
+        let deep = &profiles[find(&profiles, "deep").unwrap()];
+        assert_eq!(deep.iterations, IterationPolicy::Fixed(5000));
+        assert_eq!(deep.kernel, Some(Kernel::Scalar));
+        assert_eq!((deep.antialias, deep.adaptive), (1, true));
+        assert_eq!(deep.resolution_scale, 3);
+        assert!(find(&profiles, "draft")
+            .unwrap_err()
+            .contains("interactive"));
+        // The profiles of the configuration come before the fallback file, and --profiles
+        // before both.
+        std::fs::write(&fallback, r#"{"draft": {"iterations": 150}}"#).unwrap();
+        assert!(find(&loaded(None).unwrap(), "draft").is_ok());
+        std::fs::write(&config, r#"{"width": 800, "profiles": {"sketch": {}}}"#).unwrap();
+        let profiles = loaded(None).unwrap();
+        assert!(find(&profiles, "sketch").is_ok() && find(&profiles, "draft").is_err());
+        assert!(find(&loaded(Some(&path)).unwrap(), "sketch").is_err());
+        for invalid in [
+            r#"{"deep": {"antialias": 9}}"#,
+            r#"{"deep": {"iterations": 100, "schedule": [[1, 200]]}}"#,
+            r#"{"deep": {"adaptive": 1}}"#,
+            r#"{"deep": {"resolution_scale": 1.5}}"#,
+            r#"[]"#,
+        ] {
+            std::fs::write(&path, invalid).unwrap();
+            assert!(
+                loaded(Some(&path)).unwrap_err().contains("mine.json"),
+                "{invalid}"
+            );
+        }
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
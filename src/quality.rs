@@ -0,0 +1,108 @@
+use std::time::{Duration, Instant};
+
+// Share of the iteration cap rendered while zooming; deep detail is invisible in motion anyway.
+const MOTION_ITERATIONS: f64 = 0.25;
+// Below this the shape of the set itself starts to erode.
+const MIN_MOTION_ITERATIONS: u32 = 50;
+// Time without zooming after which the full-quality pass is rendered.
+const IDLE: Duration = Duration::from_millis(150);
+// Frames rendering faster than this are shown without coarse passes first.
+const PROGRESSIVE: Duration = Duration::from_millis(60);
+
+// Decides the rendering quality from the interaction: frames are cheaper while the view keeps
+// zooming, followed by one full-quality pass once it comes to rest.
+pub struct QualityController {
+    last_motion: Option<Instant>,
+    adaptive: bool,
+}
+
+impl Default for QualityController {
+    fn default() -> Self {
+        QualityController {
+            last_motion: None,
+            adaptive: true,
+        }
+    }
+}
+
+impl QualityController {
+    // Renders every frame at full quality.
+    pub fn fixed() -> Self {
+        QualityController {
+            adaptive: false,
+            ..QualityController::default()
+        }
+    }
+
+    pub fn moved(&mut self, now: Instant) {
+        if self.adaptive {
+            self.last_motion = Some(now);
+        }
+    }
+
+    pub fn in_motion(&self) -> bool {
+        self.last_motion.is_some()
+    }
+
+    // Iteration cap to render with.
+    pub fn iterations(&self, full: u32) -> u32 {
+        if !self.in_motion() {
+            return full;
+        }
+        ((full as f64 * MOTION_ITERATIONS) as u32)
+            .max(MIN_MOTION_ITERATIONS)
+            .min(full)
+    }
+
+    // True once when the view has been at rest long enough for the full-quality pass.
+    pub fn settle(&mut self, now: Instant) -> bool {
+        match self.last_motion {
+            Some(last) if now - last >= IDLE => {
+                self.last_motion = None;
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+// Pixel steps of the coarse passes shown while a frame renders, going by how long the last one
+// took: at 1/8 of the resolution each way, costing a 64th of the frame, and for slow frames
+// refined at 1/4 too.
+pub fn passes(frame_time: Duration) -> &'static [u32] {
+    match frame_time {
+        time if time < PROGRESSIVE => &[],
+        time if time < PROGRESSIVE * 8 => &[8],
+        _ => &[8, 4],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reduces_iterations_until_idle() {
+        let mut quality = QualityController::default();
+        let start = Instant::now();
+        assert_eq!(quality.iterations(1000), 1000);
+        quality.moved(start);
+        assert_eq!(quality.iterations(1000), 250);
+        assert_eq!(quality.iterations(100), 50);
+        assert_eq!(quality.iterations(20), 20);
+        assert!(!quality.settle(start + IDLE / 2));
+        assert!(quality.settle(start + IDLE));
+        assert!(!quality.settle(start + IDLE * 2));
+        assert_eq!(quality.iterations(1000), 1000);
+        let mut fixed = QualityController::fixed();
+        fixed.moved(start);
+        assert_eq!(fixed.iterations(1000), 1000);
+    }
+
+    #[test]
+    fn coarse_passes_for_slow_frames() {
+        assert!(passes(Duration::from_millis(20)).is_empty());
+        assert_eq!(passes(Duration::from_millis(200)), [8]);
+        assert_eq!(passes(Duration::from_secs(2)), [8, 4]);
+    }
+}
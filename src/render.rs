@@ -0,0 +1,681 @@
+use crate::buddhabrot::{self, Importance};
+use crate::certify::{self, Certainty};
+use crate::contrast;
+use crate::dd;
+use crate::formula;
+use crate::kernel;
+use crate::morton;
+use crate::noise;
+use crate::palette::{self, Palette};
+use crate::params::{Coloring, Fractal, RenderParams};
+use crate::perturbation;
+use crate::postprocess;
+use crate::scheduler::Precision;
+use crate::subdivide;
+use crate::tonemap;
+use itertools::Itertools;
+use num::complex::Complex;
+use rayon::prelude::*;
+use std::f64::consts::TAU;
+
+// Brightness of the dark stripes, decomposition halves and field lines relative to the rest.
+const SHADE: f64 = 0.4;
+// Longest cycle looked for in the orbits of interior points.
+const MAX_PERIOD: u32 = 1024;
+// Relative distance at which an orbit counts as returned to where it was.
+const PERIOD_TOLERANCE: f64 = 1e-9;
+// Rays of the argument drawn per iteration band, each doubling into the next band.
+const FIELD_LINES: f64 = 8.0;
+// Width of the field lines as a fraction of the spacing of the rays and bands.
+const FIELD_LINE_WIDTH: f64 = 0.12;
+// Most samples of a supersampled frame kept for recoloring it, about 100 MB of them.
+const MAX_SUPERSAMPLES: usize = 1 << 22;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Sample {
+    // Iteration at which the orbit escaped, `None` for points considered inside the set.
+    pub escape: Option<u32>,
+    // Last orbit value: the first one outside the bailout radius, or for interior points the
+    // value after the final iteration, or one on the cycle the orbit was found to settle on.
+    pub z: Complex<f64>,
+}
+
+// A rendered frame together with the per-pixel auxiliary buffers consumed by post-processing.
+// Frames restored from the render cache carry only `rgb`.
+pub struct Frame {
+    pub width: u32,
+    pub height: u32,
+    pub rgb: Vec<u8>,
+    pub samples: Vec<Sample>,
+    // With anti-aliasing every sample of the supersampling grids, pixel by pixel in row-major
+    // order, unless there are more than `MAX_SUPERSAMPLES`.
+    pub supersamples: Vec<Sample>,
+    // Unbounded per-pixel accumulation (Buddhabrot hit counts) that `rgb` was tone-mapped from.
+    pub accumulation: Option<Vec<f32>>,
+    // Seed of the noise of the stochastic effects, see `noise::seed`.
+    pub seed: u64,
+}
+
+impl Frame {
+    pub fn from_rgb(width: u32, height: u32, rgb: Vec<u8>) -> Frame {
+        Frame {
+            width,
+            height,
+            rgb,
+            samples: Vec::new(),
+            supersamples: Vec::new(),
+            accumulation: None,
+            seed: 0,
+        }
+    }
+
+    // The image moved by whole pixels, the uncovered part black.
+    pub fn shifted(&self, dx: i32, dy: i32) -> Frame {
+        let (width, height) = (self.width as i32, self.height as i32);
+        let mut rgb = vec![0; self.rgb.len()];
+        let columns = (dx.max(0), (width + dx).min(width));
+        if columns.0 < columns.1 {
+            for y in dy.max(0)..(height + dy).min(height) {
+                let to = ((y * width + columns.0) * 3) as usize;
+                let from = (((y - dy) * width + columns.0 - dx) * 3) as usize;
+                let length = ((columns.1 - columns.0) * 3) as usize;
+                rgb[to..to + length].copy_from_slice(&self.rgb[from..from + length]);
+            }
+        }
+        Frame::from_rgb(self.width, self.height, rgb)
+    }
+
+    // Copies the image of a smaller frame over this one with its top-left corner at (x, y).
+    pub fn paste(&mut self, x: u32, y: u32, piece: &Frame) {
+        for row in 0..piece.height {
+            let to = (((y + row) * self.width + x) * 3) as usize;
+            let from = (row * piece.width * 3) as usize;
+            let length = (piece.width * 3) as usize;
+            self.rgb[to..to + length].copy_from_slice(&piece.rgb[from..from + length]);
+        }
+    }
+}
+
+// Iterates until the orbit escapes or, with periodicity checking, comes back to where it was
+// at the last power of two iterations (Brent), so it is bounded on a cycle.
+pub fn julia(mut z: Complex<f64>, c: Complex<f64>, iterations: u32) -> Sample {
+    let periodicity = kernel::periodicity();
+    let mut saved = z;
+    for i in 0..iterations {
+        z = z * z + c;
+        if z.re * z.re + z.im * z.im > 4.0 {
+            return Sample { escape: Some(i), z };
+        }
+        if periodicity {
+            let (dr, di) = (z.re - saved.re, z.im - saved.im);
+            if dr * dr + di * di < kernel::PERIODICITY_TOLERANCE {
+                return Sample { escape: None, z };
+            }
+            if (i + 1).is_power_of_two() {
+                saved = z;
+            }
+        }
+    }
+
+    Sample { escape: None, z }
+}
+
+// A point of the attracting cycle of `c` in the main cardioid or the period-2 bulb, where the
+// orbits of zero never escape: the fixed point (1 - sqrt(1 - 4c)) / 2 while its multiplier is
+// below one, or a root of z^2 + z + c + 1, the 2-cycle, while |c + 1| < 1/4.
+pub fn main_component_cycle(c: Complex<f64>) -> Option<Complex<f64>> {
+    let one = Complex::new(1.0, 0.0);
+    let fixed = (one - (one - 4.0 * c).sqrt()) / 2.0;
+    if fixed.norm_sqr() < 0.25 {
+        return Some(fixed);
+    }
+    ((c + one).norm_sqr() < 0.0625).then(|| (-one + (-3.0 * one - 4.0 * c).sqrt()) / 2.0)
+}
+
+pub fn escape_time(fractal: Fractal, point: Complex<f64>, iterations: u32) -> Sample {
+    let (z, c) = fractal.start(point);
+    if fractal == Fractal::MANDELBROT {
+        if let Some(cycle) = main_component_cycle(c) {
+            return Sample {
+                escape: None,
+                z: cycle,
+            };
+        }
+    }
+    if fractal.quadratic() {
+        return julia(z, c, iterations);
+    }
+    if let Fractal::Formula {
+        index,
+        plane,
+        fixed,
+        ..
+    } = fractal
+    {
+        let formula = formula::get(index);
+        return iterate(z, c, iterations, formula::BAILOUT, |z, point| {
+            let (c, p) = plane.parameters(point, fixed);
+            formula.eval(z, c, p)
+        });
+    }
+    iterate(z, c, iterations, 4.0, |z, c| fractal.step(z, c))
+}
+
+// The orbit until it leaves the squared `bailout` radius, or fails to be a number, with the
+// periodicity checking of `julia`.
+fn iterate(
+    mut z: Complex<f64>,
+    c: Complex<f64>,
+    iterations: u32,
+    bailout: f64,
+    step: impl Fn(Complex<f64>, Complex<f64>) -> Complex<f64>,
+) -> Sample {
+    let periodicity = kernel::periodicity();
+    let mut saved = z;
+    for i in 0..iterations {
+        z = step(z, c);
+        let norm = z.re * z.re + z.im * z.im;
+        if norm > bailout || norm.is_nan() {
+            return Sample { escape: Some(i), z };
+        }
+        if periodicity {
+            let (dr, di) = (z.re - saved.re, z.im - saved.im);
+            if dr * dr + di * di < kernel::PERIODICITY_TOLERANCE {
+                return Sample { escape: None, z };
+            }
+            if (i + 1).is_power_of_two() {
+                saved = z;
+            }
+        }
+    }
+    Sample { escape: None, z }
+}
+
+// Smallest number of further iterations bringing `z` back to itself, for the last value of an
+// interior orbit that has settled on its attracting cycle.
+pub fn cycle_period(fractal: Fractal, z: Complex<f64>, c: Complex<f64>) -> Option<u32> {
+    let tolerance = PERIOD_TOLERANCE * z.norm().max(1.0);
+    let mut w = z;
+    for p in 1..=MAX_PERIOD {
+        w = fractal.step(w, c);
+        if (w - z).norm() < tolerance {
+            return Some(p);
+        }
+    }
+    None
+}
+
+// Hues a golden angle apart, so components of nearby periods stand apart.
+fn period_color(period: u32) -> [u8; 3] {
+    palette::lch_to_rgb(70.0, 55.0, period as f64 * 137.508)
+}
+
+fn escape_color(palette: Option<Palette>, escape: Option<u32>, iterations: u32) -> [u8; 3] {
+    match (escape, palette) {
+        (Some(iter), Some(palette)) => palette.color(iter as f64 / iterations as f64),
+        (Some(iter), None) => {
+            let c = (255 * iter / iterations) as u8;
+            [c / 2, c, c]
+        }
+        (None, _) => [0, 0, 0],
+    }
+}
+
+// The palette at the normalized iteration count, n + 1 - log2(log2 |z_n|) for the first orbit
+// value outside the bailout radius.
+fn smooth_color(palette: Option<Palette>, sample: &Sample, iterations: u32) -> [u8; 3] {
+    match contrast::smooth_value(sample) {
+        Some(smooth) => palette::shade(palette, smooth / iterations as f64),
+        None => [0, 0, 0],
+    }
+}
+
+fn colorize(params: &RenderParams, point: Complex<f64>, sample: Sample) -> [u8; 3] {
+    let coloring = match (sample.escape, params.interior) {
+        (None, Some(interior)) => interior,
+        _ => params.coloring,
+    };
+    match coloring {
+        Coloring::EscapeTime => escape_color(params.palette, sample.escape, params.iterations),
+        Coloring::Smooth => smooth_color(params.palette, &sample, params.iterations),
+        Coloring::Certified => match certify::classify(params.fractal, point, params.iterations) {
+            Certainty::Escaped(iter) => escape_color(params.palette, Some(iter), params.iterations),
+            Certainty::Bounded => escape_color(params.palette, None, params.iterations),
+            Certainty::Uncertain => certify::UNCERTAIN,
+        },
+        Coloring::Stripes { period, duty } => shaded(
+            escape_color(params.palette, sample.escape, params.iterations),
+            contrast::smooth_value(&sample)
+                .is_some_and(|smooth| (smooth / period as f64).rem_euclid(1.0) >= duty),
+        ),
+        Coloring::BinaryDecomposition => shaded(
+            escape_color(params.palette, sample.escape, params.iterations),
+            sample.escape.is_some() && sample.z.im < 0.0,
+        ),
+        Coloring::Period => {
+            let (_, c) = params.fractal.start(point);
+            match sample.escape {
+                Some(_) => escape_color(params.palette, sample.escape, params.iterations),
+                None => cycle_period(params.fractal, sample.z, c).map_or([0, 0, 0], period_color),
+            }
+        }
+        Coloring::FieldLines => shaded(
+            escape_color(params.palette, sample.escape, params.iterations),
+            contrast::smooth_value(&sample).is_some_and(|smooth| {
+                let angle = (sample.z.arg() / TAU).rem_euclid(1.0);
+                (angle * FIELD_LINES).rem_euclid(1.0) < FIELD_LINE_WIDTH
+                    || smooth.rem_euclid(1.0) < FIELD_LINE_WIDTH
+            }),
+        ),
+    }
+}
+
+fn shaded(color: [u8; 3], dark: bool) -> [u8; 3] {
+    if dark {
+        color.map(|channel| (channel as f64 * SHADE) as u8)
+    } else {
+        color
+    }
+}
+
+// Computes and colors every pixel, a tile at a time in Z order through the escape-time kernel
+// of the CPU, then scatters the tiles into the row-major buffers. With anti-aliasing the colors
+// are averaged over the supersampling grid while the auxiliary sample buffer keeps the sample
+// closest to the pixel position. Views too deep for f64 are rendered by perturbation around a
+// reference orbit instead, the one of the previous deep view while it still covers this one and
+// computed in fixed point for views too deep for double-double as well, or in double-double
+// throughout for the Burning Ship, the Tricorn and the Multibrot sets, whose steps the deltas do
+// not follow. Formulas stay in f64 at any depth. Where `subdivide` applies, the samples are
+// computed beforehand over the whole frame, skipping the inside of interior rectangles.
+pub fn escape_time_frame(params: RenderParams) -> Frame {
+    colored_frame(params, None)
+}
+
+// The frame of samples computed elsewhere, those of every point of the supersampling grids
+// pixel by pixel in row-major order, as a GPU returns them. Only the coloring is left.
+pub fn computed_frame(params: RenderParams, samples: Vec<Sample>) -> Frame {
+    colored_frame(params, Some(samples))
+}
+
+fn colored_frame(params: RenderParams, computed: Option<Vec<Sample>>) -> Frame {
+    let aa = params.antialias;
+    let grid = (aa * aa) as usize;
+    let kernel = kernel::current();
+    let center = (params.viewport.center, params.viewport.center_low);
+    let formula = matches!(params.fractal, Fractal::Formula { .. });
+    let deep = computed.is_none() && !formula && !Precision::Double.resolves(&params);
+    let reference = (deep && params.fractal.quadratic()).then(|| {
+        let size = (params.width, params.height);
+        let (w, h) = (params.width as f64, params.height as f64);
+        let corners = [(0.0, 0.0), (w, 0.0), (0.0, h), (w, h)]
+            .map(|(x, y)| params.viewport.offset(x, y, size));
+        let precision = match Precision::DoubleDouble.resolves(&params) {
+            true => Precision::DoubleDouble,
+            false => Precision::FixedPoint,
+        };
+        let view = &params.viewport;
+        perturbation::reference(params.fractal, view, precision, params.iterations, &corners)
+    });
+    let subdivided = (computed.is_none() && !deep && subdivide::applies(&params))
+        .then(|| subdivide::escape_times(&params, kernel));
+    let tiles = morton::tiles(params.width, params.height)
+        .into_par_iter()
+        .map(|origin| {
+            let pixels = morton::pixels(origin, params.width, params.height).collect::<Vec<_>>();
+            let points = pixels
+                .iter()
+                .flat_map(|&(x, y)| subpixels(&params, x, y))
+                .collect::<Vec<_>>();
+            let offsets = || pixels.iter().flat_map(|&(x, y)| suboffsets(&params, x, y));
+            let (fractal, iterations) = (params.fractal, params.iterations);
+            let samples = match &reference {
+                Some(reference) => {
+                    let offsets = offsets().collect::<Vec<_>>();
+                    let view = &params.viewport;
+                    perturbation::escape_times(fractal, view, reference, &offsets, iterations)
+                }
+                None if deep => offsets()
+                    .map(|offset| dd::escape_time(fractal, center, offset, iterations))
+                    .collect(),
+                None => match (&computed, &subdivided) {
+                    (Some(computed), _) => pixels
+                        .iter()
+                        .flat_map(|&(x, y)| {
+                            let first = (y * params.width + x) as usize * grid;
+                            computed[first..first + grid].iter().copied()
+                        })
+                        .collect(),
+                    (None, Some(subdivided)) => pixels
+                        .iter()
+                        .map(|&(x, y)| subdivided[(y * params.width + x) as usize])
+                        .collect(),
+                    (None, None) => kernel.escape_time(fractal, &points, iterations),
+                },
+            };
+            let colored = points
+                .chunks(grid)
+                .zip(samples.chunks(grid))
+                .map(|(points, samples)| {
+                    let center = samples[(aa / 2 * aa + aa / 2) as usize];
+                    (average(&params, points, samples), center)
+                })
+                .collect::<Vec<_>>();
+            (pixels, colored, samples)
+        })
+        .collect::<Vec<_>>();
+
+    let size = (params.width * params.height) as usize;
+    let mut rgb = vec![0; size * 3];
+    let mut samples = vec![
+        Sample {
+            escape: None,
+            z: Complex::new(0.0, 0.0),
+        };
+        size
+    ];
+    let keep = aa > 1 && size * grid <= MAX_SUPERSAMPLES;
+    let mut supersamples = Vec::new();
+    if keep {
+        supersamples = samples.repeat(grid);
+    }
+    for (pixels, colored, tile_samples) in tiles {
+        for (((x, y), (color, sample)), grid_samples) in pixels
+            .into_iter()
+            .zip(colored)
+            .zip(tile_samples.chunks(grid))
+        {
+            let i = (y * params.width + x) as usize;
+            rgb[i * 3..i * 3 + 3].copy_from_slice(&color);
+            samples[i] = sample;
+            if keep {
+                supersamples[i * grid..(i + 1) * grid].copy_from_slice(grid_samples);
+            }
+        }
+    }
+
+    Frame {
+        width: params.width,
+        height: params.height,
+        rgb,
+        samples,
+        supersamples,
+        accumulation: None,
+        seed: 0,
+    }
+}
+
+// Points of the supersampling grid of a pixel, row by row.
+fn subpixels(params: &RenderParams, x: u32, y: u32) -> impl Iterator<Item = Complex<f64>> + '_ {
+    suboffsets(params, x, y).map(|offset| params.viewport.center + offset)
+}
+
+// The same as offsets from the center of the view.
+fn suboffsets(params: &RenderParams, x: u32, y: u32) -> impl Iterator<Item = Complex<f64>> + '_ {
+    let aa = params.antialias;
+    (0..aa).cartesian_product(0..aa).map(move |(sy, sx)| {
+        params.viewport.offset(
+            x as f64 + (sx as f64 + 0.5) / aa as f64 - 0.5,
+            y as f64 + (sy as f64 + 0.5) / aa as f64 - 0.5,
+            (params.width, params.height),
+        )
+    })
+}
+
+// Color of a pixel, averaged over its supersampling grid.
+fn average(params: &RenderParams, points: &[Complex<f64>], samples: &[Sample]) -> [u8; 3] {
+    let mut sum = [0u32; 3];
+    for (&c, &sample) in points.iter().zip(samples) {
+        let rgb = colorize(params, c, sample);
+        for (acc, channel) in sum.iter_mut().zip(rgb) {
+            *acc += channel as u32;
+        }
+    }
+    sum.map(|channel| (channel / samples.len() as u32) as u8)
+}
+
+// Stages following the computation: tone mapping of accumulation buffers and post-processing.
+pub fn finish(params: RenderParams, frame: &mut Frame) {
+    if let Some(accumulation) = &frame.accumulation {
+        let channels = params.orbits.channels().len();
+        // Each channel of a Nebulabrot is tone-mapped on its own, a Buddhabrot is gray.
+        let mapped = (0..channels)
+            .map(|channel| {
+                let counts = accumulation.iter().skip(channel).step_by(channels);
+                tonemap::apply(params.tonemap, &counts.copied().collect::<Vec<_>>())
+            })
+            .collect::<Vec<_>>();
+        // Dithered rather than truncated to 8 bits, against the banding of the faint orbits.
+        let width = frame.width as usize;
+        frame.rgb = (0..accumulation.len() / channels)
+            .flat_map(|i| {
+                let dither =
+                    noise::value((i % width) as i64, (i / width) as i64, frame.seed) as f32;
+                [0, 1, 2].map(|k| (mapped[k % channels][i] * 255.0 + dither) as u8)
+            })
+            .collect();
+    }
+    if let Some(effect) = params.effect {
+        postprocess::apply(effect, frame);
+    }
+    if let Some(bloom) = params.bloom {
+        postprocess::bloom(bloom, frame);
+    }
+}
+
+pub fn render_frame(params: RenderParams) -> Frame {
+    match params.fractal {
+        Fractal::Buddhabrot { samples_per_pixel } => {
+            render_buddhabrot(params, samples_per_pixel, None).0
+        }
+        _ => {
+            let mut frame = escape_time_frame(params);
+            finish(params, &mut frame);
+            frame
+        }
+    }
+}
+
+// The frame tone-mapped from an accumulation buffer.
+pub fn accumulation_frame(params: RenderParams, accumulation: Vec<f32>) -> Frame {
+    let pixels = (params.width * params.height) as usize;
+    let mut frame = Frame {
+        width: params.width,
+        height: params.height,
+        rgb: Vec::new(),
+        samples: vec![
+            Sample {
+                escape: None,
+                z: Complex::new(0.0, 0.0),
+            };
+            pixels
+        ],
+        supersamples: Vec::new(),
+        accumulation: Some(accumulation),
+        seed: 0,
+    };
+    finish(params, &mut frame);
+    frame
+}
+
+// A Buddhabrot frame, its sampling warm-started from an importance map if given, and the
+// importance map learned while rendering it.
+pub fn render_buddhabrot(
+    params: RenderParams,
+    samples_per_pixel: u32,
+    warm: Option<&Importance>,
+) -> (Frame, Importance) {
+    let (accumulation, learned) = buddhabrot::accumulate(&params, samples_per_pixel, warm);
+    (accumulation_frame(params, accumulation), learned)
+}
+
+// Re-derives the image of an accumulation frame for new tone-mapping or post-processing settings
+// without recomputing it. Returns false if the frame has nothing to re-derive from.
+pub fn retonemap(params: RenderParams, frame: &mut Frame) -> bool {
+    if frame.accumulation.is_none() {
+        return false;
+    }
+    finish(params, frame);
+    true
+}
+
+// Re-derives the image of a frame for new palette, coloring, tone-mapping or post-processing
+// settings from its accumulation buffer or its samples, as a second pass over the kept escape
+// results without iterating again. Returns false if the frame has to be rendered again: it
+// has no samples (restored from the cache, or too large a supersampling grid) or the coloring
+// is certified, which iterates once more.
+pub fn recolor(params: RenderParams, frame: &mut Frame) -> bool {
+    if retonemap(params, frame) {
+        return true;
+    }
+    let grid = (params.antialias * params.antialias) as usize;
+    let samples = match grid {
+        1 => &frame.samples,
+        _ => &frame.supersamples,
+    };
+    let certified = Some(Coloring::Certified);
+    // Interior points filled by subdivision have no last orbit value of their own to find the
+    // period from.
+    let period = [Some(params.coloring), params.interior].contains(&Some(Coloring::Period));
+    if samples.len() != (frame.width * frame.height) as usize * grid
+        || Some(params.coloring) == certified
+        || params.interior == certified
+        || period && grid == 1 && subdivide::enabled()
+    {
+        return false;
+    }
+    frame.rgb = samples
+        .par_chunks(grid)
+        .enumerate()
+        .flat_map_iter(|(i, samples)| {
+            let (x, y) = (i as u32 % params.width, i as u32 / params.width);
+            let points = subpixels(&params, x, y).collect::<Vec<_>>();
+            average(&params, &points, samples)
+        })
+        .collect();
+    finish(params, frame);
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::viewport::Viewport;
+
+    #[test]
+    fn shifted_frames() {
+        let frame = Frame::from_rgb(3, 2, (0..18).collect());
+        let right = frame.shifted(1, 0);
+        assert_eq!(right.rgb[..9], [0, 0, 0, 0, 1, 2, 3, 4, 5]);
+        let up_left = frame.shifted(-2, -1);
+        assert_eq!(up_left.rgb[..3], [15, 16, 17]);
+        assert!(up_left.rgb[3..].iter().all(|&channel| channel == 0));
+        assert!(frame.shifted(5, 0).rgb.iter().all(|&channel| channel == 0));
+    }
+
+    #[test]
+    fn recolors_without_iterating() {
+        let params = RenderParams::builder()
+            .size(40, 30)
+            .iterations(100)
+            .antialias(2)
+            .build()
+            .unwrap();
+        let mut frame = render_frame(params);
+        assert_eq!(frame.supersamples.len(), 40 * 30 * 4);
+        let mut rng = crate::buddhabrot::SplitMix64::new(3);
+        let recolored = params
+            .to_builder()
+            .palette(Some(Palette::random(&mut rng)))
+            .coloring(Coloring::Stripes {
+                period: 3,
+                duty: 0.5,
+            })
+            .interior(Some(Coloring::Period))
+            .build()
+            .unwrap();
+        assert!(recolor(recolored, &mut frame));
+        assert_eq!(frame.rgb, render_frame(recolored).rgb);
+        let certified = params.to_builder().coloring(Coloring::Certified).build();
+        assert!(!recolor(certified.unwrap(), &mut frame));
+        let mut cached = Frame::from_rgb(40, 30, frame.rgb.clone());
+        assert!(!recolor(params, &mut cached));
+    }
+
+    #[test]
+    fn interior_points_stop_early() {
+        let periods = [
+            (Complex::new(-0.1, 0.1), Some(1)),
+            (Complex::new(-1.1, 0.05), Some(2)),
+            (Complex::new(0.3, 0.0), None),
+            (Complex::new(-0.75, 0.1), None),
+        ];
+        for (c, period) in periods {
+            let cycle = main_component_cycle(c);
+            assert_eq!(
+                cycle.and_then(|z| cycle_period(Fractal::MANDELBROT, z, c)),
+                period
+            );
+        }
+        // Found on its cycle in the period-3 bulb, the same escapes as iterating throughout.
+        let c = Complex::new(-0.12, 0.75);
+        let sample = escape_time(Fractal::MANDELBROT, c, 100_000);
+        assert_eq!(sample.escape, None);
+        assert_eq!(cycle_period(Fractal::MANDELBROT, sample.z, c), Some(3));
+        for i in 0..400 {
+            let c = Complex::new(-2.0 + (i % 20) as f64 * 0.13, -1.2 + (i / 20) as f64 * 0.12);
+            let mut z = Complex::new(0.0, 0.0);
+            let escape = (0..500).find(|_| {
+                z = z * z + c;
+                z.norm_sqr() > 4.0
+            });
+            assert_eq!(
+                escape_time(Fractal::MANDELBROT, c, 500).escape,
+                escape,
+                "{c}"
+            );
+        }
+    }
+
+    #[test]
+    fn variants_keep_their_symmetries() {
+        let mut rng = crate::buddhabrot::SplitMix64::new(7);
+        for _ in 0..200 {
+            let c = Complex::new(rng.next_f64() * 3.0 - 2.0, rng.next_f64() * 3.0 - 1.5);
+            let escape = |fractal, c| escape_time(fractal, c, 200).escape;
+            // Conjugated and negated orbits are exact in floating point.
+            assert_eq!(
+                escape(Fractal::Tricorn, c),
+                escape(Fractal::Tricorn, c.conj())
+            );
+            let cubic = Fractal::Multibrot { exponent: 3 };
+            assert_eq!(escape(cubic, c), escape(cubic, -c));
+        }
+        let cubic = Fractal::Multibrot { exponent: 3 };
+        assert_eq!(escape_time(cubic, Complex::new(0.0, 0.5), 500).escape, None);
+        assert!(escape_time(cubic, Complex::new(-1.0, 0.0), 500)
+            .escape
+            .is_some());
+    }
+
+    #[test]
+    fn deep_views_keep_distinct_pixels() {
+        let mut viewport = Viewport::new(Complex::new(-1.25, 0.05), 0.0, 3e-20);
+        viewport.pan(Complex::new(1.3e-17, -2.1e-17));
+        let params = RenderParams::builder()
+            .size(32, 24)
+            .viewport(viewport.fit((32, 24)))
+            .iterations(500)
+            .build()
+            .unwrap();
+        assert!(!Precision::Double.resolves(&params));
+        let frame = render_frame(params);
+        // In f64 these pixels are a handful of points at most, here every one is its own.
+        let orbits = frame
+            .samples
+            .iter()
+            .map(|s| (s.z.re.to_bits(), s.z.im.to_bits()))
+            .unique()
+            .count();
+        assert!(orbits > 100, "{orbits} distinct orbits");
+    }
+}
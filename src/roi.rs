@@ -0,0 +1,214 @@
+use crate::params::RenderParams;
+use crate::render::{self, Frame};
+use crate::viewport::Viewport;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Rect {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Rect {
+    // Rectangle spanned by two window positions, clipped to the window. `None` if empty.
+    pub fn spanning(a: (i32, i32), b: (i32, i32), window_size: (u32, u32)) -> Option<Rect> {
+        let clip = |v: i32, max: u32| v.clamp(0, max as i32) as u32;
+        let (x0, x1) = (
+            clip(a.0.min(b.0), window_size.0),
+            clip(a.0.max(b.0), window_size.0),
+        );
+        let (y0, y1) = (
+            clip(a.1.min(b.1), window_size.1),
+            clip(a.1.max(b.1), window_size.1),
+        );
+        (x1 > x0 && y1 > y0).then_some(Rect {
+            x: x0,
+            y: y0,
+            width: x1 - x0,
+            height: y1 - y0,
+        })
+    }
+}
+
+// The part of `viewport` covered by the window rectangle.
+pub fn region_viewport(viewport: &Viewport, window_size: (u32, u32), rect: Rect) -> Viewport {
+    let mut region = Viewport {
+        width: viewport.width * rect.width as f64 / window_size.0 as f64,
+        height: viewport.height * rect.height as f64 / window_size.1 as f64,
+        ..*viewport
+    };
+    region.pan(viewport.offset(
+        rect.x as f64 + 0.5 * rect.width as f64,
+        rect.y as f64 + 0.5 * rect.height as f64,
+        window_size,
+    ));
+    region
+}
+
+// The rectangle spanned by two window positions grown to the aspect ratio of the window about
+// its center: that center and the size of the rectangle relative to the window.
+fn zoom_box(a: (i32, i32), b: (i32, i32), (width, height): (u32, u32)) -> ((f64, f64), f64) {
+    let center = (0.5 * (a.0 + b.0) as f64, 0.5 * (a.1 + b.1) as f64);
+    let ratio = ((a.0 - b.0).abs() as f64 / width as f64)
+        .max((a.1 - b.1).abs() as f64 / height as f64)
+        .max(1.0 / width.min(height) as f64);
+    (center, ratio)
+}
+
+// What a drag from `a` to `b` zooms into, clipped to the window for drawing it.
+pub fn zoom_outline(a: (i32, i32), b: (i32, i32), window_size: (u32, u32)) -> Option<Rect> {
+    let ((x, y), ratio) = zoom_box(a, b, window_size);
+    let (dx, dy) = (
+        0.5 * ratio * window_size.0 as f64,
+        0.5 * ratio * window_size.1 as f64,
+    );
+    let corner = |x: f64, y: f64| (x.round() as i32, y.round() as i32);
+    Rect::spanning(corner(x - dx, y - dy), corner(x + dx, y + dy), window_size)
+}
+
+// The view zoomed into the rectangle dragged from `a` to `b`, grown to the aspect ratio of the
+// window so that all of it stays in view.
+pub fn zoom_viewport(
+    viewport: &Viewport,
+    window_size: (u32, u32),
+    a: (i32, i32),
+    b: (i32, i32),
+) -> Viewport {
+    let ((x, y), ratio) = zoom_box(a, b, window_size);
+    let mut zoomed = Viewport {
+        width: viewport.width * ratio,
+        height: viewport.height * ratio,
+        ..*viewport
+    };
+    zoomed.pan(viewport.offset(x, y, window_size));
+    zoomed
+}
+
+pub fn outline(frame: &mut Frame, rect: Rect, color: [u8; 3]) {
+    let width = frame.width as usize;
+    let mut set = |x: u32, y: u32| {
+        let index = (y as usize * width + x as usize) * 3;
+        frame.rgb[index..index + 3].copy_from_slice(&color);
+    };
+    let (right, bottom) = (rect.x + rect.width - 1, rect.y + rect.height - 1);
+    for x in rect.x..=right {
+        set(x, rect.y);
+        set(x, bottom);
+    }
+    for y in rect.y..=bottom {
+        set(rect.x, y);
+        set(right, y);
+    }
+}
+
+// Re-renders the rectangle of `frame` (rendered with `params`) with `iterations` and
+// `antialias`, composites it over the frame and outlines it in `color`.
+pub fn enhance(
+    params: RenderParams,
+    frame: &mut Frame,
+    rect: Rect,
+    iterations: u32,
+    antialias: u32,
+    color: [u8; 3],
+) -> Result<(), String> {
+    let window_size = (params.width, params.height);
+    let region = params
+        .to_builder()
+        .viewport(region_viewport(&params.viewport, window_size, rect))
+        .size(rect.width, rect.height)
+        .iterations(iterations)
+        .antialias(antialias)
+        .build()?;
+    let enhanced = render::render_frame(region);
+    let width = frame.width as usize;
+    for row in 0..rect.height as usize {
+        let src = row * rect.width as usize;
+        let dst = (rect.y as usize + row) * width + rect.x as usize;
+        let len = rect.width as usize;
+        frame.rgb[dst * 3..(dst + len) * 3]
+            .copy_from_slice(&enhanced.rgb[src * 3..(src + len) * 3]);
+        if !frame.samples.is_empty() {
+            frame.samples[dst..dst + len].copy_from_slice(&enhanced.samples[src..src + len]);
+        }
+    }
+    outline(frame, rect, color);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use num::complex::Complex;
+
+    #[test]
+    fn spanning_rect_is_normalized_and_clipped() {
+        assert_eq!(
+            Rect::spanning((50, 40), (-10, 10), (100, 80)),
+            Some(Rect {
+                x: 0,
+                y: 10,
+                width: 50,
+                height: 30
+            })
+        );
+        assert_eq!(Rect::spanning((5, 5), (5, 20), (100, 80)), None);
+    }
+
+    #[test]
+    fn zoom_rectangle_keeps_the_window_aspect_ratio() {
+        let viewport = Viewport::new(Complex::new(0.0, 0.0), 4.0, 3.0);
+        // A wide drag is grown to 4:3 about its center.
+        let zoomed = zoom_viewport(&viewport, (400, 300), (100, 130), (300, 170));
+        assert!((zoomed.width - 2.0).abs() < 1e-12 && (zoomed.height - 1.5).abs() < 1e-12);
+        assert!(zoomed.center.norm() < 1e-12);
+        assert_eq!(
+            zoom_outline((100, 130), (300, 170), (400, 300)),
+            Some(Rect {
+                x: 100,
+                y: 75,
+                width: 200,
+                height: 150
+            })
+        );
+        let corner = zoom_viewport(&viewport, (400, 300), (0, 0), (40, 60));
+        assert!((corner.height - 0.6).abs() < 1e-12);
+        assert!((corner.center - viewport.pixel_to_complex(20.0, 30.0, (400, 300))).norm() < 1e-12);
+    }
+
+    #[test]
+    fn enhanced_region_lines_up_with_the_frame() {
+        let params = RenderParams::builder()
+            .viewport(Viewport::new(Complex::new(-0.7, 0.2), 0.8, 0.6))
+            .size(80, 60)
+            .iterations(50)
+            .build()
+            .unwrap();
+        let mut frame = render::render_frame(params);
+        let rect = Rect {
+            x: 20,
+            y: 10,
+            width: 30,
+            height: 25,
+        };
+        // At the same quality the region must reproduce the frame, apart from the outline.
+        let original = frame.rgb.clone();
+        enhance(params, &mut frame, rect, 50, 1, [255, 200, 0]).unwrap();
+        let inner = Rect {
+            x: 21,
+            y: 11,
+            width: 28,
+            height: 23,
+        };
+        for y in inner.y..inner.y + inner.height {
+            for x in inner.x..inner.x + inner.width {
+                let index = ((y * 80 + x) * 3) as usize;
+                assert_eq!(frame.rgb[index..index + 3], original[index..index + 3]);
+            }
+        }
+        assert_eq!(
+            frame.rgb[((10 * 80 + 20) * 3) as usize..][..3],
+            [255, 200, 0]
+        );
+    }
+}
@@ -0,0 +1,128 @@
+use crate::json::{FromJson, Json};
+use crate::viewport::Viewport;
+use std::path::Path;
+
+// Iteration count as a function of the zoom, the magnification relative to the default view.
+// Deeper views need more iterations to resolve the boundary, so a schedule saves adjusting the
+// count by hand while exploring or for every segment of a zoom video.
+#[derive(Clone, Debug, PartialEq)]
+pub enum IterationSchedule {
+    // `(zoom, iterations)` points sorted by zoom, interpolated linearly in the logarithm of the
+    // zoom and held constant beyond the first and the last point.
+    Table(Vec<(f64, u32)>),
+    // `base + per_octave * log2(zoom)`, capped at `max`.
+    Formula {
+        base: f64,
+        per_octave: f64,
+        max: Option<u32>,
+    },
+}
+
+impl FromJson for IterationSchedule {
+    // Either a table `[[1, 200], [1e6, 2000], [1e12, 6000]]` or a formula
+    // `{"base": 200, "per_octave": 100, "max": 20000}`.
+    fn from_json(json: &Json) -> Result<Self, String> {
+        if let Json::Array(points) = json {
+            let mut table = points
+                .iter()
+                .map(|point| match point.as_array()? {
+                    [zoom, iterations] => {
+                        let (zoom, iterations) = (zoom.as_f64()?, iterations.as_f64()?);
+                        if !(zoom > 0.0 && zoom.is_finite() && iterations >= 1.0) {
+                            return Err(format!("Invalid schedule point {point}"));
+                        }
+                        Ok((zoom, iterations.min(u32::MAX as f64) as u32))
+                    }
+                    _ => Err(format!("Expected [zoom, iterations], got {point}")),
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            if table.is_empty() {
+                return Err("Empty iteration schedule".to_string());
+            }
+            table.sort_by(|a, b| a.0.total_cmp(&b.0));
+            return Ok(IterationSchedule::Table(table));
+        }
+        let max = match json.get("max") {
+            Some(max) => Some(max.as_f64()?.max(1.0) as u32),
+            None => None,
+        };
+        Ok(IterationSchedule::Formula {
+            base: json.field("base")?.as_f64()?,
+            per_octave: json.field("per_octave")?.as_f64()?,
+            max,
+        })
+    }
+}
+
+impl IterationSchedule {
+    // Followed by the automatic iteration count without a schedule of its own: the 200
+    // iterations of the initial view and 50 more for every doubling of the zoom.
+    pub const AUTO: IterationSchedule = IterationSchedule::Formula {
+        base: 200.0,
+        per_octave: 50.0,
+        max: None,
+    };
+
+    pub fn load(path: &Path) -> Result<IterationSchedule, String> {
+        let text = std::fs::read_to_string(path).map_err(|e| format!("{}: {e}", path.display()))?;
+        IterationSchedule::from_json(&Json::parse(&text)?)
+            .map_err(|e| format!("{}: {e}", path.display()))
+    }
+
+    pub fn iterations(&self, zoom: f64) -> u32 {
+        let octaves = zoom.max(f64::MIN_POSITIVE).log2();
+        let iterations = match self {
+            IterationSchedule::Table(table) => {
+                let after = table.partition_point(|&(z, _)| z.log2() <= octaves);
+                match (after.checked_sub(1).map(|i| table[i]), table.get(after)) {
+                    (Some((z0, i0)), Some(&(z1, i1))) => {
+                        let t = (octaves - z0.log2()) / (z1.log2() - z0.log2());
+                        i0 as f64 + t * (i1 as f64 - i0 as f64)
+                    }
+                    (Some((_, i)), None) | (None, Some(&(_, i))) => i as f64,
+                    (None, None) => unreachable!("schedules have at least one point"),
+                }
+            }
+            IterationSchedule::Formula {
+                base,
+                per_octave,
+                max,
+            } => (base + per_octave * octaves).min(max.map_or(f64::INFINITY, |max| max as f64)),
+        };
+        iterations.round().clamp(1.0, u32::MAX as f64) as u32
+    }
+
+    // Magnification of a view relative to the default one.
+    pub fn zoom(viewport: &Viewport) -> f64 {
+        Viewport::default().height / viewport.height
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn table_interpolates_in_log_zoom() {
+        let schedule =
+            IterationSchedule::from_json(&Json::parse("[[1e6, 2000], [1, 200]]").unwrap()).unwrap();
+        assert_eq!(schedule.iterations(0.5), 200);
+        assert_eq!(schedule.iterations(1.0), 200);
+        assert_eq!(schedule.iterations(1e3), 1100);
+        assert_eq!(schedule.iterations(1e6), 2000);
+        assert_eq!(schedule.iterations(1e9), 2000);
+        assert!(IterationSchedule::from_json(&Json::parse("[]").unwrap()).is_err());
+        assert!(IterationSchedule::from_json(&Json::parse("[[0, 10]]").unwrap()).is_err());
+    }
+
+    #[test]
+    fn formula_is_linear_in_octaves() {
+        let json = Json::parse(r#"{"base": 200, "per_octave": 50, "max": 600}"#).unwrap();
+        let schedule = IterationSchedule::from_json(&json).unwrap();
+        assert_eq!(schedule.iterations(1.0), 200);
+        assert_eq!(schedule.iterations(16.0), 400);
+        assert_eq!(schedule.iterations(1e6), 600);
+        assert_eq!(schedule.iterations(1e-9), 1);
+        assert_eq!(IterationSchedule::AUTO.iterations(1024.0), 700);
+    }
+}
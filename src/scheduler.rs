@@ -0,0 +1,335 @@
+use crate::params::{Backend, Fractal, RenderParams};
+use crate::render::{self, Frame};
+use crate::viewport::TAIL_TERMS;
+use num::complex::Complex;
+use rayon::prelude::*;
+use std::time::Instant;
+
+// Weight of the latest measurement in a worker's running throughput.
+const THROUGHPUT_SMOOTHING: f64 = 0.3;
+
+// Fewest ulps of the view coordinates a pixel has to span to be resolved.
+pub const MIN_PIXEL_ULPS: f64 = 8.0;
+
+// Number formats orbits can be computed in. GPU shaders compute in f32, or in pairs of f32
+// (double-float arithmetic) for deeper views, as GPUs without f64 have to. Pairs of f64
+// (double-double) go beyond f64, slowly, which the CPU worker switches to for deep views, and
+// fixed point with as many bits as the center has terms beyond that, for reference orbits only.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Precision {
+    Single,
+    DoubleSingle,
+    Double,
+    DoubleDouble,
+    FixedPoint,
+}
+
+impl Precision {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Precision::Single => "f32",
+            Precision::DoubleSingle => "double-f32",
+            Precision::Double => "f64",
+            Precision::DoubleDouble => "double-f64",
+            Precision::FixedPoint => "fixed-point",
+        }
+    }
+
+    // Relative precision of a coordinate.
+    pub fn epsilon(&self) -> f64 {
+        match self {
+            Precision::Single => f32::EPSILON as f64,
+            // A little less than the 48 bits of the two mantissas, which the f32 offsets of
+            // the pixels and the splitting of the products do not quite keep.
+            Precision::DoubleSingle => 2f64.powi(-44),
+            Precision::Double => f64::EPSILON,
+            Precision::DoubleDouble => 2f64.powi(-104),
+            Precision::FixedPoint => 2f64.powi(-53 * (TAIL_TERMS as i32 + 2)),
+        }
+    }
+
+    // The most precise format the CPU renders `fractal` in: the quadratic ones by perturbation
+    // around a fixed-point reference, the others in double-double.
+    pub fn reach(fractal: Fractal) -> Precision {
+        match fractal.quadratic() {
+            true => Precision::FixedPoint,
+            false => Precision::DoubleDouble,
+        }
+    }
+
+    // Smallest pixel distinguishable at the coordinates of a view centered at `center`.
+    pub fn min_pixel(&self, center: Complex<f64>) -> f64 {
+        let magnitude = center.re.abs().max(center.im.abs()).max(1.0);
+        MIN_PIXEL_ULPS * self.epsilon() * magnitude
+    }
+
+    pub fn resolves(&self, params: &RenderParams) -> bool {
+        let pixel = params.viewport.width / params.width as f64;
+        pixel >= self.min_pixel(params.viewport.center)
+    }
+}
+
+// Something that renders tiles. Every backend returns complete escape-time frames (RGB plus
+// samples), so tiles from different workers are interchangeable.
+pub trait Worker: Send + Sync {
+    fn backend(&self) -> Backend;
+    fn precision(&self) -> Precision;
+    fn render(&self, jobs: &[RenderParams]) -> Vec<Frame>;
+}
+
+pub struct CpuWorker;
+
+impl Worker for CpuWorker {
+    fn backend(&self) -> Backend {
+        Backend::Cpu
+    }
+
+    fn precision(&self) -> Precision {
+        Precision::FixedPoint
+    }
+
+    fn render(&self, jobs: &[RenderParams]) -> Vec<Frame> {
+        jobs.par_iter()
+            .map(|params| render::escape_time_frame(*params))
+            .collect()
+    }
+}
+
+// Splits `count` jobs proportionally to `weights` (largest remainder method), so faster workers
+// take the bulk and slower ones the remainder.
+pub fn split(count: usize, weights: &[f64]) -> Vec<usize> {
+    let total = weights.iter().sum::<f64>();
+    if total <= 0.0 {
+        let mut shares = vec![0; weights.len()];
+        if let Some(first) = shares.first_mut() {
+            *first = count;
+        }
+        return shares;
+    }
+    let exact = weights
+        .iter()
+        .map(|w| count as f64 * w / total)
+        .collect::<Vec<_>>();
+    let mut shares = exact.iter().map(|e| e.floor() as usize).collect::<Vec<_>>();
+    let mut order = (0..weights.len()).collect::<Vec<_>>();
+    order.sort_by(|&a, &b| (exact[b] - exact[b].floor()).total_cmp(&(exact[a] - exact[a].floor())));
+    let missing = count - shares.iter().sum::<usize>();
+    for &index in order.iter().take(missing) {
+        shares[index] += 1;
+    }
+    shares
+}
+
+// Distributes every batch of tiles over all available workers proportionally to their
+// measured throughput, running them concurrently.
+pub struct Scheduler {
+    workers: Vec<(Box<dyn Worker>, Option<f64>)>,
+}
+
+impl Scheduler {
+    // All backends usable on this machine: the CPU, and with the `gpu` feature the GPU if it
+    // can be opened.
+    pub fn detect() -> Scheduler {
+        #[allow(unused_mut)]
+        let mut workers: Vec<Box<dyn Worker>> = vec![Box::new(CpuWorker)];
+        #[cfg(feature = "gpu")]
+        if let Ok(worker) = crate::gpu::GpuWorker::detect() {
+            workers.push(Box::new(worker));
+        }
+        Scheduler::new(workers)
+    }
+
+    pub fn new(workers: Vec<Box<dyn Worker>>) -> Scheduler {
+        Scheduler {
+            workers: workers.into_iter().map(|worker| (worker, None)).collect(),
+        }
+    }
+
+    pub fn backends(&self) -> Vec<&'static str> {
+        self.workers
+            .iter()
+            .map(|(worker, _)| worker.backend().name())
+            .collect()
+    }
+
+    pub fn workers(&self) -> impl Iterator<Item = &dyn Worker> {
+        self.workers.iter().map(|(worker, _)| worker.as_ref())
+    }
+
+    // Workers precise enough for every job. When none is, the most precise one does its best.
+    fn eligible(&self, jobs: &[RenderParams]) -> Vec<bool> {
+        let precise = |worker: &dyn Worker| jobs.iter().all(|job| worker.precision().resolves(job));
+        let mut eligible = self
+            .workers
+            .iter()
+            .map(|(worker, _)| precise(worker.as_ref()))
+            .collect::<Vec<_>>();
+        if !eligible.contains(&true) {
+            if let Some(best) =
+                (0..self.workers.len()).max_by_key(|&i| self.workers[i].0.precision())
+            {
+                eligible[best] = true;
+            }
+        }
+        eligible
+    }
+
+    // How many of the jobs each worker gets, in proportion to its throughput.
+    pub fn shares(&self, jobs: &[RenderParams]) -> Vec<usize> {
+        let eligible = self.eligible(jobs);
+        // Workers that were not measured yet get the average share.
+        let known = self
+            .workers
+            .iter()
+            .filter_map(|(_, t)| *t)
+            .collect::<Vec<_>>();
+        let fallback = match known.len() {
+            0 => 1.0,
+            n => known.iter().sum::<f64>() / n as f64,
+        };
+        let weights = self
+            .workers
+            .iter()
+            .zip(&eligible)
+            .map(|((_, throughput), eligible)| match eligible {
+                true => throughput.unwrap_or(fallback),
+                false => 0.0,
+            })
+            .collect::<Vec<_>>();
+        split(jobs.len(), &weights)
+    }
+
+    // Folds a measurement of `pixels` rendered in `seconds` into the throughput of a worker.
+    pub fn measure(&mut self, worker: usize, pixels: f64, seconds: f64) {
+        if pixels <= 0.0 || seconds <= 0.0 {
+            return;
+        }
+        let measured = pixels / seconds;
+        let throughput = &mut self.workers[worker].1;
+        *throughput = Some(match *throughput {
+            Some(t) => t + THROUGHPUT_SMOOTHING * (measured - t),
+            None => measured,
+        });
+    }
+
+    pub fn render(&mut self, jobs: &[RenderParams]) -> Vec<Frame> {
+        if self.workers.len() == 1 {
+            return self.workers[0].0.render(jobs);
+        }
+        let shares = self.shares(jobs);
+        let mut batches = Vec::new();
+        let mut start = 0;
+        for share in shares {
+            batches.push(&jobs[start..start + share]);
+            start += share;
+        }
+        let results = std::thread::scope(|scope| {
+            let handles = self
+                .workers
+                .iter()
+                .zip(&batches)
+                .map(|((worker, _), batch)| {
+                    scope.spawn(move || {
+                        let stamp = Instant::now();
+                        let frames = worker.render(batch);
+                        (frames, stamp.elapsed().as_secs_f64())
+                    })
+                })
+                .collect::<Vec<_>>();
+            handles
+                .into_iter()
+                .map(|handle| handle.join().expect("render worker panicked"))
+                .collect::<Vec<_>>()
+        });
+        let mut frames = Vec::with_capacity(jobs.len());
+        for (index, (batch, (rendered, seconds))) in batches.iter().zip(results).enumerate() {
+            let pixels = batch
+                .iter()
+                .map(|p| (p.width * p.height) as f64)
+                .sum::<f64>();
+            self.measure(index, pixels, seconds);
+            frames.extend(rendered);
+        }
+        frames
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::viewport::Viewport;
+    use num::complex::Complex;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    #[test]
+    fn split_is_proportional_and_complete() {
+        assert_eq!(split(10, &[3.0, 1.0]), vec![8, 2]);
+        assert_eq!(split(7, &[1.0, 1.0, 1.0]).iter().sum::<usize>(), 7);
+        assert_eq!(split(5, &[0.0, 0.0]), vec![5, 0]);
+        assert_eq!(split(0, &[2.0, 1.0]), vec![0, 0]);
+    }
+
+    // A worker that counts its jobs.
+    struct Counting(Arc<AtomicUsize>, Precision);
+
+    impl Worker for Counting {
+        fn backend(&self) -> Backend {
+            Backend::Cpu
+        }
+
+        fn precision(&self) -> Precision {
+            self.1
+        }
+
+        fn render(&self, jobs: &[RenderParams]) -> Vec<Frame> {
+            self.0.fetch_add(jobs.len(), Ordering::SeqCst);
+            CpuWorker.render(jobs)
+        }
+    }
+
+    #[test]
+    fn faster_worker_gets_more_work() {
+        let (fast, slow) = (Arc::new(AtomicUsize::new(0)), Arc::new(AtomicUsize::new(0)));
+        let mut scheduler = Scheduler::new(vec![
+            Box::new(Counting(fast.clone(), Precision::Double)),
+            Box::new(Counting(slow.clone(), Precision::Double)),
+        ]);
+        let jobs = vec![RenderParams::builder().size(8, 8).build().unwrap(); 20];
+        // Unmeasured workers share evenly.
+        assert_eq!(scheduler.shares(&jobs), vec![10, 10]);
+        scheduler.measure(0, 4000.0, 1.0);
+        scheduler.measure(1, 1000.0, 1.0);
+        assert_eq!(scheduler.shares(&jobs), vec![16, 4]);
+        // Later measurements move the throughput only part of the way.
+        scheduler.measure(1, 4000.0, 1.0);
+        assert_eq!(scheduler.shares(&jobs), vec![14, 6]);
+        let shares = scheduler.shares(&jobs[..10]);
+        assert_eq!(scheduler.render(&jobs[..10]).len(), 10);
+        assert_eq!(fast.load(Ordering::SeqCst), shares[0]);
+        assert_eq!(slow.load(Ordering::SeqCst), shares[1]);
+    }
+
+    #[test]
+    fn deep_views_fall_back_to_precise_workers() {
+        let (double, precise) = (Arc::new(AtomicUsize::new(0)), Arc::new(AtomicUsize::new(0)));
+        let mut scheduler = Scheduler::new(vec![
+            Box::new(Counting(double.clone(), Precision::Double)),
+            Box::new(Counting(precise.clone(), Precision::DoubleDouble)),
+        ]);
+        let shallow = RenderParams::builder().size(8, 8).build().unwrap();
+        let deep = shallow
+            .to_builder()
+            .viewport(Viewport::new(Complex::new(-0.75, 0.1), 1e-20, 1e-20))
+            .build()
+            .unwrap();
+        assert!(!Precision::Double.resolves(&deep) && CpuWorker.precision().resolves(&deep));
+        scheduler.render(&[shallow; 10]);
+        assert!(double.load(Ordering::SeqCst) > 0);
+        let before = precise.load(Ordering::SeqCst);
+        double.store(0, Ordering::SeqCst);
+        scheduler.render(&[deep; 10]);
+        assert_eq!(double.load(Ordering::SeqCst), 0);
+        assert_eq!(precise.load(Ordering::SeqCst), before + 10);
+    }
+}
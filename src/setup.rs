@@ -0,0 +1,251 @@
+use crate::config::Config;
+use crate::kernel::Kernel;
+use crate::keys::KeyScheme;
+use crate::palette::Palette;
+use crate::render::Frame;
+use crate::style::Style;
+use crate::text;
+use std::time::Duration;
+
+// Window sizes offered, besides the one the explorer would open with.
+const SIZES: [(u32, u32); 5] = [
+    (800, 600),
+    (1024, 768),
+    (1280, 720),
+    (1600, 900),
+    (1920, 1080),
+];
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Input {
+    Previous,
+    Next,
+    Confirm,
+    // Keeps the defaults, so that the setup is not offered again.
+    Skip,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Step {
+    Kernel,
+    Palette,
+    Size,
+    Keys,
+    Done,
+}
+
+// The setup of the first run: one choice per step, confirmed with `Return`, its result written
+// as the configuration.
+pub struct Setup {
+    step: Step,
+    skipped: bool,
+    kernels: Vec<(Kernel, Duration)>,
+    kernel: usize,
+    palettes: Vec<(String, Palette)>,
+    // Index into `palettes`, the built-in gradient after the last.
+    palette: usize,
+    sizes: Vec<(u32, u32)>,
+    size: usize,
+    // Index into `KeyScheme::ALL`.
+    keys: usize,
+}
+
+impl Setup {
+    // The kernels timed by the benchmark, the fastest first and preselected.
+    pub fn new(
+        kernels: Vec<(Kernel, Duration)>,
+        palettes: Vec<(String, Palette)>,
+        size: (u32, u32),
+    ) -> Setup {
+        let mut sizes = SIZES.to_vec();
+        if !sizes.contains(&size) {
+            sizes.insert(0, size);
+        }
+        Setup {
+            step: Step::Kernel,
+            skipped: false,
+            kernels,
+            kernel: 0,
+            palette: palettes.len(),
+            palettes,
+            size: sizes.iter().position(|&offered| offered == size).unwrap(),
+            sizes,
+            keys: 0,
+        }
+    }
+
+    // True once the setup is over.
+    pub fn input(&mut self, input: Input) -> bool {
+        let (choice, count) = match self.step {
+            Step::Kernel => (&mut self.kernel, self.kernels.len()),
+            Step::Palette => (&mut self.palette, self.palettes.len() + 1),
+            Step::Size => (&mut self.size, self.sizes.len()),
+            Step::Keys => (&mut self.keys, KeyScheme::ALL.len()),
+            Step::Done => return true,
+        };
+        match input {
+            Input::Previous => *choice = (*choice + count - 1) % count.max(1),
+            Input::Next => *choice = (*choice + 1) % count.max(1),
+            Input::Confirm => {
+                self.step = match self.step {
+                    Step::Kernel => Step::Palette,
+                    Step::Palette => Step::Size,
+                    Step::Size => Step::Keys,
+                    _ => Step::Done,
+                }
+            }
+            Input::Skip => (self.step, self.skipped) = (Step::Done, true),
+        }
+        self.step == Step::Done
+    }
+
+    // The palette to show the choice with.
+    pub fn palette(&self) -> Option<Palette> {
+        self.palettes.get(self.palette).map(|&(_, palette)| palette)
+    }
+
+    pub fn config(&self) -> Config {
+        let (width, height) = self.sizes[self.size];
+        match self.skipped {
+            true => Config {
+                kernel: None,
+                palette: None,
+                width,
+                height,
+                keys: KeyScheme::Arrows,
+            },
+            false => Config {
+                kernel: self.kernels.get(self.kernel).map(|&(kernel, _)| kernel),
+                palette: self
+                    .palettes
+                    .get(self.palette)
+                    .map(|(name, _)| name.clone()),
+                width,
+                height,
+                keys: KeyScheme::ALL[self.keys],
+            },
+        }
+    }
+
+    fn lines(&self) -> Vec<String> {
+        let (title, choices, chosen) = match self.step {
+            Step::Kernel => (
+                "1/4 Escape-time kernel, by the time of the benchmark",
+                self.kernels
+                    .iter()
+                    .map(|(kernel, elapsed)| format!("{:<8} {elapsed:.0?}", kernel.name()))
+                    .collect::<Vec<_>>(),
+                self.kernel,
+            ),
+            Step::Palette => (
+                "2/4 Palette",
+                self.palettes
+                    .iter()
+                    .map(|(name, _)| name.clone())
+                    .chain(["built-in".to_string()])
+                    .collect(),
+                self.palette,
+            ),
+            Step::Size => (
+                "3/4 Window size",
+                self.sizes
+                    .iter()
+                    .map(|(width, height)| format!("{width}x{height}"))
+                    .collect(),
+                self.size,
+            ),
+            Step::Keys => (
+                "4/4 Keys",
+                KeyScheme::ALL
+                    .iter()
+                    .map(|scheme| format!("{:<7} {}", scheme.name(), scheme.description()))
+                    .collect(),
+                self.keys,
+            ),
+            Step::Done => return Vec::new(),
+        };
+        let mut lines = vec!["Setup".to_string(), title.to_string()];
+        lines.extend(
+            choices
+                .iter()
+                .enumerate()
+                .map(|(i, choice)| match i == chosen {
+                    true => format!("> {choice}"),
+                    false => format!("  {choice}"),
+                }),
+        );
+        lines.push("Up/Down choose, Return confirms, Esc keeps the defaults".to_string());
+        lines
+    }
+
+    // The current step over the top-left corner of the frame.
+    pub fn draw(&self, frame: &mut Frame, style: &Style) {
+        let lines = self.lines();
+        let scale = if frame.width >= 640 { 2 } else { 1 };
+        let line_height = (text::LINE_HEIGHT * scale) as i32;
+        let longest = lines.iter().map(String::len).max().unwrap_or(0) as u32;
+        let size = (
+            longest * text::ADVANCE * scale + 16,
+            lines.len() as u32 * line_height as u32 + 12,
+        );
+        style.panel(frame, (0, 0), size);
+        for (i, line) in lines.iter().enumerate() {
+            let color = if i == 0 { style.title } else { style.text };
+            text::draw(frame, 8, 8 + i as i32 * line_height, scale, color, line);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::palette;
+
+    fn offered() -> Setup {
+        let kernels = vec![
+            (Kernel::Avx2, Duration::from_millis(4)),
+            (Kernel::Scalar, Duration::from_millis(12)),
+        ];
+        let palettes = palette::NAMED
+            .iter()
+            .map(|&(name, palette)| (name.to_string(), palette))
+            .collect();
+        Setup::new(kernels, palettes, (1000, 700))
+    }
+
+    #[test]
+    fn steps_through_the_choices() {
+        let mut setup = offered();
+        assert!(!setup.input(Input::Previous));
+        assert!(!setup.input(Input::Confirm));
+        // The built-in gradient is chosen until another palette is.
+        assert_eq!(setup.palette(), None);
+        setup.input(Input::Next);
+        assert_eq!(setup.palette(), Some(palette::NAMED[0].1));
+        setup.input(Input::Confirm);
+        assert!(setup.lines().contains(&"> 1000x700".to_string()));
+        setup.input(Input::Next);
+        assert!(!setup.input(Input::Confirm));
+        assert!(setup.lines()[2].starts_with("> arrows"));
+        setup.input(Input::Previous);
+        assert!(setup.input(Input::Confirm));
+        assert_eq!(
+            setup.config(),
+            Config {
+                kernel: Some(Kernel::Scalar),
+                palette: Some(palette::NAMED[0].0.to_string()),
+                width: 800,
+                height: 600,
+                keys: KeyScheme::Vi,
+            }
+        );
+        let mut skipped = offered();
+        skipped.input(Input::Next);
+        assert!(skipped.input(Input::Skip));
+        assert_eq!(skipped.config().kernel, None);
+        assert_eq!(
+            (skipped.config().width, skipped.config().height),
+            (1000, 700)
+        );
+    }
+}
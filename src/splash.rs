@@ -0,0 +1,74 @@
+use crate::params::RenderParams;
+use crate::render::{self, Frame};
+use crate::style::Style;
+use crate::text;
+
+// Pixel steps of the coarse passes revealed before the first full-resolution frame, each one
+// costing a quarter of the next.
+pub const PASSES: [u32; 3] = [16, 8, 4];
+
+const HINTS: [&str; 4] = [
+    concat!("Mandelbrot explorer ", env!("CARGO_PKG_VERSION")),
+    "mouse: zoom in / out   arrows: pan   [ ]: rotate",
+    "+ / -: iterations   A: anti-aliasing   E: effects",
+    "C: Julia sets   U: Buddhabrot   `: console",
+];
+
+// Renders the view with one sample per `step x step` block of pixels, magnified to full size.
+pub fn coarse(params: RenderParams, step: u32) -> Frame {
+    let (width, height) = (params.width.div_ceil(step), params.height.div_ceil(step));
+    let small = render::render_frame(RenderParams {
+        width,
+        height,
+        antialias: 1,
+        ..params
+    });
+    let rgb = (0..params.height)
+        .flat_map(|y| (0..params.width).map(move |x| (x / step, y / step)))
+        .flat_map(|(x, y)| {
+            let offset = ((y * width + x) * 3) as usize;
+            [
+                small.rgb[offset],
+                small.rgb[offset + 1],
+                small.rgb[offset + 2],
+            ]
+        })
+        .collect();
+    Frame::from_rgb(params.width, params.height, rgb)
+}
+
+// Version and the most useful keys over the top-left corner, shown until the first input.
+pub fn hints(frame: &mut Frame, style: &Style) {
+    let scale = if frame.width >= 640 { 2 } else { 1 };
+    let line_height = (text::LINE_HEIGHT * scale) as i32;
+    let longest = HINTS.iter().map(|line| line.len()).max().unwrap_or(0) as u32;
+    let size = (
+        longest * text::ADVANCE * scale + 16,
+        HINTS.len() as u32 * line_height as u32 + 12,
+    );
+    style.panel(frame, (0, 0), size);
+    for (i, line) in HINTS.iter().enumerate() {
+        let color = if i == 0 { style.title } else { style.text };
+        text::draw(frame, 8, 8 + i as i32 * line_height, scale, color, line);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn coarse_passes_magnify_blocks() {
+        let params = RenderParams::builder()
+            .size(30, 20)
+            .iterations(50)
+            .build()
+            .unwrap();
+        assert_eq!(coarse(params, 1).rgb, render::render_frame(params).rgb);
+        let frame = coarse(params, 8);
+        assert_eq!(frame.rgb.len(), 30 * 20 * 3);
+        let pixel = |x: usize, y: usize| &frame.rgb[(y * 30 + x) * 3..(y * 30 + x) * 3 + 3];
+        assert_eq!(pixel(0, 0), pixel(7, 7));
+        assert_eq!(pixel(24, 16), pixel(29, 19));
+    }
+}
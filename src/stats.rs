@@ -0,0 +1,126 @@
+use crate::params::{Fractal, RenderParams};
+use crate::tiff;
+use itertools::Itertools;
+use num::complex::Complex;
+use rayon::prelude::*;
+use std::f64::consts::TAU;
+use std::path::Path;
+
+// A large bailout radius makes the smooth iteration count and the distance estimate accurate.
+const BAILOUT: f64 = 256.0;
+
+// Per-pixel orbit statistics. Interior points have zero smooth iteration count and distance.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct OrbitStats {
+    // Continuous escape time, n + 1 - log2(ln |z_n|).
+    pub smooth: f64,
+    // Distance estimate to the set, in the complex plane.
+    pub distance: f64,
+    // Argument of the final z in turns, 0..1.
+    pub angle: f64,
+    // Closest approach of the orbit (z_1, z_2, ...) to the origin.
+    pub trap: f64,
+}
+
+pub fn orbit_stats(fractal: Fractal, point: Complex<f64>, iterations: u32) -> OrbitStats {
+    // Mandelbrot orbits are differentiated with respect to c, Julia orbits with respect to z0.
+    // The folding of the Burning Ship and the conjugation of the Tricorn are not holomorphic,
+    // their distance estimates are rough. Formulas take the derivative of the quadratic step,
+    // as rough.
+    let (mut z, c) = fractal.start(point);
+    let (mut dz, dc) = match fractal {
+        Fractal::Julia { .. } => (Complex::new(1.0, 0.0), 0.0),
+        _ => (Complex::new(0.0, 0.0), 1.0),
+    };
+    let mut trap = f64::INFINITY;
+    for i in 0..iterations {
+        dz = match fractal {
+            Fractal::Multibrot { exponent } => exponent as f64 * z.powu(exponent - 1) * dz + dc,
+            _ => 2.0 * z * dz + dc,
+        };
+        z = fractal.step(z, c);
+        trap = trap.min(z.norm());
+        let modulus = z.norm();
+        if modulus > BAILOUT {
+            let log = modulus.ln();
+            return OrbitStats {
+                smooth: i as f64 + 2.0 - log.log2(),
+                distance: modulus * log / dz.norm(),
+                angle: (z.arg() / TAU).rem_euclid(1.0),
+                trap,
+            };
+        }
+    }
+    OrbitStats {
+        smooth: 0.0,
+        distance: 0.0,
+        angle: (z.arg() / TAU).rem_euclid(1.0),
+        trap,
+    }
+}
+
+pub fn compute(params: &RenderParams) -> Vec<OrbitStats> {
+    let window_size = (params.width, params.height);
+    (0..params.height)
+        .cartesian_product(0..params.width)
+        .collect::<Vec<_>>()
+        .par_iter()
+        .map(|&(y, x)| {
+            let point = params
+                .viewport
+                .pixel_to_complex(x as f64, y as f64, window_size);
+            orbit_stats(params.fractal, point, params.iterations)
+        })
+        .collect()
+}
+
+// Writes the statistics of the view as a multi-layer float TIFF for compositing and coloring
+// in external editors. The distance estimate layer is in pixels.
+pub fn export(params: &RenderParams, path: &Path) -> Result<(), String> {
+    let stats = compute(params);
+    let pixel = params.viewport.width / params.width as f64;
+    let layer = |f: fn(&OrbitStats) -> f64| stats.iter().map(|s| f(s) as f32).collect();
+    let layers = [
+        ("smooth iteration", layer(|s| s.smooth)),
+        (
+            "distance estimate",
+            stats.iter().map(|s| (s.distance / pixel) as f32).collect(),
+        ),
+        ("final angle", layer(|s| s.angle)),
+        ("orbit trap distance", layer(|s| s.trap)),
+    ];
+    tiff::write_layers(path, params.width, params.height, &layers)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn smooth_iteration_is_continuous() {
+        let stats = (0..200)
+            .map(|i| {
+                orbit_stats(
+                    Fractal::MANDELBROT,
+                    Complex::new(0.3 + i as f64 * 1e-4, 0.0),
+                    1000,
+                )
+            })
+            .collect::<Vec<_>>();
+        assert!(stats
+            .windows(2)
+            .all(|w| (w[0].smooth - w[1].smooth).abs() < 0.5));
+    }
+
+    #[test]
+    fn distance_estimate_tracks_true_distance() {
+        // The set meets the positive real axis at 0.25.
+        for x in [0.3, 0.5, 1.0] {
+            let stats = orbit_stats(Fractal::MANDELBROT, Complex::new(x, 0.0), 1000);
+            let distance = x - 0.25;
+            assert!(stats.distance > 0.1 * distance && stats.distance < 4.0 * distance);
+        }
+        let inside = orbit_stats(Fractal::MANDELBROT, Complex::new(-0.1, 0.0), 1000);
+        assert_eq!((inside.smooth, inside.distance), (0.0, 0.0));
+    }
+}
@@ -0,0 +1,108 @@
+use crate::render::Frame;
+use crate::text;
+
+// Colors of everything drawn over the fractal, set as a whole by the UI theme.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Style {
+    pub text: [u8; 3],
+    // The title line of the startup hints.
+    pub title: [u8; 3],
+    // Text backgrounds blend the frame towards `panel` by `panel_opacity`.
+    pub panel: [u8; 3],
+    pub panel_opacity: f64,
+    pub annotation: [u8; 3],
+    pub measurement: [u8; 3],
+    pub orbit: [u8; 3],
+    // Outline of the regions rendered again by `R`.
+    pub outline: [u8; 3],
+}
+
+impl Style {
+    // Background keeping text readable over any part of the fractal.
+    pub fn panel(&self, frame: &mut Frame, position: (i32, i32), size: (u32, u32)) {
+        text::blend(frame, position, size, self.panel, self.panel_opacity);
+    }
+}
+
+impl Default for Style {
+    fn default() -> Self {
+        Theme::Dark.style()
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Theme {
+    Dark,
+    Light,
+    HighContrast,
+}
+
+impl Theme {
+    pub const ALL: [Theme; 3] = [Theme::Dark, Theme::Light, Theme::HighContrast];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Theme::Dark => "dark",
+            Theme::Light => "light",
+            Theme::HighContrast => "high-contrast",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Theme> {
+        Theme::ALL.into_iter().find(|theme| theme.name() == name)
+    }
+
+    pub fn style(&self) -> Style {
+        match self {
+            Theme::Dark => Style {
+                text: [230, 230, 230],
+                title: [255, 200, 0],
+                panel: [0, 0, 0],
+                panel_opacity: 0.7,
+                annotation: [255, 64, 64],
+                measurement: [255, 220, 0],
+                orbit: [80, 255, 120],
+                outline: [255, 200, 0],
+            },
+            Theme::Light => Style {
+                text: [25, 25, 35],
+                title: [170, 60, 0],
+                panel: [255, 255, 255],
+                panel_opacity: 0.75,
+                annotation: [210, 0, 40],
+                measurement: [0, 90, 210],
+                orbit: [0, 140, 60],
+                outline: [0, 90, 210],
+            },
+            // Opaque panels and fully saturated marks, legible over the busiest areas.
+            Theme::HighContrast => Style {
+                text: [255, 255, 255],
+                title: [255, 255, 0],
+                panel: [0, 0, 0],
+                panel_opacity: 1.0,
+                annotation: [255, 0, 255],
+                measurement: [0, 255, 255],
+                orbit: [0, 255, 0],
+                outline: [255, 255, 0],
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn themes_by_name() {
+        for theme in Theme::ALL {
+            assert_eq!(Theme::from_name(theme.name()), Some(theme));
+        }
+        assert_eq!(Theme::from_name("solarized"), None);
+        let mut frame = Frame::from_rgb(4, 1, vec![200; 4 * 3]);
+        Theme::HighContrast
+            .style()
+            .panel(&mut frame, (1, 0), (2, 1));
+        assert_eq!(frame.rgb, [200, 200, 200, 0, 0, 0, 0, 0, 0, 200, 200, 200]);
+    }
+}
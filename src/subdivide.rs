@@ -0,0 +1,209 @@
+use crate::kernel::Kernel;
+use crate::params::{Coloring, Fractal, RenderParams};
+use crate::render::Sample;
+use itertools::Itertools;
+use num::complex::Complex;
+use rayon::prelude::*;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+// Rectangles with a side no longer than this are computed in full rather than subdivided.
+const MIN_SIDE: u32 = 16;
+// Pixels of the lines between the halves sent through the kernel together.
+const CHUNK: usize = 64;
+
+static ENABLED: AtomicBool = AtomicBool::new(true);
+
+// Turns the subdivision of escape-time frames on or off, it is on by default.
+pub fn enable(enabled: bool) {
+    ENABLED.store(enabled, Ordering::Relaxed);
+}
+
+pub fn enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+// Whether a frame can be computed by `escape_times`. Only rectangles bordered by interior
+// points are filled: the points escaping within the iteration count are where one of the
+// iterated polynomials (of `c` for the Mandelbrot and Multibrot sets, of `z` for a Julia set)
+// exceeds the escape radius, and by the maximum principle that region has no bounded pieces to
+// enclose. The folding of the Burning Ship and the conjugation of the Tricorn make no
+// polynomials. Filled points borrow the last orbit value of a border point, which of the
+// interior colorings only the period one looks at.
+pub fn applies(params: &RenderParams) -> bool {
+    let polynomial = matches!(
+        params.fractal,
+        Fractal::Mandelbrot { .. } | Fractal::Julia { .. } | Fractal::Multibrot { .. }
+    );
+    let period = [Some(params.coloring), params.interior].contains(&Some(Coloring::Period));
+    enabled() && polynomial && !period && params.antialias == 1
+}
+
+// Samples of every pixel in row-major order, by Mariani–Silver subdivision: a rectangle whose
+// border is all interior is filled, any other is split in two halves along a line computed in
+// between, which are continued in parallel with the parts of the border they share.
+pub fn escape_times(params: &RenderParams, kernel: Kernel) -> Vec<Sample> {
+    let (w, h) = (params.width, params.height);
+    let line = |pixels: Vec<(u32, u32)>| compute(params, kernel, &pixels);
+    let border = Border {
+        top: line((0..w).map(|x| (x, 0)).collect()),
+        bottom: line((0..w).map(|x| (x, h - 1)).collect()),
+        left: line((0..h).map(|y| (0, y)).collect()),
+        right: line((0..h).map(|y| (w - 1, y)).collect()),
+    };
+    // The blocks are scattered once at the end rather than joined up at every level.
+    let mut samples = vec![border.top[0]; (w * h) as usize];
+    let blocks = rectangle(params, kernel, (0, 0, w, h), border);
+    for ((x, y, bw, _), block) in blocks {
+        for (row, block) in block.chunks(bw as usize).enumerate() {
+            let i = ((y + row as u32) * w + x) as usize;
+            samples[i..i + block.len()].copy_from_slice(block);
+        }
+    }
+    samples
+}
+
+// Samples of the edges of a rectangle, corners included in both of theirs.
+struct Border {
+    top: Vec<Sample>,
+    bottom: Vec<Sample>,
+    left: Vec<Sample>,
+    right: Vec<Sample>,
+}
+
+// A rectangle and the samples of its pixels in row-major order.
+type Block = ((u32, u32, u32, u32), Vec<Sample>);
+
+fn rectangle(
+    params: &RenderParams,
+    kernel: Kernel,
+    (x, y, w, h): (u32, u32, u32, u32),
+    border: Border,
+) -> Vec<Block> {
+    let interior = [&border.top, &border.bottom, &border.left, &border.right]
+        .iter()
+        .all(|edge| edge.iter().all(|sample| sample.escape.is_none()));
+    if interior || w <= MIN_SIDE || h <= MIN_SIDE {
+        let mut samples = vec![border.top[0]; (w * h) as usize];
+        if !interior && w > 2 && h > 2 {
+            let inside = (y + 1..y + h - 1)
+                .flat_map(|py| (x + 1..x + w - 1).map(move |px| (px, py)))
+                .collect::<Vec<_>>();
+            for (&(px, py), sample) in inside.iter().zip(compute(params, kernel, &inside)) {
+                samples[((py - y) * w + px - x) as usize] = sample;
+            }
+        }
+        let (bw, bh) = (w as usize, h as usize);
+        samples[..bw].copy_from_slice(&border.top);
+        samples[(bh - 1) * bw..].copy_from_slice(&border.bottom);
+        for row in 0..bh {
+            samples[row * bw] = border.left[row];
+            samples[row * bw + bw - 1] = border.right[row];
+        }
+        return vec![((x, y, w, h), samples)];
+    }
+
+    // The halves overlap in the line between them.
+    let m = w.max(h) / 2;
+    let (mut first, second) = if w >= h {
+        let pixels = (y..y + h).map(|py| (x + m, py)).collect_vec();
+        let mut column = compute(params, kernel, &pixels);
+        column[0] = border.top[m as usize];
+        column[(h - 1) as usize] = border.bottom[m as usize];
+        let left = Border {
+            top: border.top[..=m as usize].to_vec(),
+            bottom: border.bottom[..=m as usize].to_vec(),
+            left: border.left,
+            right: column.clone(),
+        };
+        let right = Border {
+            top: border.top[m as usize..].to_vec(),
+            bottom: border.bottom[m as usize..].to_vec(),
+            left: column,
+            right: border.right,
+        };
+        rayon::join(
+            || rectangle(params, kernel, (x, y, m + 1, h), left),
+            || rectangle(params, kernel, (x + m, y, w - m, h), right),
+        )
+    } else {
+        let pixels = (x..x + w).map(|px| (px, y + m)).collect_vec();
+        let mut row = compute(params, kernel, &pixels);
+        row[0] = border.left[m as usize];
+        row[(w - 1) as usize] = border.right[m as usize];
+        let top = Border {
+            top: border.top,
+            bottom: row.clone(),
+            left: border.left[..=m as usize].to_vec(),
+            right: border.right[..=m as usize].to_vec(),
+        };
+        let bottom = Border {
+            top: row,
+            bottom: border.bottom,
+            left: border.left[m as usize..].to_vec(),
+            right: border.right[m as usize..].to_vec(),
+        };
+        rayon::join(
+            || rectangle(params, kernel, (x, y, w, m + 1), top),
+            || rectangle(params, kernel, (x, y + m, w, h - m), bottom),
+        )
+    };
+    first.extend(second);
+    first
+}
+
+// Samples of the pixels, in parallel chunks of them through the kernel.
+fn compute(params: &RenderParams, kernel: Kernel, pixels: &[(u32, u32)]) -> Vec<Sample> {
+    let size = (params.width, params.height);
+    pixels
+        .par_chunks(CHUNK)
+        .flat_map_iter(|pixels| {
+            let points = pixels
+                .iter()
+                .map(|&(x, y)| {
+                    params.viewport.center + params.viewport.offset(x as f64, y as f64, size)
+                })
+                .collect::<Vec<Complex<f64>>>();
+            kernel.escape_time(params.fractal, &points, params.iterations)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::kernel;
+    use crate::viewport::Viewport;
+
+    #[test]
+    fn fills_interior_rectangles_as_computed() {
+        for fractal in [
+            Fractal::MANDELBROT,
+            Fractal::Julia {
+                c: Complex::new(-0.12, 0.75),
+            },
+            Fractal::Multibrot { exponent: 3 },
+        ] {
+            let params = RenderParams::builder()
+                .size(160, 120)
+                .viewport(Viewport::new(Complex::new(-0.4, 0.0), 3.2, 2.4))
+                .fractal(fractal)
+                .iterations(200)
+                .build()
+                .unwrap();
+            assert!(applies(&params));
+            let kernel = kernel::current();
+            let samples = escape_times(&params, kernel);
+            let points = (0..120)
+                .flat_map(|y| (0..160).map(move |x| (x, y)))
+                .map(|(x, y)| {
+                    params.viewport.center + params.viewport.offset(x as f64, y as f64, (160, 120))
+                })
+                .collect::<Vec<_>>();
+            let computed = kernel.escape_time(fractal, &points, params.iterations);
+            let escapes = |samples: &[Sample]| samples.iter().map(|s| s.escape).collect::<Vec<_>>();
+            assert_eq!(escapes(&samples), escapes(&computed));
+        }
+        let period = RenderParams::builder().coloring(Coloring::Period).build();
+        assert!(!applies(&period.unwrap()));
+    }
+}
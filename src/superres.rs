@@ -0,0 +1,149 @@
+use crate::params::{Fractal, RenderParams};
+use crate::render::{self, Frame};
+use std::time::{Duration, Instant};
+
+// Pixels of the scaled frame rendered at a time, a few milliseconds of work.
+const BAND_PIXELS: u32 = 1 << 15;
+
+// The view rendered again at `scale` times its resolution in either direction while the explorer
+// is idle, a band of rows at a time so that input is still handled promptly. Once complete it is
+// shown averaged down to the window, as sharp as with supersampling by the same factor, and kept
+// whole for screenshots.
+pub struct SuperResolution {
+    // The frame the render improves on.
+    params: RenderParams,
+    scale: u32,
+    scaled: RenderParams,
+    rgb: Vec<u8>,
+    rows: u32,
+}
+
+impl SuperResolution {
+    // Memory held by the scaled frame and the one shown from it.
+    pub fn bytes(params: &RenderParams, scale: u32) -> u64 {
+        (scale as u64 * scale as u64 + 1) * 3 * params.width as u64 * params.height as u64
+    }
+
+    // `None` for a scale below 2, frames over the memory budget and those computed otherwise
+    // than pixel by pixel: Buddhabrot accumulations and the filters and bloom, which work in
+    // pixels.
+    pub fn new(params: RenderParams, scale: u32, budget: u64) -> Option<SuperResolution> {
+        let pixelwise = !matches!(params.fractal, Fractal::Buddhabrot { .. })
+            && params.effect.is_none()
+            && params.bloom.is_none();
+        if scale < 2 || !pixelwise || SuperResolution::bytes(&params, scale) > budget {
+            return None;
+        }
+        let (width, height) = (params.width * scale, params.height * scale);
+        // Moved by half a pixel less half a scaled one, the scaled pixels fall on the points of
+        // the supersampling grid.
+        let mut viewport = params.viewport;
+        let size = (params.width, params.height);
+        let shift = 0.5 - 0.5 / scale as f64;
+        let (x, y) = (params.width as f64 / 2.0, params.height as f64 / 2.0);
+        viewport.pan(params.viewport.offset(x - shift, y - shift, size));
+        Some(SuperResolution {
+            params,
+            scale,
+            scaled: RenderParams {
+                viewport,
+                width,
+                height,
+                ..params
+            },
+            rgb: Vec::with_capacity((width * height * 3) as usize),
+            rows: 0,
+        })
+    }
+
+    // The rows `top..top + rows` of the scaled frame as a view of their own.
+    fn band(&self, top: u32, rows: u32) -> RenderParams {
+        let scaled = &self.scaled;
+        let mut viewport = scaled.viewport;
+        viewport.height = scaled.viewport.height * rows as f64 / scaled.height as f64;
+        let (x, y) = (scaled.width as f64 / 2.0, top as f64 + rows as f64 / 2.0);
+        viewport.pan(scaled.viewport.offset(x, y, (scaled.width, scaled.height)));
+        RenderParams {
+            viewport,
+            height: rows,
+            ..*scaled
+        }
+    }
+
+    // Renders bands until `budget` is used up, at least one. True once the frame is complete.
+    pub fn step(&mut self, budget: Duration) -> bool {
+        let stamp = Instant::now();
+        let rows = (BAND_PIXELS / self.scaled.width).max(1);
+        while self.rows < self.scaled.height {
+            let rows = rows.min(self.scaled.height - self.rows);
+            let band = render::escape_time_frame(self.band(self.rows, rows));
+            self.rgb.extend(band.rgb);
+            self.rows += rows;
+            if stamp.elapsed() >= budget {
+                break;
+            }
+        }
+        self.rows == self.scaled.height
+    }
+
+    pub fn scale(&self) -> u32 {
+        self.scale
+    }
+
+    // The frame to show in the window and the scaled frame with its params.
+    pub fn finish(self) -> (Frame, (RenderParams, Frame)) {
+        let (width, height) = (self.params.width as usize, self.params.height as usize);
+        let scale = self.scale as usize;
+        let row = 3 * scale * width;
+        let mut rgb = vec![0; width * height * 3];
+        for (i, pixel) in rgb.chunks_mut(3).enumerate() {
+            let (x, y) = (i % width, i / width);
+            let top = scale * y * row + scale * x * 3;
+            for (channel, value) in pixel.iter_mut().enumerate() {
+                let sum = (0..scale)
+                    .flat_map(|sy| (0..scale).map(move |sx| top + sy * row + sx * 3))
+                    .map(|j| self.rgb[j + channel] as u32)
+                    .sum::<u32>();
+                *value = (sum / (scale * scale) as u32) as u8;
+            }
+        }
+        let shown = Frame::from_rgb(width as u32, height as u32, rgb);
+        let scaled = Frame::from_rgb(self.scaled.width, self.scaled.height, self.rgb);
+        (shown, (self.scaled, scaled))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::viewport::Viewport;
+
+    #[test]
+    fn scales_like_supersampling() {
+        let params = RenderParams::builder()
+            .viewport(Viewport::default().fit((200, 150)))
+            .size(200, 150)
+            .iterations(100)
+            .build()
+            .unwrap();
+        let budget = SuperResolution::bytes(&params, 2);
+        assert!(SuperResolution::new(params, 2, budget - 1).is_none());
+        assert!(SuperResolution::new(params, 1, u64::MAX).is_none());
+        for scale in [2, 4] {
+            let mut job = SuperResolution::new(params, scale, u64::MAX).unwrap();
+            // A band per step without time to spare.
+            assert!(!job.step(Duration::ZERO));
+            while !job.step(Duration::ZERO) {}
+            let (shown, (scaled, frame)) = job.finish();
+            assert_eq!((frame.width, frame.height), (200 * scale, 150 * scale));
+            // The bands join into the scaled view, and averaged down it is the view supersampled
+            // by the same factor.
+            assert_eq!(frame.rgb, render::render_frame(scaled).rgb);
+            let supersampled = RenderParams {
+                antialias: scale,
+                ..params
+            };
+            assert_eq!(shown.rgb, render::render_frame(supersampled).rgb);
+        }
+    }
+}
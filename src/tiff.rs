@@ -0,0 +1,152 @@
+use std::path::Path;
+
+// Field types of the TIFF entries written here.
+const SHORT: u16 = 3;
+const LONG: u16 = 4;
+const ASCII: u16 = 2;
+
+enum Value {
+    Short(u16),
+    Shorts(u16, u16),
+    Long(u32),
+    // Text of at most 4 bytes including the terminator fits into the entry itself, longer one
+    // is stored at the given offset.
+    Ascii(u32, [u8; 4]),
+}
+
+fn entry(out: &mut Vec<u8>, tag: u16, value: Value) {
+    let (kind, count, bytes) = match value {
+        Value::Short(v) => (SHORT, 1, [v.to_le_bytes(), [0, 0]].concat()),
+        Value::Shorts(a, b) => (SHORT, 2, [a.to_le_bytes(), b.to_le_bytes()].concat()),
+        Value::Long(v) => (LONG, 1, v.to_le_bytes().to_vec()),
+        Value::Ascii(count, bytes) => (ASCII, count, bytes.to_vec()),
+    };
+    out.extend_from_slice(&tag.to_le_bytes());
+    out.extend_from_slice(&kind.to_le_bytes());
+    out.extend_from_slice(&count.to_le_bytes());
+    out.extend_from_slice(&bytes);
+}
+
+// Encodes named single-channel 32-bit float layers as an uncompressed little-endian multi-page
+// TIFF. Every page carries its layer name as PageName, which image editors show as the layer
+// name.
+pub fn encode_layers(width: u32, height: u32, layers: &[(&str, Vec<f32>)]) -> Vec<u8> {
+    let mut out = b"II*\0".to_vec();
+    // Offset of the first IFD, patched below.
+    out.extend_from_slice(&[0; 4]);
+    let mut link = 4;
+    for (page, (name, data)) in layers.iter().enumerate() {
+        assert_eq!(data.len(), (width * height) as usize, "layer {name} size");
+        let strip = out.len() as u32;
+        out.extend(data.iter().flat_map(|v| v.to_le_bytes()));
+        let name_offset = out.len() as u32;
+        out.extend_from_slice(name.as_bytes());
+        out.push(0);
+        if out.len() % 2 == 1 {
+            out.push(0);
+        }
+
+        let count = name.len() as u32 + 1;
+        let page_name = if count <= 4 {
+            let mut inline = [0; 4];
+            inline[..name.len()].copy_from_slice(name.as_bytes());
+            Value::Ascii(count, inline)
+        } else {
+            Value::Ascii(count, name_offset.to_le_bytes())
+        };
+
+        let ifd = out.len() as u32;
+        out[link..link + 4].copy_from_slice(&ifd.to_le_bytes());
+        let entries = [
+            (256, Value::Long(width)),
+            (257, Value::Long(height)),
+            (258, Value::Short(32)),
+            // No compression.
+            (259, Value::Short(1)),
+            // BlackIsZero.
+            (262, Value::Short(1)),
+            (273, Value::Long(strip)),
+            (277, Value::Short(1)),
+            (278, Value::Long(height)),
+            (279, Value::Long(width * height * 4)),
+            (284, Value::Short(1)),
+            (285, page_name),
+            (297, Value::Shorts(page as u16, layers.len() as u16)),
+            // IEEE floating point samples.
+            (339, Value::Short(3)),
+        ];
+        out.extend_from_slice(&(entries.len() as u16).to_le_bytes());
+        for (tag, value) in entries {
+            entry(&mut out, tag, value);
+        }
+        link = out.len();
+        out.extend_from_slice(&[0; 4]);
+    }
+    out
+}
+
+pub fn write_layers(
+    path: &Path,
+    width: u32,
+    height: u32,
+    layers: &[(&str, Vec<f32>)],
+) -> Result<(), String> {
+    std::fs::write(path, encode_layers(width, height, layers))
+        .map_err(|e| format!("{}: {e}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn u16_at(data: &[u8], offset: usize) -> u16 {
+        u16::from_le_bytes([data[offset], data[offset + 1]])
+    }
+
+    fn u32_at(data: &[u8], offset: usize) -> u32 {
+        u32::from_le_bytes(data[offset..offset + 4].try_into().unwrap())
+    }
+
+    // Value of a single-valued tag of the IFD at `ifd`.
+    fn tag(data: &[u8], ifd: usize, tag: u16) -> Option<u32> {
+        (0..u16_at(data, ifd) as usize)
+            .map(|i| ifd + 2 + 12 * i)
+            .find(|entry| u16_at(data, *entry) == tag)
+            .map(|entry| match u16_at(data, entry + 2) {
+                SHORT => u16_at(data, entry + 8) as u32,
+                _ => u32_at(data, entry + 8),
+            })
+    }
+
+    #[test]
+    fn pages_are_linked_and_hold_the_layers() {
+        let layers = [
+            ("smooth", vec![1.0, 2.0, 3.0, 4.0, 5.0, 6.0]),
+            ("angle", vec![0.5; 6]),
+        ];
+        let data = encode_layers(3, 2, &layers);
+        assert_eq!(&data[..4], b"II*\0");
+        let mut ifd = u32_at(&data, 4) as usize;
+        for (name, layer) in &layers {
+            assert_eq!(tag(&data, ifd, 256), Some(3));
+            assert_eq!(tag(&data, ifd, 257), Some(2));
+            assert_eq!(tag(&data, ifd, 339), Some(3));
+            let strip = tag(&data, ifd, 273).unwrap() as usize;
+            let values = (0..6)
+                .map(|i| {
+                    f32::from_le_bytes(data[strip + 4 * i..strip + 4 * i + 4].try_into().unwrap())
+                })
+                .collect::<Vec<_>>();
+            assert_eq!(&values, layer);
+            let name_offset = tag(&data, ifd, 285).unwrap() as usize;
+            assert_eq!(
+                &data[name_offset..name_offset + name.len()],
+                name.as_bytes()
+            );
+            assert_eq!(data[name_offset + name.len()], 0);
+            let count = u16_at(&data, ifd) as usize;
+            ifd = u32_at(&data, ifd + 2 + 12 * count) as usize;
+        }
+        assert_eq!(ifd, 0, "last page ends the chain");
+    }
+}
@@ -0,0 +1,822 @@
+use crate::hilbert;
+use crate::params::{Fractal, RenderParams};
+use crate::render::{self, Frame, Sample};
+use crate::scheduler::{Precision, Scheduler};
+use crate::tonemap::ToneMap;
+use crate::viewport::Viewport;
+use num::complex::Complex;
+use rayon::prelude::*;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+pub const TILE_SIZE: u32 = 128;
+// Side of the coarse estimate of a tile the error-driven scan order computes first.
+const COARSE_SIZE: u32 = 16;
+// Zoom levels are spaced by 2^(1/8), so every eighth level halves the pixel size.
+pub const LEVELS_PER_OCTAVE: i32 = 8;
+
+pub fn level_factor(levels: i32) -> f64 {
+    2f64.powf(levels as f64 / LEVELS_PER_OCTAVE as f64)
+}
+
+// Discretizes the plane into square pixels of size `base_scale * 2^(-level / 8)`. Global pixel
+// (gx, gy) of a level has its top-left corner at (gx * scale, gy * scale).
+#[derive(Clone, Copy, Debug)]
+pub struct TileGrid {
+    pub base_scale: f64,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct GridView {
+    pub level: i32,
+    pub x: i64,
+    pub y: i64,
+}
+
+impl TileGrid {
+    pub fn scale(&self, level: i32) -> f64 {
+        self.base_scale * level_factor(-level)
+    }
+
+    fn square_pixels(viewport: &Viewport, window_size: (u32, u32)) -> Option<f64> {
+        let sx = viewport.width / window_size.0 as f64;
+        let sy = viewport.height / window_size.1 as f64;
+        // Grid positions are f64, too coarse for views rendered in double-double.
+        let shallow = sx >= Precision::Double.min_pixel(viewport.center);
+        (shallow && viewport.rotation == 0.0 && ((sx - sy) / sx).abs() < 1e-9).then_some(sx)
+    }
+
+    // Moves the viewport (by less than a pixel and a fraction of a zoom level) so that it lies
+    // on the grid. Rotated, anisotropic or deep viewports are returned unchanged.
+    pub fn snap(&self, viewport: Viewport, window_size: (u32, u32)) -> Viewport {
+        let Some(pixel) = TileGrid::square_pixels(&viewport, window_size) else {
+            return viewport;
+        };
+        let level = (-(pixel / self.base_scale).log2() * LEVELS_PER_OCTAVE as f64).round() as i32;
+        let scale = self.scale(level);
+        let (w, h) = (window_size.0 as f64, window_size.1 as f64);
+        let x = ((viewport.center.re - 0.5 * viewport.width) / scale).round();
+        let y = ((viewport.center.im - 0.5 * viewport.height) / scale).round();
+        Viewport::new(
+            Complex::new((x + 0.5 * w) * scale, (y + 0.5 * h) * scale),
+            w * scale,
+            h * scale,
+        )
+    }
+
+    // Position of an already snapped viewport on the grid.
+    pub fn locate(&self, viewport: &Viewport, window_size: (u32, u32)) -> Option<GridView> {
+        let pixel = TileGrid::square_pixels(viewport, window_size)?;
+        let level_f = -(pixel / self.base_scale).log2() * LEVELS_PER_OCTAVE as f64;
+        let level = level_f.round();
+        let scale = self.scale(level as i32);
+        let x = (viewport.center.re - 0.5 * viewport.width) / scale;
+        let y = (viewport.center.im - 0.5 * viewport.height) / scale;
+        let on_grid = (level_f - level).abs() < 1e-6
+            && (x - x.round()).abs() < 1e-3
+            && (y - y.round()).abs() < 1e-3;
+        on_grid.then_some(GridView {
+            level: level as i32,
+            x: x.round() as i64,
+            y: y.round() as i64,
+        })
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+struct TileKey {
+    scene: u64,
+    level: i32,
+    tx: i64,
+    ty: i64,
+}
+
+struct CachedTile {
+    frame: Arc<Frame>,
+    last_used: u64,
+}
+
+// Order in which the missing tiles of a view are computed: row by row from the top, along a
+// Hilbert curve, filling the view in as compact patches rather than bands, or by error: a
+// coarse estimate of every tile first, then the tiles whose estimate varies the most.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ScanOrder {
+    Rows,
+    Hilbert,
+    Error,
+}
+
+impl ScanOrder {
+    pub fn from_name(name: &str) -> Option<ScanOrder> {
+        match name {
+            "rows" => Some(ScanOrder::Rows),
+            "hilbert" => Some(ScanOrder::Hilbert),
+            "error" => Some(ScanOrder::Error),
+            _ => None,
+        }
+    }
+}
+
+// In-memory cache of rendered tiles, used for interactive views that lie on the tile grid.
+pub struct TileCache {
+    pub grid: TileGrid,
+    pub scan: ScanOrder,
+    tiles: HashMap<TileKey, CachedTile>,
+    capacity: usize,
+    clock: u64,
+    prefetch: Vec<(RenderParams, TileKey)>,
+    pub scheduler: Scheduler,
+}
+
+// Hash of everything that affects the pixels of a tile, i.e. the params minus placement and
+// the whole-frame stages.
+fn scene_hash(params: &RenderParams) -> u64 {
+    RenderParams {
+        viewport: Viewport::default(),
+        width: TILE_SIZE,
+        height: TILE_SIZE,
+        effect: None,
+        bloom: None,
+        tonemap: ToneMap::default(),
+        ..*params
+    }
+    .content_hash()
+}
+
+fn tile_params(params: &RenderParams, grid: &TileGrid, key: TileKey) -> RenderParams {
+    let scale = grid.scale(key.level);
+    let size = TILE_SIZE as f64;
+    let center = Complex::new(
+        (key.tx as f64 + 0.5) * size * scale,
+        (key.ty as f64 + 0.5) * size * scale,
+    );
+    RenderParams {
+        viewport: Viewport::new(center, size * scale, size * scale),
+        width: TILE_SIZE,
+        height: TILE_SIZE,
+        ..*params
+    }
+}
+
+// Tiles covering `width x height` pixels starting at the given global pixel.
+fn covering(view: GridView, width: u32, height: u32) -> impl Iterator<Item = (i64, i64)> {
+    let t = TILE_SIZE as i64;
+    let (tx0, ty0) = (view.x.div_euclid(t), view.y.div_euclid(t));
+    let tx1 = (view.x + width as i64 - 1).div_euclid(t);
+    let ty1 = (view.y + height as i64 - 1).div_euclid(t);
+    (ty0..=ty1).flat_map(move |ty| (tx0..=tx1).map(move |tx| (tx, ty)))
+}
+
+// The part of a tile inside the view of `width x height` pixels, and its position there.
+fn visible(
+    view: GridView,
+    width: u32,
+    height: u32,
+    key: &TileKey,
+    tile: &Frame,
+) -> (u32, u32, Frame) {
+    let t = TILE_SIZE as i64;
+    let (x0, y0) = ((key.tx * t).max(view.x), (key.ty * t).max(view.y));
+    let x1 = ((key.tx + 1) * t).min(view.x + width as i64);
+    let y1 = ((key.ty + 1) * t).min(view.y + height as i64);
+    let rgb = (y0..y1)
+        .flat_map(|gy| {
+            let from = ((gy - key.ty * t) * t + x0 - key.tx * t) as usize * 3;
+            &tile.rgb[from..from + (x1 - x0) as usize * 3]
+        })
+        .copied()
+        .collect();
+    let piece = Frame::from_rgb((x1 - x0) as u32, (y1 - y0) as u32, rgb);
+    ((x0 - view.x) as u32, (y0 - view.y) as u32, piece)
+}
+
+// Variance of the luminance of a frame: zero for a flat one, largest where the coarse samples
+// disagree the most, which is where refining them changes the most of the view.
+fn estimate_error(frame: &Frame) -> f64 {
+    let (sum, squares) = frame.rgb.chunks(3).fold((0.0, 0.0), |(sum, squares), rgb| {
+        let luminance = 0.299 * rgb[0] as f64 + 0.587 * rgb[1] as f64 + 0.114 * rgb[2] as f64;
+        (sum + luminance, squares + luminance * luminance)
+    });
+    let count = (frame.width * frame.height) as f64;
+    let mean = sum / count;
+    (squares / count - mean * mean).max(0.0)
+}
+
+// A coarse estimate of a tile blown up to its size, a block for every coarse pixel.
+fn upsample(coarse: &Frame) -> Frame {
+    let factor = TILE_SIZE / coarse.width;
+    let rgb = (0..TILE_SIZE)
+        .flat_map(|y| (0..TILE_SIZE).map(move |x| (y / factor) * coarse.width + x / factor))
+        .flat_map(|index| {
+            let index = index as usize * 3;
+            coarse.rgb[index..index + 3].to_vec()
+        })
+        .collect();
+    Frame::from_rgb(TILE_SIZE, TILE_SIZE, rgb)
+}
+
+fn blank_frame(width: u32, height: u32) -> Frame {
+    let pixels = (width * height) as usize;
+    Frame {
+        width,
+        height,
+        rgb: vec![0; pixels * 3],
+        samples: vec![
+            Sample {
+                escape: None,
+                z: Complex::new(0.0, 0.0),
+            };
+            pixels
+        ],
+        supersamples: Vec::new(),
+        accumulation: None,
+        seed: 0,
+    }
+}
+
+impl TileCache {
+    pub fn new(grid: TileGrid, capacity: usize) -> Self {
+        TileCache {
+            grid,
+            scan: ScanOrder::Rows,
+            tiles: HashMap::new(),
+            capacity,
+            clock: 0,
+            prefetch: Vec::new(),
+            scheduler: Scheduler::detect(),
+        }
+    }
+
+    fn tileable(params: &RenderParams) -> bool {
+        !matches!(params.fractal, Fractal::Buddhabrot { .. })
+    }
+
+    fn lookup(&mut self, key: &TileKey) -> Option<Arc<Frame>> {
+        self.clock += 1;
+        let clock = self.clock;
+        self.tiles.get_mut(key).map(|tile| {
+            tile.last_used = clock;
+            tile.frame.clone()
+        })
+    }
+
+    fn insert(&mut self, key: TileKey, frame: Frame) -> Arc<Frame> {
+        while self.tiles.len() >= self.capacity {
+            let oldest = self
+                .tiles
+                .iter()
+                .min_by_key(|(_, tile)| tile.last_used)
+                .map(|(key, _)| *key);
+            match oldest {
+                Some(oldest) => self.tiles.remove(&oldest),
+                None => break,
+            };
+        }
+        self.clock += 1;
+        let frame = Arc::new(frame);
+        self.tiles.insert(
+            key,
+            CachedTile {
+                frame: frame.clone(),
+                last_used: self.clock,
+            },
+        );
+        frame
+    }
+
+    // Renders the view from cached tiles, computing the missing ones. Returns `None` when the
+    // params are not tileable or the viewport is not on the grid, or when `superseded` reports,
+    // between batches of tiles, that the view is no longer wanted; the tiles computed until then
+    // stay cached.
+    pub fn render(
+        &mut self,
+        params: RenderParams,
+        superseded: impl FnMut() -> bool,
+    ) -> Option<Frame> {
+        self.render_progressively(params, superseded, |_, _, _| {})
+    }
+
+    // The same, passing every computed tile to `reveal` as soon as its batch is done: the part
+    // of it inside the view and where that goes, before the whole-frame stages. The missing
+    // tiles are computed in the scan order; by error, their coarse estimates are revealed
+    // first and the tiles are then refined in the order of decreasing estimated error.
+    pub fn render_progressively(
+        &mut self,
+        params: RenderParams,
+        mut superseded: impl FnMut() -> bool,
+        mut reveal: impl FnMut(u32, u32, Frame),
+    ) -> Option<Frame> {
+        if !TileCache::tileable(&params) {
+            return None;
+        }
+        let view = self
+            .grid
+            .locate(&params.viewport, (params.width, params.height))?;
+        let scene = scene_hash(&params);
+        let keys = covering(view, params.width, params.height)
+            .map(|(tx, ty)| TileKey {
+                scene,
+                level: view.level,
+                tx,
+                ty,
+            })
+            .collect::<Vec<_>>();
+        let mut missing = keys
+            .iter()
+            .filter(|key| self.lookup(key).is_none())
+            .copied()
+            .collect::<Vec<_>>();
+        if self.scan == ScanOrder::Hilbert {
+            let t = TILE_SIZE as i64;
+            let (tx0, ty0) = (view.x.div_euclid(t), view.y.div_euclid(t));
+            let side = (keys
+                .iter()
+                .map(|key| (key.tx - tx0).max(key.ty - ty0))
+                .max())
+            .map_or(1, |last| (last as u32 + 1).next_power_of_two());
+            missing.sort_by_key(|key| {
+                hilbert::index(side, (key.tx - tx0) as u32, (key.ty - ty0) as u32)
+            });
+        }
+        if self.scan == ScanOrder::Error && !missing.is_empty() {
+            if superseded() {
+                return None;
+            }
+            let jobs = missing
+                .iter()
+                .map(|key| RenderParams {
+                    width: COARSE_SIZE,
+                    height: COARSE_SIZE,
+                    ..tile_params(&params, &self.grid, *key)
+                })
+                .collect::<Vec<_>>();
+            let coarse = self.scheduler.render(&jobs);
+            let mut errors = HashMap::new();
+            for (key, coarse) in missing.iter().zip(coarse) {
+                let (x, y, piece) =
+                    visible(view, params.width, params.height, key, &upsample(&coarse));
+                reveal(x, y, piece);
+                errors.insert(*key, estimate_error(&coarse));
+            }
+            missing.sort_by(|a, b| errors[b].total_cmp(&errors[a]));
+        }
+        let mut tiles = HashMap::new();
+        for batch in missing.chunks(rayon::current_num_threads()) {
+            if superseded() {
+                return None;
+            }
+            let jobs = batch
+                .iter()
+                .map(|key| tile_params(&params, &self.grid, *key))
+                .collect::<Vec<_>>();
+            let rendered = self.scheduler.render(&jobs);
+            for (key, frame) in batch.iter().zip(rendered) {
+                let (x, y, piece) = visible(view, params.width, params.height, key, &frame);
+                reveal(x, y, piece);
+                tiles.insert(*key, self.insert(*key, frame));
+            }
+        }
+        for key in &keys {
+            if !tiles.contains_key(key) {
+                tiles.insert(*key, self.lookup(key)?);
+            }
+        }
+
+        let (width, height) = (params.width as usize, params.height as usize);
+        let mut frame = blank_frame(params.width, params.height);
+        let t = TILE_SIZE as i64;
+        for y in 0..height {
+            let gy = view.y + y as i64;
+            for (tx, _) in covering(view, params.width, 1) {
+                let tile = &tiles[&TileKey {
+                    scene,
+                    level: view.level,
+                    tx,
+                    ty: gy.div_euclid(t),
+                }];
+                let row = gy.rem_euclid(t) as usize;
+                let gx_start = (tx * t).max(view.x);
+                let gx_end = ((tx + 1) * t).min(view.x + width as i64);
+                let src = row * TILE_SIZE as usize + (gx_start - tx * t) as usize;
+                let dst = y * width + (gx_start - view.x) as usize;
+                let len = (gx_end - gx_start) as usize;
+                frame.samples[dst..dst + len].copy_from_slice(&tile.samples[src..src + len]);
+                frame.rgb[dst * 3..(dst + len) * 3]
+                    .copy_from_slice(&tile.rgb[src * 3..(src + len) * 3]);
+            }
+        }
+        render::finish(params, &mut frame);
+        Some(frame)
+    }
+
+    fn pixel(&self, scene: u64, level: i32, gx: i64, gy: i64) -> Option<([u8; 3], Sample)> {
+        let t = TILE_SIZE as i64;
+        let tile = self.tiles.get(&TileKey {
+            scene,
+            level,
+            tx: gx.div_euclid(t),
+            ty: gy.div_euclid(t),
+        })?;
+        let index = (gy.rem_euclid(t) * t + gx.rem_euclid(t)) as usize;
+        let rgb = &tile.frame.rgb[index * 3..index * 3 + 3];
+        Some(([rgb[0], rgb[1], rgb[2]], tile.frame.samples[index]))
+    }
+
+    // Averages the pixels of `level` covering the footprint of global pixel (gx, gy) of a level
+    // `levels` coarser, i.e. with pixels `level_factor(levels)` times larger. Pixels are sampled
+    // at their integer coordinates, so the footprint is centered there too; the sample buffer
+    // gets the pixel at the footprint center.
+    fn resample(
+        &self,
+        scene: u64,
+        level: i32,
+        levels: i32,
+        gx: i64,
+        gy: i64,
+    ) -> Option<([u8; 3], Sample)> {
+        let ratio = level_factor(levels);
+        let span = |g: i64| {
+            let start = ((g as f64 - 0.5) * ratio).round() as i64;
+            let end = (((g as f64 + 0.5) * ratio).round() as i64).max(start + 1);
+            start..end
+        };
+        let mut sum = [0u32; 3];
+        let mut count = 0;
+        for y in span(gy) {
+            for x in span(gx) {
+                let (rgb, _) = self.pixel(scene, level, x, y)?;
+                for (acc, channel) in sum.iter_mut().zip(rgb) {
+                    *acc += channel as u32;
+                }
+                count += 1;
+            }
+        }
+        let center = |g: i64| (g as f64 * ratio).round() as i64;
+        let (_, sample) = self.pixel(scene, level, center(gx), center(gy))?;
+        Some((sum.map(|channel| (channel / count) as u8), sample))
+    }
+
+    // Approximates a view whose tiles are not all cached yet from the tile pyramid: missing
+    // pixels are downsampled from the nearest deeper level that has them, or failing that
+    // upsampled from a shallower one. Returns `None` if the view is fully cached or less than
+    // half of it can be reconstructed.
+    pub fn preview(&self, params: RenderParams) -> Option<Frame> {
+        if !TileCache::tileable(&params) {
+            return None;
+        }
+        let view = self
+            .grid
+            .locate(&params.viewport, (params.width, params.height))?;
+        let scene = scene_hash(&params);
+        let complete = covering(view, params.width, params.height).all(|(tx, ty)| {
+            self.tiles.contains_key(&TileKey {
+                scene,
+                level: view.level,
+                tx,
+                ty,
+            })
+        });
+        if complete {
+            return None;
+        }
+        let mut deeper = Vec::new();
+        let mut shallower = Vec::new();
+        for key in self.tiles.keys().filter(|key| key.scene == scene) {
+            let target = if key.level > view.level {
+                &mut deeper
+            } else {
+                &mut shallower
+            };
+            if !target.contains(&key.level) {
+                target.push(key.level);
+            }
+        }
+        deeper.sort();
+        shallower.sort_by(|a, b| b.cmp(a));
+        let mut levels = vec![view.level];
+        levels.extend(deeper);
+        levels.extend(shallower);
+
+        let (width, height) = (params.width as usize, params.height as usize);
+        let mut frame = blank_frame(params.width, params.height);
+        let filled = frame
+            .rgb
+            .par_chunks_mut(width * 3)
+            .zip(frame.samples.par_chunks_mut(width))
+            .enumerate()
+            .map(|(y, (rgb, samples))| {
+                let gy = view.y + y as i64;
+                let mut filled = 0;
+                for x in 0..width {
+                    let gx = view.x + x as i64;
+                    let found = levels
+                        .iter()
+                        .find_map(|&level| self.resample(scene, level, level - view.level, gx, gy));
+                    if let Some((color, sample)) = found {
+                        rgb[x * 3..x * 3 + 3].copy_from_slice(&color);
+                        samples[x] = sample;
+                        filled += 1;
+                    }
+                }
+                filled
+            })
+            .sum::<usize>();
+        // A mostly black preview would flash just like having none.
+        if filled < width * height / 2 {
+            return None;
+        }
+        render::finish(params, &mut frame);
+        Some(frame)
+    }
+
+    // Queues a ring of tiles around the current view, then everything needed for zooming out by
+    // `zoom_out_levels` around the view center.
+    pub fn plan_prefetch(&mut self, params: RenderParams, zoom_out_levels: i32) {
+        self.prefetch.clear();
+        if !TileCache::tileable(&params) {
+            return;
+        }
+        let window_size = (params.width, params.height);
+        let Some(view) = self.grid.locate(&params.viewport, window_size) else {
+            return;
+        };
+        let scene = scene_hash(&params);
+        let t = TILE_SIZE as i64;
+        let ring = GridView {
+            level: view.level,
+            x: view.x - t,
+            y: view.y - t,
+        };
+        let mut keys = covering(
+            ring,
+            params.width + 2 * TILE_SIZE,
+            params.height + 2 * TILE_SIZE,
+        )
+        .map(|(tx, ty)| TileKey {
+            scene,
+            level: view.level,
+            tx,
+            ty,
+        })
+        .collect::<Vec<_>>();
+
+        let mut zoomed_out = params.viewport;
+        zoomed_out.zoom_about(zoomed_out.center, level_factor(zoom_out_levels));
+        let zoomed_out = self.grid.snap(zoomed_out, window_size);
+        if let Some(out) = self.grid.locate(&zoomed_out, window_size) {
+            keys.extend(
+                covering(out, params.width, params.height).map(|(tx, ty)| TileKey {
+                    scene,
+                    level: out.level,
+                    tx,
+                    ty,
+                }),
+            );
+        }
+        keys.retain(|key| !self.tiles.contains_key(key));
+        // Leave room for the tiles of the current view.
+        keys.truncate(self.capacity / 2);
+        // Popped from the back, so reverse to render the same-level ring first.
+        self.prefetch = keys.into_iter().rev().map(|key| (params, key)).collect();
+    }
+
+    pub fn has_prefetch_work(&self) -> bool {
+        !self.prefetch.is_empty()
+    }
+
+    // Renders queued prefetch tiles in small parallel batches until `budget` is used up, so the
+    // caller can go back to handling input. Returns the number of tiles rendered.
+    pub fn prefetch(&mut self, budget: Duration) -> usize {
+        let stamp = Instant::now();
+        let batch = rayon::current_num_threads();
+        let mut done = 0;
+        while stamp.elapsed() < budget && !self.prefetch.is_empty() {
+            let start = self.prefetch.len().saturating_sub(batch);
+            let work = self.prefetch.split_off(start);
+            let jobs = work
+                .iter()
+                .map(|(params, key)| tile_params(params, &self.grid, *key))
+                .collect::<Vec<_>>();
+            let rendered = self.scheduler.render(&jobs);
+            for ((_, key), frame) in work.into_iter().zip(rendered) {
+                self.insert(key, frame);
+                done += 1;
+            }
+        }
+        done
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scheduler::CpuWorker;
+
+    fn grid() -> TileGrid {
+        TileGrid {
+            base_scale: 4.0 / 800.0,
+        }
+    }
+
+    #[test]
+    fn snap_puts_viewport_on_grid() {
+        let grid = grid();
+        let mut viewport = Viewport::default();
+        viewport.zoom_about(Complex::new(-0.7013, 0.313), level_factor(-3));
+        viewport.pan(Complex::new(0.00123, -0.0041));
+        let snapped = grid.snap(viewport, (800, 600));
+        assert!((snapped.center - viewport.center).norm() < snapped.width / 800.0);
+        assert_eq!(
+            grid.locate(&snapped, (800, 600)).map(|view| view.level),
+            Some(3)
+        );
+    }
+
+    #[test]
+    fn rotated_views_are_not_tiled() {
+        let mut viewport = Viewport::default();
+        viewport.rotate(0.5);
+        assert_eq!(grid().snap(viewport, (800, 600)), viewport);
+        assert_eq!(grid().locate(&viewport, (800, 600)), None);
+    }
+
+    #[test]
+    fn tiled_render_matches_direct_render() {
+        let mut cache = TileCache::new(grid(), 64);
+        // On the CPU alone, which the direct render is computed on too.
+        cache.scheduler = Scheduler::new(vec![Box::new(CpuWorker)]);
+        let viewport = cache.grid.snap(
+            Viewport::new(Complex::new(-0.75, 0.1), 1.5, 1.0),
+            (300, 200),
+        );
+        let params = RenderParams::builder()
+            .viewport(viewport)
+            .size(300, 200)
+            .iterations(100)
+            .build()
+            .unwrap();
+        // A view superseded after the first batch keeps the tiles of that batch, one per thread.
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(2)
+            .build()
+            .unwrap();
+        let mut checks = 0;
+        let superseded = pool.install(|| {
+            cache.render(params, || {
+                checks += 1;
+                checks > 1
+            })
+        });
+        assert!(superseded.is_none());
+        assert_eq!(cache.tiles.len(), 2);
+        let tiled = cache.render(params, || false).unwrap();
+        let direct = render::render_frame(params);
+        let mismatched = tiled
+            .samples
+            .iter()
+            .zip(&direct.samples)
+            .filter(|(a, b)| a.escape != b.escape)
+            .count();
+        // Tile and frame pixel positions differ by rounding only.
+        assert!(mismatched < 30, "{mismatched} pixels differ");
+    }
+
+    #[test]
+    fn preview_downsamples_deeper_tiles() {
+        let mut cache = TileCache::new(grid(), 64);
+        let view = cache.grid.snap(
+            Viewport::new(Complex::new(-0.6, 0.3), 1.28, 1.28),
+            (128, 128),
+        );
+        let params = RenderParams::builder()
+            .viewport(view)
+            .size(128, 128)
+            .iterations(100)
+            .build()
+            .unwrap();
+        assert!(cache.preview(params).is_none(), "nothing is cached yet");
+
+        // The same area at twice the resolution, one octave deeper.
+        let deeper = cache.grid.snap(view, (256, 256));
+        cache
+            .render(
+                params
+                    .to_builder()
+                    .viewport(deeper)
+                    .size(256, 256)
+                    .build()
+                    .unwrap(),
+                || false,
+            )
+            .unwrap();
+        let preview = cache.preview(params).unwrap();
+        let direct = render::render_frame(params);
+        let mismatched = preview
+            .samples
+            .iter()
+            .zip(&direct.samples)
+            .filter(|(a, b)| a.escape != b.escape)
+            .count();
+        assert!(mismatched < 128 * 128 / 50, "{mismatched} pixels differ");
+
+        cache.render(params, || false).unwrap();
+        assert!(cache.preview(params).is_none(), "the view is cached");
+    }
+
+    #[test]
+    fn prefetch_fills_ring_and_zoom_out() {
+        let mut cache = TileCache::new(grid(), 1024);
+        let viewport = cache
+            .grid
+            .snap(Viewport::new(Complex::new(-0.5, 0.0), 2.0, 2.0), (256, 256));
+        let params = RenderParams::builder()
+            .viewport(viewport)
+            .size(256, 256)
+            .iterations(20)
+            .build()
+            .unwrap();
+        cache.plan_prefetch(params, 2);
+        assert!(cache.has_prefetch_work());
+        while cache.has_prefetch_work() {
+            cache.prefetch(Duration::from_secs(1));
+        }
+        let before = cache.tiles.len();
+        let mut panned = params.viewport;
+        panned.pan(Complex::new(64.0 * viewport.width / 256.0, 0.0));
+        cache
+            .render(
+                params.to_builder().viewport(panned).build().unwrap(),
+                || false,
+            )
+            .unwrap();
+        let mut zoomed_out = params.viewport;
+        zoomed_out.zoom_about(zoomed_out.center, level_factor(2));
+        let zoomed_out = cache.grid.snap(zoomed_out, (256, 256));
+        cache
+            .render(
+                params.to_builder().viewport(zoomed_out).build().unwrap(),
+                || false,
+            )
+            .unwrap();
+        assert_eq!(cache.tiles.len(), before, "no new tiles were needed");
+    }
+
+    #[test]
+    fn reveals_tiles_along_the_scan_order() {
+        let mut cache = TileCache::new(grid(), 64);
+        cache.scan = ScanOrder::Hilbert;
+        let viewport = cache
+            .grid
+            .snap(Viewport::new(Complex::new(-0.5, 0.0), 3.0, 2.0), (300, 200));
+        let params = RenderParams::builder()
+            .viewport(viewport)
+            .size(300, 200)
+            .iterations(20)
+            .build()
+            .unwrap();
+        let mut pieces = Vec::new();
+        let frame = cache
+            .render_progressively(params, || false, |x, y, piece| pieces.push((x, y, piece)))
+            .unwrap();
+        let mut revealed = Frame::from_rgb(300, 200, vec![0; 300 * 200 * 3]);
+        for (x, y, piece) in &pieces {
+            revealed.paste(*x, *y, piece);
+        }
+        assert_eq!(revealed.rgb, frame.rgb);
+        // The first four tiles are a square in the corner rather than a row of them.
+        let corner = pieces[..4].iter().map(|(x, y, _)| (*x).max(*y));
+        assert!(corner.max() <= Some(TILE_SIZE));
+    }
+
+    #[test]
+    fn refines_the_coarse_estimates_by_error() {
+        let flat = Frame::from_rgb(4, 4, vec![90; 4 * 4 * 3]);
+        let stripes = (0..16).flat_map(|i| [(i % 2 * 255) as u8; 3]).collect();
+        assert_eq!(estimate_error(&flat), 0.0);
+        assert!(estimate_error(&Frame::from_rgb(4, 4, stripes)) > 1000.0);
+
+        let mut cache = TileCache::new(grid(), 64);
+        cache.scan = ScanOrder::Error;
+        let viewport = cache
+            .grid
+            .snap(Viewport::new(Complex::new(-0.5, 0.0), 3.0, 2.0), (300, 200));
+        let params = RenderParams::builder()
+            .viewport(viewport)
+            .size(300, 200)
+            .iterations(50)
+            .build()
+            .unwrap();
+        let mut pieces = Vec::new();
+        let frame = cache
+            .render_progressively(params, || false, |x, y, piece| pieces.push((x, y, piece)))
+            .unwrap();
+        // A coarse piece and a refined one for every tile, the refined ones covering them.
+        assert_eq!(pieces.len(), 2 * cache.tiles.len());
+        let mut revealed = Frame::from_rgb(300, 200, vec![0; 300 * 200 * 3]);
+        for (x, y, piece) in &pieces {
+            revealed.paste(*x, *y, piece);
+        }
+        assert_eq!(revealed.rgb, frame.rgb);
+    }
+}
@@ -0,0 +1,162 @@
+use rayon::prelude::*;
+
+const SUM_CHUNK: usize = 4096;
+
+// Maps unbounded accumulation buffers (e.g. Buddhabrot hit counts) to displayable values.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ToneOperator {
+    Linear,
+    Sqrt,
+    Log,
+    Reinhard,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ToneMap {
+    pub operator: ToneOperator,
+    // Multiplier applied to the normalized buffer before the operator, the "exposure" of the
+    // image.
+    pub exposure: f32,
+}
+
+impl Default for ToneMap {
+    fn default() -> Self {
+        ToneMap {
+            operator: ToneOperator::Sqrt,
+            exposure: 1.0,
+        }
+    }
+}
+
+impl ToneOperator {
+    pub const ALL: [ToneOperator; 4] = [
+        ToneOperator::Linear,
+        ToneOperator::Sqrt,
+        ToneOperator::Log,
+        ToneOperator::Reinhard,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            ToneOperator::Linear => "linear",
+            ToneOperator::Sqrt => "sqrt",
+            ToneOperator::Log => "log",
+            ToneOperator::Reinhard => "reinhard",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<ToneOperator> {
+        ToneOperator::ALL.into_iter().find(|op| op.name() == name)
+    }
+
+    pub fn next(&self) -> ToneOperator {
+        let index = ToneOperator::ALL
+            .iter()
+            .position(|op| op == self)
+            .unwrap_or(0);
+        ToneOperator::ALL[(index + 1) % ToneOperator::ALL.len()]
+    }
+}
+
+// Tone-maps `buffer` to 0..1 intensities. Linear, sqrt and log normalize by the maximum,
+// Reinhard by the mean of the non-empty entries so that exposure 1.0 puts the average at 0.5.
+pub fn apply(tonemap: ToneMap, buffer: &[f32]) -> Vec<f32> {
+    let max = buffer.par_iter().cloned().reduce(|| 0.0, f32::max);
+    if max <= 0.0 {
+        return vec![0.0; buffer.len()];
+    }
+    // Summed in fixed chunks combined in order, so the mean does not depend on the number of
+    // threads.
+    let (sum, count) = buffer
+        .par_chunks(SUM_CHUNK)
+        .map(|chunk| {
+            chunk
+                .iter()
+                .filter(|v| **v > 0.0)
+                .fold((0.0f64, 0usize), |(s, n), v| (s + *v as f64, n + 1))
+        })
+        .collect::<Vec<_>>()
+        .into_iter()
+        .fold((0.0, 0), |a, b| (a.0 + b.0, a.1 + b.1));
+    let mean = (sum / count as f64) as f32;
+    let exposure = tonemap.exposure;
+    buffer
+        .par_iter()
+        .map(|&v| {
+            let mapped = match tonemap.operator {
+                ToneOperator::Linear => exposure * v / max,
+                ToneOperator::Sqrt => (exposure * v / max).sqrt(),
+                ToneOperator::Log => (1.0 + exposure * v).ln() / (1.0 + exposure * max).ln(),
+                ToneOperator::Reinhard => {
+                    let x = exposure * v / mean;
+                    x / (1.0 + x)
+                }
+            };
+            mapped.clamp(0.0, 1.0)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn operators_are_monotonic_and_bounded() {
+        let buffer = [0.0, 1.0, 2.0, 10.0, 100.0, 1000.0];
+        for operator in ToneOperator::ALL {
+            for exposure in [0.5, 1.0, 4.0] {
+                let mapped = apply(ToneMap { operator, exposure }, &buffer);
+                assert_eq!(mapped[0], 0.0);
+                assert!(mapped.windows(2).all(|w| w[0] <= w[1]), "{operator:?}");
+                assert!(mapped.iter().all(|v| (0.0..=1.0).contains(v)));
+            }
+        }
+    }
+
+    #[test]
+    fn exposure_brightens() {
+        let buffer = [0.0, 3.0, 50.0, 400.0];
+        for operator in ToneOperator::ALL {
+            let dim = apply(
+                ToneMap {
+                    operator,
+                    exposure: 1.0,
+                },
+                &buffer,
+            );
+            let bright = apply(
+                ToneMap {
+                    operator,
+                    exposure: 2.0,
+                },
+                &buffer,
+            );
+            assert!(bright[1] > dim[1], "{operator:?}");
+        }
+    }
+
+    #[test]
+    fn independent_of_thread_count() {
+        let buffer = (0..100_000)
+            .map(|i| ((i * 7919) % 1013) as f32 * 0.37)
+            .collect::<Vec<_>>();
+        let tonemap = ToneMap {
+            operator: ToneOperator::Reinhard,
+            exposure: 1.0,
+        };
+        let [one, many] = [1, 7].map(|threads| {
+            rayon::ThreadPoolBuilder::new()
+                .num_threads(threads)
+                .build()
+                .unwrap()
+                .install(|| apply(tonemap, &buffer))
+        });
+        assert_eq!(one, many);
+    }
+
+    #[test]
+    fn empty_buffer_is_black() {
+        assert_eq!(apply(ToneMap::default(), &[0.0; 4]), vec![0.0; 4]);
+    }
+}
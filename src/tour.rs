@@ -0,0 +1,318 @@
+use crate::bookmarks;
+use crate::location::Location;
+use crate::params::RenderParams;
+use crate::png;
+use crate::render;
+use crate::video;
+use crate::viewport::Viewport;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+// How the flight between two keyframes speeds up and slows down, in the fraction of the way
+// covered against the fraction of the time.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Easing {
+    Linear,
+    In,
+    Out,
+    InOut,
+}
+
+impl Easing {
+    pub const ALL: [Easing; 4] = [Easing::Linear, Easing::In, Easing::Out, Easing::InOut];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Easing::Linear => "linear",
+            Easing::In => "in",
+            Easing::Out => "out",
+            Easing::InOut => "in-out",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Easing> {
+        Easing::ALL.into_iter().find(|easing| easing.name() == name)
+    }
+
+    pub fn apply(&self, t: f64) -> f64 {
+        match self {
+            Easing::Linear => t,
+            Easing::In => t * t,
+            Easing::Out => 1.0 - (1.0 - t) * (1.0 - t),
+            Easing::InOut => t * t * (3.0 - 2.0 * t),
+        }
+    }
+}
+
+// The `tour` subcommand: a video flying from keyframe to keyframe, the bookmarks of a file in
+// their order or a start and an end view, every frame rendered directly.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Tour {
+    pub bookmarks: Option<PathBuf>,
+    // The views flown between without bookmarks, of the Mandelbrot set at `iterations`.
+    pub from: Option<Location>,
+    pub to: Option<Location>,
+    pub iterations: u32,
+    // Seconds.
+    pub duration: f64,
+    pub fps: u32,
+    pub width: u32,
+    pub height: u32,
+    // Applied to every leg between two keyframes.
+    pub easing: Easing,
+    pub output: PathBuf,
+}
+
+impl Default for Tour {
+    fn default() -> Self {
+        Tour {
+            bookmarks: None,
+            from: None,
+            to: None,
+            iterations: 1000,
+            duration: 20.0,
+            fps: 30,
+            width: 1280,
+            height: 720,
+            easing: Easing::InOut,
+            output: PathBuf::from("tour.mp4"),
+        }
+    }
+}
+
+impl Tour {
+    pub fn frame_count(&self) -> u32 {
+        ((self.duration * self.fps as f64).round() as u32).max(2)
+    }
+
+    // The keyframes at the video resolution.
+    pub fn keyframes(&self) -> Result<Vec<RenderParams>, String> {
+        let keyframes = match (&self.bookmarks, self.from, self.to) {
+            (Some(path), None, None) => bookmarks::load(path)?
+                .into_iter()
+                .map(|bookmark| bookmarks::resized(bookmark.params, self.width, self.height))
+                .collect::<Result<Vec<_>, _>>()?,
+            (None, Some(from), Some(to)) => [from, to]
+                .into_iter()
+                .map(|location| {
+                    let height = location.height.unwrap_or(Viewport::default().height);
+                    let viewport = Viewport::new(location.center, height, height)
+                        .fit((self.width, self.height));
+                    let viewport = location.place(viewport);
+                    RenderParams::builder()
+                        .viewport(viewport)
+                        .size(self.width, self.height)
+                        .iterations(self.iterations)
+                        .build()
+                })
+                .collect::<Result<Vec<_>, _>>()?,
+            _ => return Err("tour needs a bookmarks file or both --from and --to".to_string()),
+        };
+        if keyframes.len() < 2 {
+            return Err("tour needs at least two keyframes".to_string());
+        }
+        Ok(keyframes)
+    }
+
+    // The params of the frame at `time`, a fraction of the whole tour.
+    pub fn frame_params(&self, keyframes: &[RenderParams], time: f64) -> RenderParams {
+        let lengths = keyframes
+            .windows(2)
+            .map(|leg| leg_length(&leg[0].viewport, &leg[1].viewport))
+            .collect::<Vec<_>>();
+        let total = lengths.iter().sum::<f64>();
+        let mut at = time.clamp(0.0, 1.0) * total;
+        for (i, length) in lengths.iter().enumerate() {
+            if at <= *length || i == lengths.len() - 1 {
+                let t = if *length > 0.0 { at / length } else { 1.0 };
+                return fly(
+                    &keyframes[i],
+                    &keyframes[i + 1],
+                    self.easing.apply(t.min(1.0)),
+                );
+            }
+            at -= length;
+        }
+        unreachable!("a tour has at least one leg")
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        if self.duration.is_nan() || self.duration <= 0.0 || self.fps == 0 {
+            return Err("Duration and fps must be positive".to_string());
+        }
+        if self.width == 0 || self.height == 0 {
+            return Err("tour needs a positive --size".to_string());
+        }
+        // The frames between two keyframes are no deeper than the deeper of them, so those are
+        // the ones to check, as for `zoomvideo`.
+        for (i, keyframe) in self.keyframes()?.iter().enumerate() {
+            if let Some(excess) = video::beyond_precision(keyframe) {
+                return Err(format!(
+                    "Keyframe {} is about {excess:.0} times deeper than the renderer resolves at \
+                     its location",
+                    i + 1
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+// How far a leg goes, which the time it takes is proportional to: the octaves zoomed and the
+// view heights panned, at the larger of the two heights.
+fn leg_length(from: &Viewport, to: &Viewport) -> f64 {
+    let octaves = (from.height / to.height).log2().abs();
+    let panned = to.displacement(from).norm() / from.height.max(to.height);
+    octaves + panned
+}
+
+// The view `progress` of the way from one keyframe to the next, the height changing at a
+// constant rate in its logarithm and the center approaching as fast as the height does, like
+// the dive of the explorer. Everything else is that of the keyframe left until the next one is
+// reached.
+fn fly(from: &RenderParams, to: &RenderParams, progress: f64) -> RenderParams {
+    if progress >= 1.0 {
+        return *to;
+    }
+    let (a, b) = (&from.viewport, &to.viewport);
+    let height = a.height * (b.height / a.height).powf(progress);
+    let approach = if a.height == b.height {
+        progress
+    } else {
+        (a.height - height) / (a.height - b.height)
+    };
+    let mut viewport = Viewport {
+        width: height * a.width / a.height,
+        height,
+        rotation: a.rotation + (b.rotation - a.rotation) * progress,
+        ..*a
+    };
+    viewport.pan(b.displacement(a) * approach);
+    let ratio = to.iterations as f64 / from.iterations as f64;
+    let iterations = from.iterations as f64 * ratio.powf(progress);
+    RenderParams {
+        viewport,
+        iterations: iterations.round().max(1.0) as u32,
+        ..*from
+    }
+}
+
+fn frame_path(dir: &Path, index: u32) -> PathBuf {
+    dir.join(format!("frame_{index:05}.png"))
+}
+
+pub fn run(job: &Tour) -> Result<(), String> {
+    job.validate()?;
+    let keyframes = job.keyframes()?;
+    let stamp = Instant::now();
+    let mut encoder = if video::is_video_file(&job.output) {
+        Some(video::spawn_encoder(
+            &job.output,
+            (job.width, job.height),
+            job.fps,
+        )?)
+    } else {
+        std::fs::create_dir_all(&job.output)
+            .map_err(|e| format!("{}: {e}", job.output.display()))?;
+        None
+    };
+    let frames = job.frame_count();
+    for index in 0..frames {
+        let params = job.frame_params(&keyframes, index as f64 / (frames - 1) as f64);
+        let frame = render::render_frame(params);
+        match &mut encoder {
+            Some(encoder) => encoder
+                .stdin
+                .as_mut()
+                .ok_or("ffmpeg input is closed")?
+                .write_all(&frame.rgb)
+                .map_err(|e| format!("Writing to ffmpeg failed: {e}"))?,
+            None => png::write(
+                &frame_path(&job.output, index),
+                frame.width,
+                frame.height,
+                &frame.rgb,
+            )?,
+        }
+        println!(
+            "Frame {}/{frames} (height {:.3e})",
+            index + 1,
+            params.viewport.height
+        );
+    }
+    if let Some(mut encoder) = encoder {
+        drop(encoder.stdin.take());
+        let status = encoder.wait().map_err(|e| e.to_string())?;
+        if !status.success() {
+            return Err(format!("ffmpeg failed with {status}"));
+        }
+    }
+    println!(
+        "Wrote {} in {:?}",
+        job.output.display(),
+        Instant::now() - stamp
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::viewport::NO_TAIL;
+    use num::complex::Complex;
+
+    fn location(re: f64, im: f64, height: f64) -> Location {
+        Location {
+            center: Complex::new(re, im),
+            center_low: Complex::new(0.0, 0.0),
+            center_tail: NO_TAIL,
+            height: Some(height),
+        }
+    }
+
+    #[test]
+    fn flies_through_the_keyframes() {
+        let job = Tour {
+            from: Some(location(-0.5, 0.0, 3.0)),
+            to: Some(location(-0.75, 0.1, 3.0 / 1024.0)),
+            width: 64,
+            height: 48,
+            easing: Easing::Linear,
+            ..Tour::default()
+        };
+        let keyframes = job.keyframes().unwrap();
+        let start = job.frame_params(&keyframes, 0.0);
+        assert_eq!(start.viewport, keyframes[0].viewport);
+        assert_eq!(job.frame_params(&keyframes, 1.0), keyframes[1]);
+        // Linear in the logarithm of the height: halfway is five of the ten octaves.
+        let half = job.frame_params(&keyframes, 0.5).viewport;
+        assert!((half.height - 3.0 / 32.0).abs() < 1e-12);
+        assert!((half.width / half.height - 64.0 / 48.0).abs() < 1e-12);
+        let target = keyframes[1].viewport.center;
+        let heights = (0..=10)
+            .map(|i| job.frame_params(&keyframes, i as f64 / 10.0).viewport)
+            .collect::<Vec<_>>();
+        assert!(heights.windows(2).all(|w| w[1].height < w[0].height
+            && (w[1].center - target).norm() <= (w[0].center - target).norm()));
+
+        // Easing in and out slows down at both ends.
+        let eased = Tour {
+            easing: Easing::InOut,
+            ..job.clone()
+        };
+        let step = |job: &Tour, t: f64| {
+            let height = |t| job.frame_params(&keyframes, t).viewport.height.log2();
+            height(t) - height(t + 0.05)
+        };
+        assert!(step(&eased, 0.0) < step(&job, 0.0));
+        assert!(step(&eased, 0.45) > step(&job, 0.45));
+        assert!(Tour::default().keyframes().is_err());
+        assert_eq!(job.validate(), Ok(()));
+        let beyond = Tour {
+            to: Some(location(-0.75, 0.1, 1e-300)),
+            ..job.clone()
+        };
+        assert!(beyond.validate().unwrap_err().starts_with("Keyframe 2 "));
+    }
+}
@@ -0,0 +1,828 @@
+use crate::json::{FromJson, Json, ToJson};
+use crate::params::{Fractal, RenderParams};
+use crate::postprocess;
+use crate::render::{self, Frame};
+use crate::schedule::IterationSchedule;
+use crate::scheduler::Precision;
+use crate::viewport::Viewport;
+use num::complex::Complex;
+use rayon::prelude::*;
+use std::collections::HashMap;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Stdio};
+use std::time::Instant;
+
+// Keyframes are rendered at this multiple of the video resolution, so reprojected frames are
+// always downsampled, never magnified.
+const KEYFRAME_SCALE: u32 = 2;
+// Height of the complex plane visible in the first frame.
+const START_HEIGHT: f64 = 3.0;
+
+// A timestamped change of the zoom, e.g. on a beat of the soundtrack. `speed` is the zoom
+// speed relative to the default from `time` on, `hue` the palette rotation in degrees.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SyncEvent {
+    pub time: f64,
+    pub speed: Option<f64>,
+    pub hue: Option<f64>,
+}
+
+impl FromJson for SyncEvent {
+    fn from_json(json: &Json) -> Result<Self, String> {
+        let optional = |key: &str| json.get(key).map(Json::as_f64).transpose();
+        let event = SyncEvent {
+            time: json.field("time")?.as_f64()?,
+            speed: optional("speed")?,
+            hue: optional("hue")?,
+        };
+        if event.time.is_nan() || event.time < 0.0 {
+            return Err(format!("Invalid event time {}", event.time));
+        }
+        if event
+            .speed
+            .is_some_and(|speed| speed.is_nan() || speed < 0.0)
+        {
+            return Err(format!("Invalid zoom speed at {}s", event.time));
+        }
+        Ok(event)
+    }
+}
+
+// Reads a JSON array of events, e.g. `[{"time": 1.5, "speed": 2}, {"time": 3, "hue": 90}]`.
+pub fn load_events(path: &Path) -> Result<Vec<SyncEvent>, String> {
+    let text = std::fs::read_to_string(path).map_err(|e| format!("{}: {e}", path.display()))?;
+    let mut events = Json::parse(&text)?
+        .as_array()?
+        .iter()
+        .map(SyncEvent::from_json)
+        .collect::<Result<Vec<_>, _>>()?;
+    events.sort_by(|a, b| a.time.total_cmp(&b.time));
+    Ok(events)
+}
+
+// How frames are derived from the keyframes, from fastest to best. `Render` skips the
+// keyframes and renders every frame directly.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Interpolation {
+    Nearest,
+    Box,
+    Bilinear,
+    Bicubic,
+    Render,
+}
+
+impl Interpolation {
+    pub const ALL: [Interpolation; 5] = [
+        Interpolation::Nearest,
+        Interpolation::Box,
+        Interpolation::Bilinear,
+        Interpolation::Bicubic,
+        Interpolation::Render,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Interpolation::Nearest => "nearest",
+            Interpolation::Box => "box",
+            Interpolation::Bilinear => "bilinear",
+            Interpolation::Bicubic => "bicubic",
+            Interpolation::Render => "render",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Interpolation> {
+        Interpolation::ALL.into_iter().find(|i| i.name() == name)
+    }
+
+    // Reconstruction kernel and its support in pixels, for the filtering modes.
+    fn kernel(&self) -> Option<(Kernel, f64)> {
+        match self {
+            Interpolation::Box => Some((|t| if t.abs() <= 0.5 { 1.0 } else { 0.0 }, 0.5)),
+            Interpolation::Bilinear => Some((|t| (1.0 - t.abs()).max(0.0), 1.0)),
+            Interpolation::Bicubic => Some((catmull_rom, 2.0)),
+            Interpolation::Nearest | Interpolation::Render => None,
+        }
+    }
+}
+
+type Kernel = fn(f64) -> f64;
+
+fn catmull_rom(t: f64) -> f64 {
+    let t = t.abs();
+    if t < 1.0 {
+        1.5 * t * t * t - 2.5 * t * t + 1.0
+    } else if t < 2.0 {
+        -0.5 * t * t * t + 2.5 * t * t - 4.0 * t + 2.0
+    } else {
+        0.0
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ZoomVideo {
+    pub center: Complex<f64>,
+    // Total magnification between the first and the last frame.
+    pub depth: f64,
+    // Seconds.
+    pub duration: f64,
+    pub fps: u32,
+    pub width: u32,
+    pub height: u32,
+    pub iterations: u32,
+    // Overrides `iterations` with a count depending on the zoom.
+    pub iterations_schedule: Option<IterationSchedule>,
+    pub antialias: u32,
+    pub output: PathBuf,
+    // Sorted by time.
+    pub events: Vec<SyncEvent>,
+    pub interpolation: Interpolation,
+    // Sub-frames averaged into every frame, spread over the frame interval (motion blur).
+    pub shutter: u32,
+    // Keep the frames of an interrupted export that are still valid.
+    pub resume: bool,
+    // Show the frames in a window as they complete.
+    pub preview: bool,
+}
+
+impl Default for ZoomVideo {
+    fn default() -> Self {
+        ZoomVideo {
+            center: Complex::new(-0.75, 0.0),
+            depth: 1e6,
+            duration: 30.0,
+            fps: 30,
+            width: 1280,
+            height: 720,
+            iterations: 1000,
+            iterations_schedule: None,
+            antialias: 2,
+            output: PathBuf::from("zoom.mp4"),
+            events: Vec::new(),
+            interpolation: Interpolation::Box,
+            shutter: 1,
+            resume: false,
+            preview: false,
+        }
+    }
+}
+
+impl ZoomVideo {
+    pub fn frame_count(&self) -> u32 {
+        ((self.duration * self.fps as f64).round() as u32).max(1)
+    }
+
+    // Integral of the zoom speed from the start, speed changes taking effect at their events.
+    fn progress(&self, time: f64) -> f64 {
+        let mut progress = 0.0;
+        let (mut since, mut speed) = (0.0, 1.0);
+        for event in &self.events {
+            let Some(next) = event.speed else {
+                continue;
+            };
+            if event.time >= time {
+                break;
+            }
+            progress += speed * (event.time - since);
+            (since, speed) = (event.time, next);
+        }
+        progress + speed * (time - since)
+    }
+
+    // Zoom of a frame as a power of two. It grows linearly in time at the default speed, so the
+    // zoom looks steady, and always reaches the full depth in the last frame.
+    fn zoom(&self, frame: f64) -> f64 {
+        let last_frame = (self.frame_count() - 1) as f64;
+        let last = self.progress(last_frame / self.fps as f64);
+        if last <= 0.0 {
+            return 0.0;
+        }
+        let time = frame.clamp(0.0, last_frame) / self.fps as f64;
+        self.depth.log2() * self.progress(time) / last
+    }
+
+    // Zooms of the sub-frames averaged into a frame, evenly spread over the interval centered
+    // at the frame time.
+    fn shutter_zooms(&self, frame: u32) -> Vec<f64> {
+        (0..self.shutter)
+            .map(|i| {
+                let offset = (i as f64 + 0.5) / self.shutter as f64 - 0.5;
+                self.zoom(frame as f64 + offset)
+            })
+            .collect()
+    }
+
+    // Hash of everything a frame's pixels depend on, recorded in the manifest so that resuming
+    // never keeps frames of a different export.
+    fn frame_hash(&self, frame: u32) -> Result<u64, String> {
+        let zooms = self.shutter_zooms(frame);
+        let mut fields = vec![
+            ("keyframe", self.keyframe_params(0)?.to_json()),
+            (
+                "interpolation",
+                Json::String(self.interpolation.name().to_string()),
+            ),
+            (
+                "zooms",
+                Json::Array(zooms.iter().copied().map(Json::Number).collect()),
+            ),
+            ("hue", Json::Number(self.hue(frame))),
+        ];
+        if self.iterations_schedule.is_some() {
+            // Counts of the direct render and of both keyframes of every sub-frame.
+            let counts = zooms.iter().flat_map(|&zoom| {
+                [zoom, zoom.floor(), zoom.floor() + 1.0]
+                    .map(|zoom| Json::Number(self.iterations_at(zoom) as f64))
+            });
+            fields.push(("iterations", Json::Array(counts.collect())));
+        }
+        Ok(Json::object(fields).content_hash())
+    }
+
+    // Iteration count at zoom 2^`zoom`.
+    fn iterations_at(&self, zoom: f64) -> u32 {
+        self.iterations_schedule
+            .as_ref()
+            .map_or(self.iterations, |schedule| {
+                schedule.iterations(2f64.powf(zoom))
+            })
+    }
+
+    fn hue(&self, frame: u32) -> f64 {
+        let time = frame as f64 / self.fps as f64;
+        self.events
+            .iter()
+            .take_while(|event| event.time <= time)
+            .filter_map(|event| event.hue)
+            .last()
+            .unwrap_or(0.0)
+    }
+
+    fn viewport(&self, zoom: f64) -> Viewport {
+        let height = START_HEIGHT * 2f64.powf(-zoom);
+        Viewport::new(
+            self.center,
+            height * self.width as f64 / self.height as f64,
+            height,
+        )
+    }
+
+    // Keyframe `k` shows the view at zoom 2^k at `KEYFRAME_SCALE` times the video resolution.
+    pub fn keyframe_params(&self, k: u32) -> Result<RenderParams, String> {
+        RenderParams::builder()
+            .fractal(Fractal::MANDELBROT)
+            .viewport(self.viewport(k as f64))
+            .size(self.width * KEYFRAME_SCALE, self.height * KEYFRAME_SCALE)
+            .iterations(self.iterations_at(k as f64))
+            .antialias(self.antialias)
+            .build()
+    }
+
+    // Direct render of a frame, anti-aliased as finely as a keyframe pixel is.
+    fn frame_params(&self, zoom: f64) -> Result<RenderParams, String> {
+        RenderParams::builder()
+            .fractal(Fractal::MANDELBROT)
+            .viewport(self.viewport(zoom))
+            .size(self.width, self.height)
+            .iterations(self.iterations_at(zoom))
+            .antialias((self.antialias * KEYFRAME_SCALE).min(8))
+            .build()
+    }
+
+    // The deepest keyframe must still resolve distinct pixels in the most precise format the
+    // renderer has for it, so deeper zooms are rejected up front rather than rendered as
+    // blocks.
+    fn check_precision(&self) -> Result<(), String> {
+        let deepest = self.keyframe_params(self.depth.log2().ceil() as u32 + 1)?;
+        if let Some(excess) = beyond_precision(&deepest) {
+            let max_depth = self.depth / excess;
+            return Err(format!(
+                "Zoom depth {:e} exceeds the precision of the renderer at this location (at most \
+                 {:e})",
+                self.depth, max_depth
+            ));
+        }
+        Ok(())
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        if !(self.depth >= 1.0 && self.depth.is_finite()) {
+            return Err(format!("Invalid zoom depth {}", self.depth));
+        }
+        if self.duration.is_nan() || self.duration <= 0.0 || self.fps == 0 || self.shutter == 0 {
+            return Err("Duration, fps and shutter must be positive".to_string());
+        }
+        self.keyframe_params(0)?;
+        if self.resume && is_video_file(&self.output) {
+            return Err(
+                "--resume needs a frame directory as output, encode the frames once complete"
+                    .to_string(),
+            );
+        }
+        self.check_precision()
+    }
+}
+
+// How many times too small the pixels of the view are for the renderer, `None` while they are
+// still resolved.
+pub fn beyond_precision(params: &RenderParams) -> Option<f64> {
+    let precision = Precision::reach(params.fractal);
+    let pixel = params.viewport.width / params.width as f64;
+    (!precision.resolves(params)).then(|| precision.min_pixel(params.viewport.center) / pixel)
+}
+
+fn pixel(frame: &Frame, x: i64, y: i64) -> [f64; 3] {
+    let x = x.clamp(0, frame.width as i64 - 1);
+    let y = y.clamp(0, frame.height as i64 - 1);
+    let index = (y * frame.width as i64 + x) as usize * 3;
+    let rgb = &frame.rgb[index..index + 3];
+    [rgb[0] as f64, rgb[1] as f64, rgb[2] as f64]
+}
+
+// Resamples the keyframe at a fractional pixel position. Pixel `i` is sampled at coordinate `i`,
+// the kernel is stretched over the output pixel `footprint` (in keyframe pixels) when that is
+// larger than a pixel so that downsampling does not alias.
+fn sample(frame: &Frame, x: f64, y: f64, footprint: f64, interpolation: Interpolation) -> [u8; 3] {
+    let Some((kernel, support)) = interpolation.kernel() else {
+        return pixel(frame, x.round() as i64, y.round() as i64).map(|c| c as u8);
+    };
+    let scale = footprint.max(1.0);
+    let reach = support * scale;
+    let taps = |center: f64| {
+        let weights = ((center - reach).ceil() as i64..=(center + reach).floor() as i64)
+            .map(|i| (i, kernel((i as f64 - center) / scale)))
+            .filter(|(_, weight)| *weight != 0.0)
+            .collect::<Vec<_>>();
+        // The box kernel can miss every tap when its reach is under half a pixel.
+        if weights.is_empty() {
+            vec![(center.round() as i64, 1.0)]
+        } else {
+            weights
+        }
+    };
+    let (xs, ys) = (taps(x), taps(y));
+    let mut sum = [0.0; 3];
+    let mut total = 0.0;
+    for &(py, wy) in &ys {
+        for &(px, wx) in &xs {
+            let weight = wx * wy;
+            for (acc, channel) in sum.iter_mut().zip(pixel(frame, px, py)) {
+                *acc += weight * channel;
+            }
+            total += weight;
+        }
+    }
+    sum.map(|channel| (channel / total).round().clamp(0.0, 255.0) as u8)
+}
+
+// Reprojects the frame at `zoom` (between keyframes `k` and `k + 1`) from the two keyframes:
+// the center comes from the more detailed inner keyframe, the border from the outer one.
+fn compose(job: &ZoomVideo, zoom: f64, k: u32, outer: &Frame, inner: &Frame) -> Vec<u8> {
+    let interpolation = job.interpolation;
+    let size = (job.width, job.height);
+    let view = job.viewport(zoom);
+    let keyframes = [
+        (job.viewport(k as f64 + 1.0), inner),
+        (job.viewport(k as f64), outer),
+    ];
+    let coords = (0..job.height)
+        .flat_map(|y| (0..job.width).map(move |x| (x, y)))
+        .collect::<Vec<_>>();
+    coords
+        .par_iter()
+        .flat_map_iter(|&(x, y)| {
+            let point = view.pixel_to_complex(x as f64, y as f64, size);
+            let project = |(keyframe, frame): &(Viewport, &Frame)| {
+                let (kx, ky) = keyframe.complex_to_pixel(point, (frame.width, frame.height));
+                let inside = (0.0..=frame.width as f64).contains(&kx)
+                    && (0.0..=frame.height as f64).contains(&ky);
+                inside.then_some((kx, ky))
+            };
+            let ((keyframe, frame), (kx, ky)) = match project(&keyframes[0]) {
+                Some(position) => (keyframes[0], position),
+                None => (keyframes[1], project(&keyframes[1]).unwrap_or((0.0, 0.0))),
+            };
+            // Output pixel footprint measured in keyframe pixels.
+            let footprint = view.height / job.height as f64 * frame.height as f64 / keyframe.height;
+            sample(frame, kx, ky, footprint, interpolation)
+        })
+        .collect()
+}
+
+pub fn is_video_file(path: &Path) -> bool {
+    matches!(
+        path.extension().and_then(|ext| ext.to_str()),
+        Some("mp4" | "mkv" | "mov" | "webm")
+    )
+}
+
+pub fn write_ppm(path: &Path, width: u32, height: u32, rgb: &[u8]) -> Result<(), String> {
+    let mut data = format!("P6\n{width} {height}\n255\n").into_bytes();
+    data.extend_from_slice(rgb);
+    std::fs::write(path, data).map_err(|e| format!("{}: {e}", path.display()))
+}
+
+const MANIFEST: &str = "manifest.jsonl";
+
+// Log of the frames completed in a frame directory, one `{"frame": N, "hash": "..."}` line each,
+// appended only once the frame file is fully written.
+struct Manifest {
+    file: std::fs::File,
+    done: HashMap<u32, String>,
+}
+
+impl Manifest {
+    fn open(dir: &Path, resume: bool) -> Result<Manifest, String> {
+        let path = dir.join(MANIFEST);
+        let mut done = HashMap::new();
+        if resume {
+            // A missing manifest just means nothing was completed; a torn last line after a
+            // crash is ignored.
+            let text = std::fs::read_to_string(&path).unwrap_or_default();
+            for line in text.lines() {
+                let Ok(entry) = Json::parse(line) else {
+                    continue;
+                };
+                if let (Some(Json::Number(frame)), Some(Json::String(hash))) =
+                    (entry.get("frame"), entry.get("hash"))
+                {
+                    done.insert(*frame as u32, hash.clone());
+                }
+            }
+        }
+        let file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(resume)
+            .write(true)
+            .truncate(!resume)
+            .open(&path)
+            .map_err(|e| format!("{}: {e}", path.display()))?;
+        Ok(Manifest { file, done })
+    }
+
+    fn record(&mut self, frame: u32, hash: u64) -> Result<(), String> {
+        let entry = Json::object([
+            ("frame", Json::Number(frame as f64)),
+            ("hash", Json::String(format!("{hash:016x}"))),
+        ]);
+        writeln!(self.file, "{entry}")
+            .and_then(|_| self.file.flush())
+            .map_err(|e| format!("Writing the manifest failed: {e}"))
+    }
+}
+
+// ffmpeg encoding the raw RGB frames written to its input into the video file.
+pub fn spawn_encoder(path: &Path, (width, height): (u32, u32), fps: u32) -> Result<Child, String> {
+    Command::new("ffmpeg")
+        .args([
+            "-y",
+            "-loglevel",
+            "error",
+            "-f",
+            "rawvideo",
+            "-pix_fmt",
+            "rgb24",
+        ])
+        .args(["-s", &format!("{width}x{height}")])
+        .args(["-r", &fps.to_string(), "-i", "-"])
+        .args(["-c:v", "libx264", "-preset", "slow", "-crf", "18"])
+        .args(["-pix_fmt", "yuv420p", "-movflags", "+faststart"])
+        .arg(path)
+        .stdin(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("Cannot start ffmpeg: {e}"))
+}
+
+// Frames are either piped to ffmpeg (for video file outputs) or written as a numbered PPM
+// sequence into a directory, together with a manifest.
+enum Output {
+    Encoder(Child),
+    Frames(PathBuf, Manifest),
+}
+
+fn frame_path(dir: &Path, index: u32) -> PathBuf {
+    dir.join(format!("frame_{index:05}.ppm"))
+}
+
+impl Output {
+    fn open(job: &ZoomVideo) -> Result<Output, String> {
+        if !is_video_file(&job.output) {
+            std::fs::create_dir_all(&job.output)
+                .map_err(|e| format!("{}: {e}", job.output.display()))?;
+            let manifest = Manifest::open(&job.output, job.resume)?;
+            return Ok(Output::Frames(job.output.clone(), manifest));
+        }
+        let encoder = spawn_encoder(&job.output, (job.width, job.height), job.fps)?;
+        Ok(Output::Encoder(encoder))
+    }
+
+    fn write(&mut self, index: u32, width: u32, height: u32, rgb: &[u8]) -> Result<(), String> {
+        match self {
+            Output::Encoder(encoder) => encoder
+                .stdin
+                .as_mut()
+                .ok_or("ffmpeg input is closed")?
+                .write_all(rgb)
+                .map_err(|e| format!("Writing to ffmpeg failed: {e}")),
+            Output::Frames(dir, _) => write_ppm(&frame_path(dir, index), width, height, rgb),
+        }
+    }
+
+    // Whether the frame is already on disk from an earlier run of the same export: listed in
+    // the manifest with the same hash and with a complete file.
+    fn has(&self, job: &ZoomVideo, index: u32, hash: u64) -> bool {
+        let Output::Frames(dir, manifest) = self else {
+            return false;
+        };
+        let expected = format!("P6\n{} {}\n255\n", job.width, job.height).len()
+            + (job.width * job.height * 3) as usize;
+        manifest.done.get(&index) == Some(&format!("{hash:016x}"))
+            && std::fs::metadata(frame_path(dir, index))
+                .is_ok_and(|meta| meta.len() == expected as u64)
+    }
+
+    fn completed(&mut self, index: u32, hash: u64) -> Result<(), String> {
+        match self {
+            Output::Encoder(_) => Ok(()),
+            Output::Frames(_, manifest) => manifest.record(index, hash),
+        }
+    }
+
+    fn finish(self) -> Result<(), String> {
+        if let Output::Encoder(mut encoder) = self {
+            drop(encoder.stdin.take());
+            let status = encoder.wait().map_err(|e| e.to_string())?;
+            if !status.success() {
+                return Err(format!("ffmpeg failed with {status}"));
+            }
+        }
+        Ok(())
+    }
+}
+
+// Keyframes rendered so far that later frames may still need.
+struct Keyframes<'a> {
+    job: &'a ZoomVideo,
+    frames: Vec<(u32, Frame)>,
+}
+
+impl Keyframes<'_> {
+    // Renders keyframes `k` and `k + 1` unless they are at hand, dropping the shallower ones,
+    // which the remaining (deeper) frames will not use. Sub-frames of a frame can straddle a
+    // keyframe, so `k - 1` is kept as well.
+    fn pair(&mut self, k: u32) -> Result<(&Frame, &Frame), String> {
+        self.frames.retain(|(index, _)| index + 1 >= k);
+        for index in [k, k + 1] {
+            if !self.frames.iter().any(|(i, _)| *i == index) {
+                let frame = render::render_frame(self.job.keyframe_params(index)?);
+                self.frames.push((index, frame));
+            }
+        }
+        let get = |index| &self.frames.iter().find(|(i, _)| *i == index).unwrap().1;
+        Ok((get(k), get(k + 1)))
+    }
+}
+
+// Renders and writes all frames, passing each completed one to `on_frame` (e.g. for a preview).
+pub fn run(job: &ZoomVideo, mut on_frame: impl FnMut(u32, &mut [u8])) -> Result<(), String> {
+    job.validate()?;
+    let frames = job.frame_count();
+    let stamp = Instant::now();
+    let mut output = Output::open(job)?;
+    let mut keyframes = Keyframes {
+        job,
+        frames: Vec::new(),
+    };
+    let pixels = (job.width * job.height * 3) as usize;
+    let mut skipped = 0;
+    for index in 0..frames {
+        let hash = job.frame_hash(index)?;
+        if job.resume && output.has(job, index, hash) {
+            skipped += 1;
+            continue;
+        }
+        let zooms = job.shutter_zooms(index);
+        let mut sum = vec![0u32; pixels];
+        for &zoom in &zooms {
+            let rgb = if job.interpolation == Interpolation::Render {
+                render::render_frame(job.frame_params(zoom)?).rgb
+            } else {
+                let k = zoom.floor() as u32;
+                let (outer, inner) = keyframes.pair(k)?;
+                compose(job, zoom, k, outer, inner)
+            };
+            sum.iter_mut()
+                .zip(rgb)
+                .for_each(|(acc, channel)| *acc += channel as u32);
+        }
+        let mut rgb = sum
+            .into_iter()
+            .map(|channel| (channel / zooms.len() as u32) as u8)
+            .collect::<Vec<_>>();
+        let hue = job.hue(index);
+        if hue != 0.0 {
+            postprocess::rotate_hue(&mut rgb, hue);
+        }
+        output.write(index, job.width, job.height, &rgb)?;
+        output.completed(index, hash)?;
+        on_frame(index, &mut rgb);
+        println!(
+            "Frame {}/{frames} (zoom 2^{:.2})",
+            index + 1,
+            job.zoom(index as f64)
+        );
+    }
+    output.finish()?;
+    if skipped > 0 {
+        println!("Kept {skipped} frames from the previous run");
+    }
+    println!(
+        "Wrote {} in {:?}",
+        job.output.display(),
+        Instant::now() - stamp
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn job() -> ZoomVideo {
+        ZoomVideo {
+            center: Complex::new(-0.743643887037151, 0.13182590420533),
+            depth: 16.0,
+            duration: 1.0,
+            fps: 9,
+            width: 64,
+            height: 48,
+            iterations: 200,
+            iterations_schedule: None,
+            antialias: 1,
+            output: PathBuf::from("frames"),
+            events: Vec::new(),
+            interpolation: Interpolation::Box,
+            shutter: 1,
+            resume: false,
+            preview: false,
+        }
+    }
+
+    #[test]
+    fn zoom_is_exponential_in_time() {
+        let job = job();
+        assert_eq!(job.frame_count(), 9);
+        assert_eq!(job.zoom(0.0), 0.0);
+        assert_eq!(job.zoom(8.0), 4.0);
+        let heights = (0..9)
+            .map(|frame| job.viewport(job.zoom(frame as f64)).height)
+            .collect::<Vec<_>>();
+        let ratios = heights.windows(2).map(|w| w[1] / w[0]).collect::<Vec<_>>();
+        assert!(ratios.iter().all(|r| (r - ratios[0]).abs() < 1e-12));
+    }
+
+    #[test]
+    fn events_change_speed_and_hue() {
+        let event = |time, speed, hue| SyncEvent { time, speed, hue };
+        let job = ZoomVideo {
+            fps: 10,
+            duration: 1.1,
+            events: vec![
+                event(0.2, Some(0.0), None),
+                event(0.4, None, Some(90.0)),
+                event(0.6, Some(3.0), Some(180.0)),
+            ],
+            ..job()
+        };
+        // Paused between 0.2s and 0.6s, then three times as fast.
+        assert_eq!(job.zoom(3.0), job.zoom(6.0));
+        let step = |frame: f64| job.zoom(frame + 1.0) - job.zoom(frame);
+        assert!((step(8.0) - 3.0 * step(0.0)).abs() < 1e-9);
+        assert!((job.zoom(10.0) - 4.0).abs() < 1e-9);
+        assert_eq!([job.hue(3), job.hue(4), job.hue(9)], [0.0, 90.0, 180.0]);
+    }
+
+    #[test]
+    fn parses_events() {
+        let json = Json::parse(r#"{"time": 2.5, "speed": 0.5}"#).unwrap();
+        assert_eq!(
+            SyncEvent::from_json(&json),
+            Ok(SyncEvent {
+                time: 2.5,
+                speed: Some(0.5),
+                hue: None
+            })
+        );
+        let json = Json::parse(r#"{"time": 1, "speed": -1}"#).unwrap();
+        assert!(SyncEvent::from_json(&json).is_err());
+    }
+
+    #[test]
+    fn composed_frames_match_direct_render() {
+        let job = job();
+        let outer = render::render_frame(job.keyframe_params(1).unwrap());
+        let inner = render::render_frame(job.keyframe_params(2).unwrap());
+        let direct = render::render_frame(job.frame_params(1.5).unwrap());
+        for interpolation in Interpolation::ALL {
+            if interpolation == Interpolation::Render {
+                continue;
+            }
+            let job = ZoomVideo {
+                interpolation,
+                ..job.clone()
+            };
+            let composed = compose(&job, 1.5, 1, &outer, &inner);
+            let error = composed
+                .iter()
+                .zip(&direct.rgb)
+                .map(|(a, b)| (*a as f64 - *b as f64).abs())
+                .sum::<f64>()
+                / composed.len() as f64;
+            assert!(error < 10.0, "{interpolation:?}: mean error {error}");
+        }
+    }
+
+    #[test]
+    fn shutter_spreads_sub_frames_over_the_interval() {
+        let job = ZoomVideo {
+            shutter: 4,
+            ..job()
+        };
+        let zooms = job.shutter_zooms(4);
+        assert_eq!(zooms.len(), 4);
+        assert!(zooms.windows(2).all(|w| w[0] < w[1]));
+        assert!((zooms.iter().sum::<f64>() / 4.0 - job.zoom(4.0)).abs() < 1e-9);
+        assert!(
+            job.zoom(job.zoom(8.5)) <= job.zoom(8.0),
+            "clamped to the last frame"
+        );
+    }
+
+    #[test]
+    fn resume_keeps_only_matching_frames() {
+        let dir = std::env::temp_dir().join(format!("mandelbrot-resume-{}", std::process::id()));
+        let job = ZoomVideo {
+            output: dir.clone(),
+            fps: 4,
+            width: 16,
+            height: 12,
+            ..job()
+        };
+        run(&job, |_, _| {}).unwrap();
+        // Simulate an interrupted run: the last frame is lost, another one is corrupted.
+        std::fs::remove_file(frame_path(&dir, 3)).unwrap();
+        std::fs::write(frame_path(&dir, 1), b"P6\n").unwrap();
+        let resumed = ZoomVideo {
+            resume: true,
+            ..job.clone()
+        };
+        let output = Output::open(&resumed).unwrap();
+        let kept = (0..4)
+            .map(|i| output.has(&resumed, i, resumed.frame_hash(i).unwrap()))
+            .collect::<Vec<_>>();
+        assert_eq!(kept, [true, false, true, false]);
+        let changed = ZoomVideo {
+            iterations: 300,
+            ..resumed.clone()
+        };
+        assert!(!output.has(&changed, 0, changed.frame_hash(0).unwrap()));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn schedule_sets_iterations_per_keyframe() {
+        let job = ZoomVideo {
+            iterations_schedule: Some(IterationSchedule::Formula {
+                base: 100.0,
+                per_octave: 50.0,
+                max: None,
+            }),
+            ..job()
+        };
+        assert_eq!(job.keyframe_params(0).unwrap().iterations, 100);
+        assert_eq!(job.keyframe_params(3).unwrap().iterations, 250);
+        assert_eq!(job.frame_params(1.5).unwrap().iterations, 175);
+        assert_ne!(
+            job.frame_hash(2).unwrap(),
+            self::job().frame_hash(2).unwrap()
+        );
+    }
+
+    #[test]
+    fn rejects_zooms_beyond_the_precision() {
+        assert!(job().validate().is_ok());
+        // Past double-double, in fixed point.
+        assert!(ZoomVideo {
+            depth: 1e40,
+            ..job()
+        }
+        .validate()
+        .is_ok());
+        let deep = ZoomVideo {
+            depth: 1e300,
+            ..job()
+        };
+        assert!(deep.validate().is_err());
+        assert!(ZoomVideo { fps: 0, ..job() }.validate().is_err());
+    }
+}
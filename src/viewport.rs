@@ -0,0 +1,426 @@
+use crate::fixed::{Fixed, CENTER_BITS};
+use crate::json::{FromJson, Json, ToJson};
+use num::complex::Complex;
+
+// Terms of the center after its high and low part, for about 1e-280 together.
+pub const TAIL_TERMS: usize = 16;
+pub const NO_TAIL: [Complex<f64>; TAIL_TERMS] = [Complex::new(0.0, 0.0); TAIL_TERMS];
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Viewport {
+    pub center: Complex<f64>,
+    // The rest of the center beyond f64, which deep zooms render from in double-double.
+    pub center_low: Complex<f64>,
+    // The rest beyond double-double, which deeper zooms render from in fixed point.
+    pub center_tail: [Complex<f64>; TAIL_TERMS],
+    pub width: f64,
+    pub height: f64,
+    pub rotation: f64,
+}
+
+impl Default for Viewport {
+    fn default() -> Self {
+        Viewport::from_corners(Complex::new(-2.0, -1.5), Complex::new(2.0, 1.5))
+    }
+}
+
+impl Viewport {
+    pub fn new(center: Complex<f64>, width: f64, height: f64) -> Self {
+        Viewport {
+            center,
+            center_low: Complex::new(0.0, 0.0),
+            center_tail: NO_TAIL,
+            width,
+            height,
+            rotation: 0.0,
+        }
+    }
+
+    pub fn from_corners(min: Complex<f64>, max: Complex<f64>) -> Self {
+        let d = max - min;
+        Viewport::new((min + max) * 0.5, d.re, d.im)
+    }
+
+    // Maps a (possibly sub-pixel) window position to the complex plane. Pixel (0, 0) is the
+    // top-left corner of the window, which corresponds to the minimal re/im corner when the
+    // viewport is not rotated.
+    pub fn pixel_to_complex(&self, x: f64, y: f64, window_size: (u32, u32)) -> Complex<f64> {
+        self.center + self.offset(x, y, window_size)
+    }
+
+    // The window position relative to the center on the complex plane, which unlike the point
+    // itself keeps its precision at any depth.
+    pub fn offset(&self, x: f64, y: f64, window_size: (u32, u32)) -> Complex<f64> {
+        let offset = Complex::new(
+            (x / window_size.0 as f64 - 0.5) * self.width,
+            (y / window_size.1 as f64 - 0.5) * self.height,
+        );
+        offset * Complex::from_polar(1.0, self.rotation)
+    }
+
+    pub fn complex_to_pixel(&self, c: Complex<f64>, window_size: (u32, u32)) -> (f64, f64) {
+        self.offset_to_pixel(c - self.center - self.center_low, window_size)
+    }
+
+    pub fn offset_to_pixel(&self, offset: Complex<f64>, window_size: (u32, u32)) -> (f64, f64) {
+        let offset = offset * Complex::from_polar(1.0, -self.rotation);
+        (
+            (offset.re / self.width + 0.5) * window_size.0 as f64,
+            (offset.im / self.height + 0.5) * window_size.1 as f64,
+        )
+    }
+
+    // How far the center moved from that of `other`.
+    pub fn displacement(&self, other: &Viewport) -> Complex<f64> {
+        let (re, im) = self.center_fixed(CENTER_BITS);
+        let (other_re, other_im) = other.center_fixed(CENTER_BITS);
+        Complex::new((&re - &other_re).to_f64(), (&im - &other_im).to_f64())
+    }
+
+    // The terms of the center, real and imaginary part, up to the last one in use.
+    pub fn center_terms(&self) -> (Vec<f64>, Vec<f64>) {
+        let terms = [self.center, self.center_low]
+            .into_iter()
+            .chain(self.center_tail)
+            .collect::<Vec<_>>();
+        let used = terms
+            .iter()
+            .rposition(|term| *term != Complex::new(0.0, 0.0))
+            .map_or(1, |last| last + 1);
+        let terms = &terms[..used];
+        (
+            terms.iter().map(|term| term.re).collect(),
+            terms.iter().map(|term| term.im).collect(),
+        )
+    }
+
+    // The center in fixed point with `bits` fraction bits, real and imaginary part.
+    pub fn center_fixed(&self, bits: u64) -> (Fixed, Fixed) {
+        let (re, im) = self.center_terms();
+        (Fixed::from_terms(&re, bits), Fixed::from_terms(&im, bits))
+    }
+
+    // Moves the center to the point given in fixed point, split into as many terms as it has.
+    pub fn set_center(&mut self, re: &Fixed, im: &Fixed) {
+        let (re, im) = (re.terms(TAIL_TERMS + 2), im.terms(TAIL_TERMS + 2));
+        let term = |i: usize| {
+            Complex::new(
+                re.get(i).copied().unwrap_or(0.0),
+                im.get(i).copied().unwrap_or(0.0),
+            )
+        };
+        self.center = term(0);
+        self.center_low = term(1);
+        self.center_tail = std::array::from_fn(|i| term(i + 2));
+    }
+
+    // Scales the visible extent by `factor` while keeping `point` at the same window position,
+    // so factors below 1.0 zoom in and factors above 1.0 zoom out.
+    pub fn zoom_about(&mut self, point: Complex<f64>, factor: f64) {
+        self.zoom_at(point - self.center - self.center_low, factor);
+    }
+
+    // The same for the point at `offset` from the center.
+    pub fn zoom_at(&mut self, offset: Complex<f64>, factor: f64) {
+        self.pan(offset * (1.0 - factor));
+        self.width *= factor;
+        self.height *= factor;
+    }
+
+    // The same center, height and rotation widened or narrowed to the aspect ratio of a window,
+    // so that its pixels are square.
+    pub fn fit(&self, window_size: (u32, u32)) -> Viewport {
+        Viewport {
+            width: self.height * window_size.0 as f64 / window_size.1 as f64,
+            ..*self
+        }
+    }
+
+    pub fn pan(&mut self, delta: Complex<f64>) {
+        let (re, im) = self.center_fixed(CENTER_BITS);
+        let re = &re + &Fixed::from_f64(delta.re, CENTER_BITS);
+        let im = &im + &Fixed::from_f64(delta.im, CENTER_BITS);
+        self.set_center(&re, &im);
+    }
+
+    pub fn rotate(&mut self, angle: f64) {
+        self.rotation = (self.rotation + angle).rem_euclid(std::f64::consts::TAU);
+    }
+}
+
+impl ToJson for Viewport {
+    fn to_json(&self) -> Json {
+        let mut fields = vec![
+            ("center_re", Json::Number(self.center.re)),
+            ("center_im", Json::Number(self.center.im)),
+            ("width", Json::Number(self.width)),
+            ("height", Json::Number(self.height)),
+            ("rotation", Json::Number(self.rotation)),
+        ];
+        // Only deep views have digits beyond f64 to keep.
+        if self.center_low != Complex::new(0.0, 0.0) {
+            fields.push(("center_re_low", Json::Number(self.center_low.re)));
+            fields.push(("center_im_low", Json::Number(self.center_low.im)));
+        }
+        let (re, im) = self.center_terms();
+        if re.len() > 2 {
+            let tail =
+                |terms: &[f64]| Json::Array(terms[2..].iter().map(|&t| Json::Number(t)).collect());
+            fields.push(("center_re_tail", tail(&re)));
+            fields.push(("center_im_tail", tail(&im)));
+        }
+        Json::object(fields)
+    }
+}
+
+// The terms of a part of the center beyond double-double, none when missing.
+fn tail(json: &Json, key: &str) -> Result<Vec<f64>, String> {
+    let Some(terms) = json.get(key) else {
+        return Ok(Vec::new());
+    };
+    let terms = terms.as_array()?;
+    if terms.len() > TAIL_TERMS {
+        return Err(format!("{key} has more than {TAIL_TERMS} terms"));
+    }
+    terms.iter().map(Json::as_f64).collect()
+}
+
+impl FromJson for Viewport {
+    fn from_json(json: &Json) -> Result<Self, String> {
+        let (re, im) = (tail(json, "center_re_tail")?, tail(json, "center_im_tail")?);
+        let term = |terms: &[f64], i: usize| terms.get(i).copied().unwrap_or(0.0);
+        let viewport = Viewport {
+            center: Complex::new(
+                json.field("center_re")?.as_f64()?,
+                json.field("center_im")?.as_f64()?,
+            ),
+            center_low: Complex::new(
+                json.get("center_re_low").map_or(Ok(0.0), Json::as_f64)?,
+                json.get("center_im_low").map_or(Ok(0.0), Json::as_f64)?,
+            ),
+            center_tail: std::array::from_fn(|i| Complex::new(term(&re, i), term(&im, i))),
+            width: json.field("width")?.as_f64()?,
+            height: json.field("height")?.as_f64()?,
+            rotation: match json.get("rotation") {
+                Some(rotation) => rotation.as_f64()?,
+                None => 0.0,
+            },
+        };
+        if !(viewport.width > 0.0 && viewport.height > 0.0) {
+            return Err("Viewport extent must be positive".to_string());
+        }
+        Ok(viewport)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f64::consts::{FRAC_PI_2, PI};
+
+    const WINDOW: (u32, u32) = (800, 600);
+
+    fn assert_close(a: Complex<f64>, b: Complex<f64>) {
+        assert!((a - b).norm() < 1e-12, "{a} != {b}");
+    }
+
+    #[test]
+    fn default_matches_initial_view() {
+        let viewport = Viewport::default();
+        assert_close(viewport.center, Complex::new(0.0, 0.0));
+        assert_eq!((viewport.width, viewport.height), (4.0, 3.0));
+        assert_close(
+            viewport.pixel_to_complex(0.0, 0.0, WINDOW),
+            Complex::new(-2.0, -1.5),
+        );
+        assert_close(
+            viewport.pixel_to_complex(800.0, 600.0, WINDOW),
+            Complex::new(2.0, 1.5),
+        );
+        assert_close(
+            viewport.pixel_to_complex(400.0, 300.0, WINDOW),
+            Complex::new(0.0, 0.0),
+        );
+    }
+
+    #[test]
+    fn from_corners_handles_offset_regions() {
+        let viewport = Viewport::from_corners(Complex::new(-1.0, 0.5), Complex::new(0.0, 1.25));
+        assert_close(viewport.center, Complex::new(-0.5, 0.875));
+        assert!((viewport.width - 1.0).abs() < 1e-12);
+        assert!((viewport.height - 0.75).abs() < 1e-12);
+    }
+
+    #[test]
+    fn pixel_round_trip_with_rotation() {
+        let mut viewport = Viewport::new(Complex::new(-0.75, 0.1), 0.02, 0.015);
+        for angle in [0.0, 0.3, FRAC_PI_2, PI, 5.0] {
+            viewport.rotation = angle;
+            for (x, y) in [(0.0, 0.0), (123.5, 456.25), (799.0, 1.0), (400.0, 300.0)] {
+                let c = viewport.pixel_to_complex(x, y, WINDOW);
+                let (px, py) = viewport.complex_to_pixel(c, WINDOW);
+                assert!((px - x).abs() < 1e-6 && (py - y).abs() < 1e-6);
+            }
+        }
+    }
+
+    #[test]
+    fn zoom_keeps_point_fixed() {
+        let mut viewport = Viewport::default();
+        let (x, y) = (200.0, 450.0);
+        let point = viewport.pixel_to_complex(x, y, WINDOW);
+        viewport.zoom_about(point, 0.9);
+        assert_close(viewport.pixel_to_complex(x, y, WINDOW), point);
+        assert!((viewport.width - 3.6).abs() < 1e-12);
+        assert!((viewport.height - 2.7).abs() < 1e-12);
+    }
+
+    #[test]
+    fn zoom_matches_legacy_corner_update() {
+        let mut viewport = Viewport::default();
+        let (min, max) = (Complex::new(-2.0, -1.5), Complex::new(2.0, 1.5));
+        let click = viewport.pixel_to_complex(600.0, 150.0, WINDOW);
+        let d = max - min;
+        let rel = Complex::new((click.re - min.re) / d.re, (click.im - min.im) / d.im);
+        let legacy_min = Complex::new(min.re + d.re * 0.1 * rel.re, min.im + d.im * 0.1 * rel.im);
+        let legacy_max = Complex::new(
+            max.re - d.re * 0.1 * (1.0 - rel.re),
+            max.im - d.im * 0.1 * (1.0 - rel.im),
+        );
+        viewport.zoom_about(click, 0.9);
+        assert_close(viewport.pixel_to_complex(0.0, 0.0, WINDOW), legacy_min);
+        assert_close(viewport.pixel_to_complex(800.0, 600.0, WINDOW), legacy_max);
+    }
+
+    #[test]
+    fn zoom_in_and_out_is_reversible() {
+        let mut viewport = Viewport::new(Complex::new(0.3, -0.2), 1.0, 0.75);
+        viewport.rotation = 1.0;
+        let original = viewport;
+        let point = Complex::new(0.1, 0.1);
+        viewport.zoom_about(point, 0.5);
+        viewport.zoom_about(point, 2.0);
+        assert_close(viewport.center, original.center);
+        assert!((viewport.width - original.width).abs() < 1e-12);
+    }
+
+    #[test]
+    fn zoom_with_rotation_keeps_point_fixed() {
+        let mut viewport = Viewport::default();
+        viewport.rotate(0.7);
+        let point = viewport.pixel_to_complex(100.0, 500.0, WINDOW);
+        viewport.zoom_about(point, 0.25);
+        assert_close(viewport.pixel_to_complex(100.0, 500.0, WINDOW), point);
+    }
+
+    #[test]
+    fn fit_keeps_pixels_square() {
+        let mut viewport = Viewport::default();
+        viewport.rotate(0.5);
+        let wide = viewport.fit((1600, 600));
+        assert_eq!((wide.width, wide.height), (8.0, 3.0));
+        assert_eq!(
+            (wide.center, wide.rotation),
+            (viewport.center, viewport.rotation)
+        );
+        assert_eq!(viewport.fit(WINDOW), viewport);
+    }
+
+    #[test]
+    fn pan_translates_every_pixel() {
+        let mut viewport = Viewport::default();
+        let before = viewport.pixel_to_complex(10.0, 20.0, WINDOW);
+        viewport.pan(Complex::new(0.5, -0.25));
+        let after = viewport.pixel_to_complex(10.0, 20.0, WINDOW);
+        assert_close(after - before, Complex::new(0.5, -0.25));
+    }
+
+    #[test]
+    fn rotate_keeps_center_and_wraps_angle() {
+        let mut viewport = Viewport::default();
+        viewport.rotate(FRAC_PI_2);
+        assert_close(
+            viewport.pixel_to_complex(400.0, 300.0, WINDOW),
+            Complex::new(0.0, 0.0),
+        );
+        // The right edge midpoint now lies on the positive imaginary axis.
+        assert_close(
+            viewport.pixel_to_complex(800.0, 300.0, WINDOW),
+            Complex::new(0.0, 2.0),
+        );
+        viewport.rotate(2.0 * PI);
+        assert!((viewport.rotation - FRAC_PI_2).abs() < 1e-12);
+        viewport.rotate(-PI);
+        assert!((viewport.rotation - 3.0 * FRAC_PI_2).abs() < 1e-12);
+    }
+
+    #[test]
+    fn json_round_trip() {
+        let mut viewport = Viewport::new(
+            Complex::new(-0.743643887037151, 0.13182590420533),
+            1e-10,
+            7.5e-11,
+        );
+        viewport.rotate(0.123);
+        let json = viewport.to_json().to_string();
+        let parsed = Viewport::from_json(&Json::parse(&json).unwrap()).unwrap();
+        assert_eq!(parsed, viewport);
+        assert!(!json.contains("low"));
+        viewport.pan(Complex::new(1e-25, -3e-24));
+        let json = viewport.to_json().to_string();
+        let parsed = Viewport::from_json(&Json::parse(&json).unwrap()).unwrap();
+        assert_eq!(parsed, viewport);
+    }
+
+    #[test]
+    fn deep_pans_and_zooms_keep_their_precision() {
+        let mut viewport = Viewport::new(Complex::new(-0.75, 0.1), 4e-24, 3e-24);
+        let start = viewport;
+        viewport.pan(Complex::new(1e-24, 0.0));
+        // Too small a step for f64 on its own.
+        assert_eq!(viewport.center, start.center);
+        assert_eq!(viewport.displacement(&start), Complex::new(1e-24, 0.0));
+        let offset = viewport.offset(600.0, 150.0, WINDOW);
+        viewport.zoom_at(offset, 0.5);
+        let (x, y) = viewport.offset_to_pixel(offset * 0.5, WINDOW);
+        assert!((x - 600.0).abs() < 1e-9 && (y - 150.0).abs() < 1e-9);
+        let moved = viewport.displacement(&start) - Complex::new(1e-24, 0.0);
+        assert!((moved - offset * 0.5).norm() < 1e-12 * viewport.width);
+    }
+
+    #[test]
+    fn centers_keep_digits_beyond_double_double() {
+        let digits = |last: &str| format!("-0.{}{last}", "7".repeat(98));
+        let mut viewport = Viewport::new(Complex::new(0.0, 0.0), 4e-100, 3e-100);
+        let re = Fixed::parse(&digits("71"), CENTER_BITS).unwrap();
+        viewport.set_center(&re, &Fixed::from_f64(0.1, CENTER_BITS));
+        assert_ne!(viewport.center_tail, NO_TAIL);
+        assert_eq!(
+            viewport.center_fixed(CENTER_BITS).0.decimal(100),
+            digits("71")
+        );
+        let start = viewport;
+        viewport.pan(Complex::new(1e-100, -2e-101));
+        assert_eq!(
+            viewport.center_fixed(CENTER_BITS).0.decimal(100),
+            digits("70")
+        );
+        let moved = viewport.displacement(&start) - Complex::new(1e-100, -2e-101);
+        assert!(moved.norm() < 1e-115, "{moved}");
+        let json = viewport.to_json().to_string();
+        assert!(json.contains("center_re_tail"));
+        let parsed = Viewport::from_json(&Json::parse(&json).unwrap()).unwrap();
+        assert_eq!(parsed, viewport);
+        let json = r#"{"center_re":0,"center_im":0,"width":1,"height":1,"center_re_tail":[#]}"#;
+        let json = json.replace('#', &["1e-40"; TAIL_TERMS + 1].join(","));
+        assert!(Viewport::from_json(&Json::parse(&json).unwrap()).is_err());
+    }
+
+    #[test]
+    fn json_rejects_invalid_extent() {
+        let json = Json::parse(r#"{"center_re":0,"center_im":0,"width":0,"height":1}"#).unwrap();
+        assert!(Viewport::from_json(&json).is_err());
+        let json = Json::parse(r#"{"center_re":0,"center_im":0,"width":2}"#).unwrap();
+        assert!(Viewport::from_json(&json).is_err());
+    }
+}
@@ -0,0 +1,274 @@
+use crate::buddhabrot::SplitMix64;
+use crate::palette::Palette;
+use crate::params::{Fractal, RenderParams};
+use crate::png;
+use crate::render;
+use crate::viewport::Viewport;
+use num::complex::Complex;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+// Candidate locations tried before settling for the fallback.
+const ATTEMPTS: u32 = 500;
+// Size of the preview judging a candidate.
+const PREVIEW: (u32, u32) = (48, 36);
+// A view is interesting with some, but not too much, of the set in it and enough distinct
+// escape counts around it.
+const INTERIOR: (f64, f64) = (0.02, 0.6);
+const MIN_DISTINCT_ESCAPES: usize = 24;
+// Magnifications over the initial view as powers of ten.
+const DEPTH: (f64, f64) = (1.0, 5.0);
+const FALLBACK: Complex<f64> = Complex::new(-0.743643887, 0.131825904);
+
+// The `wallpaper` subcommand: a random interesting place rendered at the desktop resolution,
+// optionally set as the desktop background.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Wallpaper {
+    // The resolution of the desktop when `None`.
+    pub size: Option<(u32, u32)>,
+    pub iterations: u32,
+    // A random place every run without a seed.
+    pub seed: Option<u64>,
+    pub output: PathBuf,
+    // Set as the desktop background, in builds with the `wallpaper` feature.
+    pub set: bool,
+    // File receiving a daily schedule of the same command for cron or the Task Scheduler.
+    pub schedule: Option<PathBuf>,
+}
+
+impl Default for Wallpaper {
+    fn default() -> Self {
+        Wallpaper {
+            size: None,
+            iterations: 2000,
+            seed: None,
+            output: PathBuf::from("wallpaper.png"),
+            set: false,
+            schedule: None,
+        }
+    }
+}
+
+// Whether the view has structure worth looking at, judged from a small preview.
+fn interesting(viewport: Viewport, iterations: u32) -> bool {
+    let preview = render::escape_time_frame(
+        RenderParams::builder()
+            .viewport(viewport.fit(PREVIEW))
+            .size(PREVIEW.0, PREVIEW.1)
+            .iterations(iterations)
+            .build()
+            .unwrap(),
+    );
+    let inside = preview
+        .samples
+        .iter()
+        .filter(|s| s.escape.is_none())
+        .count();
+    let share = inside as f64 / preview.samples.len() as f64;
+    let escapes = preview
+        .samples
+        .iter()
+        .filter_map(|s| s.escape)
+        .collect::<HashSet<_>>();
+    (INTERIOR.0..=INTERIOR.1).contains(&share) && escapes.len() >= MIN_DISTINCT_ESCAPES
+}
+
+// A view of the Mandelbrot set centered near its boundary, at a random depth, picked among
+// random points that escape late and checked to show some of the set and its surroundings.
+pub fn find(rng: &mut SplitMix64, iterations: u32) -> Viewport {
+    let initial = Viewport::default();
+    for _ in 0..ATTEMPTS {
+        let c = Complex::new(-2.0 + 2.5 * rng.next_f64(), -1.2 + 2.4 * rng.next_f64());
+        let depth = DEPTH.0 + rng.next_f64() * (DEPTH.1 - DEPTH.0);
+        let sample = render::escape_time(Fractal::MANDELBROT, c, iterations);
+        if sample.escape.is_none_or(|escape| escape < iterations / 50) {
+            continue;
+        }
+        let height = initial.height / 10f64.powf(depth);
+        let viewport = Viewport::new(c, height * initial.width / initial.height, height);
+        if interesting(viewport, iterations) {
+            return viewport;
+        }
+    }
+    Viewport::new(FALLBACK, 0.004, 0.003)
+}
+
+// Lines to install with `crontab FILE`, or on Windows a command registering the daily task.
+fn daily_schedule(command: &[String]) -> String {
+    let quoted = command
+        .iter()
+        .map(|arg| format!("\"{arg}\""))
+        .collect::<Vec<_>>()
+        .join(" ");
+    if cfg!(windows) {
+        let quoted = quoted.replace('"', "\\\"");
+        format!(
+            "schtasks /create /sc daily /st 08:00 /tn \"Mandelbrot wallpaper\" /tr \"{quoted}\"\n"
+        )
+    } else {
+        format!("# Install with: crontab FILE\n0 8 * * * {quoted}\n")
+    }
+}
+
+#[cfg(feature = "wallpaper")]
+fn set_desktop(path: &Path) -> Result<(), String> {
+    use std::process::Command;
+    let path = path.display().to_string();
+    let commands: Vec<Command> = if cfg!(target_os = "macos") {
+        let mut osascript = Command::new("osascript");
+        osascript.args([
+            "-e",
+            &format!(
+                "tell application \"System Events\" to tell every desktop to set picture to \
+                 \"{path}\""
+            ),
+        ]);
+        vec![osascript]
+    } else if cfg!(windows) {
+        let mut powershell = Command::new("powershell");
+        powershell.args([
+            "-NoProfile",
+            "-Command",
+            &format!(
+                "Add-Type -TypeDefinition 'using System.Runtime.InteropServices; public class \
+                 W {{ [DllImport(\"user32.dll\")] public static extern int \
+                 SystemParametersInfo(int a, int b, string c, int d); }}'; \
+                 [W]::SystemParametersInfo(20, 0, '{path}', 3)"
+            ),
+        ]);
+        vec![powershell]
+    } else {
+        // GNOME, with the dark style variant too.
+        ["picture-uri", "picture-uri-dark"]
+            .into_iter()
+            .map(|key| {
+                let mut gsettings = Command::new("gsettings");
+                gsettings.args([
+                    "set",
+                    "org.gnome.desktop.background",
+                    key,
+                    &format!("file://{path}"),
+                ]);
+                gsettings
+            })
+            .collect()
+    };
+    for mut command in commands {
+        let status = command.status().map_err(|e| format!("{command:?}: {e}"))?;
+        if !status.success() {
+            return Err(format!("{command:?} failed with {status}"));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(not(feature = "wallpaper"))]
+fn set_desktop(_: &Path) -> Result<(), String> {
+    Err("Setting the wallpaper needs a build with the wallpaper feature".to_string())
+}
+
+pub fn run(job: &Wallpaper) -> Result<(), String> {
+    let stamp = Instant::now();
+    let (width, height) = job
+        .size
+        .ok_or_else(|| "The wallpaper size is unknown".to_string())?;
+    let seed = job.seed.unwrap_or_else(|| {
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map_or(0, |elapsed| elapsed.as_nanos() as u64)
+    });
+    let mut rng = SplitMix64::new(seed);
+    let viewport = find(&mut rng, job.iterations).fit((width, height));
+    println!(
+        "Wallpaper at {:.12} {:+.12}i, height {:.3e} (seed {seed})",
+        viewport.center.re, viewport.center.im, viewport.height
+    );
+    let params = RenderParams::builder()
+        .viewport(viewport)
+        .size(width, height)
+        .iterations(job.iterations)
+        .palette(Some(Palette::random(&mut rng)))
+        .antialias(2)
+        .build()?;
+    let frame = render::render_frame(params);
+    png::write(&job.output, width, height, &frame.rgb)?;
+    println!(
+        "Wrote {} ({width}x{height}) in {:?}",
+        job.output.display(),
+        Instant::now() - stamp
+    );
+    // The desktop and the scheduler need the full path.
+    let output = std::path::absolute(&job.output).map_err(|e| e.to_string())?;
+    if job.set {
+        set_desktop(&output)?;
+        println!("Set {} as the wallpaper", output.display());
+    }
+    if let Some(path) = &job.schedule {
+        let program = std::env::current_exe().map_err(|e| e.to_string())?;
+        let command = [
+            program.display().to_string(),
+            "wallpaper".to_string(),
+            "--size".to_string(),
+            format!("{width}x{height}"),
+            "--iterations".to_string(),
+            job.iterations.to_string(),
+            "--output".to_string(),
+            output.display().to_string(),
+            "--set".to_string(),
+        ];
+        std::fs::write(path, daily_schedule(&command))
+            .map_err(|e| format!("{}: {e}", path.display()))?;
+        println!("Wrote the daily schedule {}", path.display());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_interesting_places() {
+        let found = find(&mut SplitMix64::new(5), 500);
+        assert_eq!(found, find(&mut SplitMix64::new(5), 500));
+        assert_ne!(found.center, FALLBACK);
+        assert!(interesting(found, 500));
+        assert!(found.height <= Viewport::default().height / 10.0);
+        // Deep inside the cardioid, or far outside the set, there is nothing to see.
+        assert!(!interesting(
+            Viewport::new(Complex::new(-0.1, 0.0), 0.01, 0.01),
+            500
+        ));
+        assert!(!interesting(
+            Viewport::new(Complex::new(1.5, 1.5), 0.01, 0.01),
+            500
+        ));
+        let command = ["mandelbrot-explorer".to_string(), "wallpaper".to_string()];
+        assert!(daily_schedule(&command).contains("\"mandelbrot-explorer\" \"wallpaper\""));
+    }
+
+    #[test]
+    fn renders_wallpaper() {
+        let dir = std::env::temp_dir().join(format!("mandelbrot-wallpaper-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let job = Wallpaper {
+            size: Some((32, 18)),
+            iterations: 300,
+            seed: Some(1),
+            output: dir.join("wallpaper.png"),
+            schedule: Some(dir.join("wallpaper.cron")),
+            ..Wallpaper::default()
+        };
+        run(&job).unwrap();
+        assert!(job.output.exists());
+        let schedule = std::fs::read_to_string(dir.join("wallpaper.cron")).unwrap();
+        assert!(schedule.contains("--size\" \"32x18\""));
+        assert!(run(&Wallpaper {
+            size: None,
+            ..job.clone()
+        })
+        .is_err());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}